@@ -0,0 +1,124 @@
+use once_cell::sync::OnceCell;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, RwLock, RwLockReadGuard};
+use std::time::Duration;
+use tracing::{debug, error};
+
+use crate::config::AppConfig;
+
+/// Quiet time before queued edits are written; edits within it end up in one write.
+const SAVE_DEBOUNCE: Duration = Duration::from_millis(500);
+
+static CURRENT: OnceCell<AppState> = OnceCell::new();
+
+/// The state registered by [`AppState::start`], if the app has started.
+pub fn current() -> Option<&'static AppState> {
+    CURRENT.get()
+}
+
+enum Persist {
+    Changed,
+    /// Write now and report the result.
+    Flush(Sender<Result<(), String>>),
+}
+
+/// The app configuration shared by all handlers. Edits are made on the in-memory copy under
+/// one lock, so two handlers changing different fields can't overwrite each other; a single
+/// background task writes the config file.
+#[derive(Clone)]
+pub struct AppState {
+    config: Arc<RwLock<AppConfig>>,
+    persist: Sender<Persist>,
+}
+
+impl AppState {
+    /// Loads the config file and starts the persistence task. Call once at startup.
+    pub fn start() -> AppState {
+        let config = Arc::new(RwLock::new(crate::config::read_config_file()));
+        let (persist, rx) = mpsc::channel();
+        {
+            let config = Arc::clone(&config);
+            std::thread::spawn(move || persistence_loop(config, rx));
+        }
+        let state = AppState { config, persist };
+        let _ = CURRENT.set(state.clone());
+        state
+    }
+
+    pub fn read(&self) -> RwLockReadGuard<'_, AppConfig> {
+        self.config.read().unwrap()
+    }
+
+    /// The config, or `None` if it is being edited right now. For code that must not block.
+    pub fn try_read(&self) -> Option<RwLockReadGuard<'_, AppConfig>> {
+        self.config.try_read().ok()
+    }
+
+    /// Copy of the current config.
+    pub fn config(&self) -> AppConfig {
+        self.read().clone()
+    }
+
+    /// Edits the config; the file is written shortly after, together with other edits.
+    pub fn update<R>(&self, edit: impl FnOnce(&mut AppConfig) -> R) -> R {
+        let result = edit(&mut self.config.write().unwrap());
+        let _ = self.persist.send(Persist::Changed);
+        result
+    }
+
+    /// Edits the config and writes it right away, for Save buttons that report errors.
+    pub fn save(&self, edit: impl FnOnce(&mut AppConfig)) -> Result<(), String> {
+        edit(&mut self.config.write().unwrap());
+        self.flush()
+    }
+
+    /// Writes the config now, including edits still waiting for the debounce.
+    pub fn flush(&self) -> Result<(), String> {
+        let (reply, result) = mpsc::channel();
+        self.persist.send(Persist::Flush(reply)).map_err(|_| "Tác vụ lưu cấu hình đã dừng".to_string())?;
+        result.recv().map_err(|_| "Tác vụ lưu cấu hình đã dừng".to_string())?
+    }
+}
+
+fn write(config: &RwLock<AppConfig>) -> Result<(), String> {
+    let snapshot = config.read().unwrap().clone();
+    crate::config::save_config(&snapshot)
+}
+
+fn persistence_loop(config: Arc<RwLock<AppConfig>>, rx: Receiver<Persist>) {
+    let mut dirty = false;
+    loop {
+        let message = if dirty {
+            match rx.recv_timeout(SAVE_DEBOUNCE) {
+                Ok(message) => Some(message),
+                Err(RecvTimeoutError::Timeout) => None,
+                Err(RecvTimeoutError::Disconnected) => {
+                    if let Err(e) = write(&config) {
+                        error!("Failed to save config: {}", e);
+                    }
+                    return;
+                }
+            }
+        } else {
+            match rx.recv() {
+                Ok(message) => Some(message),
+                Err(_) => return,
+            }
+        };
+        match message {
+            Some(Persist::Changed) => dirty = true,
+            Some(Persist::Flush(reply)) => {
+                let result = write(&config);
+                dirty = result.is_err();
+                let _ = reply.send(result);
+            }
+            None => {
+                match write(&config) {
+                    Ok(()) => debug!("Config saved"),
+                    Err(e) => error!("Failed to save config: {}", e),
+                }
+                dirty = false;
+            }
+        }
+    }
+}
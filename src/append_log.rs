@@ -0,0 +1,79 @@
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tracing::warn;
+
+const STATE_FILE_NAME: &str = "append_offsets.json";
+
+/// Bytes already shipped per "bucket|key", for append-only (log shipping) mode.
+static OFFSETS: Lazy<Mutex<HashMap<String, u64>>> = Lazy::new(|| Mutex::new(load_offsets()));
+
+fn state_key(bucket: &str, key: &str) -> String {
+    format!("{}|{}", bucket, key)
+}
+
+/// Byte range still to upload for a file now `len` bytes long, given what was shipped before.
+/// A file shorter than the recorded offset was rotated or truncated and starts over.
+pub fn pending_range(shipped: Option<u64>, len: u64) -> Option<std::ops::Range<u64>> {
+    let start = match shipped {
+        Some(offset) if offset <= len => offset,
+        _ => 0,
+    };
+    (start < len).then_some(start..len)
+}
+
+pub fn shipped(bucket: &str, key: &str) -> Option<u64> {
+    OFFSETS.lock().unwrap().get(&state_key(bucket, key)).copied()
+}
+
+/// Records that everything up to `offset` has been uploaded. Saved right away so an
+/// interrupted sync doesn't ship the same bytes twice.
+pub fn record_shipped(bucket: &str, key: &str, offset: u64) {
+    let mut offsets = OFFSETS.lock().unwrap();
+    offsets.insert(state_key(bucket, key), offset);
+    save_offsets(&offsets);
+}
+
+/// Key of the object holding one appended chunk: `logs/app.log` → `logs/app.log.20240501_023000-1048576`
+/// (upload time, then the byte offset the chunk starts at).
+pub fn chunk_key(key: &str, now: &chrono::DateTime<chrono::Local>, start: u64) -> String {
+    format!("{}.{}-{}", key, now.format("%Y%m%d_%H%M%S"), start)
+}
+
+/// State file lives next to the confy config file.
+fn state_path() -> Option<PathBuf> {
+    crate::config::get_config_path().and_then(|p| p.parent().map(|d| d.join(STATE_FILE_NAME)))
+}
+
+fn load_offsets() -> HashMap<String, u64> {
+    state_path()
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+fn save_offsets(offsets: &HashMap<String, u64>) {
+    let Some(path) = state_path() else { return };
+    let result = serde_json::to_string(offsets)
+        .map_err(|e| e.to_string())
+        .and_then(|text| std::fs::write(&path, text).map_err(|e| e.to_string()));
+    if let Err(e) = result {
+        warn!("Không thể ghi offset append-only {:?}: {}", path, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pending_range() {
+        assert_eq!(pending_range(None, 100), Some(0..100));
+        assert_eq!(pending_range(Some(60), 100), Some(60..100));
+        assert_eq!(pending_range(Some(100), 100), None);
+        // Rotated: the new file is shorter than what was shipped
+        assert_eq!(pending_range(Some(500), 100), Some(0..100));
+        assert_eq!(pending_range(None, 0), None);
+    }
+}
@@ -0,0 +1,210 @@
+use aws_sdk_s3::Client;
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::types::{CompletedMultipartUpload, CompletedPart};
+use std::io::Write;
+use std::path::Path;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+use walkdir::WalkDir;
+
+use crate::config::FilterConfig;
+use crate::progress::StatusFn;
+
+/// Size of each multipart part; at most this much of the archive is held in memory per buffer.
+const PART_SIZE: usize = 8 * 1024 * 1024;
+/// Parts buffered between the compressor and the uploader.
+const PARTS_IN_FLIGHT: usize = 2;
+
+pub const ARCHIVE_EXTENSION: &str = "tar.gz";
+
+/// Dated key for a folder's archive: `backups/site` + `C:\www` → `backups/site/www-2024-05-01_0230.tar.gz`.
+pub fn archive_key(s3_prefix: &str, local_dir: &Path, now: &chrono::DateTime<chrono::Local>) -> String {
+    let name = local_dir.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| "archive".to_string());
+    let file = format!("{}-{}.{}", name, now.format("%Y-%m-%d_%H%M"), ARCHIVE_EXTENSION);
+    let prefix = s3_prefix.trim_matches('/');
+    if prefix.is_empty() { file } else { format!("{}/{}", prefix, file) }
+}
+
+/// Cuts the compressed stream into parts and hands them to the uploader.
+struct PartWriter {
+    tx: mpsc::Sender<Vec<u8>>,
+    buf: Vec<u8>,
+}
+
+impl PartWriter {
+    fn send(&mut self) -> std::io::Result<()> {
+        let part = std::mem::replace(&mut self.buf, Vec::with_capacity(PART_SIZE));
+        self.tx
+            .blocking_send(part)
+            .map_err(|_| std::io::Error::new(std::io::ErrorKind::BrokenPipe, "upload stopped"))
+    }
+}
+
+impl Write for PartWriter {
+    fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+        self.buf.extend_from_slice(data);
+        if self.buf.len() >= PART_SIZE {
+            self.send()?;
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Writes `dir` as a gzipped tar into `out`, applying the upload filters. Runs on a blocking
+/// thread; returns the number of files archived.
+fn write_archive(dir: &Path, filter_config: &FilterConfig, out: PartWriter) -> Result<usize, String> {
+    let root = dir.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let mut tar = tar::Builder::new(flate2::write::GzEncoder::new(out, flate2::Compression::default()));
+    tar.follow_symlinks(false);
+    let mut files = 0;
+    let entries = WalkDir::new(dir)
+        .min_depth(1)
+        .into_iter()
+        .filter_entry(|e| !e.metadata().is_ok_and(|m| crate::utils::is_reparse_point(&m)));
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        let relative = path.strip_prefix(dir).unwrap_or(path);
+        let name = Path::new(&root).join(relative);
+        if entry.file_type().is_dir() {
+            tar.append_dir(&name, path)
+        } else if crate::utils::should_include_file(path, dir, filter_config) {
+            files += 1;
+            tar.append_path_with_name(path, &name)
+        } else {
+            continue;
+        }
+        .map_err(|e| format!("Lỗi nén {}: {}", path.display(), e))?;
+    }
+    let mut out = tar
+        .into_inner()
+        .and_then(|gz| gz.finish())
+        .map_err(|e| format!("Lỗi nén {}: {}", dir.display(), e))?;
+    out.send().map_err(|e| e.to_string())?;
+    Ok(files)
+}
+
+/// Archives `dir` and streams it to `key` with a multipart upload, so the archive is never
+/// staged on disk. Returns (files archived, compressed bytes uploaded).
+pub async fn upload_archive(
+    client: &Client,
+    bucket: &str,
+    key: &str,
+    dir: &Path,
+    filter_config: FilterConfig,
+    status: &StatusFn,
+) -> Result<(usize, u64), String> {
+    let upload_id = client
+        .create_multipart_upload()
+        .bucket(bucket)
+        .key(key)
+        .content_type("application/gzip")
+        .send()
+        .await
+        .map_err(|e| format!("Lỗi tạo multipart upload {}: {}", key, e))?
+        .upload_id
+        .ok_or("S3 không trả về UploadId")?;
+
+    let result = upload_parts(client, bucket, key, &upload_id, dir, filter_config, status).await;
+    match result {
+        Ok((files, bytes, parts)) => {
+            client
+                .complete_multipart_upload()
+                .bucket(bucket)
+                .key(key)
+                .upload_id(&upload_id)
+                .multipart_upload(CompletedMultipartUpload::builder().set_parts(Some(parts)).build())
+                .send()
+                .await
+                .map_err(|e| format!("Lỗi hoàn tất upload {}: {}", key, e))?;
+            info!("Uploaded archive s3://{}/{} ({} files, {} bytes)", bucket, key, files, bytes);
+            Ok((files, bytes))
+        }
+        Err(e) => {
+            // Don't leave billable incomplete parts behind
+            if let Err(abort) = client.abort_multipart_upload().bucket(bucket).key(key).upload_id(&upload_id).send().await {
+                warn!("Failed to abort multipart upload {}: {}", key, abort);
+            }
+            Err(e)
+        }
+    }
+}
+
+async fn upload_parts(
+    client: &Client,
+    bucket: &str,
+    key: &str,
+    upload_id: &str,
+    dir: &Path,
+    filter_config: FilterConfig,
+    status: &StatusFn,
+) -> Result<(usize, u64, Vec<CompletedPart>), String> {
+    let (tx, mut rx) = mpsc::channel(PARTS_IN_FLIGHT);
+    let writer = {
+        let dir = dir.to_path_buf();
+        tokio::task::spawn_blocking(move || write_archive(&dir, &filter_config, PartWriter { tx, buf: Vec::with_capacity(PART_SIZE) }))
+    };
+
+    let mut parts = Vec::new();
+    let mut bytes = 0u64;
+    while let Some(data) = rx.recv().await {
+        let part_number = parts.len() as i32 + 1;
+        let len = data.len() as u64;
+        let etag = client
+            .upload_part()
+            .bucket(bucket)
+            .key(key)
+            .upload_id(upload_id)
+            .part_number(part_number)
+            .body(ByteStream::from(data))
+            .send()
+            .await
+            .map_err(|e| format!("Lỗi upload phần {} của {}: {}", part_number, key, e))?
+            .e_tag;
+        parts.push(CompletedPart::builder().part_number(part_number).set_e_tag(etag).build());
+        bytes += len;
+        status(
+            format!("Đang nén và upload {}: {}", key, crate::utils::format_size(bytes)),
+            crate::progress::INDETERMINATE,
+            false,
+        );
+    }
+    let files = writer.await.map_err(|e| format!("Lỗi nén: {}", e))??;
+    Ok((files, bytes, parts))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_archive_streams_parts() {
+        let dir = std::env::temp_dir().join(format!("s3sync-archive-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("a.txt"), "hello").unwrap();
+        std::fs::write(dir.join("sub/b.txt"), "world").unwrap();
+
+        let (tx, mut rx) = mpsc::channel(16);
+        let filter_config = FilterConfig { enable_filtering: false, ..Default::default() };
+        let files = write_archive(&dir, &filter_config, PartWriter { tx, buf: Vec::new() }).unwrap();
+        let mut data = Vec::new();
+        while let Ok(part) = rx.try_recv() {
+            data.extend(part);
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(files, 2);
+        let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(&data[..]));
+        let mut names: Vec<String> = archive
+            .entries()
+            .unwrap()
+            .map(|e| e.unwrap().path().unwrap().to_string_lossy().replace('\\', "/"))
+            .collect();
+        names.sort();
+        let root = dir.file_name().unwrap().to_string_lossy();
+        assert_eq!(names, vec![format!("{}/a.txt", root), format!("{}/sub", root), format!("{}/sub/b.txt", root)]);
+    }
+}
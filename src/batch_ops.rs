@@ -0,0 +1,234 @@
+//! S3 Batch Operations for large replica copies. When a copy-mode replica has more objects than
+//! the configured threshold, the keys are written to a CSV manifest in a separate (private)
+//! manifest bucket and one Batch Operations copy job does the work server-side instead of one
+//! CopyObject per key. Local uploads can't use Batch Operations; it only copies objects already
+//! in S3.
+
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3control::types::{
+    JobManifest, JobManifestFieldName, JobManifestFormat, JobManifestLocation, JobManifestSpec, JobOperation, JobReport,
+    JobStatus, RequestedJobStatus, S3CopyObjectOperation,
+};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::progress::StatusFn;
+
+/// Where manifests are written in the manifest bucket.
+const MANIFEST_PREFIX: &str = ".s3sync/batch/";
+/// How often a running job is checked.
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+/// DescribeJob failures in a row after which tracking gives up.
+const MAX_DESCRIBE_FAILURES: u32 = 10;
+
+/// Whether a copy of `objects` keys goes through Batch Operations: a threshold is set, it is
+/// exceeded, and there is a role for the job to run as and a bucket for its manifest.
+pub fn use_batch(objects: usize, threshold: usize, role_arn: &str, manifest_bucket: &str) -> bool {
+    threshold > 0 && objects > threshold && !role_arn.trim().is_empty() && !manifest_bucket.trim().is_empty()
+}
+
+/// ARN partition of a region: `aws-cn` for China, `aws-us-gov` for GovCloud, else `aws`.
+pub fn partition(region: &str) -> &'static str {
+    if region.starts_with("cn-") {
+        "aws-cn"
+    } else if region.starts_with("us-gov-") {
+        "aws-us-gov"
+    } else {
+        "aws"
+    }
+}
+
+/// The `bucket,key` CSV manifest for copying `keys` out of `bucket`; keys must be URL-encoded.
+pub fn manifest_csv(bucket: &str, keys: &[String]) -> String {
+    keys.iter().map(|key| format!("{},{}\n", bucket, crate::utils::percent_encode_key(key))).collect()
+}
+
+/// What a copy job copies, and where it keeps its manifest.
+pub struct CopyJobSpec<'a> {
+    pub account_id: &'a str,
+    /// Region of the source bucket, where the job runs.
+    pub region: &'a str,
+    pub source_bucket: &'a str,
+    pub target_bucket: &'a str,
+    /// Private bucket for the manifest; the source bucket may well be public.
+    pub manifest_bucket: &'a str,
+    pub keys: &'a [String],
+    pub role_arn: &'a str,
+}
+
+/// A submitted copy job, tracked until it finishes.
+#[derive(Debug, Clone)]
+pub struct BatchJob {
+    pub job_id: String,
+    pub account_id: String,
+    pub target_bucket: String,
+    pub objects: usize,
+    pub manifest_bucket: String,
+    pub manifest_key: String,
+}
+
+/// Uploads the manifest to the manifest bucket and creates the copy job in the source region.
+/// The job waits for confirmation (see [`confirm`]) and writes no completion report.
+pub async fn start_copy_job(
+    s3: &aws_sdk_s3::Client,
+    control: &aws_sdk_s3control::Client,
+    spec: CopyJobSpec<'_>,
+) -> Result<BatchJob, String> {
+    let CopyJobSpec { account_id, region, source_bucket, target_bucket, manifest_bucket, keys, role_arn } = spec;
+    let partition = partition(region);
+    let stamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
+    let manifest_key = format!("{}{}-{}.csv", MANIFEST_PREFIX, stamp, target_bucket);
+    let put = s3
+        .put_object()
+        .bucket(manifest_bucket)
+        .key(&manifest_key)
+        .content_type("text/csv")
+        .body(ByteStream::from(manifest_csv(source_bucket, keys).into_bytes()))
+        .send()
+        .await
+        .map_err(|e| format!("Lỗi upload manifest s3://{}/{}: {}", manifest_bucket, manifest_key, e))?;
+    let etag = put.e_tag().unwrap_or_default().trim_matches('"').to_string();
+
+    let location = JobManifestLocation::builder()
+        .object_arn(format!("arn:{}:s3:::{}/{}", partition, manifest_bucket, manifest_key))
+        .e_tag(etag)
+        .build()
+        .map_err(|e| e.to_string())?;
+    let spec = JobManifestSpec::builder()
+        .format(JobManifestFormat::S3BatchOperationsCsv20180820)
+        .fields(JobManifestFieldName::Bucket)
+        .fields(JobManifestFieldName::Key)
+        .build()
+        .map_err(|e| e.to_string())?;
+    let operation = JobOperation::builder()
+        .s3_put_object_copy(
+            S3CopyObjectOperation::builder().target_resource(format!("arn:{}:s3:::{}", partition, target_bucket)).build(),
+        )
+        .build();
+    let created = control
+        .create_job()
+        .account_id(account_id)
+        .confirmation_required(true)
+        .operation(operation)
+        .manifest(JobManifest::builder().spec(spec).location(location).build())
+        .report(JobReport::builder().enabled(false).build())
+        .priority(10)
+        .role_arn(role_arn.trim())
+        // Tokens are limited to 64 characters
+        .client_request_token(format!("s3sync-{}-{}", stamp, target_bucket).chars().take(64).collect::<String>())
+        .description(format!("S3 Sync Tool replica {} -> {}", source_bucket, target_bucket))
+        .send()
+        .await
+        .map_err(|e| format!("Lỗi tạo S3 Batch job: {}", e))?;
+    let job_id = created.job_id().unwrap_or_default().to_string();
+    info!("Created S3 Batch copy job {} ({} objects, {} -> {})", job_id, keys.len(), source_bucket, target_bucket);
+    Ok(BatchJob {
+        job_id,
+        account_id: account_id.to_string(),
+        target_bucket: target_bucket.to_string(),
+        objects: keys.len(),
+        manifest_bucket: manifest_bucket.to_string(),
+        manifest_key,
+    })
+}
+
+/// Lets a job created with confirmation required start running.
+pub async fn confirm(control: &aws_sdk_s3control::Client, job: &BatchJob) -> Result<(), String> {
+    control
+        .update_job_status()
+        .account_id(&job.account_id)
+        .job_id(&job.job_id)
+        .requested_job_status(RequestedJobStatus::Ready)
+        .send()
+        .await
+        .map(|_| ())
+        .map_err(|e| format!("Lỗi xác nhận S3 Batch job {}: {}", job.job_id, e))
+}
+
+fn is_finished(status: &JobStatus) -> bool {
+    matches!(status, JobStatus::Complete | JobStatus::Failed | JobStatus::Cancelled)
+}
+
+/// Polls the job in the background and reports its state and task counts through `status`
+/// until it completes, fails or is cancelled, then removes its manifest. Gives up after
+/// [`MAX_DESCRIBE_FAILURES`] failed checks in a row.
+pub fn track(control: Arc<aws_sdk_s3control::Client>, s3: Arc<aws_sdk_s3::Client>, job: BatchJob, status: StatusFn) {
+    tokio::spawn(async move {
+        let label = format!("S3 Batch job {} → {}", job.job_id, job.target_bucket);
+        let mut failures = 0;
+        loop {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            let described = control.describe_job().account_id(&job.account_id).job_id(&job.job_id).send().await;
+            let descriptor = match described {
+                Ok(out) => {
+                    failures = 0;
+                    out.job().cloned()
+                }
+                Err(e) => {
+                    failures += 1;
+                    warn!("Cannot check {} ({}/{}): {}", label, failures, MAX_DESCRIBE_FAILURES, e);
+                    if failures >= MAX_DESCRIBE_FAILURES {
+                        status(format!("{}: không kiểm tra được trạng thái, ngừng theo dõi ({})", label, e), 0.0, true);
+                        return;
+                    }
+                    continue;
+                }
+            };
+            let Some(descriptor) = descriptor else { continue };
+            let state = descriptor.status().cloned().unwrap_or(JobStatus::New);
+            let (done, failed, total) = descriptor
+                .progress_summary()
+                .map(|p| {
+                    (
+                        p.number_of_tasks_succeeded().unwrap_or(0),
+                        p.number_of_tasks_failed().unwrap_or(0),
+                        p.total_number_of_tasks().unwrap_or(job.objects as i64),
+                    )
+                })
+                .unwrap_or((0, 0, job.objects as i64));
+            let progress = if total > 0 { (done + failed) as f32 / total as f32 } else { 0.0 };
+            let text = format!("{}: {} ({} xong, {} lỗi / {})", label, state.as_str(), done, failed, total);
+            if state == JobStatus::Suspended {
+                status(format!("{} - chờ xác nhận trên S3 console", text), progress, false);
+                continue;
+            }
+            if !is_finished(&state) {
+                status(text, progress, false);
+                continue;
+            }
+            let reason = descriptor.failure_reasons().first().and_then(|f| f.failure_reason()).unwrap_or_default();
+            let is_error = state != JobStatus::Complete || failed > 0;
+            info!("{} {}", text, reason);
+            status(if reason.is_empty() { text } else { format!("{} - {}", text, reason) }, 1.0, is_error);
+            break;
+        }
+        if let Err(e) = s3.delete_object().bucket(&job.manifest_bucket).key(&job.manifest_key).send().await {
+            warn!("Failed to delete manifest s3://{}/{}: {}", job.manifest_bucket, job.manifest_key, e);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest_and_threshold() {
+        let keys = vec!["docs/a b.html".to_string(), "img/ảnh+1.png".to_string()];
+        assert_eq!(
+            manifest_csv("site", &keys),
+            "site,docs/a%20b.html\nsite,img/%E1%BA%A3nh%2B1.png\n"
+        );
+        let role = "arn:aws:iam::1:role/batch";
+        assert!(!use_batch(5000, 0, role, "manifests"));
+        assert!(!use_batch(5000, 10_000, role, "manifests"));
+        assert!(!use_batch(50_000, 10_000, " ", "manifests"));
+        assert!(!use_batch(50_000, 10_000, role, ""));
+        assert!(use_batch(50_000, 10_000, role, "manifests"));
+
+        assert_eq!(partition("ap-northeast-1"), "aws");
+        assert_eq!(partition("cn-north-1"), "aws-cn");
+        assert_eq!(partition("us-gov-west-1"), "aws-us-gov");
+    }
+}
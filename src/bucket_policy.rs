@@ -0,0 +1,234 @@
+//! What the target bucket allows before anything is uploaded: whether Block Public Access is on,
+//! and whether the bucket policy denies PutObject requests like ours. The app sends no
+//! server-side encryption or ACL headers, so a policy that requires one rejects every upload.
+
+use aws_sdk_s3::Client;
+use serde_json::Value;
+
+/// Request headers a bucket policy can demand on PutObject that the app never sends.
+const HEADER_KEYS: [(&str, &str); 3] = [
+    ("s3:x-amz-server-side-encryption", "mã hóa server-side"),
+    ("s3:x-amz-server-side-encryption-aws-kms-key-id", "KMS key"),
+    ("s3:x-amz-acl", "ACL"),
+];
+
+/// Block Public Access settings of the bucket, summarised for display.
+pub async fn public_access_summary(client: &Client, bucket: &str) -> Result<String, String> {
+    match client.get_public_access_block().bucket(bucket).send().await {
+        Ok(out) => Ok(out
+            .public_access_block_configuration()
+            .map(|c| {
+                describe_block([
+                    c.block_public_acls().unwrap_or(false),
+                    c.ignore_public_acls().unwrap_or(false),
+                    c.block_public_policy().unwrap_or(false),
+                    c.restrict_public_buckets().unwrap_or(false),
+                ])
+            })
+            .unwrap_or_else(|| describe_block([false; 4]))),
+        Err(e) if e.as_service_error().and_then(|s| s.meta().code()) == Some("NoSuchPublicAccessBlockConfiguration") => {
+            Ok(describe_block([false; 4]))
+        }
+        Err(e) => Err(format!("Không đọc được Block Public Access của {}: {}", bucket, e)),
+    }
+}
+
+/// `[BlockPublicAcls, IgnorePublicAcls, BlockPublicPolicy, RestrictPublicBuckets]` as text.
+pub fn describe_block(flags: [bool; 4]) -> String {
+    const NAMES: [&str; 4] = ["BlockPublicAcls", "IgnorePublicAcls", "BlockPublicPolicy", "RestrictPublicBuckets"];
+    if flags.iter().all(|f| *f) {
+        return "chặn hoàn toàn truy cập public".to_string();
+    }
+    let on: Vec<&str> = NAMES.iter().zip(flags).filter(|(_, f)| *f).map(|(name, _)| *name).collect();
+    if on.is_empty() {
+        "không chặn ở mức bucket (có thể bị chặn ở mức account)".to_string()
+    } else {
+        format!("chặn một phần: {}", on.join(", "))
+    }
+}
+
+fn as_list(value: &Value) -> Vec<&str> {
+    match value {
+        Value::String(s) => vec![s.as_str()],
+        Value::Array(items) => items.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn covers_put_object(statement: &Value) -> bool {
+    statement.get("Action").map(as_list).unwrap_or_default().iter().any(|action| {
+        let action = action.to_ascii_lowercase();
+        action == "*" || action == "s3:*" || action == "s3:putobject" || action == "s3:put*"
+    })
+}
+
+/// Whether the statement applies to every caller. One limited to certain principals (or
+/// excluding some with NotPrincipal) may well not apply to us, so it isn't reported.
+fn applies_to_everyone(statement: &Value) -> bool {
+    if statement.get("NotPrincipal").is_some() {
+        return false;
+    }
+    match statement.get("Principal") {
+        Some(Value::String(s)) => s == "*",
+        Some(Value::Object(principals)) => principals.get("AWS").is_some_and(|aws| as_list(aws).contains(&"*")),
+        _ => false,
+    }
+}
+
+/// Whether a Resource pattern can match an object key under one of `prefixes` in `bucket`.
+/// Only the literal part before the first wildcard is compared, so this errs towards a match.
+fn covers_destination(statement: &Value, bucket: &str, prefixes: &[String]) -> bool {
+    if statement.get("NotResource").is_some() {
+        return false;
+    }
+    statement.get("Resource").map(as_list).unwrap_or_default().iter().any(|resource| {
+        if *resource == "*" {
+            return true;
+        }
+        // arn:<partition>:s3:::<bucket>/<key pattern>
+        let Some(object) = resource.splitn(6, ':').nth(5) else { return false };
+        let literal = &object[..object.find(['*', '?']).unwrap_or(object.len())];
+        prefixes.iter().any(|prefix| {
+            let dest = format!("{}/{}", bucket, prefix);
+            dest.starts_with(literal) || literal.starts_with(&dest)
+        })
+    })
+}
+
+/// Whether one condition is true for a request that sends none of [`HEADER_KEYS`]. `None` when
+/// the key is something else and the outcome can't be known here.
+fn holds_without_header(operator: &str, key: &str, values: &Value) -> Option<bool> {
+    if !HEADER_KEYS.iter().any(|(k, _)| k.eq_ignore_ascii_case(key)) {
+        return None;
+    }
+    let operator = operator.trim_end_matches("IfExists");
+    Some(match operator {
+        // Null: "true" means the key is absent, which it is
+        "Null" => as_list(values).contains(&"true"),
+        "StringNotEquals" | "StringNotLike" | "StringNotEqualsIgnoreCase" => true,
+        _ => false,
+    })
+}
+
+/// Guidance for each Deny statement that would reject our uploads under `prefixes` of `bucket`.
+/// Statements that also depend on other conditions (source IP, VPC endpoint, ...) or on who
+/// sends the request are left out, since they may not apply.
+pub fn policy_conflicts(policy: &str, bucket: &str, prefixes: &[String]) -> Result<Vec<String>, String> {
+    let policy: Value = serde_json::from_str(policy).map_err(|e| format!("Bucket policy không phải JSON hợp lệ: {}", e))?;
+    let statements = match policy.get("Statement") {
+        Some(Value::Array(items)) => items.clone(),
+        Some(single) => vec![single.clone()],
+        None => Vec::new(),
+    };
+    let mut conflicts = Vec::new();
+    for statement in &statements {
+        if statement.get("Effect").and_then(Value::as_str) != Some("Deny")
+            || !covers_put_object(statement)
+            || !applies_to_everyone(statement)
+            || !covers_destination(statement, bucket, prefixes)
+        {
+            continue;
+        }
+        let Some(Value::Object(conditions)) = statement.get("Condition") else { continue };
+        let mut required = Vec::new();
+        let mut denied = true;
+        for (operator, keys) in conditions {
+            let Value::Object(keys) = keys else { continue };
+            for (key, values) in keys {
+                match holds_without_header(operator, key, values) {
+                    Some(true) => required.extend(HEADER_KEYS.iter().filter(|(k, _)| k.eq_ignore_ascii_case(key)).map(|(_, l)| *l)),
+                    _ => denied = false,
+                }
+            }
+        }
+        if denied && !required.is_empty() {
+            let sid = statement.get("Sid").and_then(Value::as_str).unwrap_or("(không Sid)");
+            conflicts.push(format!(
+                "Bucket policy '{}' từ chối upload không có header {}. Bỏ điều kiện này, hoặc dùng default encryption của bucket thay cho yêu cầu header",
+                sid,
+                required.join(", ")
+            ));
+        }
+    }
+    Ok(conflicts)
+}
+
+/// Reads the bucket policy and returns the statements that would reject our uploads.
+pub async fn check_policy(client: &Client, bucket: &str, prefixes: &[String]) -> Result<Vec<String>, String> {
+    match client.get_bucket_policy().bucket(bucket).send().await {
+        Ok(out) => policy_conflicts(out.policy().unwrap_or("{}"), bucket, prefixes),
+        Err(e) if e.as_service_error().and_then(|s| s.meta().code()) == Some("NoSuchBucketPolicy") => Ok(Vec::new()),
+        Err(e) => Err(format!("Không đọc được bucket policy của {}: {}", bucket, e)),
+    }
+}
+
+/// What to do about an AccessDenied, from the wording of S3's error message.
+pub fn denied_hint(message: &str) -> Option<&'static str> {
+    let message = message.to_ascii_lowercase();
+    if message.contains("explicit deny in a resource-based policy") {
+        Some("bucket policy chặn request này, kiểm tra các statement Deny (mã hóa, ACL, IP nguồn)")
+    } else if message.contains("explicit deny in a service control policy") {
+        Some("Service Control Policy của AWS Organizations chặn request, liên hệ quản trị account")
+    } else if message.contains("no identity-based policy allows") {
+        Some("IAM user/role thiếu quyền s3:PutObject trên bucket này")
+    } else if message.contains("kms") {
+        Some("thiếu quyền kms:GenerateDataKey trên KMS key mã hóa mặc định của bucket")
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_conflicts() {
+        let policy = r#"{
+            "Version": "2012-10-17",
+            "Statement": [
+                {"Sid": "RequireSSE", "Effect": "Deny", "Principal": "*", "Action": "s3:PutObject",
+                 "Resource": "arn:aws:s3:::b/*",
+                 "Condition": {"StringNotEquals": {"s3:x-amz-server-side-encryption": "aws:kms"}}},
+                {"Sid": "OwnerAcl", "Effect": "Deny", "Principal": "*", "Action": ["s3:PutObject"],
+                 "Resource": "arn:aws:s3:::b/*",
+                 "Condition": {"StringNotEquals": {"s3:x-amz-acl": "bucket-owner-full-control"},
+                               "IpAddress": {"aws:SourceIp": "10.0.0.0/8"}}},
+                {"Sid": "NoPublicRead", "Effect": "Deny", "Principal": "*", "Action": "s3:*",
+                 "Resource": "arn:aws:s3:::b/*",
+                 "Condition": {"StringEquals": {"s3:x-amz-acl": "public-read"}}},
+                {"Sid": "HttpsOnly", "Effect": "Deny", "Principal": "*", "Action": "s3:*",
+                 "Resource": "arn:aws:s3:::b/*",
+                 "Condition": {"Bool": {"aws:SecureTransport": "false"}}},
+                {"Sid": "RequireHeader", "Effect": "Deny", "Principal": "*", "Action": "s3:Put*",
+                 "Resource": "arn:aws:s3:::b/*",
+                 "Condition": {"Null": {"s3:x-amz-server-side-encryption": "true"}}},
+                {"Sid": "OtherPrefix", "Effect": "Deny", "Principal": "*", "Action": "s3:PutObject",
+                 "Resource": "arn:aws:s3:::b/private/*",
+                 "Condition": {"Null": {"s3:x-amz-acl": "true"}}},
+                {"Sid": "OneRole", "Effect": "Deny", "Principal": {"AWS": "arn:aws:iam::111122223333:role/ci"},
+                 "Action": "s3:PutObject", "Resource": "arn:aws:s3:::b/*",
+                 "Condition": {"Null": {"s3:x-amz-acl": "true"}}},
+                {"Sid": "AllButAdmin", "Effect": "Deny", "NotPrincipal": {"AWS": "arn:aws:iam::111122223333:root"},
+                 "Action": "s3:PutObject", "Resource": "arn:aws:s3:::b/*",
+                 "Condition": {"Null": {"s3:x-amz-acl": "true"}}}
+            ]
+        }"#;
+        let site = ["site/".to_string()];
+        let conflicts = policy_conflicts(policy, "b", &site).unwrap();
+        assert_eq!(conflicts.len(), 2);
+        assert!(conflicts[0].contains("'RequireSSE'") && conflicts[0].contains("mã hóa server-side"));
+        assert!(conflicts[1].contains("'RequireHeader'"));
+        let conflicts = policy_conflicts(policy, "b", &["private/docs/".to_string()]).unwrap();
+        assert!(conflicts[2].contains("'OtherPrefix'"));
+        // A whole-bucket sync can hit the prefix-limited statement too
+        assert_eq!(policy_conflicts(policy, "b", &[String::new()]).unwrap().len(), 3);
+        assert!(policy_conflicts(policy, "other", &site).unwrap().is_empty());
+        assert!(policy_conflicts("not json", "b", &site).is_err());
+
+        assert_eq!(describe_block([true; 4]), "chặn hoàn toàn truy cập public");
+        assert_eq!(describe_block([true, false, true, false]), "chặn một phần: BlockPublicAcls, BlockPublicPolicy");
+        assert!(denied_hint("... with an explicit deny in a resource-based policy").unwrap().contains("bucket policy"));
+        assert_eq!(denied_hint("Access Denied"), None);
+    }
+}
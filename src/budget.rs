@@ -0,0 +1,95 @@
+//! Upload budget per sync run, so a metered connection isn't drained by an unexpectedly big sync.
+//!
+//! When the planned upload is over budget, the confirmation dialog shows the overage. The user
+//! either allows it for that one run or lets the sync trim the file list by priority: mapping
+//! order first (rows higher in the list win), then walk order within a mapping. Append-only runs
+//! count only the unshipped tail of each file; archive runs keep or drop whole folders, sized
+//! before compression.
+
+use once_cell::sync::Lazy;
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+/// Sync sessions that may go over the budget, approved in the confirmation dialog. A session is
+/// the main sync plus its replica uploads, identified by their scheduler job id.
+static APPROVED: Lazy<Mutex<HashSet<u64>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+/// Lets every upload of `session` go over budget until [`end_session`].
+pub fn approve_overage(session: u64) {
+    APPROVED.lock().unwrap().insert(session);
+}
+
+pub fn is_approved(session: u64) -> bool {
+    APPROVED.lock().unwrap().contains(&session)
+}
+
+/// Drops the approval of a finished session.
+pub fn end_session(session: u64) {
+    APPROVED.lock().unwrap().remove(&session);
+}
+
+/// Converts the configured budget in MB to bytes; 0 means no budget.
+pub fn budget_bytes(budget_mb: u64) -> u64 {
+    budget_mb.saturating_mul(1024 * 1024)
+}
+
+/// The overage line shown in the confirmation dialog, or `None` when the plan fits.
+pub fn overage_text(planned: u64, budget: u64) -> Option<String> {
+    (budget > 0 && planned > budget).then(|| {
+        format!(
+            "Vượt ngân sách upload {} (kế hoạch {}, giới hạn {})",
+            crate::utils::format_size(planned - budget),
+            crate::utils::format_size(planned),
+            crate::utils::format_size(budget)
+        )
+    })
+}
+
+/// Keeps files in priority order while they fit in `budget` bytes. A file that doesn't fit is
+/// dropped, but later, smaller files may still fill the remaining room. Returns the kept files
+/// and the dropped ones with their total size.
+pub fn trim_to_budget<T>(files: Vec<T>, budget: u64, size_of: impl Fn(&T) -> u64) -> (Vec<T>, Vec<T>, u64) {
+    let mut remaining = budget;
+    let mut kept = Vec::new();
+    let mut dropped = Vec::new();
+    let mut dropped_bytes = 0u64;
+    for file in files {
+        let size = size_of(&file);
+        if size <= remaining {
+            remaining -= size;
+            kept.push(file);
+        } else {
+            dropped_bytes += size;
+            dropped.push(file);
+        }
+    }
+    (kept, dropped, dropped_bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trim_keeps_priority_order() {
+        let files = vec![("a", 40), ("b", 50), ("c", 10), ("d", 5)];
+        let (kept, dropped, dropped_bytes) = trim_to_budget(files, 55, |f| f.1);
+        assert_eq!(kept, vec![("a", 40), ("c", 10), ("d", 5)]);
+        assert_eq!(dropped, vec![("b", 50)]);
+        assert_eq!(dropped_bytes, 50);
+    }
+
+    #[test]
+    fn test_overage_and_approval() {
+        assert_eq!(overage_text(10, 0), None);
+        assert_eq!(overage_text(10, 10), None);
+        assert!(overage_text(3 * 1024 * 1024, budget_bytes(1)).unwrap().contains("2.0 MB"));
+
+        approve_overage(u64::MAX);
+        assert!(is_approved(u64::MAX));
+        assert!(is_approved(u64::MAX));
+        assert!(!is_approved(u64::MAX - 1));
+        end_session(u64::MAX);
+        assert!(!is_approved(u64::MAX));
+    }
+}
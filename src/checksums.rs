@@ -0,0 +1,49 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::dedup::HashCache;
+
+/// Builds key → SHA-256 for the files of a sync. Keys copied server-side from a duplicate
+/// (`copies`: source key, copy key) get the hash of their source. Blocking: hashes files the
+/// cache hasn't seen yet.
+pub fn build_manifest(
+    files: &[(PathBuf, String)],
+    copies: &[(String, String)],
+    cache: &HashCache,
+) -> Result<BTreeMap<String, String>, String> {
+    let mut manifest = BTreeMap::new();
+    for (path, key) in files {
+        let hash = cache.sha256(path).map_err(|e| format!("Lỗi tính hash {}: {}", path.display(), e))?;
+        manifest.insert(key.clone(), hash);
+    }
+    for (source, copy) in copies {
+        if let Some(hash) = manifest.get(source).cloned() {
+            manifest.insert(copy.clone(), hash);
+        }
+    }
+    Ok(manifest)
+}
+
+/// `sha256sum` format (`<hash>  <key>` per line, sorted by key), so consumers can check a
+/// downloaded copy with `sha256sum -c manifest.sha256`.
+pub fn format_manifest(manifest: &BTreeMap<String, String>) -> String {
+    manifest.iter().map(|(key, hash)| format!("{}  {}\n", hash, key)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_manifest() {
+        let path = std::env::temp_dir().join(format!("s3sync_checksums_{}.txt", std::process::id()));
+        std::fs::write(&path, "hello").unwrap();
+        let files = vec![(path.clone(), "site/a.txt".to_string())];
+        let copies = vec![("site/a.txt".to_string(), "site/copy.txt".to_string())];
+        let manifest = build_manifest(&files, &copies, &HashCache::default()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let hello = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+        assert_eq!(format_manifest(&manifest), format!("{hello}  site/a.txt\n{hello}  site/copy.txt\n"));
+    }
+}
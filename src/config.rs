@@ -1,8 +1,25 @@
+use once_cell::sync::{Lazy, OnceCell};
 use serde::{Deserialize, Serialize};
-use tracing::warn;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use tracing::{info, warn};
 
 const APP_NAME: &str = "S3SyncTool";
 
+/// Command-line flag for portable mode.
+pub const PORTABLE_FLAG: &str = "--portable";
+/// A file with this name next to the executable turns portable mode on without the flag.
+pub const PORTABLE_FLAG_FILE: &str = "portable.flag";
+/// In portable mode, config and state files live in this folder next to the executable...
+const PORTABLE_DATA_DIR: &str = "data";
+/// ...and new configs log here. Stored relative, so it survives a changed drive letter.
+const PORTABLE_LOG_DIR: &str = "logs";
+
+/// Folder of the executable when running in portable mode.
+static PORTABLE_ROOT: OnceCell<PathBuf> = OnceCell::new();
+/// Set when the config file exists but couldn't be read, so saves don't replace it unbacked.
+static UNREADABLE: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct FilterConfig {
     #[serde(default = "default_exclude_patterns")]
@@ -13,6 +30,17 @@ pub struct FilterConfig {
     pub max_file_size: u64,
     #[serde(default = "default_true")]
     pub enable_filtering: bool,
+    /// MIME rules (`video/*`, `video`, `image/png`) matched against the type from the file extension
+    #[serde(default)]
+    pub exclude_mime: Vec<String>,
+    #[serde(default)]
+    pub include_mime: Vec<String>,
+    /// Skip dotfiles, dot-folders and files with the Windows hidden or system attribute
+    #[serde(default)]
+    pub skip_hidden: bool,
+    /// Hidden file or folder names still uploaded when `skip_hidden` is on
+    #[serde(default = "default_hidden_allow")]
+    pub hidden_allow: Vec<String>,
 }
 
 fn default_exclude_patterns() -> Vec<String> {
@@ -51,6 +79,10 @@ fn default_include_patterns() -> Vec<String> {
     ]
 }
 
+fn default_hidden_allow() -> Vec<String> {
+    vec![".well-known".to_string()]
+}
+
 fn default_max_file_size() -> u64 {
     100 * 1024 * 1024
 }
@@ -67,6 +99,15 @@ fn default_buckets() -> Vec<String> {
     ]
 }
 
+fn default_protected_buckets() -> Vec<String> {
+    default_buckets().into_iter().filter(|b| looks_like_production(b)).collect()
+}
+
+/// Production-looking bucket names are protected unless the user unprotects them.
+pub fn looks_like_production(bucket: &str) -> bool {
+    bucket.to_lowercase().contains("prod")
+}
+
 impl Default for FilterConfig {
     fn default() -> Self {
         Self {
@@ -74,12 +115,630 @@ impl Default for FilterConfig {
             include_patterns: default_include_patterns(),
             max_file_size: default_max_file_size(),
             enable_filtering: default_true(),
+            exclude_mime: Vec::new(),
+            include_mime: Vec::new(),
+            skip_hidden: false,
+            hidden_allow: default_hidden_allow(),
+        }
+    }
+}
+
+/// How byte-identical files uploaded under multiple keys are handled.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum DedupMode {
+    /// No duplicate scan.
+    #[default]
+    Off,
+    /// Scan and report duplicates, upload everything.
+    Report,
+    /// Upload only the first copy, skip the other keys.
+    Skip,
+    /// Upload the first copy, then server-side copy it to the other keys.
+    Copy,
+}
+
+impl DedupMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DedupMode::Off => "off",
+            DedupMode::Report => "report",
+            DedupMode::Skip => "skip",
+            DedupMode::Copy => "copy",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "off" => Some(DedupMode::Off),
+            "report" => Some(DedupMode::Report),
+            "skip" => Some(DedupMode::Skip),
+            "copy" => Some(DedupMode::Copy),
+            _ => None,
+        }
+    }
+}
+
+/// Date partition inserted between a mapping's S3 prefix and each file's path
+/// (`exports/2024/05/01/sales.csv`), e.g. for Athena-queryable layouts.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum DatePartition {
+    #[default]
+    Off,
+    /// Date the sync started.
+    Sync,
+    /// Each file's last-modified date.
+    Mtime,
+}
+
+impl DatePartition {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DatePartition::Off => "off",
+            DatePartition::Sync => "sync",
+            DatePartition::Mtime => "mtime",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "off" => Some(DatePartition::Off),
+            "sync" => Some(DatePartition::Sync),
+            "mtime" => Some(DatePartition::Mtime),
+            _ => None,
+        }
+    }
+}
+
+/// What to do with online-only cloud placeholders (OneDrive/Dropbox files not stored locally).
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PlaceholderPolicy {
+    /// Leave them out of the sync.
+    #[default]
+    Skip,
+    /// Download them through the cloud client first, then upload.
+    Hydrate,
+}
+
+impl PlaceholderPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PlaceholderPolicy::Skip => "skip",
+            PlaceholderPolicy::Hydrate => "hydrate",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "skip" => Some(PlaceholderPolicy::Skip),
+            "hydrate" => Some(PlaceholderPolicy::Hydrate),
+            _ => None,
+        }
+    }
+}
+
+/// What a sync does with sparse files (VM disks, database files), which would otherwise upload
+/// their full logical size.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SparsePolicy {
+    /// Upload them and list them in the sync warnings.
+    #[default]
+    Warn,
+    /// Leave them out of the sync.
+    Skip,
+}
+
+impl SparsePolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SparsePolicy::Warn => "warn",
+            SparsePolicy::Skip => "skip",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "warn" => Some(SparsePolicy::Warn),
+            "skip" => Some(SparsePolicy::Skip),
+            _ => None,
+        }
+    }
+}
+
+/// How sync sessions are split into log files in the log folder.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFileMode {
+    /// One `sync_log_DD_MM_YYYY.log` per day shared by every session.
+    #[default]
+    Daily,
+    /// One `sync_YYYYMMDD_HHMM_<bucket>.log` per session.
+    Session,
+}
+
+impl LogFileMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LogFileMode::Daily => "daily",
+            LogFileMode::Session => "session",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "daily" => Some(LogFileMode::Daily),
+            "session" => Some(LogFileMode::Session),
+            _ => None,
+        }
+    }
+}
+
+/// Which side wins when a file changed both locally and remotely in two-way sync.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictPolicy {
+    /// The side with the later modification time wins.
+    #[default]
+    Newest,
+    Local,
+    Remote,
+    /// Leave conflicts unresolved until the user picks a side.
+    Ask,
+}
+
+impl ConflictPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConflictPolicy::Newest => "newest",
+            ConflictPolicy::Local => "local",
+            ConflictPolicy::Remote => "remote",
+            ConflictPolicy::Ask => "ask",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "newest" => Some(ConflictPolicy::Newest),
+            "local" => Some(ConflictPolicy::Local),
+            "remote" => Some(ConflictPolicy::Remote),
+            "ask" => Some(ConflictPolicy::Ask),
+            _ => None,
+        }
+    }
+}
+
+/// Naming rules every computed S3 key must satisfy before a sync starts.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct KeyPolicy {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Maximum key length in bytes (S3 allows up to 1024).
+    #[serde(default = "default_max_key_length")]
+    pub max_key_length: usize,
+    /// Characters that must not appear in a key.
+    #[serde(default)]
+    pub forbidden_chars: String,
+    /// Regex every key must match, e.g. `^(assets|static)/`. Empty disables the check.
+    #[serde(default)]
+    pub required_prefix_regex: String,
+}
+
+fn default_max_key_length() -> usize {
+    1024
+}
+
+impl Default for KeyPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_key_length: default_max_key_length(),
+            forbidden_chars: String::new(),
+            required_prefix_regex: String::new(),
+        }
+    }
+}
+
+/// Extra content headers for uploaded keys matching a glob pattern.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct HeaderRule {
+    pub pattern: String,
+    /// `Content-Language` value, e.g. `vi`. Empty leaves the header unset.
+    #[serde(default)]
+    pub content_language: String,
+    /// Charset appended to the content type, e.g. `utf-8`. Empty leaves it unchanged.
+    #[serde(default)]
+    pub charset: String,
+}
+
+/// `Content-Disposition` set on uploaded keys matching a glob pattern.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct DispositionRule {
+    pub pattern: String,
+    /// e.g. `attachment; filename={name}`; `{name}` is replaced by the key's file name.
+    pub disposition: String,
+}
+
+/// Upload behavior options applied by the sync engine.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SyncOptions {
+    #[serde(default)]
+    pub dedup_mode: DedupMode,
+    #[serde(default)]
+    pub key_policy: KeyPolicy,
+    /// Back up existing objects into `.trash/<timestamp>/` before overwriting them.
+    #[serde(default)]
+    pub trash_overwritten: bool,
+    #[serde(default)]
+    pub conflict_policy: ConflictPolicy,
+    /// How many queued jobs run at the same time (1 = one after another).
+    #[serde(default = "default_queue_parallel")]
+    pub queue_parallel: usize,
+    /// Checked in order; the first rule setting a value wins.
+    #[serde(default)]
+    pub header_rules: Vec<HeaderRule>,
+    /// Checked in order; the first matching rule wins.
+    #[serde(default)]
+    pub disposition_rules: Vec<DispositionRule>,
+    /// Mapping file (old path → new URL) turned into website redirect objects on each sync.
+    #[serde(default)]
+    pub redirects_file: String,
+    /// `URL [key]` lines fetched after each sync to confirm the new content is live.
+    #[serde(default)]
+    pub smoke_test_urls: Vec<String>,
+    /// Keys matching these patterns are uploaded last, after all other files succeeded.
+    #[serde(default)]
+    pub entry_patterns: Vec<String>,
+    /// Upload into `.staging/<timestamp>/`, verify, then server-side copy to the live keys.
+    #[serde(default)]
+    pub staged_deploy: bool,
+    /// Create zero-byte `folder/` keys for empty local directories.
+    #[serde(default)]
+    pub create_folder_markers: bool,
+    #[serde(default)]
+    pub placeholder_policy: PlaceholderPolicy,
+    #[serde(default)]
+    pub sparse_policy: SparsePolicy,
+    /// Files at least this large (MB) upload in the large-file lane.
+    #[serde(default = "default_large_file_threshold_mb")]
+    pub large_file_threshold_mb: u64,
+    /// Upload slots reserved for large files, on top of the shared slots (0 = no separate lane).
+    #[serde(default = "default_large_file_lane")]
+    pub large_file_lane: usize,
+    /// Keys matching these patterns get a content hash in their name (`app.js` → `app.3f9ab2.js`).
+    #[serde(default)]
+    pub fingerprint_patterns: Vec<String>,
+    /// Key of the JSON manifest mapping original → fingerprinted keys.
+    #[serde(default = "default_fingerprint_manifest")]
+    pub fingerprint_manifest: String,
+    /// Key of the `sha256sum`-style checksum list uploaded after each sync; empty disables it.
+    #[serde(default)]
+    pub checksum_manifest: String,
+    /// Delete the staging copy after a successful promote.
+    #[serde(default = "default_true")]
+    pub staged_cleanup: bool,
+    /// Encrypt file contents with AES-256-GCM before upload. The passphrase is never saved.
+    /// Files are encrypted in memory, so ones over 1 GiB are refused.
+    #[serde(default)]
+    pub client_side_encryption: bool,
+    /// Upload each folder as one dated .tar.gz archive instead of mirroring its files.
+    #[serde(default)]
+    pub archive_upload: bool,
+    /// Log shipping: upload only what was appended to each file since the last sync, as new dated objects.
+    #[serde(default)]
+    pub append_only: bool,
+    #[serde(default)]
+    pub date_partition: DatePartition,
+    /// Detect the Content-Type of extensionless/unknown files from their first bytes.
+    #[serde(default)]
+    pub sniff_mime: bool,
+    /// Extra buckets (possibly in other regions) that receive the same files after the main bucket.
+    #[serde(default)]
+    pub replica_targets: Vec<ReplicaTarget>,
+    #[serde(default)]
+    pub replica_mode: ReplicaMode,
+    /// Most a single sync may upload, in MB, unless the user allows the overage (0 = no limit).
+    #[serde(default)]
+    pub session_budget_mb: u64,
+    /// Folder levels below a mapping root the scan may enter before aborting (0 = no limit).
+    #[serde(default)]
+    pub scan_max_depth: usize,
+    /// Files per mapping the scan may find before aborting (0 = no limit).
+    #[serde(default = "default_scan_max_files")]
+    pub scan_max_files: u64,
+    /// Also store each file's creation time as `x-amz-meta-ctime`; the mtime is always stored.
+    #[serde(default)]
+    pub store_ctime: bool,
+    /// Hours the job queue may upload, `HH:MM-HH:MM` (may wrap midnight); empty = any time.
+    #[serde(default)]
+    pub sync_window: String,
+    /// Pause the job queue while the connection is metered.
+    #[serde(default)]
+    pub pause_on_metered: bool,
+    /// Generate an `index.html` listing in every uploaded folder that has none of its own.
+    #[serde(default)]
+    pub dir_index: bool,
+    /// HTML template file for those pages; empty uses the built-in one.
+    #[serde(default)]
+    pub dir_index_template: String,
+    /// Copy-mode replicas with more objects than this use one S3 Batch Operations job
+    /// (0 = always copy from the app).
+    #[serde(default)]
+    pub batch_threshold: usize,
+    /// IAM role the Batch Operations job runs as; required for batch copies.
+    #[serde(default)]
+    pub batch_role_arn: String,
+    /// Private bucket in the main bucket's region for Batch Operations manifests; required for
+    /// batch copies, since the main bucket is often public.
+    #[serde(default)]
+    pub batch_manifest_bucket: String,
+    /// Local folders whose files upload directly under the mapping prefix, without subfolders.
+    #[serde(default)]
+    pub flatten_paths: Vec<String>,
+    /// After a sync, HeadObject this many random uploads and compare their headers (0 = off).
+    #[serde(default)]
+    pub verify_sample: usize,
+}
+
+/// A bucket that receives a copy of every sync, written as `bucket@region` in the UI.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ReplicaTarget {
+    pub bucket: String,
+    pub region: String,
+}
+
+impl ReplicaTarget {
+    /// Parses `bucket@region, bucket2@region2`. A target without `@region` uses `default_region`.
+    pub fn parse_list(text: &str, default_region: &str) -> Result<Vec<ReplicaTarget>, String> {
+        text.split([',', '\n'])
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .map(|t| {
+                let (bucket, region) = t.split_once('@').unwrap_or((t, default_region));
+                let (bucket, region) = (bucket.trim(), region.trim());
+                if bucket.is_empty() || region.is_empty() || bucket.contains(char::is_whitespace) {
+                    return Err(format!("Target không hợp lệ: '{}' (dạng bucket@region)", t));
+                }
+                Ok(ReplicaTarget { bucket: bucket.to_string(), region: region.to_string() })
+            })
+            .collect()
+    }
+}
+
+impl std::fmt::Display for ReplicaTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}@{}", self.bucket, self.region)
+    }
+}
+
+/// How replica targets get their files.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ReplicaMode {
+    /// Upload the local files again to every target.
+    #[default]
+    Upload,
+    /// Server-side copy the keys uploaded to the main bucket (no second upload from this machine).
+    Copy,
+}
+
+fn default_queue_parallel() -> usize {
+    1
+}
+
+fn default_large_file_threshold_mb() -> u64 {
+    100
+}
+
+fn default_large_file_lane() -> usize {
+    5
+}
+
+fn default_scan_max_files() -> u64 {
+    500_000
+}
+
+fn default_fingerprint_manifest() -> String {
+    "asset-manifest.json".to_string()
+}
+
+impl Default for SyncOptions {
+    fn default() -> Self {
+        Self {
+            dedup_mode: DedupMode::default(),
+            key_policy: KeyPolicy::default(),
+            trash_overwritten: false,
+            conflict_policy: ConflictPolicy::default(),
+            queue_parallel: default_queue_parallel(),
+            header_rules: Vec::new(),
+            disposition_rules: Vec::new(),
+            redirects_file: String::new(),
+            smoke_test_urls: Vec::new(),
+            entry_patterns: Vec::new(),
+            staged_deploy: false,
+            staged_cleanup: true,
+            create_folder_markers: false,
+            placeholder_policy: PlaceholderPolicy::default(),
+            sparse_policy: SparsePolicy::default(),
+            large_file_threshold_mb: default_large_file_threshold_mb(),
+            large_file_lane: default_large_file_lane(),
+            fingerprint_patterns: Vec::new(),
+            fingerprint_manifest: default_fingerprint_manifest(),
+            client_side_encryption: false,
+            archive_upload: false,
+            append_only: false,
+            date_partition: DatePartition::default(),
+            sniff_mime: false,
+            checksum_manifest: String::new(),
+            replica_targets: Vec::new(),
+            replica_mode: ReplicaMode::default(),
+            session_budget_mb: 0,
+            scan_max_depth: 0,
+            scan_max_files: default_scan_max_files(),
+            store_ctime: false,
+            sync_window: String::new(),
+            dir_index: false,
+            dir_index_template: String::new(),
+            batch_threshold: 0,
+            batch_role_arn: String::new(),
+            batch_manifest_bucket: String::new(),
+            pause_on_metered: false,
+            flatten_paths: Vec::new(),
+            verify_sample: 0,
         }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Default)]
+/// SMTP settings for the post-sync email report. The password is not part of them; see
+/// [`crate::notify::smtp_password`].
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SmtpConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub host: String,
+    #[serde(default = "default_smtp_port")]
+    pub port: u16,
+    #[serde(default)]
+    pub username: String,
+    #[serde(default)]
+    pub from: String,
+    /// Comma-separated recipient list.
+    #[serde(default)]
+    pub to: String,
+    #[serde(default = "default_report_max_errors")]
+    pub max_errors: usize,
+}
+
+fn default_smtp_port() -> u16 {
+    587
+}
+
+fn default_report_max_errors() -> usize {
+    10
+}
+
+impl Default for SmtpConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            host: String::new(),
+            port: default_smtp_port(),
+            username: String::new(),
+            from: String::new(),
+            to: String::new(),
+            max_errors: default_report_max_errors(),
+        }
+    }
+}
+
+/// Defaults for restoring objects archived in Glacier / Deep Archive.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RestoreOptions {
+    /// Standard, Bulk or Expedited.
+    pub tier: String,
+    /// How long the restored copy stays readable.
+    pub days: u32,
+}
+
+impl Default for RestoreOptions {
+    fn default() -> Self {
+        Self { tier: "Standard".to_string(), days: 7 }
+    }
+}
+
+/// Performance and network settings from the Settings window. Read through [`settings`],
+/// so saved changes apply to the next request without restarting.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct AdvancedSettings {
+    /// Upload slots shared by all running jobs.
+    pub concurrency: usize,
+    /// How long the bucket's top-level prefix listing is cached.
+    pub cache_ttl_secs: u64,
+    /// Attempts per S3 request, the first one included.
+    pub max_attempts: u32,
+    pub connect_timeout_secs: u64,
+    /// Limit for a whole S3 request including retries; 0 = no limit.
+    pub operation_timeout_secs: u64,
+    /// Look for a newer release on GitHub at startup. Off unless the user turns it on.
+    pub check_updates: bool,
+    /// Look for the local folders on S3 when suggesting a mapping's path; off = base path or
+    /// the last local folders only.
+    pub smart_match: bool,
+}
+
+impl Default for AdvancedSettings {
+    fn default() -> Self {
+        // Picks up the environment variables these settings replace, so existing setups keep their values
+        fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
+            std::env::var(name).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+        }
+        Self {
+            concurrency: env_or("S3_SYNC_CONCURRENCY", 50usize).max(1),
+            cache_ttl_secs: env_or("S3_CACHE_TTL_SECS", 300),
+            max_attempts: 3,
+            connect_timeout_secs: 10,
+            operation_timeout_secs: 0,
+            check_updates: false,
+            smart_match: true,
+        }
+    }
+}
+
+impl AdvancedSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=500).contains(&self.concurrency) {
+            return Err("Số luồng upload phải từ 1 đến 500".to_string());
+        }
+        if !(1..=10).contains(&self.max_attempts) {
+            return Err("Số lần thử phải từ 1 đến 10".to_string());
+        }
+        if !(1..=300).contains(&self.connect_timeout_secs) {
+            return Err("Timeout kết nối phải từ 1 đến 300 giây".to_string());
+        }
+        Ok(())
+    }
+}
+
+static SETTINGS: Lazy<RwLock<AdvancedSettings>> = Lazy::new(|| RwLock::new(load_config().settings));
+
+/// Settings currently in effect.
+pub fn settings() -> AdvancedSettings {
+    *SETTINGS.read().unwrap()
+}
+
+/// Makes saved settings take effect for everything started from now on.
+pub fn apply_settings(settings: AdvancedSettings) {
+    *SETTINGS.write().unwrap() = settings;
+}
+
+/// Unsaved filter edits in effect while live filtering is on. Never written to disk.
+static FILTER_OVERRIDE: Lazy<RwLock<Option<FilterConfig>>> = Lazy::new(|| RwLock::new(None));
+
+/// Makes unsaved filter edits apply to previews and syncs started from now on; `None` goes
+/// back to the saved filter.
+pub fn set_filter_override(filter: Option<FilterConfig>) {
+    *FILTER_OVERRIDE.write().unwrap() = filter;
+}
+
+/// The unsaved filter edits in effect, if live filtering is on.
+pub fn filter_override() -> Option<FilterConfig> {
+    FILTER_OVERRIDE.read().unwrap().clone()
+}
+
+/// The filter to apply: the live edits if there are any, else the saved `filter`.
+pub fn effective_filter(saved: FilterConfig) -> FilterConfig {
+    filter_override().unwrap_or(saved)
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct AppConfig {
+    /// Schema version the file was written with; files from before versioning have none (0).
+    #[serde(default)]
+    pub config_version: u32,
     #[serde(default)]
     pub log_path: String,
     #[serde(default)]
@@ -94,6 +753,149 @@ pub struct AppConfig {
     pub selected_bucket: String,
     #[serde(default = "default_region")]
     pub selected_region: String,
+    #[serde(default = "default_log_level")]
+    pub log_level: String,
+    #[serde(default)]
+    pub log_file_mode: LogFileMode,
+    #[serde(default)]
+    pub smtp: SmtpConfig,
+    /// Prometheus textfile written after each sync; empty disables it.
+    #[serde(default)]
+    pub metrics_path: String,
+    #[serde(default)]
+    pub sync_options: SyncOptions,
+    /// Recently synced local folders, most recent first.
+    #[serde(default)]
+    pub recent_folders: Vec<String>,
+    /// Buckets that need the bucket name typed before every sync.
+    #[serde(default = "default_protected_buckets")]
+    pub protected_buckets: Vec<String>,
+    /// Last check of each bucket from the bucket manager, by bucket name.
+    #[serde(default)]
+    pub bucket_checks: std::collections::BTreeMap<String, BucketCheck>,
+    /// Prefix and key template applied to paths computed for a bucket, by bucket name.
+    #[serde(default)]
+    pub bucket_defaults: std::collections::BTreeMap<String, BucketDefaults>,
+    #[serde(default)]
+    pub restore: RestoreOptions,
+    #[serde(default)]
+    pub settings: AdvancedSettings,
+    #[serde(default)]
+    pub prefix_rules: PrefixRules,
+    /// The first-run wizard was finished or skipped.
+    #[serde(default)]
+    pub onboarding_done: bool,
+}
+
+/// Applied to the S3 paths computed for folders added while this bucket is selected.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct BucketDefaults {
+    /// Put in front of every computed path, e.g. `public/`.
+    #[serde(default)]
+    pub base_prefix: String,
+    /// Empty = the computed path as is; see [`crate::utils::apply_bucket_defaults`].
+    #[serde(default)]
+    pub key_template: String,
+    /// Trailing local folders used when the path is guessed: 0 = automatic (up to 3),
+    /// 1 = folder name only, up to [`MAX_PREVIEW_DEPTH`].
+    #[serde(default)]
+    pub preview_depth: u8,
+}
+
+/// Deepest preview prefix that can be configured per bucket.
+pub const MAX_PREVIEW_DEPTH: u8 = 5;
+
+impl BucketDefaults {
+    pub fn is_empty(&self) -> bool {
+        self.base_prefix.trim_matches('/').is_empty() && self.key_template.trim().is_empty() && self.preview_depth == 0
+    }
+}
+
+/// How a local path is turned into a suggested S3 prefix.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct PrefixRules {
+    /// Leading folders dropped from local paths (case-insensitive). Only the start of the path
+    /// is checked, so a project folder named `temp` further down is kept.
+    pub system_folders: Vec<String>,
+}
+
+impl Default for PrefixRules {
+    fn default() -> Self {
+        Self {
+            system_folders: ["users", "home", "desktop", "documents", "downloads", "appdata", "local", "temp", "admin"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+        }
+    }
+}
+
+/// What a `HeadBucket` from the bucket manager found.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BucketStatus {
+    Reachable,
+    AccessDenied,
+    NotFound,
+    Error,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct BucketCheck {
+    pub status: BucketStatus,
+    /// Region the bucket lives in; empty if S3 didn't say.
+    #[serde(default)]
+    pub region: String,
+    /// Unix seconds.
+    #[serde(default)]
+    pub checked_at: i64,
+}
+
+impl BucketCheck {
+    /// Badge for the bucket list. Relative to the selected region, so switching region
+    /// updates it without checking again.
+    pub fn badge(&self, selected_region: &str) -> &'static str {
+        let elsewhere = !self.region.is_empty() && self.region != selected_region;
+        match self.status {
+            BucketStatus::Reachable | BucketStatus::AccessDenied if elsewhere => "wrong_region",
+            BucketStatus::Reachable => "reachable",
+            BucketStatus::AccessDenied => "access_denied",
+            BucketStatus::NotFound => "not_found",
+            BucketStatus::Error => "error",
+        }
+    }
+}
+
+/// How many recent folders are remembered.
+const MAX_RECENT_FOLDERS: usize = 10;
+
+impl AppConfig {
+    /// Moves the synced folders to the front of the recent list. Files are ignored.
+    pub fn remember_recent_folders<'a>(&mut self, local_paths: impl IntoIterator<Item = &'a str>) {
+        for path in local_paths {
+            if !std::path::Path::new(path).is_dir() {
+                continue;
+            }
+            self.recent_folders.retain(|f| f != path);
+            self.recent_folders.insert(0, path.to_string());
+        }
+        self.recent_folders.truncate(MAX_RECENT_FOLDERS);
+    }
+
+    pub fn is_protected(&self, bucket: &str) -> bool {
+        self.protected_buckets.iter().any(|b| b == bucket)
+    }
+
+    /// Whether syncing to `bucket` needs its name typed: protected buckets, and production-looking
+    /// names typed in directly that aren't in the bucket list to be unprotected from.
+    pub fn requires_typed_confirmation(&self, bucket: &str) -> bool {
+        self.is_protected(bucket) || (!self.buckets.iter().any(|b| b == bucket) && looks_like_production(bucket))
+    }
+}
+
+fn default_log_level() -> String {
+    "info".to_string()
 }
 
 fn default_region() -> String {
@@ -110,26 +912,373 @@ fn default_regions() -> Vec<String> {
     ]
 }
 
-/// Load config from file. Returns default if file doesn't exist or is invalid.
+/// Current config schema version. Bump it together with a new entry in [`MIGRATIONS`].
+pub const CONFIG_VERSION: u32 = 4;
+
+/// `MIGRATIONS[n]` upgrades the raw TOML of a version `n` file to version `n + 1`.
+/// Fields that were only added get their serde default and need no step.
+const MIGRATIONS: &[fn(&mut toml::Table)] = &[
+    // v0 → v1: versioning introduced, layout unchanged
+    |_| {},
+    // v1 → v2: first-run wizard added; existing users have already set the app up
+    |table| {
+        table.insert("onboarding_done".to_string(), toml::Value::Boolean(true));
+    },
+    // v2 → v3: protection became per bucket; production-looking buckets stay protected
+    |table| {
+        let buckets: Vec<String> = match table.get("buckets").and_then(|b| b.as_array()) {
+            Some(buckets) => buckets.iter().filter_map(|b| b.as_str()).map(String::from).collect(),
+            None => default_buckets(),
+        };
+        let mut protected: Vec<toml::Value> = table
+            .get("protected_buckets")
+            .and_then(|p| p.as_array())
+            .cloned()
+            .unwrap_or_default();
+        for bucket in buckets.into_iter().filter(|b| looks_like_production(b)) {
+            let bucket = toml::Value::String(bucket);
+            if !protected.contains(&bucket) {
+                protected.push(bucket);
+            }
+        }
+        table.insert("protected_buckets".to_string(), toml::Value::Array(protected));
+    },
+    // v3 → v4: the SMTP password moved out of the file (see [`ParsedConfig::smtp_password`])
+    |table| {
+        take_smtp_password(table);
+    },
+];
+
+/// A parsed config file.
+#[derive(Debug)]
+pub struct ParsedConfig {
+    pub config: AppConfig,
+    /// Version the file was written with.
+    pub version: u32,
+    /// SMTP password a pre-v4 file stored in plain text, for the caller to keep in memory.
+    pub smtp_password: Option<String>,
+}
+
+/// Removes the SMTP password that versions before v4 saved in plain text.
+fn take_smtp_password(table: &mut toml::Table) -> Option<String> {
+    let password = table.get_mut("smtp")?.as_table_mut()?.remove("password")?;
+    password.as_str().map(String::from)
+}
+
+/// Parses a config file, running the migrations it needs.
+pub fn parse_config(text: &str) -> Result<ParsedConfig, String> {
+    let mut table: toml::Table = text.parse().map_err(|e: toml::de::Error| e.to_string())?;
+    let version = match table.get("config_version") {
+        None => 0,
+        Some(v) => v.as_integer().and_then(|v| u32::try_from(v).ok()).ok_or("config_version không hợp lệ")?,
+    };
+    if version > CONFIG_VERSION {
+        return Err(format!("Config được tạo bởi phiên bản mới hơn (v{}, hỗ trợ đến v{})", version, CONFIG_VERSION));
+    }
+    let smtp_password = match version {
+        0..=3 => take_smtp_password(&mut table).filter(|p| !p.is_empty()),
+        _ => None,
+    };
+    for migrate in &MIGRATIONS[version as usize..] {
+        migrate(&mut table);
+    }
+    table.insert("config_version".to_string(), toml::Value::Integer(CONFIG_VERSION.into()));
+    let config = toml::Value::Table(table).try_into().map_err(|e: toml::de::Error| e.to_string())?;
+    Ok(ParsedConfig { config, version, smtp_password })
+}
+
+/// Current config: the shared [`AppState`](crate::app_state::AppState) copy once the app has
+/// started, otherwise read from disk. For code outside the UI handlers (sync engine, restores).
 pub fn load_config() -> AppConfig {
-    match confy::load(APP_NAME, None) {
-        Ok(cfg) => cfg,
+    match crate::app_state::current() {
+        Some(state) => state.config(),
+        None => read_config_file(),
+    }
+}
+
+/// Reads the config file, migrating older versions. A file that can't be parsed is kept as a
+/// `.broken-*` backup and defaults are used, so a later save doesn't wipe it unnoticed; one that
+/// can't be read at all is backed up on the first save instead, see [`guard_unreadable`].
+pub fn read_config_file() -> AppConfig {
+    let Some(path) = get_config_path() else {
+        return AppConfig { config_version: CONFIG_VERSION, ..Default::default() };
+    };
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            let log_path = if portable_root().is_some() { PORTABLE_LOG_DIR.to_string() } else { String::new() };
+            let config = AppConfig { config_version: CONFIG_VERSION, log_path, ..Default::default() };
+            if let Err(e) = save_config(&config) {
+                warn!("Không thể tạo file config {:?}: {}", path, e);
+            }
+            return config;
+        }
         Err(e) => {
-            warn!(
-                "Không thể load config (có thể file cũ/lỗi), sử dụng mặc định: {}",
-                e
-            );
-            AppConfig::default()
+            UNREADABLE.store(true, std::sync::atomic::Ordering::SeqCst);
+            warn!("Không thể đọc config {:?}, sử dụng mặc định: {}", path, e);
+            return AppConfig { config_version: CONFIG_VERSION, ..Default::default() };
+        }
+    };
+    match parse_config(&text) {
+        Ok(ParsedConfig { config, version, smtp_password }) => {
+            if let Some(password) = smtp_password {
+                warn!("SMTP password removed from the config file; kept until the app closes, set S3_SYNC_SMTP_PASSWORD for unattended runs");
+                crate::notify::set_smtp_password(&password);
+            }
+            if version < CONFIG_VERSION {
+                backup_config(&path, &format!("v{}", version));
+                match save_config(&config) {
+                    Ok(()) => info!("Migrated config from v{} to v{}", version, CONFIG_VERSION),
+                    Err(e) => warn!("Không thể ghi config đã nâng cấp: {}", e),
+                }
+            }
+            config
         }
+        Err(e) => {
+            static BACKED_UP: std::sync::Once = std::sync::Once::new();
+            BACKED_UP.call_once(|| {
+                backup_config(&path, &format!("broken-{}", chrono::Local::now().format("%Y%m%d_%H%M%S")));
+            });
+            warn!("Không thể load config (file lỗi), sử dụng mặc định: {}", e);
+            AppConfig { config_version: CONFIG_VERSION, ..Default::default() }
+        }
+    }
+}
+
+/// Copies the config file to `<file>.<suffix>.bak` next to it, leaving out an SMTP password
+/// saved by an older version. Returns whether the backup was written.
+fn backup_config(path: &std::path::Path, suffix: &str) -> bool {
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(format!(".{}.bak", suffix));
+    let scrubbed = std::fs::read_to_string(path).ok().and_then(|text| {
+        let mut table: toml::Table = text.parse().ok()?;
+        take_smtp_password(&mut table)?;
+        toml::to_string_pretty(&table).ok()
+    });
+    let result = match scrubbed {
+        Some(text) => std::fs::write(&backup, text),
+        None => std::fs::copy(path, &backup).map(|_| ()),
+    };
+    match result {
+        Ok(_) => info!("Backed up config to {:?}", backup),
+        Err(ref e) => warn!("Không thể backup config {:?}: {}", path, e),
+    }
+    result.is_ok()
+}
+
+/// Before replacing a config file that couldn't be read at startup, keeps it as an
+/// `.unreadable-*` backup. Refuses the save while that still fails, since the app is running on
+/// defaults and would otherwise overwrite the user's settings.
+fn guard_unreadable(path: &std::path::Path) -> Result<(), String> {
+    use std::sync::atomic::Ordering;
+    if !UNREADABLE.load(Ordering::SeqCst) || !path.exists() {
+        return Ok(());
     }
+    if !backup_config(path, &format!("unreadable-{}", chrono::Local::now().format("%Y%m%d_%H%M%S"))) {
+        return Err(format!("Không đọc được config {:?} nên không ghi đè; hãy kiểm tra quyền truy cập file", path));
+    }
+    UNREADABLE.store(false, Ordering::SeqCst);
+    Ok(())
+}
+
+/// Writes the config file. Handlers go through [`AppState`](crate::app_state::AppState),
+/// whose persistence task calls this. The previous file is kept as `<file>.prev.bak`.
+pub fn save_config(config: &AppConfig) -> Result<(), String> {
+    let text = toml::to_string_pretty(config).map_err(|e| e.to_string())?;
+    write_config_text(&text)
 }
 
-/// Save config to file.
-pub fn save_config(config: &AppConfig) -> Result<(), confy::ConfyError> {
-    confy::store(APP_NAME, None, config)
+/// Writes to a temp file next to the config and renames it over the config, so a crash
+/// mid-write leaves either the old or the new file, never a truncated one.
+fn write_config_text(text: &str) -> Result<(), String> {
+    use std::io::Write;
+    let path = get_config_path().ok_or("Không xác định được đường dẫn config")?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    guard_unreadable(&path)?;
+    if path.exists() {
+        backup_config(&path, "prev");
+    }
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = std::path::PathBuf::from(tmp);
+    let mut file = std::fs::File::create(&tmp).map_err(|e| format!("{:?}: {}", tmp, e))?;
+    file.write_all(text.as_bytes())
+        .and_then(|_| file.sync_all())
+        .map_err(|e| format!("{:?}: {}", tmp, e))?;
+    drop(file);
+    std::fs::rename(&tmp, &path).map_err(|e| format!("{:?}: {}", path, e))
 }
 
-/// Get the config file path for debugging purposes.
+/// Get the config file path for debugging purposes. State files are kept next to it.
 pub fn get_config_path() -> Option<std::path::PathBuf> {
-    confy::get_configuration_file_path(APP_NAME, None).ok()
+    match portable_root() {
+        Some(root) => Some(root.join(PORTABLE_DATA_DIR).join(format!("{}.toml", APP_NAME))),
+        None => confy::get_configuration_file_path(APP_NAME, None).ok(),
+    }
+}
+
+fn portable_requested(args: &[String], exe_dir: &Path) -> bool {
+    args.iter().any(|a| a == PORTABLE_FLAG) || exe_dir.join(PORTABLE_FLAG_FILE).exists()
+}
+
+/// Switches to portable mode if `--portable` is in `args` or `portable.flag` sits next to the
+/// executable. Must run before anything reads the config, so before logging is set up: the
+/// caller logs the result. Returns whether portable mode is on; an error means it is on but its
+/// folders couldn't be created.
+pub fn init_portable_mode(args: &[String]) -> Result<bool, String> {
+    let Some(exe_dir) = std::env::current_exe().ok().and_then(|exe| exe.parent().map(Path::to_path_buf)) else {
+        return Ok(false);
+    };
+    if !portable_requested(args, &exe_dir) {
+        return Ok(false);
+    }
+    let errors: Vec<String> = [PORTABLE_DATA_DIR, PORTABLE_LOG_DIR]
+        .into_iter()
+        .filter_map(|dir| {
+            let dir = exe_dir.join(dir);
+            std::fs::create_dir_all(&dir).err().map(|e| format!("Không tạo được {:?}: {}", dir, e))
+        })
+        .collect();
+    let enabled = PORTABLE_ROOT.set(exe_dir).is_ok();
+    if !errors.is_empty() {
+        return Err(errors.join("; "));
+    }
+    Ok(enabled)
+}
+
+/// The executable's folder in portable mode.
+pub fn portable_root() -> Option<&'static Path> {
+    PORTABLE_ROOT.get().map(PathBuf::as_path)
+}
+
+/// Where the debug log goes: the portable data folder, or the working directory.
+pub fn debug_log_dir() -> PathBuf {
+    portable_root().map(|root| root.join(PORTABLE_DATA_DIR)).unwrap_or_else(|| PathBuf::from("."))
+}
+
+/// What the app itself writes while running: the config folder (config and state files), the
+/// sync log folder, the debug log and the metrics file. Syncs leave these out.
+pub fn app_owned_paths(log_path: &str, metrics_path: &str) -> Vec<PathBuf> {
+    let mut paths = vec![debug_log_dir().join(crate::logging::DEBUG_LOG_FILE)];
+    paths.extend(get_config_path().and_then(|p| p.parent().map(Path::to_path_buf)));
+    paths.extend([log_path, metrics_path].into_iter().filter(|p| !p.is_empty()).map(|p| PathBuf::from(resolve_user_path(p))));
+    paths
+}
+
+fn resolve_under(root: Option<&Path>, path: &str) -> String {
+    match root {
+        Some(root) if !path.is_empty() && Path::new(path).is_relative() => root.join(path).to_string_lossy().to_string(),
+        _ => path.to_string(),
+    }
+}
+
+fn relative_under(root: Option<&Path>, path: &str) -> String {
+    root.and_then(|root| Path::new(path).strip_prefix(root).ok())
+        .filter(|rel| !rel.as_os_str().is_empty())
+        .map(|rel| rel.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string())
+}
+
+/// Turns a path read from the config into an absolute one. In portable mode, relative paths
+/// are under the executable's folder.
+pub fn resolve_user_path(path: &str) -> String {
+    resolve_under(portable_root(), path)
+}
+
+/// The form of `path` to save in the config: in portable mode, paths under the executable's
+/// folder are stored relative, so they still work when the drive letter changes.
+pub fn store_user_path(path: &str) -> String {
+    relative_under(portable_root(), path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config_migrates_unversioned_file() {
+        let ParsedConfig { config, version, .. } = parse_config("log_path = \"/var/log/s3\"\nbuckets = [\"site\"]\n").unwrap();
+        assert_eq!(version, 0);
+        assert_eq!(config.config_version, CONFIG_VERSION);
+        assert_eq!(config.log_path, "/var/log/s3");
+        assert_eq!(config.buckets, vec!["site"]);
+        assert!(config.onboarding_done);
+        assert!(!parse_config(&format!("config_version = {}", CONFIG_VERSION)).unwrap().config.onboarding_done);
+
+        let config = parse_config("config_version = 2\nbuckets = [\"site-prod\", \"site-dev\", \"ien-PROD\"]\n").unwrap().config;
+        assert_eq!(config.protected_buckets, vec!["site-prod", "ien-PROD"]);
+        assert!(config.requires_typed_confirmation("site-prod"));
+        assert!(!config.requires_typed_confirmation("site-dev"));
+        assert!(config.requires_typed_confirmation("typed-prod-bucket"));
+        let config = parse_config("config_version = 2\n").unwrap().config;
+        assert_eq!(config.protected_buckets, vec!["i-ocean-global-prod-contents", "ien-corp-prod-contents"]);
+
+        let parsed = parse_config("config_version = 3\n[smtp]\nhost = \"mail\"\npassword = \"legacy-smtp-secret\"\n").unwrap();
+        assert_eq!(parsed.config.smtp.host, "mail");
+        assert!(!toml::to_string_pretty(&parsed.config).unwrap().contains("legacy-smtp-secret"));
+        assert_eq!(parsed.smtp_password.as_deref(), Some("legacy-smtp-secret"));
+
+        assert!(parse_config("config_version = 99").is_err());
+        assert!(parse_config("buckets = 3").is_err());
+    }
+
+    #[test]
+    fn test_unreadable_config_is_backed_up_before_save() {
+        use std::sync::atomic::Ordering;
+        let dir = std::env::temp_dir().join(format!("s3sync_unreadable_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        UNREADABLE.store(true, Ordering::SeqCst);
+        // Still unreadable (a folder stands in for a locked file): no save
+        assert!(guard_unreadable(&dir).is_err());
+        assert!(UNREADABLE.load(Ordering::SeqCst));
+
+        let path = dir.join("config.toml");
+        std::fs::write(&path, "buckets = [\"site\"]\n").unwrap();
+        assert!(guard_unreadable(&path).is_ok());
+        assert!(!UNREADABLE.load(Ordering::SeqCst));
+        let backups: Vec<_> = std::fs::read_dir(&dir)
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_name().to_string_lossy().contains(".unreadable-"))
+            .collect();
+        assert_eq!(backups.len(), 1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_bucket_check_badge() {
+        let check = |status, region: &str| BucketCheck { status, region: region.to_string(), checked_at: 0 };
+        assert_eq!(check(BucketStatus::Reachable, "ap-northeast-1").badge("ap-northeast-1"), "reachable");
+        assert_eq!(check(BucketStatus::Reachable, "us-east-1").badge("ap-northeast-1"), "wrong_region");
+        assert_eq!(check(BucketStatus::AccessDenied, "").badge("ap-northeast-1"), "access_denied");
+        assert_eq!(check(BucketStatus::NotFound, "").badge("ap-northeast-1"), "not_found");
+
+        let mut config = AppConfig::default();
+        config.bucket_checks.insert("my.site".to_string(), check(BucketStatus::AccessDenied, "eu-west-1"));
+        let text = toml::to_string_pretty(&config).unwrap();
+        assert_eq!(parse_config(&text).unwrap().config.bucket_checks, config.bucket_checks);
+    }
+
+    #[test]
+    fn test_portable_paths() {
+        let root = std::env::temp_dir().join(format!("s3sync_portable_test_{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        assert!(portable_requested(&["--portable".to_string()], &root));
+        assert!(!portable_requested(&[], &root));
+        std::fs::write(root.join(PORTABLE_FLAG_FILE), "").unwrap();
+        assert!(portable_requested(&[], &root));
+
+        let logs = root.join("logs").to_string_lossy().to_string();
+        assert_eq!(relative_under(Some(&root), &logs), "logs");
+        assert_eq!(resolve_under(Some(&root), "logs"), logs);
+        assert_eq!(resolve_under(None, "logs"), "logs");
+        assert_eq!(resolve_under(Some(&root), ""), "");
+        let elsewhere = std::env::temp_dir().join("other").to_string_lossy().to_string();
+        assert_eq!(relative_under(Some(&root), &elsewhere), elsewhere);
+        assert_eq!(resolve_under(Some(&root), &elsewhere), elsewhere);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
 }
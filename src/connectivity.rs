@@ -0,0 +1,135 @@
+use aws_sdk_s3::Client;
+use aws_sdk_s3::error::SdkError;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+use crate::progress::StatusFn;
+
+/// Consecutive connection errors (after the SDK's own retries) that mean the network is gone.
+const OFFLINE_THRESHOLD: usize = 3;
+/// How often the endpoint is probed while offline.
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+/// How long the sync waits for the network before it fails.
+const MAX_OFFLINE: Duration = Duration::from_secs(30 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Link {
+    Online,
+    Offline,
+    /// Offline for longer than [`MAX_OFFLINE`]; waiting tasks give up.
+    Lost,
+}
+
+/// True for errors where the request never got a response: DNS, refused/reset connection, timeouts.
+pub fn is_connection_error<E, R>(error: &SdkError<E, R>) -> bool {
+    matches!(error, SdkError::DispatchFailure(_) | SdkError::TimeoutError(_))
+}
+
+/// Shared by every upload task of a sync. When connection errors pile up the queue is paused,
+/// the endpoint is polled, and waiting tasks resume once it answers again.
+pub struct Connectivity {
+    consecutive_errors: AtomicUsize,
+    link: watch::Sender<Link>,
+}
+
+impl Default for Connectivity {
+    fn default() -> Self {
+        Self { consecutive_errors: AtomicUsize::new(0), link: watch::Sender::new(Link::Online) }
+    }
+}
+
+impl Connectivity {
+    /// Waits while the sync is paused for a lost connection. Errors once the network has been
+    /// gone for longer than [`MAX_OFFLINE`].
+    pub async fn wait_online(&self) -> Result<(), String> {
+        let mut rx = self.link.subscribe();
+        match rx.wait_for(|link| *link != Link::Offline).await.map(|link| *link) {
+            Ok(Link::Lost) => Err(format!("Mất kết nối mạng quá {} phút, dừng sync", MAX_OFFLINE.as_secs() / 60)),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn record_success(&self) {
+        self.consecutive_errors.store(0, Ordering::Relaxed);
+    }
+
+    /// Counts a connection error; returns true for the error that takes the sync offline.
+    pub fn record_connection_error(&self) -> bool {
+        let errors = self.consecutive_errors.fetch_add(1, Ordering::Relaxed) + 1;
+        errors >= OFFLINE_THRESHOLD
+            && self.link.send_if_modified(|link| {
+                let going_offline = *link == Link::Online;
+                if going_offline {
+                    *link = Link::Offline;
+                }
+                going_offline
+            })
+    }
+
+    fn set_online(&self) {
+        self.consecutive_errors.store(0, Ordering::Relaxed);
+        self.link.send_replace(Link::Online);
+    }
+
+    /// Handles a request that got no response: pauses the queue and starts polling once the
+    /// network looks down. The caller retries after `wait_online`.
+    pub fn on_connection_error(self: &Arc<Self>, client: &Arc<Client>, bucket: &str, status: &StatusFn) {
+        if self.record_connection_error() {
+            warn!("Connection lost, pausing uploads until {} is reachable", bucket);
+            status("Mất kết nối mạng - tạm dừng, sẽ tự tiếp tục khi có mạng...".to_string(), 0.0, true);
+            tokio::spawn(Arc::clone(self).poll_until_online(Arc::clone(client), bucket.to_string(), Arc::clone(status)));
+        }
+    }
+
+    async fn poll_until_online(self: Arc<Self>, client: Arc<Client>, bucket: String, status: StatusFn) {
+        let started = Instant::now();
+        loop {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            // Only the poller is left: the sync has ended (failed or aborted), nobody is waiting
+            if Arc::strong_count(&self) == 1 {
+                return;
+            }
+            if started.elapsed() >= MAX_OFFLINE {
+                warn!("{} unreachable for {:?}, giving up", bucket, MAX_OFFLINE);
+                self.link.send_replace(Link::Lost);
+                return;
+            }
+            match client.head_bucket().bucket(&bucket).send().await {
+                Err(e) if is_connection_error(&e) => continue,
+                // Any response, even an error one, means the endpoint is reachable again
+                _ => break,
+            }
+        }
+        info!("Connection restored, resuming uploads");
+        status("Đã có mạng trở lại, tiếp tục upload...".to_string(), 0.0, false);
+        self.set_online();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_goes_offline_after_consecutive_errors() {
+        let connectivity = Connectivity::default();
+        assert!(!connectivity.record_connection_error());
+        connectivity.record_success();
+        assert!(!connectivity.record_connection_error());
+        assert!(!connectivity.record_connection_error());
+        assert!(connectivity.record_connection_error());
+        assert_eq!(*connectivity.link.borrow(), Link::Offline);
+        // Only the first error past the threshold starts polling
+        assert!(!connectivity.record_connection_error());
+        connectivity.set_online();
+        assert!(connectivity.wait_online().await.is_ok());
+
+        // Offline for too long: waiting uploads fail instead of hanging
+        connectivity.link.send_replace(Link::Lost);
+        assert!(connectivity.wait_online().await.is_err());
+        assert!(!connectivity.record_connection_error());
+    }
+}
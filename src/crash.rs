@@ -0,0 +1,156 @@
+use once_cell::sync::Lazy;
+use std::collections::BTreeMap;
+use std::panic::PanicHookInfo;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Name of the file, next to the config file, that points at the last crash report until the
+/// next start has shown it.
+const MARKER_FILE: &str = "last_crash.txt";
+
+/// What each running sync is doing, by guard id; written into the crash report.
+static ACTIVE_SYNCS: Lazy<Mutex<BTreeMap<u64, String>>> = Lazy::new(|| Mutex::new(BTreeMap::new()));
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Keeps a sync's description in crash reports until dropped.
+pub struct SyncContextGuard(u64);
+
+impl Drop for SyncContextGuard {
+    fn drop(&mut self) {
+        if let Ok(mut active) = ACTIVE_SYNCS.lock() {
+            active.remove(&self.0);
+        }
+    }
+}
+
+/// Records what a sync is working on (bucket, folders) for the crash report.
+pub fn enter_sync(description: String) -> SyncContextGuard {
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    if let Ok(mut active) = ACTIVE_SYNCS.lock() {
+        active.insert(id, description);
+    }
+    SyncContextGuard(id)
+}
+
+/// A crash from the previous run that hasn't been shown yet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrashNotice {
+    pub report_path: PathBuf,
+    /// The panic message line of the report.
+    pub summary: String,
+}
+
+fn config_dir() -> Option<PathBuf> {
+    crate::config::get_config_path()?.parent().map(Path::to_path_buf)
+}
+
+/// The user's log folder if one is set, else the config folder. Doesn't wait for the config
+/// lock: the panic may have happened while it was held.
+fn report_dir() -> Option<PathBuf> {
+    let log_path = crate::app_state::current()
+        .and_then(|state| state.try_read().map(|config| crate::config::resolve_user_path(&config.log_path)))
+        .unwrap_or_default();
+    if log_path.is_empty() { config_dir() } else { Some(PathBuf::from(log_path)) }
+}
+
+fn format_report(time: &str, thread: &str, message: &str, location: &str, syncs: &[String], backtrace: &str) -> String {
+    let mut report = format!(
+        "S3 Sync Tool {} crashed at {}\nThread: {}\nPanic: {}\nLocation: {}\n",
+        env!("CARGO_PKG_VERSION"),
+        time,
+        thread,
+        message,
+        location
+    );
+    if syncs.is_empty() {
+        report.push_str("Active syncs: none\n");
+    } else {
+        report.push_str("Active syncs:\n");
+        for sync in syncs {
+            report.push_str(&format!("  - {}\n", sync));
+        }
+    }
+    report.push_str("\nBacktrace:\n");
+    report.push_str(backtrace);
+    report
+}
+
+fn panic_message(info: &PanicHookInfo<'_>) -> String {
+    if let Some(s) = info.payload().downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = info.payload().downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "(không rõ)".to_string()
+    }
+}
+
+fn write_report(info: &PanicHookInfo<'_>) -> Option<PathBuf> {
+    let now = chrono::Local::now();
+    let thread = std::thread::current();
+    let syncs: Vec<String> = ACTIVE_SYNCS.try_lock().map(|a| a.values().cloned().collect()).unwrap_or_default();
+    let report = format_report(
+        &now.format("%Y-%m-%d %H:%M:%S").to_string(),
+        thread.name().unwrap_or("unnamed"),
+        &panic_message(info),
+        &info.location().map(|l| l.to_string()).unwrap_or_default(),
+        &syncs,
+        &std::backtrace::Backtrace::force_capture().to_string(),
+    );
+
+    let dir = report_dir()?;
+    std::fs::create_dir_all(&dir).ok()?;
+    let path = dir.join(format!("crash_{}.log", now.format("%Y%m%d_%H%M%S")));
+    std::fs::write(&path, report).ok()?;
+    if let Some(config_dir) = config_dir() {
+        let _ = std::fs::write(config_dir.join(MARKER_FILE), path.to_string_lossy().as_bytes());
+    }
+    Some(path)
+}
+
+/// Installs a panic hook that writes a crash report (message, backtrace, running syncs) before
+/// the default hook runs. Call once, after logging is set up.
+pub fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        match write_report(info) {
+            Some(path) => tracing::error!("Panic: {} (report: {})", info, path.display()),
+            None => tracing::error!("Panic: {} (report could not be written)", info),
+        }
+        default_hook(info);
+    }));
+}
+
+/// Returns the crash recorded by the previous run, once: the marker is removed.
+pub fn take_last_crash() -> Option<CrashNotice> {
+    let marker = config_dir()?.join(MARKER_FILE);
+    let report_path = PathBuf::from(std::fs::read_to_string(&marker).ok()?.trim());
+    let _ = std::fs::remove_file(&marker);
+    let summary = std::fs::read_to_string(&report_path)
+        .ok()
+        .and_then(|report| report.lines().find_map(|l| l.strip_prefix("Panic: ").map(String::from)))
+        .unwrap_or_default();
+    Some(CrashNotice { report_path, summary })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_lists_active_syncs() {
+        let guard = enter_sync("bucket-a: C:\\site -> docs/".to_string());
+        let syncs: Vec<String> = ACTIVE_SYNCS.lock().unwrap().values().cloned().collect();
+        assert!(syncs.contains(&"bucket-a: C:\\site -> docs/".to_string()));
+
+        let report = format_report("2025-01-01 12:00:00", "main", "boom", "src/a.rs:1:1", &syncs, "0: main");
+        assert!(report.contains("Panic: boom\nLocation: src/a.rs:1:1\n"));
+        assert!(report.contains("  - bucket-a: C:\\site -> docs/\n"));
+        assert!(report.ends_with("Backtrace:\n0: main"));
+
+        drop(guard);
+        assert!(!ACTIVE_SYNCS.lock().unwrap().values().any(|s| s.starts_with("bucket-a")));
+        assert!(format_report("t", "main", "x", "l", &[], "").contains("Active syncs: none\n"));
+    }
+}
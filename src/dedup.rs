@@ -0,0 +1,95 @@
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Computes the hex SHA-256 of a file's contents.
+pub fn sha256_file(path: &Path) -> std::io::Result<String> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut hasher = Sha256::new();
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+/// File hashes computed during one sync, so dedup, fingerprinting and the checksum manifest
+/// read each file at most once.
+#[derive(Default)]
+pub struct HashCache(Mutex<HashMap<PathBuf, String>>);
+
+impl HashCache {
+    pub fn sha256(&self, path: &Path) -> std::io::Result<String> {
+        if let Some(hash) = self.0.lock().unwrap().get(path) {
+            return Ok(hash.clone());
+        }
+        let hash = sha256_file(path)?;
+        self.0.lock().unwrap().insert(path.to_path_buf(), hash.clone());
+        Ok(hash)
+    }
+}
+
+/// Groups byte-identical files. Returns groups of indices into `files` with at least two
+/// members; the first index of each group is the one to keep.
+/// Files are only hashed when another file has the same size.
+pub fn find_duplicate_groups(files: &[PathBuf], cache: &HashCache) -> Vec<Vec<usize>> {
+    let mut by_size: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (i, path) in files.iter().enumerate() {
+        if let Ok(meta) = std::fs::metadata(path) {
+            by_size.entry(meta.len()).or_default().push(i);
+        }
+    }
+
+    let mut groups = Vec::new();
+    for candidates in by_size.into_values().filter(|c| c.len() > 1) {
+        let mut by_hash: HashMap<String, Vec<usize>> = HashMap::new();
+        for i in candidates {
+            if let Ok(hash) = cache.sha256(&files[i]) {
+                by_hash.entry(hash).or_default().push(i);
+            }
+        }
+        groups.extend(by_hash.into_values().filter(|g| g.len() > 1).map(|mut g| {
+            g.sort_unstable();
+            g
+        }));
+    }
+    groups.sort_by_key(|g| g[0]);
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_duplicate_groups() {
+        let dir = std::env::temp_dir().join(format!("s3sync_dedup_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let write = |name: &str, content: &str| {
+            let p = dir.join(name);
+            std::fs::write(&p, content).unwrap();
+            p
+        };
+        let files = vec![
+            write("a.txt", "hello"),
+            write("b.txt", "world"),
+            write("c.txt", "hello"),
+            write("d.txt", "hello!"),
+        ];
+
+        let groups = find_duplicate_groups(&files, &HashCache::default());
+        std::fs::remove_dir_all(&dir).ok();
+        assert_eq!(groups, vec![vec![0, 2]]);
+    }
+}
@@ -0,0 +1,177 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::object_store::ObjectStore;
+
+/// Remote object metadata needed for comparisons.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RemoteObject {
+    pub size: u64,
+    pub etag: String,
+    /// Unix timestamp (seconds).
+    pub last_modified: i64,
+    pub storage_class: String,
+    /// Modification time of the uploaded file from the object metadata (Unix seconds). Only a
+    /// HeadObject returns it, so listings leave it empty.
+    #[serde(default)]
+    pub file_modified: Option<i64>,
+}
+
+impl RemoteObject {
+    /// When the content last changed: the original file's mtime if known, else the upload time.
+    pub fn modified_time(&self) -> i64 {
+        self.file_modified.unwrap_or(self.last_modified)
+    }
+}
+
+/// Objects under a prefix, keyed by path relative to that prefix.
+pub type RemoteListing = BTreeMap<String, RemoteObject>;
+
+/// Result of comparing two listings.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PrefixDiff {
+    pub only_a: Vec<String>,
+    pub only_b: Vec<String>,
+    /// (relative key, human readable reason)
+    pub changed: Vec<(String, String)>,
+    pub identical: usize,
+}
+
+impl PrefixDiff {
+    pub fn is_identical(&self) -> bool {
+        self.only_a.is_empty() && self.only_b.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Normalizes a prefix for listing: no leading slash, trailing slash unless empty (bucket root).
+pub fn normalize_list_prefix(prefix: &str) -> String {
+    let trimmed = prefix.trim().replace('\\', "/");
+    let trimmed = trimmed.trim_matches('/');
+    if trimmed.is_empty() {
+        String::new()
+    } else {
+        format!("{}/", trimmed)
+    }
+}
+
+/// Lists every object under `prefix` (paginated), keyed by the key relative to the prefix.
+pub async fn list_remote_objects(store: &dyn ObjectStore, bucket: &str, prefix: &str) -> Result<RemoteListing, String> {
+    let prefix = normalize_list_prefix(prefix);
+    let list = store
+        .list(bucket, &prefix, false)
+        .await
+        .map_err(|e| format!("Lỗi list s3://{}/{}: {}", bucket, prefix, e))?;
+
+    Ok(list
+        .objects
+        .into_iter()
+        .filter_map(|(key, object)| {
+            let relative = key.strip_prefix(&prefix).unwrap_or(&key);
+            // Folder marker objects carry no content worth comparing
+            if relative.is_empty() || relative.ends_with('/') {
+                return None;
+            }
+            Some((relative.to_string(), object))
+        })
+        .collect())
+}
+
+/// Compares two listings by relative key, size and ETag.
+pub fn diff_listings(a: &RemoteListing, b: &RemoteListing) -> PrefixDiff {
+    let mut diff = PrefixDiff::default();
+
+    for (key, obj_a) in a {
+        match b.get(key) {
+            None => diff.only_a.push(key.clone()),
+            Some(obj_b) if obj_a.size != obj_b.size => diff.changed.push((
+                key.clone(),
+                format!("size {} ≠ {}", obj_a.size, obj_b.size),
+            )),
+            Some(obj_b) if obj_a.etag != obj_b.etag => diff.changed.push((
+                key.clone(),
+                format!("etag {} ≠ {}", obj_a.etag, obj_b.etag),
+            )),
+            Some(_) => diff.identical += 1,
+        }
+    }
+    diff.only_b = b.keys().filter(|k| !a.contains_key(*k)).cloned().collect();
+
+    diff
+}
+
+/// Storage classes offered for cold objects, cheapest retrieval first.
+pub const TRANSITION_CLASSES: [&str; 4] = ["STANDARD_IA", "GLACIER_IR", "GLACIER", "DEEP_ARCHIVE"];
+
+/// Largest object CopyObject can copy in place; bigger ones need a multipart copy.
+const MAX_COPY_IN_PLACE_BYTES: u64 = 5 * 1024 * 1024 * 1024;
+
+/// Objects still in STANDARD that haven't changed for `months` (30-day months) as of `now`,
+/// with their age in months. Objects too large to copy in place are left out.
+pub fn cold_objects(listing: &RemoteListing, now: i64, months: u32) -> Vec<(String, u32)> {
+    const MONTH_SECS: i64 = 30 * 24 * 3600;
+    listing
+        .iter()
+        .filter(|(_, obj)| obj.storage_class.is_empty() || obj.storage_class == "STANDARD")
+        .filter(|(_, obj)| obj.size <= MAX_COPY_IN_PLACE_BYTES)
+        .filter_map(|(key, obj)| {
+            let age = ((now - obj.last_modified) / MONTH_SECS) as u32;
+            (age >= months).then(|| (key.clone(), age))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn obj(size: u64, etag: &str) -> RemoteObject {
+        RemoteObject {
+            size,
+            etag: etag.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_diff_listings() {
+        let mut a = RemoteListing::new();
+        a.insert("index.html".to_string(), obj(10, "x"));
+        a.insert("app.js".to_string(), obj(20, "y"));
+        a.insert("old.css".to_string(), obj(5, "z"));
+        a.insert("logo.png".to_string(), obj(7, "p"));
+
+        let mut b = RemoteListing::new();
+        b.insert("index.html".to_string(), obj(10, "x"));
+        b.insert("app.js".to_string(), obj(20, "changed"));
+        b.insert("new.css".to_string(), obj(5, "z"));
+        b.insert("logo.png".to_string(), obj(8, "p"));
+
+        let diff = diff_listings(&a, &b);
+        assert_eq!(diff.only_a, vec!["old.css".to_string()]);
+        assert_eq!(diff.only_b, vec!["new.css".to_string()]);
+        assert_eq!(diff.changed.len(), 2);
+        assert_eq!(diff.identical, 1);
+        assert!(!diff.is_identical());
+    }
+
+    #[test]
+    fn test_cold_objects() {
+        let now = 400 * 24 * 3600;
+        let mut listing = RemoteListing::new();
+        let mut old = obj(1, "a");
+        old.last_modified = now - 200 * 24 * 3600;
+        listing.insert("old.tar".to_string(), old.clone());
+        listing.insert("archived.tar".to_string(), RemoteObject { storage_class: "GLACIER".to_string(), ..old });
+        listing.insert("fresh.tar".to_string(), RemoteObject { last_modified: now, ..obj(1, "b") });
+        assert_eq!(cold_objects(&listing, now, 6), vec![("old.tar".to_string(), 6)]);
+        assert!(cold_objects(&listing, now, 7).is_empty());
+    }
+
+    #[test]
+    fn test_normalize_list_prefix() {
+        assert_eq!(normalize_list_prefix(""), "");
+        assert_eq!(normalize_list_prefix("/"), "");
+        assert_eq!(normalize_list_prefix("/site/prod"), "site/prod/");
+        assert_eq!(normalize_list_prefix("site\\prod\\"), "site/prod/");
+    }
+}
@@ -0,0 +1,192 @@
+//! Generated `index.html` directory listings for uploaded prefixes, for documentation or
+//! artifact folders browsed through the website endpoint (which has no listing of its own).
+//! Pages are built from the bucket listing, so files unchanged by this sync are included.
+
+use aws_sdk_s3::Client;
+use aws_sdk_s3::primitives::ByteStream;
+use chrono::{Local, TimeZone};
+use std::collections::BTreeMap;
+use std::path::Path;
+use tracing::info;
+
+use crate::diff::RemoteListing;
+
+/// Name of the generated page in every folder.
+pub const INDEX_NAME: &str = "index.html";
+
+/// Built-in page; a custom template uses the same `{title}`, `{entries}` and `{generated}`
+/// placeholders. `{entries}` becomes one `<tr>` per folder or file.
+pub const DEFAULT_TEMPLATE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{title}</title>
+<style>
+body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; }
+td { padding: 2px 16px 2px 0; }
+</style>
+</head>
+<body>
+<h1>{title}</h1>
+<table>
+{entries}
+</table>
+<p><small>{generated}</small></p>
+</body>
+</html>
+"#;
+
+/// One row of a listing page.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub name: String,
+    pub is_dir: bool,
+    pub size: u64,
+    /// Unix timestamp (seconds); 0 for folders.
+    pub modified: i64,
+}
+
+/// Groups a listing (keys relative to the mapping prefix) into one page per folder, keyed by the
+/// folder's relative path (`""` for the prefix itself, otherwise ending in `/`). Existing index
+/// pages, folder markers and the app's bookkeeping objects (trash, staging, `.s3sync/`) are
+/// not listed.
+pub fn build_pages(listing: &RemoteListing) -> BTreeMap<String, Vec<Entry>> {
+    let mut pages: BTreeMap<String, BTreeMap<String, Entry>> = BTreeMap::new();
+    pages.entry(String::new()).or_default();
+    for (key, object) in listing {
+        if crate::s3_client::is_bookkeeping_key(key) || key.ends_with('/') {
+            continue;
+        }
+        let parts: Vec<&str> = key.split('/').collect();
+        let (name, dirs) = parts.split_last().expect("split yields at least one part");
+        let mut dir = String::new();
+        for sub in dirs {
+            let child = Entry { name: sub.to_string(), is_dir: true, size: 0, modified: 0 };
+            pages.entry(dir.clone()).or_default().entry(format!("{}/", sub)).or_insert(child);
+            dir = format!("{}{}/", dir, sub);
+        }
+        if *name == INDEX_NAME {
+            pages.entry(dir).or_default();
+            continue;
+        }
+        let file = Entry { name: name.to_string(), is_dir: false, size: object.size, modified: object.last_modified };
+        pages.entry(dir).or_default().insert(name.to_string(), file);
+    }
+    // Folders first, then files, each by name
+    pages
+        .into_iter()
+        .map(|(dir, entries)| {
+            let (mut folders, files): (Vec<Entry>, Vec<Entry>) = entries.into_values().partition(|e| e.is_dir);
+            folders.extend(files);
+            (dir, folders)
+        })
+        .collect()
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Renders one page. `title` is the folder's full key path.
+pub fn render(template: &str, title: &str, entries: &[Entry], is_root: bool) -> String {
+    let mut rows = Vec::new();
+    if !is_root {
+        rows.push(r#"<tr><td><a href="../">../</a></td><td></td><td></td></tr>"#.to_string());
+    }
+    for entry in entries {
+        let href = crate::utils::percent_encode_key(&entry.name);
+        let (name, size, date) = if entry.is_dir {
+            (format!("{}/", entry.name), String::new(), String::new())
+        } else {
+            let date = Local
+                .timestamp_opt(entry.modified, 0)
+                .single()
+                .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+                .unwrap_or_default();
+            (entry.name.clone(), crate::utils::format_size(entry.size), date)
+        };
+        let href = if entry.is_dir { format!("{}/", href) } else { href };
+        rows.push(format!(
+            r#"<tr><td><a href="{}">{}</a></td><td>{}</td><td>{}</td></tr>"#,
+            escape_html(&href),
+            escape_html(&name),
+            size,
+            date
+        ));
+    }
+    template
+        .replace("{title}", &escape_html(title))
+        .replace("{entries}", &rows.join("\n"))
+        .replace("{generated}", &format!("Tạo bởi S3 Sync Tool lúc {}", Local::now().format("%Y-%m-%d %H:%M")))
+}
+
+/// Writes an index page into every folder under `prefix` that doesn't have its own `index.html`
+/// in `local_root`. Returns the uploaded keys.
+pub async fn upload_indexes(
+    client: &Client,
+    bucket: &str,
+    prefix: &str,
+    local_root: &Path,
+    template: &str,
+) -> Result<Vec<String>, String> {
+    let listing = crate::diff::list_remote_objects(client, bucket, prefix).await?;
+    let base = crate::diff::normalize_list_prefix(prefix);
+    let mut uploaded = Vec::new();
+    for (dir, entries) in build_pages(&listing) {
+        if local_root.join(&dir).join(INDEX_NAME).exists() {
+            continue;
+        }
+        let key = format!("{}{}{}", base, dir, INDEX_NAME);
+        let title = format!("/{}{}", base, dir);
+        let page = render(template, &title, &entries, base.is_empty() && dir.is_empty());
+        client
+            .put_object()
+            .bucket(bucket)
+            .key(&key)
+            .content_type("text/html; charset=utf-8")
+            .cache_control("no-cache")
+            .body(ByteStream::from(page.into_bytes()))
+            .send()
+            .await
+            .map_err(|e| format!("Lỗi upload trang index {}: {}", key, e))?;
+        uploaded.push(key);
+    }
+    info!("Generated {} directory index pages under s3://{}/{}", uploaded.len(), bucket, base);
+    Ok(uploaded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::RemoteObject;
+
+    #[test]
+    fn test_pages_per_folder() {
+        let object = |size| RemoteObject { size, last_modified: 1_700_000_000, ..Default::default() };
+        let listing: RemoteListing = [
+            ("readme.txt", object(10)),
+            ("v1/app.zip", object(2048)),
+            ("v1/docs/a&b.html", object(5)),
+            ("v1/index.html", object(1)),
+            (".s3sync/batch/x.csv", object(1)),
+            (".trash/20240101_120000/readme.txt", object(1)),
+            (".staging/20240101_120000/v1/app.zip", object(1)),
+            ("empty/", object(0)),
+        ]
+        .into_iter()
+        .map(|(k, o)| (k.to_string(), o))
+        .collect();
+
+        let pages = build_pages(&listing);
+        assert_eq!(pages.keys().collect::<Vec<_>>(), vec!["", "v1/", "v1/docs/"]);
+        let names = |dir: &str| pages[dir].iter().map(|e| e.name.clone()).collect::<Vec<_>>();
+        assert_eq!(names(""), vec!["v1", "readme.txt"]);
+        assert_eq!(names("v1/"), vec!["docs", "app.zip"]);
+
+        let page = render("{title}|{entries}", "/site/v1/docs/", &pages["v1/docs/"], false);
+        assert!(page.starts_with("/site/v1/docs/|"));
+        assert!(page.contains(r#"<a href="../">"#));
+        assert!(page.contains(r#"<a href="a%26b.html">a&amp;b.html</a>"#));
+    }
+}
@@ -0,0 +1,189 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use once_cell::sync::Lazy;
+use ring::aead::{AES_256_GCM, Aad, LessSafeKey, NONCE_LEN, Nonce, UnboundKey};
+use ring::rand::{SecureRandom, SystemRandom};
+use std::collections::HashMap;
+use std::num::NonZeroU32;
+use std::sync::{Arc, RwLock};
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+/// Object metadata (`x-amz-meta-*`) written next to encrypted content; all of it is needed
+/// together with the passphrase to decrypt.
+pub const META_ALGORITHM: &str = "cse-alg";
+pub const META_KDF: &str = "cse-kdf";
+pub const META_ITERATIONS: &str = "cse-iterations";
+pub const META_SALT: &str = "cse-salt";
+pub const META_NONCE: &str = "cse-nonce";
+/// Content-Type of the plaintext; the object itself is stored as application/octet-stream.
+pub const META_CONTENT_TYPE: &str = "cse-content-type";
+/// What the ciphertext is bound to as AAD. `key` = the object key, so a ciphertext copied or
+/// moved to another key fails to decrypt.
+pub const META_AAD: &str = "cse-aad";
+const AAD_KEY: &str = "key";
+
+const ALGORITHM: &str = "AES-256-GCM";
+const KDF: &str = "PBKDF2-SHA256";
+const PBKDF2_ITERATIONS: u32 = 310_000;
+const SALT_LEN: usize = 16;
+
+/// Largest file encrypted or decrypted; the whole content is held in memory.
+pub const MAX_IN_MEMORY_SIZE: u64 = 1024 * 1024 * 1024;
+/// Files encrypted or decrypted at once, however many uploads run in parallel.
+const IN_MEMORY_SLOTS: usize = 4;
+static MEMORY_SLOTS: Lazy<Semaphore> = Lazy::new(|| Semaphore::new(IN_MEMORY_SLOTS));
+
+/// Environment variable used when no passphrase was typed in this session.
+const PASSPHRASE_ENV: &str = "S3_SYNC_ENCRYPTION_PASSPHRASE";
+
+/// Passphrase typed in Sync Options. Kept in memory only, never written to the config file.
+static PASSPHRASE: Lazy<RwLock<Option<String>>> = Lazy::new(|| RwLock::new(None));
+
+pub fn set_passphrase(passphrase: &str) {
+    crate::redact::register_secrets(&[passphrase]);
+    *PASSPHRASE.write().unwrap() = Some(passphrase.to_string());
+}
+
+/// Passphrase for this session, falling back to `S3_SYNC_ENCRYPTION_PASSPHRASE`.
+pub fn passphrase() -> Option<String> {
+    let passphrase = PASSPHRASE.read().unwrap().clone();
+    passphrase
+        .or_else(|| {
+            let passphrase = std::env::var(PASSPHRASE_ENV).ok()?;
+            crate::redact::register_secrets(&[&passphrase]);
+            Some(passphrase)
+        })
+        .filter(|p| !p.is_empty())
+}
+
+/// Waits for one of the few slots for holding the `size` bytes of `key` in memory. Files above
+/// [`MAX_IN_MEMORY_SIZE`] are refused.
+pub async fn memory_slot(size: u64, key: &str) -> Result<SemaphorePermit<'static>, String> {
+    if size > MAX_IN_MEMORY_SIZE {
+        return Err(format!(
+            "{} quá lớn cho mã hóa phía client ({}, tối đa {})",
+            key,
+            crate::utils::format_size(size),
+            crate::utils::format_size(MAX_IN_MEMORY_SIZE)
+        ));
+    }
+    MEMORY_SLOTS.acquire().await.map_err(|e| e.to_string())
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], iterations: u32) -> Result<LessSafeKey, String> {
+    let iterations = NonZeroU32::new(iterations).ok_or("Số vòng PBKDF2 không hợp lệ")?;
+    let mut key = [0u8; 32];
+    ring::pbkdf2::derive(ring::pbkdf2::PBKDF2_HMAC_SHA256, iterations, salt, passphrase.as_bytes(), &mut key);
+    UnboundKey::new(&AES_256_GCM, &key)
+        .map(LessSafeKey::new)
+        .map_err(|_| "Không tạo được khóa AES".to_string())
+}
+
+/// Encrypts file contents before upload. The key is derived once per sync (one salt per
+/// session); every file gets its own random nonce.
+pub struct Encryptor {
+    key: LessSafeKey,
+    salt: [u8; SALT_LEN],
+    rng: SystemRandom,
+}
+
+impl Encryptor {
+    pub fn new(passphrase: &str) -> Result<Self, String> {
+        let rng = SystemRandom::new();
+        let mut salt = [0u8; SALT_LEN];
+        rng.fill(&mut salt).map_err(|_| "Không tạo được salt ngẫu nhiên")?;
+        Ok(Self { key: derive_key(passphrase, &salt, PBKDF2_ITERATIONS)?, salt, rng })
+    }
+
+    /// Encrypts `data` in memory for the object `key`, returning the ciphertext (with GCM tag)
+    /// and the object metadata.
+    pub fn encrypt(&self, mut data: Vec<u8>, content_type: &str, key: &str) -> Result<(Vec<u8>, HashMap<String, String>), String> {
+        let mut nonce = [0u8; NONCE_LEN];
+        self.rng.fill(&mut nonce).map_err(|_| "Không tạo được nonce ngẫu nhiên")?;
+        self.key
+            .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::from(key.as_bytes()), &mut data)
+            .map_err(|_| "Lỗi mã hóa".to_string())?;
+        let metadata = HashMap::from([
+            (META_ALGORITHM.to_string(), ALGORITHM.to_string()),
+            (META_KDF.to_string(), KDF.to_string()),
+            (META_ITERATIONS.to_string(), PBKDF2_ITERATIONS.to_string()),
+            (META_SALT.to_string(), BASE64.encode(self.salt)),
+            (META_NONCE.to_string(), BASE64.encode(nonce)),
+            (META_CONTENT_TYPE.to_string(), content_type.to_string()),
+            (META_AAD.to_string(), AAD_KEY.to_string()),
+        ]);
+        Ok((data, metadata))
+    }
+}
+
+/// [`Encryptor::encrypt`] on a blocking thread; sealing a large file would stall the runtime.
+pub async fn encrypt_blocking(
+    encryptor: &Arc<Encryptor>,
+    data: Vec<u8>,
+    content_type: &str,
+    key: &str,
+) -> Result<(Vec<u8>, HashMap<String, String>), String> {
+    let encryptor = Arc::clone(encryptor);
+    let (content_type, key) = (content_type.to_string(), key.to_string());
+    tokio::task::spawn_blocking(move || encryptor.encrypt(data, &content_type, &key))
+        .await
+        .map_err(|e| format!("Lỗi mã hóa: {}", e))?
+}
+
+/// Decrypts the object `object_key` uploaded with client-side encryption, given its user metadata.
+pub fn decrypt(passphrase: &str, metadata: &HashMap<String, String>, mut data: Vec<u8>, object_key: &str) -> Result<Vec<u8>, String> {
+    let field = |name: &str| metadata.get(name).ok_or_else(|| format!("Thiếu metadata {}", name));
+    if field(META_ALGORITHM)? != ALGORITHM || field(META_KDF)? != KDF {
+        return Err("Thuật toán mã hóa không được hỗ trợ".to_string());
+    }
+    // Metadata can be rewritten by anyone with write access; a huge count would stall every download
+    if field(META_ITERATIONS)?.parse::<u32>().ok() != Some(PBKDF2_ITERATIONS) {
+        return Err("Metadata cse-iterations không hợp lệ".to_string());
+    }
+    let salt = BASE64.decode(field(META_SALT)?).map_err(|_| "Metadata cse-salt không hợp lệ")?;
+    let nonce = BASE64.decode(field(META_NONCE)?).map_err(|_| "Metadata cse-nonce không hợp lệ")?;
+    let nonce = Nonce::try_assume_unique_for_key(&nonce).map_err(|_| "Metadata cse-nonce không hợp lệ")?;
+    let aad = match field(META_AAD)?.as_str() {
+        AAD_KEY => object_key.as_bytes(),
+        other => return Err(format!("Metadata cse-aad không được hỗ trợ: {}", other)),
+    };
+    let key = derive_key(passphrase, &salt, PBKDF2_ITERATIONS)?;
+    let plain_len = key
+        .open_in_place(nonce, Aad::from(aad), &mut data)
+        .map_err(|_| "Sai passphrase hoặc dữ liệu bị hỏng".to_string())?
+        .len();
+    data.truncate(plain_len);
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_roundtrip() {
+        let encryptor = Encryptor::new("correct horse").unwrap();
+        let (ciphertext, metadata) = encryptor.encrypt(b"hello s3".to_vec(), "text/plain", "docs/a.txt").unwrap();
+        assert_ne!(&ciphertext[..8], b"hello s3");
+        assert_eq!(metadata[META_CONTENT_TYPE], "text/plain");
+        assert_eq!(decrypt("correct horse", &metadata, ciphertext.clone(), "docs/a.txt").unwrap(), b"hello s3");
+        assert!(decrypt("wrong", &metadata, ciphertext.clone(), "docs/a.txt").is_err());
+        // Swapped to another key, the ciphertext no longer opens
+        assert!(decrypt("correct horse", &metadata, ciphertext, "docs/b.txt").is_err());
+
+    }
+
+    #[test]
+    fn test_decrypt_rejects_tampered_metadata() {
+        let encryptor = Encryptor::new("correct horse").unwrap();
+        let (ciphertext, metadata) = encryptor.encrypt(b"hello s3".to_vec(), "text/plain", "docs/a.txt").unwrap();
+
+        let mut no_aad = metadata.clone();
+        no_aad.remove(META_AAD);
+        assert!(decrypt("correct horse", &no_aad, ciphertext.clone(), "docs/a.txt").is_err());
+
+        let mut slow = metadata.clone();
+        slow.insert(META_ITERATIONS.to_string(), u32::MAX.to_string());
+        assert!(decrypt("correct horse", &slow, ciphertext, "docs/a.txt").is_err());
+    }
+}
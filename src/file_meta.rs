@@ -0,0 +1,152 @@
+//! Local file attributes stored as object metadata (`x-amz-meta-*`) on upload and put back on
+//! download, so a restored file keeps its original timestamps like `aws s3 sync` does, and
+//! scripts and binaries stay executable.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Last modification time, as Unix seconds with millisecond fraction.
+pub const META_MTIME: &str = "mtime";
+/// Creation time, same format; only stored when the sync options ask for it.
+pub const META_CTIME: &str = "ctime";
+/// POSIX permission bits in octal, e.g. `755`. Only recorded and applied on Unix.
+pub const META_MODE: &str = "mode";
+
+fn format_time(time: SystemTime) -> Option<String> {
+    let since = time.duration_since(UNIX_EPOCH).ok()?;
+    Some(format!("{}.{:03}", since.as_secs(), since.subsec_millis()))
+}
+
+/// Parses a timestamp written by [`timestamp_metadata`]. Whole seconds are accepted too.
+pub fn parse_time(text: &str) -> Option<SystemTime> {
+    let (secs, frac) = text.trim().split_once('.').unwrap_or((text.trim(), ""));
+    let secs: u64 = secs.parse().ok()?;
+    let millis: u64 = if frac.is_empty() { 0 } else { format!("{:0<3}", &frac[..frac.len().min(3)]).parse().ok()? };
+    UNIX_EPOCH.checked_add(Duration::from_secs(secs) + Duration::from_millis(millis))
+}
+
+/// Metadata entries recording the file's timestamps; times the platform doesn't report are left out.
+pub fn timestamp_metadata(meta: &fs::Metadata, with_ctime: bool) -> HashMap<String, String> {
+    let mut out = HashMap::new();
+    if let Some(mtime) = meta.modified().ok().and_then(format_time) {
+        out.insert(META_MTIME.to_string(), mtime);
+    }
+    if with_ctime && let Some(ctime) = meta.created().ok().and_then(format_time) {
+        out.insert(META_CTIME.to_string(), ctime);
+    }
+    out
+}
+
+/// The permission bits of a file, as stored in [`META_MODE`]. `None` where there are none (Windows).
+#[cfg(unix)]
+pub fn file_mode(meta: &fs::Metadata) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+    Some(meta.permissions().mode() & 0o7777)
+}
+
+#[cfg(not(unix))]
+pub fn file_mode(_meta: &fs::Metadata) -> Option<u32> {
+    None
+}
+
+/// Parses an octal mode written by [`attribute_metadata`]. Only the rwx bits are kept: anyone
+/// who can write the object metadata must not get setuid/setgid files on download.
+pub fn parse_mode(text: &str) -> Option<u32> {
+    u32::from_str_radix(text.trim(), 8).ok().filter(|mode| *mode <= 0o7777).map(|mode| mode & 0o777)
+}
+
+/// Everything recorded for an uploaded file: its timestamps and, on Unix, its permission bits.
+pub fn attribute_metadata(meta: &fs::Metadata, with_ctime: bool) -> HashMap<String, String> {
+    let mut out = timestamp_metadata(meta, with_ctime);
+    if let Some(mode) = file_mode(meta) {
+        out.insert(META_MODE.to_string(), format!("{:o}", mode));
+    }
+    out
+}
+
+/// The modification time stored on an object, if it has one.
+pub fn stored_mtime(metadata: &HashMap<String, String>) -> Option<SystemTime> {
+    metadata.get(META_MTIME).and_then(|t| parse_time(t))
+}
+
+/// Sets the downloaded file's modification time from the object metadata. Objects uploaded
+/// without it keep the download time.
+pub fn restore_timestamps(path: &Path, metadata: Option<&HashMap<String, String>>) -> Result<(), String> {
+    let Some(mtime) = metadata.and_then(stored_mtime) else { return Ok(()) };
+    fs::File::options()
+        .write(true)
+        .open(path)
+        .and_then(|file| file.set_modified(mtime))
+        .map_err(|e| format!("Không thể đặt thời gian sửa đổi cho {}: {}", path.display(), e))
+}
+
+/// Applies the stored permission bits to a downloaded file. Does nothing off Unix or when the
+/// object has no mode recorded.
+#[cfg(unix)]
+pub fn restore_mode(path: &Path, metadata: Option<&HashMap<String, String>>) -> Result<(), String> {
+    use std::os::unix::fs::PermissionsExt;
+    let Some(mode) = metadata.and_then(|m| m.get(META_MODE)).and_then(|m| parse_mode(m)) else { return Ok(()) };
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))
+        .map_err(|e| format!("Không thể đặt quyền {:o} cho {}: {}", mode, path.display(), e))
+}
+
+#[cfg(not(unix))]
+pub fn restore_mode(_path: &Path, _metadata: Option<&HashMap<String, String>>) -> Result<(), String> {
+    Ok(())
+}
+
+/// Puts back everything [`attribute_metadata`] recorded. The mode goes last, since a read-only
+/// mode would stop the timestamp from being set.
+pub fn restore_attributes(path: &Path, metadata: Option<&HashMap<String, String>>) -> Result<(), String> {
+    restore_timestamps(path, metadata)?;
+    restore_mode(path, metadata)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timestamp_round_trip() {
+        let path = std::env::temp_dir().join(format!("s3sync_file_meta_{}.exr", std::process::id()));
+        fs::write(&path, b"data").unwrap();
+        let original = UNIX_EPOCH + Duration::from_millis(1_700_000_000_250);
+        fs::File::options().write(true).open(&path).unwrap().set_modified(original).unwrap();
+
+        let metadata = timestamp_metadata(&fs::metadata(&path).unwrap(), false);
+        assert_eq!(metadata.get(META_MTIME).map(String::as_str), Some("1700000000.250"));
+        assert!(!metadata.contains_key(META_CTIME));
+
+        fs::write(&path, b"downloaded").unwrap();
+        restore_timestamps(&path, Some(&metadata)).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().modified().unwrap(), original);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(parse_time("1700000000"), Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000)));
+        assert_eq!(parse_time("1700000000.5"), Some(UNIX_EPOCH + Duration::from_millis(1_700_000_000_500)));
+        assert_eq!(parse_time("yesterday"), None);
+    }
+
+    #[test]
+    fn test_mode_round_trip() {
+        assert_eq!(parse_mode("755"), Some(0o755));
+        assert_eq!(parse_mode("rwx"), None);
+        assert_eq!(parse_mode("17777"), None);
+        assert_eq!(parse_mode("4755"), Some(0o755));
+
+        let path = std::env::temp_dir().join(format!("s3sync_file_mode_{}.sh", std::process::id()));
+        fs::write(&path, b"#!/bin/sh\n").unwrap();
+        let metadata = HashMap::from([(META_MODE.to_string(), "750".to_string())]);
+        restore_attributes(&path, Some(&metadata)).unwrap();
+        let meta = fs::metadata(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        if cfg!(unix) {
+            assert_eq!(file_mode(&meta), Some(0o750));
+            assert_eq!(attribute_metadata(&meta, false).get(META_MODE).map(String::as_str), Some("750"));
+        } else {
+            assert_eq!(file_mode(&meta), None);
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Hex characters of the content hash inserted into fingerprinted names.
+const HASH_LEN: usize = 6;
+
+/// Inserts `hash` before the file extension: `js/app.js` → `js/app.3f9ab2.js`.
+/// Names without an extension (or dotfiles) get the hash appended.
+pub fn fingerprinted_key(key: &str, hash: &str) -> String {
+    let (dir, file_name) = match key.rfind('/') {
+        Some(i) => key.split_at(i + 1),
+        None => ("", key),
+    };
+    match file_name.rfind('.') {
+        Some(i) if i > 0 => format!("{}{}.{}{}", dir, &file_name[..i], hash, &file_name[i..]),
+        _ => format!("{}{}.{}", dir, file_name, hash),
+    }
+}
+
+/// Renames the keys of files matching `patterns` to their fingerprinted form.
+/// Returns the manifest (original key → fingerprinted key). Blocking: hashes file contents.
+pub fn fingerprint_files(
+    files: &mut [(PathBuf, PathBuf, String)],
+    patterns: &[String],
+    cache: &crate::dedup::HashCache,
+) -> Result<BTreeMap<String, String>, String> {
+    let mut manifest = BTreeMap::new();
+    for (path, _, key) in files.iter_mut() {
+        if !crate::utils::matches_any_pattern(key, patterns) {
+            continue;
+        }
+        let hash = cache.sha256(path).map_err(|e| format!("Lỗi tính hash {}: {}", path.display(), e))?;
+        let hashed = fingerprinted_key(key, &hash[..HASH_LEN]);
+        manifest.insert(std::mem::replace(key, hashed.clone()), hashed);
+    }
+    Ok(manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprinted_key() {
+        assert_eq!(fingerprinted_key("app.js", "3f9ab2"), "app.3f9ab2.js");
+        assert_eq!(fingerprinted_key("static/css/site.min.css", "00aa11"), "static/css/site.min.00aa11.css");
+        assert_eq!(fingerprinted_key("v1.2/LICENSE", "abcdef"), "v1.2/LICENSE.abcdef");
+        assert_eq!(fingerprinted_key("assets/.env", "abcdef"), "assets/.env.abcdef");
+    }
+}
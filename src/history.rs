@@ -0,0 +1,75 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tracing::warn;
+
+use crate::s3_client::SyncSummary;
+
+/// Number of sessions kept on disk; older entries are dropped.
+const MAX_SESSIONS: usize = 50;
+const HISTORY_FILE_NAME: &str = "sessions.json";
+
+/// A recorded sync session with the exact key set it uploaded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncSession {
+    pub id: String,
+    pub started_at: String,
+    pub bucket: String,
+    pub region: String,
+    pub success: bool,
+    pub files: usize,
+    pub bytes: u64,
+    pub keys: Vec<String>,
+}
+
+impl SyncSession {
+    pub fn from_summary(summary: &SyncSummary, region: &str) -> Self {
+        let now = chrono::Local::now();
+        Self {
+            id: now.format("%Y%m%d_%H%M%S%3f").to_string(),
+            started_at: now.format("%Y-%m-%d %H:%M:%S").to_string(),
+            bucket: summary.bucket.clone(),
+            region: region.to_string(),
+            success: summary.is_success(),
+            files: summary.uploaded,
+            bytes: summary.bytes_uploaded,
+            keys: summary.uploaded_keys.clone(),
+        }
+    }
+}
+
+/// History file lives next to the confy config file.
+fn history_path() -> Option<PathBuf> {
+    crate::config::get_config_path()
+        .and_then(|p| p.parent().map(|d| d.join(HISTORY_FILE_NAME)))
+}
+
+/// Loads recorded sessions, newest first. Returns an empty list if the file is missing or invalid.
+pub fn load_sessions() -> Vec<SyncSession> {
+    let Some(path) = history_path() else { return Vec::new() };
+    match std::fs::read_to_string(&path) {
+        Ok(text) => serde_json::from_str(&text).unwrap_or_else(|e| {
+            warn!("Không thể đọc lịch sử sync {:?}: {}", path, e);
+            Vec::new()
+        }),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Prepends a session to the history and trims it to `MAX_SESSIONS`.
+pub fn record_session(session: SyncSession) -> Result<(), String> {
+    let path = history_path().ok_or_else(|| "Không xác định được thư mục cấu hình".to_string())?;
+    let mut sessions = load_sessions();
+    sessions.insert(0, session);
+    sessions.truncate(MAX_SESSIONS);
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let text = serde_json::to_string_pretty(&sessions).map_err(|e| e.to_string())?;
+    std::fs::write(&path, text).map_err(|e| format!("Không thể ghi lịch sử sync: {}", e))
+}
+
+/// Finds a session by id.
+pub fn find_session(id: &str) -> Option<SyncSession> {
+    load_sessions().into_iter().find(|s| s.id == id)
+}
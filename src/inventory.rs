@@ -0,0 +1,202 @@
+use aws_sdk_s3::Client;
+use serde::Deserialize;
+use std::io::Read;
+use std::path::Path;
+use tracing::info;
+
+use crate::diff::{RemoteListing, RemoteObject};
+
+/// Column order of an inventory CSV when the manifest doesn't say otherwise.
+const DEFAULT_SCHEMA: &str = "Bucket, Key, Size, LastModifiedDate, ETag, StorageClass";
+
+/// The parts of an S3 Inventory `manifest.json` needed to read its data files.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Manifest {
+    #[serde(default)]
+    destination_bucket: String,
+    file_format: String,
+    file_schema: String,
+    files: Vec<ManifestFile>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ManifestFile {
+    key: String,
+}
+
+/// Loads the remote state of `prefix` from a file instead of listing the bucket:
+/// - an S3 Inventory `manifest.json` (CSV format). Data files are read from `data/` next to
+///   the manifest when downloaded, otherwise from the inventory destination bucket with `client`;
+/// - a single inventory `.csv` / `.csv.gz` data file;
+/// - a listing previously saved with [`save_listing`] (keys already relative to its prefix).
+///
+/// Keys are returned relative to `prefix`, like [`crate::diff::list_remote_objects`].
+pub async fn load_listing(client: &Client, path: &Path, prefix: &str) -> Result<RemoteListing, String> {
+    let prefix = crate::diff::normalize_list_prefix(prefix);
+    let name = path.file_name().unwrap_or_default().to_string_lossy().to_lowercase();
+    if name.ends_with(".csv") || name.ends_with(".csv.gz") {
+        let text = read_data_file(&std::fs::read(path).map_err(|e| format!("Không đọc được {}: {}", path.display(), e))?, &name)?;
+        let mut listing = RemoteListing::new();
+        parse_inventory_csv(&text, DEFAULT_SCHEMA, &prefix, &mut listing)?;
+        return Ok(listing);
+    }
+
+    let text = std::fs::read_to_string(path).map_err(|e| format!("Không đọc được {}: {}", path.display(), e))?;
+    if !text.contains("\"fileSchema\"") {
+        let saved: RemoteListing =
+            serde_json::from_str(&text).map_err(|e| format!("File listing không hợp lệ {}: {}", path.display(), e))?;
+        return Ok(saved);
+    }
+
+    let manifest: Manifest =
+        serde_json::from_str(&text).map_err(|e| format!("Manifest inventory không hợp lệ {}: {}", path.display(), e))?;
+    if !manifest.file_format.eq_ignore_ascii_case("CSV") {
+        return Err(format!(
+            "Inventory dạng {} chưa được hỗ trợ, hãy cấu hình S3 Inventory xuất CSV",
+            manifest.file_format
+        ));
+    }
+    let destination = manifest.destination_bucket.trim_start_matches("arn:aws:s3:::").to_string();
+    let local_dir = path.parent().unwrap_or(Path::new(".")).join("data");
+
+    let mut listing = RemoteListing::new();
+    for file in &manifest.files {
+        let file_name = file.key.rsplit('/').next().unwrap_or(&file.key).to_string();
+        let local = local_dir.join(&file_name);
+        let bytes = if local.is_file() {
+            std::fs::read(&local).map_err(|e| format!("Không đọc được {}: {}", local.display(), e))?
+        } else {
+            client
+                .get_object()
+                .bucket(&destination)
+                .key(&file.key)
+                .send()
+                .await
+                .map_err(|e| format!("Lỗi tải inventory s3://{}/{}: {}", destination, file.key, e))?
+                .body
+                .collect()
+                .await
+                .map_err(|e| format!("Lỗi tải inventory s3://{}/{}: {}", destination, file.key, e))?
+                .to_vec()
+        };
+        let text = read_data_file(&bytes, &file_name.to_lowercase())?;
+        parse_inventory_csv(&text, &manifest.file_schema, &prefix, &mut listing)?;
+    }
+    info!("Loaded {} objects under '{}' from inventory {}", listing.len(), prefix, path.display());
+    Ok(listing)
+}
+
+/// Saves a listing so later comparisons can use it instead of listing the bucket again.
+pub fn save_listing(path: &Path, listing: &RemoteListing) -> Result<(), String> {
+    let text = serde_json::to_string(listing).map_err(|e| e.to_string())?;
+    std::fs::write(path, text).map_err(|e| format!("Không ghi được {}: {}", path.display(), e))
+}
+
+fn read_data_file(bytes: &[u8], name: &str) -> Result<String, String> {
+    if name.ends_with(".gz") {
+        let mut text = String::new();
+        flate2::read::GzDecoder::new(bytes)
+            .read_to_string(&mut text)
+            .map_err(|e| format!("Lỗi giải nén {}: {}", name, e))?;
+        Ok(text)
+    } else {
+        String::from_utf8(bytes.to_vec()).map_err(|e| format!("{} không phải UTF-8: {}", name, e))
+    }
+}
+
+/// Adds the objects under `prefix` from inventory CSV rows. Keys in inventory files are URL-encoded.
+fn parse_inventory_csv(text: &str, schema: &str, prefix: &str, listing: &mut RemoteListing) -> Result<(), String> {
+    let columns: Vec<String> = schema.split(',').map(|c| c.trim().to_string()).collect();
+    let column = |name: &str| columns.iter().position(|c| c == name);
+    let key_col = column("Key").ok_or("Inventory thiếu cột Key")?;
+    let (size_col, modified_col) = (column("Size"), column("LastModifiedDate"));
+    let (etag_col, class_col) = (column("ETag"), column("StorageClass"));
+
+    for line in text.lines().filter(|l| !l.trim().is_empty()) {
+        let fields = split_csv_line(line);
+        let field = |col: Option<usize>| col.and_then(|c| fields.get(c)).map(String::as_str).unwrap_or_default();
+        let key = percent_decode(field(Some(key_col)));
+        let Some(relative) = key.strip_prefix(prefix) else { continue };
+        if relative.is_empty() || relative.ends_with('/') {
+            continue;
+        }
+        listing.insert(
+            relative.to_string(),
+            RemoteObject {
+                size: field(size_col).parse().unwrap_or(0),
+                etag: field(etag_col).to_string(),
+                last_modified: chrono::DateTime::parse_from_rfc3339(field(modified_col))
+                    .map(|t| t.timestamp())
+                    .unwrap_or(0),
+                storage_class: field(class_col).to_string(),
+                file_modified: None,
+            },
+        );
+    }
+    Ok(())
+}
+
+/// Splits one CSV line, honouring double quotes and `""` escapes.
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok()).and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(b)) => {
+                out.push(b);
+                i += 3;
+            }
+            (b'+', _) => {
+                out.push(b' ');
+                i += 1;
+            }
+            (b, _) => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_inventory_csv() {
+        let csv = "\"my-bucket\",\"site/prod/a%20b.html\",\"12\",\"2024-01-02T03:04:05.000Z\",\"abc\",\"STANDARD\"\n\
+                   \"my-bucket\",\"site/prod/img/\",\"0\",\"2024-01-02T03:04:05.000Z\",\"d41d\",\"STANDARD\"\n\
+                   \"my-bucket\",\"other/x.js\",\"5\",\"2024-01-02T03:04:05.000Z\",\"def\",\"GLACIER\"\n";
+        let mut listing = RemoteListing::new();
+        parse_inventory_csv(csv, DEFAULT_SCHEMA, "site/prod/", &mut listing).unwrap();
+        assert_eq!(listing.len(), 1);
+        let obj = &listing["a b.html"];
+        assert_eq!((obj.size, obj.etag.as_str(), obj.storage_class.as_str()), (12, "abc", "STANDARD"));
+        assert_eq!(obj.last_modified, 1704164645);
+        assert_eq!(split_csv_line("\"a,b\",\"say \"\"hi\"\"\""), vec!["a,b", "say \"hi\""]);
+    }
+}
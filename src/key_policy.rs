@@ -0,0 +1,97 @@
+use regex::Regex;
+
+use crate::config::KeyPolicy;
+
+/// A key that breaks one of the configured naming rules.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyViolation {
+    pub key: String,
+    pub reason: String,
+}
+
+/// Validates the policy itself. Returns an error message if invalid, or None if valid.
+pub fn validate_policy(policy: &KeyPolicy) -> Option<String> {
+    if policy.max_key_length == 0 || policy.max_key_length > 1024 {
+        return Some("Độ dài key tối đa phải từ 1 đến 1024".to_string());
+    }
+    if !policy.required_prefix_regex.is_empty()
+        && let Err(e) = Regex::new(&policy.required_prefix_regex)
+    {
+        return Some(format!("Regex không hợp lệ: {}", e));
+    }
+    None
+}
+
+/// Checks every key against the policy and returns all violations (one per broken rule).
+pub fn check_keys<'a>(policy: &KeyPolicy, keys: impl IntoIterator<Item = &'a str>) -> Result<Vec<KeyViolation>, String> {
+    if let Some(err) = validate_policy(policy) {
+        return Err(err);
+    }
+    let prefix_re = if policy.required_prefix_regex.is_empty() {
+        None
+    } else {
+        Some(Regex::new(&policy.required_prefix_regex).map_err(|e| e.to_string())?)
+    };
+
+    let mut violations = Vec::new();
+    for key in keys {
+        let mut push = |reason: String| {
+            violations.push(KeyViolation {
+                key: key.to_string(),
+                reason,
+            })
+        };
+
+        if key.len() > policy.max_key_length {
+            push(format!("dài {} bytes (tối đa {})", key.len(), policy.max_key_length));
+        }
+        let forbidden: String = key.chars().filter(|c| policy.forbidden_chars.contains(*c)).collect();
+        if !forbidden.is_empty() {
+            push(format!("chứa ký tự cấm '{}'", forbidden));
+        }
+        if let Some(re) = &prefix_re
+            && !re.is_match(key)
+        {
+            push(format!("không khớp {}", policy.required_prefix_regex));
+        }
+    }
+    Ok(violations)
+}
+
+/// Builds a readable report listing at most `max_lines` violations.
+pub fn format_violations(violations: &[KeyViolation], max_lines: usize) -> String {
+    let mut report = format!("{} vi phạm quy tắc đặt tên key:\n", violations.len());
+    for v in violations.iter().take(max_lines) {
+        report.push_str(&format!("- {}: {}\n", v.key, v.reason));
+    }
+    if violations.len() > max_lines {
+        report.push_str(&format!("... và {} vi phạm khác\n", violations.len() - max_lines));
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_keys() {
+        let policy = KeyPolicy {
+            enabled: true,
+            max_key_length: 20,
+            forbidden_chars: " #".to_string(),
+            required_prefix_regex: "^(assets|static)/".to_string(),
+        };
+        let keys = ["assets/app.js", "static/my file.png", "docs/readme.md", "assets/a-very-long-name.css"];
+
+        let violations = check_keys(&policy, keys).unwrap();
+        let broken: Vec<&str> = violations.iter().map(|v| v.key.as_str()).collect();
+        assert_eq!(broken, vec!["static/my file.png", "docs/readme.md", "assets/a-very-long-name.css"]);
+
+        let bad = KeyPolicy {
+            required_prefix_regex: "(".to_string(),
+            ..policy
+        };
+        assert!(check_keys(&bad, ["x"]).is_err());
+    }
+}
@@ -0,0 +1,48 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tracing::warn;
+
+const SESSION_FILE_NAME: &str = "last_session.json";
+
+/// One entry of the mapping list as it was when the app closed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedMapping {
+    pub local_path: String,
+    pub s3_path: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// Session file lives next to the confy config file.
+fn session_path() -> Option<PathBuf> {
+    crate::config::get_config_path().and_then(|p| p.parent().map(|d| d.join(SESSION_FILE_NAME)))
+}
+
+/// Mapping list saved by the previous run. Empty if there is none.
+pub fn load_mappings() -> Vec<SavedMapping> {
+    let Some(path) = session_path() else { return Vec::new() };
+    match std::fs::read_to_string(&path) {
+        Ok(text) => serde_json::from_str(&text).unwrap_or_else(|e| {
+            warn!("Không thể đọc phiên làm việc trước {:?}: {}", path, e);
+            Vec::new()
+        }),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Saves the current mapping list for the next run; an empty list removes the file.
+pub fn save_mappings(mappings: &[SavedMapping]) -> Result<(), String> {
+    let path = session_path().ok_or_else(|| "Không xác định được thư mục cấu hình".to_string())?;
+    if mappings.is_empty() {
+        if path.exists() {
+            std::fs::remove_file(&path).map_err(|e| format!("Không thể xóa {:?}: {}", path, e))?;
+        }
+        return Ok(());
+    }
+    let text = serde_json::to_string_pretty(mappings).map_err(|e| e.to_string())?;
+    std::fs::write(&path, text).map_err(|e| format!("Không thể ghi {:?}: {}", path, e))
+}
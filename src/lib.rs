@@ -1 +1,51 @@
-slint::include_modules!();
+//! Sync engine shared by the desktop app. Nothing here depends on the UI; progress is
+//! reported through [`progress::ProgressSink`].
+
+pub mod app_state;
+pub mod append_log;
+pub mod archive;
+pub mod batch_ops;
+pub mod bucket_policy;
+pub mod budget;
+pub mod checksums;
+pub mod config;
+pub mod connectivity;
+pub mod crash;
+pub mod dedup;
+pub mod diff;
+pub mod dir_index;
+pub mod encryption;
+pub mod file_meta;
+pub mod fingerprint;
+pub mod history;
+pub mod inventory;
+pub mod key_policy;
+pub mod last_session;
+pub mod lifecycle;
+pub mod logging;
+pub mod metrics;
+pub mod notify;
+pub mod object_store;
+pub mod preflight;
+pub mod progress;
+pub mod queue;
+pub mod redact;
+pub mod redirects;
+pub mod regions;
+pub mod replicas;
+pub mod restore;
+pub mod s3_client;
+pub mod scheduler;
+pub mod single_instance;
+pub mod smoke_test;
+pub mod sync_error;
+pub mod sync_log;
+pub mod sync_window;
+pub mod throughput;
+pub mod transfers;
+pub mod two_way;
+pub mod updates;
+pub mod usage;
+pub mod utils;
+pub mod verify;
+pub mod website;
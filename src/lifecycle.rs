@@ -0,0 +1,132 @@
+//! Lifecycle rules that touch the sync destination. Shown before a sync so nobody is surprised
+//! when uploads under a prefix move to Glacier or get deleted after N days.
+
+use aws_sdk_s3::Client;
+use aws_sdk_s3::types::{ExpirationStatus, LifecycleRule};
+
+/// The parts of an enabled lifecycle rule that matter for uploaded objects.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RuleInfo {
+    pub id: String,
+    /// Key prefix the rule is limited to; empty = whole bucket.
+    pub prefix: String,
+    /// The rule also filters on tags or object size, so it may not hit every upload.
+    pub conditional: bool,
+    pub expire_days: Option<i32>,
+    pub expire_date: Option<String>,
+    /// (days, storage class) for each transition.
+    pub transitions: Vec<(i32, String)>,
+    pub noncurrent_days: Option<i32>,
+}
+
+fn rule_info(rule: &LifecycleRule) -> RuleInfo {
+    let filter = rule.filter();
+    let and = filter.and_then(|f| f.and());
+    #[allow(deprecated)]
+    let prefix = filter
+        .and_then(|f| f.prefix())
+        .or_else(|| and.and_then(|a| a.prefix()))
+        .or_else(|| rule.prefix())
+        .unwrap_or_default();
+    let conditional = filter.is_some_and(|f| {
+        f.tag().is_some() || f.object_size_greater_than().is_some() || f.object_size_less_than().is_some()
+    }) || and.is_some_and(|a| {
+        !a.tags().is_empty() || a.object_size_greater_than().is_some() || a.object_size_less_than().is_some()
+    });
+    RuleInfo {
+        id: rule.id().unwrap_or("(không tên)").to_string(),
+        prefix: prefix.to_string(),
+        conditional,
+        expire_days: rule.expiration().and_then(|e| e.days()),
+        expire_date: rule.expiration().and_then(|e| e.date()).map(|d| d.to_string()),
+        transitions: rule
+            .transitions()
+            .iter()
+            .filter_map(|t| Some((t.days()?, t.storage_class()?.as_str().to_string())))
+            .collect(),
+        noncurrent_days: rule.noncurrent_version_expiration().and_then(|n| n.noncurrent_days()),
+    }
+}
+
+/// Enabled rules of the bucket; empty when it has no lifecycle configuration.
+pub async fn fetch_rules(client: &Client, bucket: &str) -> Result<Vec<RuleInfo>, String> {
+    match client.get_bucket_lifecycle_configuration().bucket(bucket).send().await {
+        Ok(out) => Ok(out.rules().iter().filter(|r| *r.status() == ExpirationStatus::Enabled).map(rule_info).collect()),
+        Err(e) if e.as_service_error().and_then(|s| s.meta().code()) == Some("NoSuchLifecycleConfiguration") => Ok(Vec::new()),
+        Err(e) => Err(format!("Không đọc được lifecycle của {}: {}", bucket, e)),
+    }
+}
+
+/// Whether a rule limited to `rule_prefix` can act on objects uploaded under `dest`: either the
+/// rule covers the whole destination, or it covers a part of it.
+pub fn overlaps(rule_prefix: &str, dest: &str) -> bool {
+    dest.starts_with(rule_prefix) || rule_prefix.starts_with(dest)
+}
+
+/// What the rule does, e.g. "xóa object sau 30 ngày, chuyển sang GLACIER sau 90 ngày".
+/// `None` for rules that don't affect current objects' data (e.g. only abort multipart uploads).
+pub fn describe(rule: &RuleInfo) -> Option<String> {
+    let mut actions = Vec::new();
+    if let Some(days) = rule.expire_days {
+        actions.push(format!("xóa object sau {} ngày", days));
+    }
+    if let Some(date) = &rule.expire_date {
+        actions.push(format!("xóa object từ {}", date));
+    }
+    for (days, class) in &rule.transitions {
+        actions.push(format!("chuyển sang {} sau {} ngày", class, days));
+    }
+    if let Some(days) = rule.noncurrent_days {
+        actions.push(format!("xóa phiên bản cũ sau {} ngày", days));
+    }
+    if actions.is_empty() {
+        return None;
+    }
+    Some(actions.join(", "))
+}
+
+/// One warning per rule that overlaps any destination prefix.
+pub fn warnings(rules: &[RuleInfo], prefixes: &[String]) -> Vec<String> {
+    rules
+        .iter()
+        .filter(|rule| prefixes.iter().any(|dest| overlaps(&rule.prefix, dest)))
+        .filter_map(|rule| {
+            let actions = describe(rule)?;
+            let scope = if rule.prefix.is_empty() { "toàn bucket".to_string() } else { format!("prefix '{}'", rule.prefix) };
+            let condition = if rule.conditional { " (chỉ object khớp tag/kích thước)" } else { "" };
+            Some(format!("Lifecycle '{}' trên {}{}: {}", rule.id, scope, condition, actions))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rules_matching_destination() {
+        assert!(overlaps("", "site/"));
+        assert!(overlaps("logs/", "logs/2024/"));
+        assert!(overlaps("logs/tmp/", "logs/"));
+        assert!(!overlaps("logs/", "site/"));
+
+        let rules = vec![
+            RuleInfo { id: "expire-logs".into(), prefix: "logs/".into(), expire_days: Some(30), ..Default::default() },
+            RuleInfo {
+                id: "archive".into(),
+                transitions: vec![(90, "GLACIER".into())],
+                conditional: true,
+                ..Default::default()
+            },
+            RuleInfo { id: "abort-mpu".into(), ..Default::default() },
+        ];
+        assert_eq!(
+            warnings(&rules, &["logs/app/".to_string()]),
+            vec![
+                "Lifecycle 'expire-logs' trên prefix 'logs/': xóa object sau 30 ngày",
+                "Lifecycle 'archive' trên toàn bucket (chỉ object khớp tag/kích thước): chuyển sang GLACIER sau 90 ngày",
+            ]
+        );
+        assert_eq!(warnings(&rules, &["site/".to_string()]).len(), 1);
+    }
+}
@@ -0,0 +1,49 @@
+use crate::redact::RedactingMakeWriter;
+use once_cell::sync::OnceCell;
+use tracing_subscriber::{EnvFilter, Registry, fmt, prelude::*, reload};
+
+/// File name of the debug log in [`crate::config::debug_log_dir`].
+pub const DEBUG_LOG_FILE: &str = "s3_debug.log";
+
+/// Verbosity levels offered in the settings menu, from least to most verbose.
+pub const LOG_LEVELS: [&str; 5] = ["error", "warn", "info", "debug", "trace"];
+
+/// Handle used to swap the active filter without restarting the app.
+static FILTER_HANDLE: OnceCell<reload::Handle<EnvFilter, Registry>> = OnceCell::new();
+
+/// Returns the level if it is one of `LOG_LEVELS`, otherwise `None`.
+pub fn parse_log_level(level: &str) -> Option<&'static str> {
+    let level = level.trim().to_lowercase();
+    LOG_LEVELS.iter().find(|l| **l == level).copied()
+}
+
+/// Initializes tracing with a reloadable filter.
+/// `RUST_LOG` takes precedence over the configured level at startup.
+pub fn init(level: &str) -> tracing_appender::non_blocking::WorkerGuard {
+    let file_appender = tracing_appender::rolling::never(crate::config::debug_log_dir(), DEBUG_LOG_FILE);
+    let (non_blocking, guard) = tracing_appender::non_blocking(file_appender);
+
+    let level = parse_log_level(level).unwrap_or("info");
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(level));
+    let (filter_layer, handle) = reload::Layer::new(filter);
+
+    tracing_subscriber::registry()
+        .with(filter_layer)
+        .with(fmt::layer().with_writer(RedactingMakeWriter(non_blocking)))
+        .with(fmt::layer().with_writer(RedactingMakeWriter(std::io::stdout)))
+        .init();
+
+    let _ = FILTER_HANDLE.set(handle);
+    guard
+}
+
+/// Replaces the active tracing filter with the given level.
+pub fn set_log_level(level: &str) -> Result<(), String> {
+    let level = parse_log_level(level).ok_or_else(|| format!("Log level không hợp lệ: {}", level))?;
+    let handle = FILTER_HANDLE
+        .get()
+        .ok_or_else(|| "Logging chưa được khởi tạo".to_string())?;
+    handle
+        .reload(EnvFilter::new(level))
+        .map_err(|e| format!("Không thể đổi log level: {}", e))
+}
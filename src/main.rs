@@ -1,31 +1,58 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use tracing::info;
-use tracing_subscriber::{EnvFilter, fmt, prelude::*};
+use slint::Model;
+use tracing::{info, warn};
 
 use rust_project::*;
 
-mod config;
-mod s3_client;
+slint::include_modules!();
+
 mod ui_handlers;
-mod utils;
 
 #[tokio::main]
 async fn main() -> Result<(), anyhow::Error> {
-    // Initialize logging
-    let file_appender = tracing_appender::rolling::never(".", "s3_debug.log");
-    let (non_blocking, _guard) = tracing_appender::non_blocking(file_appender);
+    // Portable mode decides where the config lives, so it goes before anything reads it
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let portable = config::init_portable_mode(&args);
+
+    // A second launch hands its paths to the running window and exits, before it loads (and
+    // possibly migrates) the config or opens the log
+    let launch_paths: Vec<String> = args.into_iter().filter(|a| a != config::PORTABLE_FLAG).collect();
+    let instance = match single_instance::start(&launch_paths) {
+        Ok(single_instance::Startup::HandedOff) => return Ok(()),
+        Ok(startup) => startup,
+        Err(e) => {
+            rfd::MessageDialog::new()
+                .set_level(rfd::MessageLevel::Error)
+                .set_title("S3 Sync Tool")
+                .set_description(format!("Ứng dụng đang chạy ở cửa sổ khác nhưng không phản hồi.\n{}", e))
+                .show();
+            return Ok(());
+        }
+    };
 
-    tracing_subscriber::registry()
-        .with(EnvFilter::from_default_env().add_directive(tracing::Level::DEBUG.into()))
-        .with(fmt::layer().with_writer(non_blocking))
-        .with(fmt::layer())
-        .init();
+    // Load saved config; handlers share it through the app state
+    let state = app_state::AppState::start();
+    let app_config = state.config();
+
+    // Initialize logging
+    let _guard = logging::init(&app_config.log_level);
+    crash::install_panic_hook();
+    if !app_config.metrics_path.is_empty() {
+        metrics::load_timestamps(std::path::Path::new(&app_config.metrics_path));
+    }
 
     info!("Ứng dụng S3 Sync Tool đang khởi động...");
-    
-    // Load saved config
-    let app_config = config::load_config();
+    match portable {
+        Ok(true) => info!("Portable mode: data stored next to the executable"),
+        Ok(false) => {}
+        Err(e) => warn!("Portable mode: {}", e),
+    }
+    match &instance {
+        single_instance::Startup::First(_) => info!("Single-instance lock taken"),
+        single_instance::Startup::Unguarded(reason) => warn!("{}; running without single-instance lock", reason),
+        single_instance::Startup::HandedOff => {}
+    }
     info!("Config loaded from: {:?}", config::get_config_path());
     info!("Loaded log_path: '{}'", app_config.log_path);
     
@@ -33,7 +60,7 @@ async fn main() -> Result<(), anyhow::Error> {
     
     // Apply saved config to UI
     if !app_config.log_path.is_empty() {
-        ui.set_log_path(app_config.log_path.into());
+        ui.set_log_path(config::resolve_user_path(&app_config.log_path).into());
     }
     if !app_config.s3_base_path.is_empty() {
         ui.set_s3_base_path(app_config.s3_base_path.into());
@@ -47,6 +74,10 @@ async fn main() -> Result<(), anyhow::Error> {
     ui.set_enable_filtering(app_config.filter_config.enable_filtering);
     ui.set_exclude_patterns_text(exclude_text.into());
     ui.set_include_patterns_text(include_text.into());
+    ui.set_exclude_mime_text(app_config.filter_config.exclude_mime.join(", ").into());
+    ui.set_include_mime_text(app_config.filter_config.include_mime.join(", ").into());
+    ui.set_skip_hidden(app_config.filter_config.skip_hidden);
+    ui.set_hidden_allow_text(app_config.filter_config.hidden_allow.join(", ").into());
     ui.set_max_file_size_text(max_size_text.into());
 
     if !app_config.selected_bucket.is_empty() {
@@ -55,16 +86,115 @@ async fn main() -> Result<(), anyhow::Error> {
     if !app_config.selected_region.is_empty() {
         ui.set_region(app_config.selected_region.into());
     }
+    ui.set_smtp_enabled(app_config.smtp.enabled);
+    ui.set_smtp_host(app_config.smtp.host.clone().into());
+    ui.set_smtp_port_text(app_config.smtp.port.to_string().into());
+    ui.set_smtp_username(app_config.smtp.username.clone().into());
+    ui.set_smtp_from(app_config.smtp.from.clone().into());
+    ui.set_smtp_to(app_config.smtp.to.clone().into());
+    ui_handlers::set_app_settings(&ui, &app_config.settings);
+    ui_handlers::set_prefix_rules(&ui, &app_config.prefix_rules);
+    ui.set_dedup_mode(app_config.sync_options.dedup_mode.as_str().into());
+    ui.set_queue_parallel_text(app_config.sync_options.queue_parallel.to_string().into());
+    ui.set_conflict_policy(app_config.sync_options.conflict_policy.as_str().into());
+    ui.set_header_rules_text(utils::format_header_rules(&app_config.sync_options.header_rules).into());
+    ui.set_disposition_rules_text(utils::format_disposition_rules(&app_config.sync_options.disposition_rules).into());
+    ui.set_entry_patterns_text(app_config.sync_options.entry_patterns.join(", ").into());
+    ui.set_fingerprint_patterns_text(app_config.sync_options.fingerprint_patterns.join(", ").into());
+    ui.set_fingerprint_manifest(app_config.sync_options.fingerprint_manifest.clone().into());
+    ui.set_client_side_encryption(app_config.sync_options.client_side_encryption);
+    ui.set_archive_upload(app_config.sync_options.archive_upload);
+    ui.set_append_only(app_config.sync_options.append_only);
+    ui.set_checksum_manifest(app_config.sync_options.checksum_manifest.clone().into());
+    ui.set_replica_targets_text(
+        app_config.sync_options.replica_targets.iter().map(|t| t.to_string()).collect::<Vec<_>>().join(", ").into(),
+    );
+    ui.set_replica_copy(app_config.sync_options.replica_mode == config::ReplicaMode::Copy);
+    ui.set_dir_index(app_config.sync_options.dir_index);
+    ui.set_dir_index_template(app_config.sync_options.dir_index_template.clone().into());
+    ui.set_batch_threshold_text(app_config.sync_options.batch_threshold.to_string().into());
+    ui.set_batch_role_arn(app_config.sync_options.batch_role_arn.clone().into());
+    ui.set_batch_manifest_bucket(app_config.sync_options.batch_manifest_bucket.clone().into());
+    ui.set_redirects_file(app_config.sync_options.redirects_file.clone().into());
+    ui.set_smoke_test_text(app_config.sync_options.smoke_test_urls.join("\n").into());
+    ui.set_verify_sample_text(app_config.sync_options.verify_sample.to_string().into());
+    ui.set_trash_overwritten(app_config.sync_options.trash_overwritten);
+    ui.set_staged_deploy(app_config.sync_options.staged_deploy);
+    ui.set_staged_cleanup(app_config.sync_options.staged_cleanup);
+    ui.set_create_folder_markers(app_config.sync_options.create_folder_markers);
+    ui.set_placeholder_policy(app_config.sync_options.placeholder_policy.as_str().into());
+    ui.set_sparse_policy(app_config.sync_options.sparse_policy.as_str().into());
+    ui.set_date_partition(app_config.sync_options.date_partition.as_str().into());
+    ui.set_sniff_mime(app_config.sync_options.sniff_mime);
+    ui.set_store_ctime(app_config.sync_options.store_ctime);
+    ui.set_large_file_threshold_text(app_config.sync_options.large_file_threshold_mb.to_string().into());
+    ui.set_large_file_lane_text(app_config.sync_options.large_file_lane.to_string().into());
+    ui.set_sync_window_text(app_config.sync_options.sync_window.clone().into());
+    ui.set_pause_on_metered(app_config.sync_options.pause_on_metered);
+    ui.set_session_budget_text(app_config.sync_options.session_budget_mb.to_string().into());
+    ui.set_scan_max_depth_text(app_config.sync_options.scan_max_depth.to_string().into());
+    ui.set_scan_max_files_text(app_config.sync_options.scan_max_files.to_string().into());
+    let key_policy = &app_config.sync_options.key_policy;
+    ui.set_key_policy_enabled(key_policy.enabled);
+    ui.set_max_key_length_text(key_policy.max_key_length.to_string().into());
+    ui.set_forbidden_chars(key_policy.forbidden_chars.clone().into());
+    ui.set_required_prefix_regex(key_policy.required_prefix_regex.clone().into());
+    ui.set_log_level(logging::parse_log_level(&app_config.log_level).unwrap_or("info").into());
+    ui.set_log_file_mode(app_config.log_file_mode.as_str().into());
 
     // Set lists for ComboBoxes
     let bucket_model = slint::VecModel::from(app_config.buckets.iter().map(|s| s.clone().into()).collect::<Vec<slint::SharedString>>());
     ui.set_bucket_list(slint::ModelRc::from(std::rc::Rc::new(bucket_model)));
 
-    let region_model = slint::VecModel::from(app_config.regions.iter().map(|s| s.clone().into()).collect::<Vec<slint::SharedString>>());
-    ui.set_region_list(slint::ModelRc::from(std::rc::Rc::new(region_model)));
+    ui_handlers::setup_all_handlers(&ui, &state);
+    let _throughput_timer = ui_handlers::start_throughput_timer(&ui);
+    let _transfers_timer = ui_handlers::start_transfers_timer(&ui);
 
-    ui_handlers::setup_all_handlers(&ui);
+    if !app_config.onboarding_done {
+        ui.set_show_onboarding(true);
+    }
+
+    if let single_instance::Startup::First(lock) = instance {
+        let ui_handle = ui.as_weak();
+        let state = state.clone();
+        lock.listen(move |paths| {
+            let state = state.clone();
+            let _ = ui_handle.upgrade_in_event_loop(move |ui| ui_handlers::handle_second_launch(&ui, &state, paths));
+        });
+    }
+    ui_handlers::add_launch_paths(&ui, &state, launch_paths);
+
+    if let Some(notice) = crash::take_last_crash() {
+        warn!("Previous run crashed: {} ({})", notice.summary, notice.report_path.display());
+        ui.set_crash_summary(notice.summary.into());
+        ui.set_crash_report_path(notice.report_path.to_string_lossy().to_string().into());
+        ui.set_show_crash_report(true);
+    }
+
+    let saved_mappings = last_session::load_mappings();
+    if !saved_mappings.is_empty() {
+        ui.set_restore_session_count(saved_mappings.len() as i32);
+        ui.set_show_restore_session(true);
+    }
 
     ui.run()?;
+    sync_log::flush().await;
+    if let Err(e) = state.flush() {
+        tracing::error!("Failed to save config on exit: {}", e);
+    }
+
+    // Keep the mapping list for the next run
+    let mappings: Vec<last_session::SavedMapping> = ui
+        .get_local_paths()
+        .iter()
+        .map(|item| last_session::SavedMapping {
+            local_path: item.local_path.to_string(),
+            s3_path: item.s3_path.to_string(),
+            enabled: item.enabled,
+        })
+        .collect();
+    if let Err(e) = last_session::save_mappings(&mappings) {
+        warn!("Failed to save mapping list: {}", e);
+    }
     Ok(())
 }
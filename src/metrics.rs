@@ -0,0 +1,107 @@
+use once_cell::sync::Lazy;
+use std::fmt::Write as _;
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::s3_client::SyncSummary;
+
+/// File name used when the user picks a metrics folder.
+pub const METRICS_FILE_NAME: &str = "s3_sync.prom";
+
+/// Cumulative counters for the lifetime of the process.
+#[derive(Debug, Default, Clone)]
+pub struct SyncMetrics {
+    pub syncs_total: u64,
+    pub syncs_failed: u64,
+    pub files_uploaded: u64,
+    pub bytes_uploaded: u64,
+    pub upload_errors: u64,
+    pub last_sync_timestamp: i64,
+    pub last_success_timestamp: i64,
+    pub last_duration_secs: f64,
+}
+
+static METRICS: Lazy<Mutex<SyncMetrics>> = Lazy::new(|| Mutex::new(SyncMetrics::default()));
+
+/// Adds a finished sync session to the counters and returns a snapshot.
+pub fn record_sync(summary: &SyncSummary) -> SyncMetrics {
+    let now = chrono::Utc::now().timestamp();
+    let mut m = METRICS.lock().unwrap_or_else(|e| e.into_inner());
+    m.syncs_total += 1;
+    m.files_uploaded += summary.uploaded as u64;
+    m.bytes_uploaded += summary.bytes_uploaded;
+    m.upload_errors += summary.failed as u64;
+    m.last_sync_timestamp = now;
+    m.last_duration_secs = summary.duration.as_secs_f64();
+    if summary.is_success() {
+        m.last_success_timestamp = now;
+    } else {
+        m.syncs_failed += 1;
+    }
+    m.clone()
+}
+
+/// Picks up the last sync and last success times from the metrics file of an earlier run, so a
+/// restart doesn't export 0 and trip staleness alerts. The counters start again from 0.
+pub fn load_timestamps(path: &Path) {
+    let Ok(text) = std::fs::read_to_string(path) else { return };
+    apply_timestamps(&mut METRICS.lock().unwrap_or_else(|e| e.into_inner()), &text);
+}
+
+fn apply_timestamps(m: &mut SyncMetrics, text: &str) {
+    for line in text.lines().filter(|line| !line.starts_with('#')) {
+        let Some((name, value)) = line.split_once(' ') else { continue };
+        let Ok(value) = value.trim().parse::<i64>() else { continue };
+        match name {
+            "s3sync_last_sync_timestamp_seconds" => m.last_sync_timestamp = value,
+            "s3sync_last_success_timestamp_seconds" => m.last_success_timestamp = value,
+            _ => {}
+        }
+    }
+}
+
+/// Returns the current counters.
+pub fn snapshot() -> SyncMetrics {
+    METRICS.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Renders metrics in the Prometheus text exposition format.
+pub fn render(m: &SyncMetrics) -> String {
+    let mut out = String::new();
+    let mut metric = |name: &str, kind: &str, help: &str, value: String| {
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        let _ = writeln!(out, "# TYPE {} {}", name, kind);
+        let _ = writeln!(out, "{} {}", name, value);
+    };
+    metric("s3sync_syncs_total", "counter", "Sync sessions run.", m.syncs_total.to_string());
+    metric("s3sync_syncs_failed_total", "counter", "Sync sessions that ended with an error.", m.syncs_failed.to_string());
+    metric("s3sync_files_uploaded_total", "counter", "Files uploaded.", m.files_uploaded.to_string());
+    metric("s3sync_bytes_uploaded_total", "counter", "Bytes uploaded.", m.bytes_uploaded.to_string());
+    metric("s3sync_upload_errors_total", "counter", "Failed uploads.", m.upload_errors.to_string());
+    metric("s3sync_last_sync_timestamp_seconds", "gauge", "Unix time of the last finished sync.", m.last_sync_timestamp.to_string());
+    metric("s3sync_last_success_timestamp_seconds", "gauge", "Unix time of the last successful sync.", m.last_success_timestamp.to_string());
+    metric("s3sync_last_duration_seconds", "gauge", "Duration of the last sync.", format!("{:.3}", m.last_duration_secs));
+    out
+}
+
+/// Writes the metrics file atomically (temp file + rename) so collectors never read a partial file.
+pub fn write_metrics_file(path: &Path, m: &SyncMetrics) -> std::io::Result<()> {
+    let tmp = path.with_extension("prom.tmp");
+    std::fs::write(&tmp, render(m))?;
+    std::fs::rename(&tmp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timestamps_survive_a_restart() {
+        let before = SyncMetrics { syncs_total: 4, last_sync_timestamp: 1_700_000_500, last_success_timestamp: 1_700_000_000, ..Default::default() };
+        let mut after = SyncMetrics::default();
+        apply_timestamps(&mut after, &render(&before));
+        assert_eq!(after.last_sync_timestamp, 1_700_000_500);
+        assert_eq!(after.last_success_timestamp, 1_700_000_000);
+        assert_eq!(after.syncs_total, 0);
+    }
+}
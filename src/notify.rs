@@ -0,0 +1,207 @@
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use once_cell::sync::Lazy;
+use std::sync::RwLock;
+
+use crate::config::SmtpConfig;
+use crate::s3_client::SyncSummary;
+
+/// Environment variable used when no SMTP password was typed in this session.
+const SMTP_PASSWORD_ENV: &str = "S3_SYNC_SMTP_PASSWORD";
+
+const MISSING_PASSWORD: &str = "Chưa có mật khẩu SMTP: nhập trong Email settings hoặc đặt biến môi trường S3_SYNC_SMTP_PASSWORD (bắt buộc cho các lần sync tự động sau khi khởi động lại)";
+
+/// SMTP password typed in the email settings. Kept in memory only, never written to the config file.
+static SMTP_PASSWORD: Lazy<RwLock<Option<String>>> = Lazy::new(|| RwLock::new(None));
+
+pub fn set_smtp_password(password: &str) {
+    crate::redact::register_secrets(&[password]);
+    *SMTP_PASSWORD.write().unwrap() = Some(password.to_string());
+}
+
+/// SMTP password for this session, falling back to `S3_SYNC_SMTP_PASSWORD`. A typed password
+/// is gone after a restart, so unattended runs need the environment variable.
+pub fn smtp_password() -> Option<String> {
+    let password = SMTP_PASSWORD.read().unwrap().clone();
+    password
+        .or_else(|| {
+            let password = std::env::var(SMTP_PASSWORD_ENV).ok()?;
+            crate::redact::register_secrets(&[&password]);
+            Some(password)
+        })
+        .filter(|p| !p.is_empty())
+}
+
+/// Builds the subject and plain-text body of a sync report.
+pub fn format_report(summary: &SyncSummary, max_errors: usize) -> (String, String) {
+    let status = if summary.is_success() { "SUCCESS" } else { "FAILED" };
+    let subject = format!("[S3 Sync] {} - {}", status, summary.bucket);
+
+    let mut body = format!(
+        "Bucket: {}\nStatus: {}\nDuration: {:.1}s\nFiles: {} total, {} uploaded, {} failed, {} filtered\n",
+        summary.bucket,
+        status,
+        summary.duration.as_secs_f64(),
+        summary.total_files,
+        summary.uploaded,
+        summary.failed,
+        summary.filtered,
+    );
+
+    if summary.deduplicated > 0 {
+        body.push_str(&format!("Duplicates: {} (not uploaded directly)\n", summary.deduplicated));
+    }
+    if summary.skipped_special > 0 {
+        body.push_str(&format!("Skipped junctions/placeholders: {}\n", summary.skipped_special));
+    }
+
+    let slowest = summary.slowest_uploads(crate::s3_client::SLOWEST_UPLOADS_REPORTED);
+    if !slowest.is_empty() {
+        body.push_str(&format!("\nSlowest {} uploads:\n", slowest.len()));
+        for timing in slowest {
+            body.push_str(&format!("- {}\n", crate::s3_client::format_upload_timing(timing)));
+        }
+    }
+
+    if !summary.warnings.is_empty() {
+        body.push_str("\nWarnings:\n");
+        for w in &summary.warnings {
+            body.push_str(&format!("- {}\n", w));
+        }
+    }
+
+    if !summary.error_counts.is_empty() {
+        let counts: Vec<String> = summary.error_counts.iter().map(|(category, n)| format!("{} {}", n, category)).collect();
+        body.push_str(&format!("Errors by type: {}\n", counts.join(", ")));
+    }
+
+    if !summary.errors.is_empty() {
+        body.push_str("\nErrors:\n");
+        for e in summary.errors.iter().take(max_errors) {
+            body.push_str(&format!("- {}\n", e));
+        }
+        if summary.errors.len() > max_errors {
+            body.push_str(&format!("... and {} more\n", summary.errors.len() - max_errors));
+        }
+    }
+
+    (subject, crate::redact::redact(&body).into_owned())
+}
+
+/// Validates SMTP settings. Returns an error message if invalid, or None if valid.
+pub fn validate_smtp_config(cfg: &SmtpConfig) -> Option<String> {
+    if !cfg.enabled {
+        return None;
+    }
+    if cfg.host.trim().is_empty() {
+        return Some("SMTP host không được để trống".to_string());
+    }
+    if cfg.from.trim().parse::<Mailbox>().is_err() {
+        return Some(format!("Địa chỉ gửi không hợp lệ: {}", cfg.from));
+    }
+    let recipients = parse_recipients(&cfg.to);
+    if recipients.is_empty() {
+        return Some("Cần ít nhất một địa chỉ nhận".to_string());
+    }
+    if let Some(bad) = recipients.iter().find(|r| r.parse::<Mailbox>().is_err()) {
+        return Some(format!("Địa chỉ nhận không hợp lệ: {}", bad));
+    }
+    None
+}
+
+fn parse_recipients(to: &str) -> Vec<String> {
+    to.split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// Sends the sync report email using the configured SMTP server (STARTTLS).
+pub async fn send_sync_report(cfg: &SmtpConfig, summary: &SyncSummary) -> Result<(), String> {
+    if let Some(err) = validate_smtp_config(cfg) {
+        return Err(err);
+    }
+
+    let (subject, body) = format_report(summary, cfg.max_errors);
+
+    let mut builder = Message::builder()
+        .from(cfg.from.trim().parse::<Mailbox>().map_err(|e| e.to_string())?)
+        .subject(subject);
+    for to in parse_recipients(&cfg.to) {
+        builder = builder.to(to.parse::<Mailbox>().map_err(|e| e.to_string())?);
+    }
+    let email = builder
+        .body(body)
+        .map_err(|e| format!("Lỗi tạo email: {}", e))?;
+
+    let mut transport = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(cfg.host.trim())
+        .map_err(|e| format!("Lỗi SMTP: {}", e))?
+        .port(cfg.port);
+    if !cfg.username.is_empty() {
+        let password = smtp_password().ok_or(MISSING_PASSWORD)?;
+        transport = transport.credentials(Credentials::new(cfg.username.clone(), password));
+    }
+
+    transport
+        .build()
+        .send(email)
+        .await
+        .map(|_| ())
+        .map_err(|e| format!("Lỗi gửi email: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_format_report_truncates_errors() {
+        let summary = SyncSummary {
+            bucket: "my-bucket".to_string(),
+            total_files: 5,
+            uploaded: 2,
+            bytes_uploaded: 2048,
+            uploaded_keys: vec![],
+            failed: 3,
+            filtered: 1,
+            deduplicated: 0,
+            skipped_special: 0,
+            duration: Duration::from_secs(4),
+            errors: vec!["a".to_string(), "b".to_string(), "c".to_string()],
+            error_counts: [("access_denied", 1), ("upload", 2)].into_iter().collect(),
+            upload_timings: vec![],
+            warnings: vec![],
+        };
+
+        let (subject, body) = format_report(&summary, 2);
+        assert_eq!(subject, "[S3 Sync] FAILED - my-bucket");
+        assert!(body.contains("5 total, 2 uploaded, 3 failed, 1 filtered"));
+        assert!(body.contains("Errors by type: 1 access_denied, 2 upload\n"));
+        assert!(body.contains("- a\n- b\n"));
+        assert!(!body.contains("- c"));
+        assert!(body.contains("... and 1 more"));
+
+        let (subject, body) = format_report(&SyncSummary::aborted("my-bucket", "Preflight failed".to_string()), 2);
+        assert_eq!(subject, "[S3 Sync] FAILED - my-bucket");
+        assert!(body.contains("- Preflight failed\n"));
+    }
+
+    #[test]
+    fn test_validate_smtp_config() {
+        let mut cfg = SmtpConfig::default();
+        assert!(validate_smtp_config(&cfg).is_none());
+
+        cfg.enabled = true;
+        cfg.host = "smtp.example.com".to_string();
+        cfg.from = "sync@example.com".to_string();
+        assert!(validate_smtp_config(&cfg).is_some());
+
+        cfg.to = "ops@example.com, dev@example.com".to_string();
+        assert!(validate_smtp_config(&cfg).is_none());
+
+        cfg.to = "not-an-address".to_string();
+        assert!(validate_smtp_config(&cfg).is_some());
+    }
+}
@@ -0,0 +1,335 @@
+use async_trait::async_trait;
+use aws_sdk_s3::Client;
+use aws_sdk_s3::primitives::ByteStream;
+
+use crate::diff::RemoteObject;
+
+/// Result of a listing under one prefix, all pages included.
+#[derive(Debug, Clone, Default)]
+pub struct ObjectList {
+    /// (full key, metadata)
+    pub objects: Vec<(String, RemoteObject)>,
+    /// Common prefixes (only filled for delimited listings), with their trailing `/`.
+    pub prefixes: Vec<String>,
+}
+
+/// The S3 operations used by the listing, diff, copy and delete helpers. `Client` is the real
+/// implementation; tests use [`MemoryStore`] so that logic runs without network access.
+/// Errors are the plain error text; callers add their own context.
+#[async_trait]
+pub trait ObjectStore: Send + Sync {
+    async fn put(&self, bucket: &str, key: &str, body: Vec<u8>) -> Result<(), String>;
+
+    /// Lists every object under `prefix`. With `delimited`, keys below the next `/` are folded
+    /// into `prefixes` instead.
+    async fn list(&self, bucket: &str, prefix: &str, delimited: bool) -> Result<ObjectList, String>;
+
+    /// Whether any object exists under `prefix`. Reads at most one key.
+    async fn prefix_exists(&self, bucket: &str, prefix: &str) -> Result<bool, String>;
+
+    /// `None` if the key does not exist.
+    async fn head(&self, bucket: &str, key: &str) -> Result<Option<RemoteObject>, String>;
+
+    /// Deletes up to 1000 keys in one request.
+    async fn delete(&self, bucket: &str, keys: &[String]) -> Result<(), String>;
+
+    async fn copy(
+        &self,
+        src_bucket: &str,
+        src_key: &str,
+        dst_bucket: &str,
+        dst_key: &str,
+        storage_class: Option<&str>,
+    ) -> Result<(), String>;
+}
+
+#[async_trait]
+impl ObjectStore for Client {
+    async fn put(&self, bucket: &str, key: &str, body: Vec<u8>) -> Result<(), String> {
+        self.put_object()
+            .bucket(bucket)
+            .key(key)
+            .body(ByteStream::from(body))
+            .send()
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    async fn list(&self, bucket: &str, prefix: &str, delimited: bool) -> Result<ObjectList, String> {
+        let mut list = ObjectList::default();
+        let mut pages = self
+            .list_objects_v2()
+            .bucket(bucket)
+            .prefix(prefix)
+            .set_delimiter(delimited.then(|| "/".to_string()))
+            .into_paginator()
+            .send();
+
+        while let Some(page) = pages.next().await {
+            let page = page.map_err(|e| e.to_string())?;
+            list.prefixes
+                .extend(page.common_prefixes().iter().filter_map(|cp| cp.prefix()).map(String::from));
+            list.objects.extend(page.contents().iter().filter_map(|obj| {
+                let object = RemoteObject {
+                    size: obj.size().unwrap_or(0).max(0) as u64,
+                    etag: obj.e_tag().unwrap_or_default().trim_matches('"').to_string(),
+                    last_modified: obj.last_modified().map(|t| t.secs()).unwrap_or(0),
+                    storage_class: obj.storage_class().map(|c| c.as_str().to_string()).unwrap_or_default(),
+                    file_modified: None,
+                };
+                Some((obj.key()?.to_string(), object))
+            }));
+        }
+
+        Ok(list)
+    }
+
+    async fn prefix_exists(&self, bucket: &str, prefix: &str) -> Result<bool, String> {
+        self.list_objects_v2()
+            .bucket(bucket)
+            .prefix(prefix)
+            .max_keys(1)
+            .send()
+            .await
+            .map(|resp| !resp.contents().is_empty())
+            .map_err(|e| e.to_string())
+    }
+
+    async fn head(&self, bucket: &str, key: &str) -> Result<Option<RemoteObject>, String> {
+        match self.head_object().bucket(bucket).key(key).send().await {
+            Ok(head) => Ok(Some(RemoteObject {
+                size: head.content_length().unwrap_or(0).max(0) as u64,
+                etag: head.e_tag().unwrap_or_default().trim_matches('"').to_string(),
+                last_modified: head.last_modified().map(|t| t.secs()).unwrap_or(0),
+                storage_class: head.storage_class().map(|c| c.as_str().to_string()).unwrap_or_default(),
+                file_modified: head
+                    .metadata()
+                    .and_then(crate::file_meta::stored_mtime)
+                    .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                    .map(|d| d.as_secs() as i64),
+            })),
+            Err(e) if e.as_service_error().is_some_and(|se| se.is_not_found()) => Ok(None),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    async fn delete(&self, bucket: &str, keys: &[String]) -> Result<(), String> {
+        use aws_sdk_s3::types::{Delete, ObjectIdentifier};
+
+        let objects = keys
+            .iter()
+            .filter_map(|k| ObjectIdentifier::builder().key(k).build().ok())
+            .collect();
+        let delete = Delete::builder()
+            .set_objects(Some(objects))
+            .quiet(true)
+            .build()
+            .map_err(|e| e.to_string())?;
+        let resp = self
+            .delete_objects()
+            .bucket(bucket)
+            .delete(delete)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+
+        match resp.errors().first() {
+            Some(err) => Err(format!(
+                "{}: {} ({} lỗi trong batch)",
+                err.key().unwrap_or_default(),
+                err.message().unwrap_or_default(),
+                resp.errors().len()
+            )),
+            None => Ok(()),
+        }
+    }
+
+    async fn copy(
+        &self,
+        src_bucket: &str,
+        src_key: &str,
+        dst_bucket: &str,
+        dst_key: &str,
+        storage_class: Option<&str>,
+    ) -> Result<(), String> {
+        self.copy_object()
+            .copy_source(crate::utils::encode_copy_source(src_bucket, src_key))
+            .bucket(dst_bucket)
+            .key(dst_key)
+            .set_storage_class(storage_class.map(aws_sdk_s3::types::StorageClass::from))
+            .send()
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// (bucket, key) -> (content, metadata)
+#[cfg(test)]
+type MemoryObjects = std::collections::BTreeMap<(String, String), (Vec<u8>, RemoteObject)>;
+
+/// In-memory buckets for tests. ETags are content hashes, so copies keep their ETag.
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemoryStore {
+    objects: std::sync::Mutex<MemoryObjects>,
+}
+
+#[cfg(test)]
+impl MemoryStore {
+    pub fn keys(&self, bucket: &str) -> Vec<String> {
+        let objects = self.objects.lock().unwrap();
+        objects.keys().filter(|(b, _)| b == bucket).map(|(_, k)| k.clone()).collect()
+    }
+
+    pub fn body(&self, bucket: &str, key: &str) -> Option<Vec<u8>> {
+        let objects = self.objects.lock().unwrap();
+        objects.get(&(bucket.to_string(), key.to_string())).map(|(data, _)| data.clone())
+    }
+}
+
+#[cfg(test)]
+#[async_trait]
+impl ObjectStore for MemoryStore {
+    async fn put(&self, bucket: &str, key: &str, body: Vec<u8>) -> Result<(), String> {
+        use sha2::{Digest, Sha256};
+
+        let object = RemoteObject {
+            size: body.len() as u64,
+            etag: format!("{:x}", Sha256::digest(&body)),
+            last_modified: chrono::Utc::now().timestamp(),
+            storage_class: "STANDARD".to_string(),
+            file_modified: None,
+        };
+        self.objects.lock().unwrap().insert((bucket.to_string(), key.to_string()), (body, object));
+        Ok(())
+    }
+
+    async fn list(&self, bucket: &str, prefix: &str, delimited: bool) -> Result<ObjectList, String> {
+        let objects = self.objects.lock().unwrap();
+        let mut list = ObjectList::default();
+        for ((b, key), (_, object)) in objects.iter() {
+            let Some(rest) = key.strip_prefix(prefix).filter(|_| b == bucket) else { continue };
+            match rest.find('/').filter(|_| delimited) {
+                Some(slash) => {
+                    let common = format!("{}{}", prefix, &rest[..=slash]);
+                    if list.prefixes.last() != Some(&common) {
+                        list.prefixes.push(common);
+                    }
+                }
+                None => list.objects.push((key.clone(), object.clone())),
+            }
+        }
+        Ok(list)
+    }
+
+    async fn prefix_exists(&self, bucket: &str, prefix: &str) -> Result<bool, String> {
+        let objects = self.objects.lock().unwrap();
+        Ok(objects.keys().any(|(b, key)| b == bucket && key.starts_with(prefix)))
+    }
+
+    async fn head(&self, bucket: &str, key: &str) -> Result<Option<RemoteObject>, String> {
+        let objects = self.objects.lock().unwrap();
+        Ok(objects.get(&(bucket.to_string(), key.to_string())).map(|(_, object)| object.clone()))
+    }
+
+    async fn delete(&self, bucket: &str, keys: &[String]) -> Result<(), String> {
+        let mut objects = self.objects.lock().unwrap();
+        for key in keys {
+            objects.remove(&(bucket.to_string(), key.clone()));
+        }
+        Ok(())
+    }
+
+    async fn copy(
+        &self,
+        src_bucket: &str,
+        src_key: &str,
+        dst_bucket: &str,
+        dst_key: &str,
+        storage_class: Option<&str>,
+    ) -> Result<(), String> {
+        let mut objects = self.objects.lock().unwrap();
+        let (data, mut object) = objects
+            .get(&(src_bucket.to_string(), src_key.to_string()))
+            .cloned()
+            .ok_or_else(|| format!("NoSuchKey: {}", src_key))?;
+        if let Some(class) = storage_class {
+            object.storage_class = class.to_string();
+        }
+        objects.insert((dst_bucket.to_string(), dst_key.to_string()), (data, object));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    async fn store_with(keys: &[(&str, &str)]) -> Arc<MemoryStore> {
+        let store = Arc::new(MemoryStore::default());
+        for (key, body) in keys {
+            store.put("site", key, body.as_bytes().to_vec()).await.unwrap();
+        }
+        store
+    }
+
+    #[tokio::test]
+    async fn test_diff_and_prefix_lookup_on_memory_store() {
+        let store = store_with(&[("a/x.txt", "1"), ("a/y.txt", "2"), ("b/x.txt", "1"), ("b/y.txt", "3"), ("b/z/w.txt", "4")]).await;
+
+        let a = crate::diff::list_remote_objects(store.as_ref(), "site", "a").await.unwrap();
+        let b = crate::diff::list_remote_objects(store.as_ref(), "site", "/b/").await.unwrap();
+        let diff = crate::diff::diff_listings(&a, &b);
+        assert_eq!(diff.identical, 1);
+        assert_eq!(diff.changed.iter().map(|(k, _)| k.as_str()).collect::<Vec<_>>(), vec!["y.txt"]);
+        assert_eq!(diff.only_b, vec!["z/w.txt".to_string()]);
+
+        let level = crate::s3_client::list_bucket_level(store.as_ref(), "site", "b/").await.unwrap();
+        assert_eq!(level.prefixes, vec!["b/z/".to_string()]);
+        assert_eq!(level.objects, vec![("b/x.txt".to_string(), 1), ("b/y.txt".to_string(), 1)]);
+
+        let cache = crate::s3_client::GlobalPrefixCache::default();
+        assert!(crate::s3_client::is_s3_prefix_exists_cached(store.as_ref(), "site", "b", &cache).await);
+        assert!(!crate::s3_client::is_s3_prefix_exists_cached(store.as_ref(), "site", "c/", &cache).await);
+    }
+
+    #[tokio::test]
+    async fn test_find_best_prefix_reports_source() {
+        use crate::s3_client::{find_best_s3_prefix, PrefixSource};
+
+        let store = store_with(&[("projects/site/index.html", "1")]).await;
+        let rules = crate::config::PrefixRules::default();
+        let cache = crate::s3_client::GlobalPrefixCache::default();
+        let found = find_best_s3_prefix(store.clone(), "site", std::path::Path::new("/projects"), &rules, 0, &cache).await;
+        assert_eq!(found, ("projects".to_string(), PrefixSource::SmartMatch { matched: 1 }));
+        // Nested prefixes aren't in the top-level listing; they're probed
+        let nested = find_best_s3_prefix(store.clone(), "site", std::path::Path::new("C:/work/projects/site"), &rules, 0, &cache).await;
+        assert_eq!(nested, ("projects/site".to_string(), PrefixSource::SmartMatch { matched: 2 }));
+        assert_eq!(cache.lock().await["site"].probed.get("projects/site"), Some(&true));
+        let guessed = find_best_s3_prefix(store.clone(), "site", std::path::Path::new("/work/other/app"), &rules, 0, &cache).await;
+        assert_eq!(guessed, ("work/other/app".to_string(), PrefixSource::Preview));
+        assert!(guessed.1.is_guess());
+    }
+
+    #[tokio::test]
+    async fn test_trash_backup_restore_and_delete_prefix() {
+        let store = store_with(&[("index.html", "old"), ("css/app.css", "body{}")]).await;
+        let trash = ".trash/20250101_120000/";
+
+        assert!(crate::s3_client::backup_existing_object(store.as_ref(), "site", "index.html", trash).await.unwrap());
+        assert!(!crate::s3_client::backup_existing_object(store.as_ref(), "site", "new.html", trash).await.unwrap());
+        store.put("site", "index.html", b"new".to_vec()).await.unwrap();
+
+        let restored = crate::s3_client::restore_trash_session(store.clone(), "site", trash, |_, _| {}).await.unwrap();
+        assert_eq!(restored, 1);
+        assert_eq!(store.body("site", "index.html").unwrap(), b"old");
+
+        assert!(crate::s3_client::delete_prefix(store.as_ref(), "site", "", |_| {}).await.is_err());
+        let deleted = crate::s3_client::delete_prefix(store.as_ref(), "site", ".trash", |_| {}).await.unwrap();
+        assert_eq!(deleted, 1);
+        assert_eq!(store.keys("site"), vec!["css/app.css".to_string(), "index.html".to_string()]);
+    }
+}
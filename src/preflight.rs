@@ -0,0 +1,101 @@
+use std::path::Path;
+
+/// Minimum free space kept in the log directory.
+const MIN_LOG_FREE_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Local paths of `mappings` that no longer exist (e.g. an unplugged USB drive).
+pub fn missing_paths(mappings: &[(String, String)]) -> Vec<String> {
+    mappings
+        .iter()
+        .filter(|(local, _)| !Path::new(local).exists())
+        .map(|(local, _)| local.clone())
+        .collect()
+}
+
+/// Checks that the log directory exists and has room for the session log.
+pub fn check_log_dir(log_path: &str) -> Result<(), String> {
+    let dir = Path::new(log_path);
+    if !dir.is_dir() {
+        return Err(format!("Thư mục log không tồn tại: {}", log_path));
+    }
+    let free = fs2::available_space(dir).map_err(|e| format!("Không đọc được dung lượng trống của {}: {}", log_path, e))?;
+    if free < MIN_LOG_FREE_BYTES {
+        return Err(format!(
+            "Thư mục log {} chỉ còn {} trống, cần ít nhất {}",
+            log_path,
+            crate::utils::format_size(free),
+            crate::utils::format_size(MIN_LOG_FREE_BYTES)
+        ));
+    }
+    Ok(())
+}
+
+/// Makes sure `concurrent_uploads` files plus their connections can be open at once,
+/// raising the soft open-file limit up to the hard limit when needed.
+#[cfg(unix)]
+pub fn check_open_file_limit(concurrent_uploads: usize) -> Result<(), String> {
+    // File descriptors needed beyond the uploads themselves (logs, UI, DNS)
+    const FD_MARGIN: libc::rlim_t = 64;
+    // Each upload holds the file and a connection
+    let needed = concurrent_uploads as libc::rlim_t * 2 + FD_MARGIN;
+    let mut limit = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+    // SAFETY: getrlimit only writes into the provided struct.
+    if unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit) } != 0 {
+        return Ok(());
+    }
+    if limit.rlim_cur >= needed {
+        return Ok(());
+    }
+    if limit.rlim_max >= needed {
+        let raised = libc::rlimit { rlim_cur: needed, rlim_max: limit.rlim_max };
+        // SAFETY: setrlimit only reads the provided struct.
+        if unsafe { libc::setrlimit(libc::RLIMIT_NOFILE, &raised) } == 0 {
+            tracing::info!("Raised open file limit from {} to {}", limit.rlim_cur, needed);
+            return Ok(());
+        }
+    }
+    Err(format!(
+        "Giới hạn file mở (ulimit -n) là {}, cần ít nhất {}. Tăng ulimit hoặc giảm số luồng upload trong Settings",
+        limit.rlim_cur, needed
+    ))
+}
+
+/// Windows has no practical per-process handle limit for this workload.
+#[cfg(not(unix))]
+pub fn check_open_file_limit(_concurrent_uploads: usize) -> Result<(), String> {
+    Ok(())
+}
+
+/// Runs all checks and returns every problem found, so they can be fixed in one go.
+pub fn run(mappings: &[(String, String)], log_path: &str, concurrent_uploads: usize) -> Vec<String> {
+    let mut problems: Vec<String> = missing_paths(mappings)
+        .into_iter()
+        .map(|p| format!("Không tìm thấy: {} (ổ đĩa đã bị rút hoặc thư mục đã bị xóa?)", p))
+        .collect();
+    if !log_path.is_empty()
+        && let Err(e) = check_log_dir(log_path)
+    {
+        problems.push(e);
+    }
+    if let Err(e) = check_open_file_limit(concurrent_uploads) {
+        problems.push(e);
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_paths() {
+        let existing = std::env::temp_dir().to_string_lossy().to_string();
+        let mappings = vec![
+            (existing.clone(), "a".to_string()),
+            ("/definitely/not/here/s3sync".to_string(), "b".to_string()),
+        ];
+        assert_eq!(missing_paths(&mappings), vec!["/definitely/not/here/s3sync".to_string()]);
+        assert!(check_log_dir(&existing).is_ok());
+        assert!(check_log_dir("/definitely/not/here/s3sync").is_err());
+    }
+}
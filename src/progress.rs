@@ -0,0 +1,196 @@
+use std::cell::Cell;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Receives sync status updates: (text, progress 0..1, is_error).
+pub type StatusFn = Arc<dyn Fn(String, f32, bool) + Send + Sync>;
+
+/// How often coalesced progress is pushed to the UI.
+pub const UI_UPDATE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Progress value for stages with no known total; the UI shows an indeterminate bar.
+pub const INDETERMINATE: f32 = -1.0;
+
+type Update = (String, f32, bool);
+
+/// One status update from the sync engine.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProgressUpdate {
+    pub text: String,
+    pub progress: f32,
+    pub is_error: bool,
+}
+
+/// Destination for sync engine progress. The engine only calls the [`StatusFn`] made by
+/// [`sink_fn`], so the window, a headless run or a test can each plug in their own sink.
+pub trait ProgressSink: Send + Sync + 'static {
+    fn report(&self, update: ProgressUpdate);
+}
+
+/// Turns a sink into the callback taken by the sync functions.
+pub fn sink_fn(sink: impl ProgressSink) -> StatusFn {
+    Arc::new(move |text, progress, is_error| sink.report(ProgressUpdate { text, progress, is_error }))
+}
+
+/// Forwards every update to a channel, for callers that consume progress on another task.
+pub struct ChannelSink(pub tokio::sync::mpsc::UnboundedSender<ProgressUpdate>);
+
+impl ProgressSink for ChannelSink {
+    fn report(&self, update: ProgressUpdate) {
+        // The receiver going away just means nobody is watching any more
+        let _ = self.0.send(update);
+    }
+}
+
+/// A callback whose updates arrive, uncoalesced, on the returned receiver.
+pub fn channel() -> (StatusFn, tokio::sync::mpsc::UnboundedReceiver<ProgressUpdate>) {
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    (sink_fn(ChannelSink(tx)), rx)
+}
+
+struct Coalescer {
+    inner: StatusFn,
+    latest: Mutex<Option<Update>>,
+}
+
+impl Coalescer {
+    fn flush(&self) {
+        let update = self.latest.lock().unwrap().take();
+        if let Some((text, progress, is_error)) = update {
+            (self.inner)(text, progress, is_error);
+        }
+    }
+}
+
+impl Drop for Coalescer {
+    // The last update of a sync (e.g. "done") must not be lost
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+/// Wraps `inner` so that progress from many upload tasks reaches it at most once per `interval`,
+/// keeping only the latest update. Errors are forwarded immediately.
+/// Must be called from within a Tokio runtime.
+pub fn coalesce(inner: StatusFn, interval: Duration) -> StatusFn {
+    let coalescer = Arc::new(Coalescer { inner, latest: Mutex::new(None) });
+    let weak = Arc::downgrade(&coalescer);
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        loop {
+            ticker.tick().await;
+            match weak.upgrade() {
+                Some(coalescer) => coalescer.flush(),
+                None => break,
+            }
+        }
+    });
+    Arc::new(move |text, progress, is_error| {
+        if is_error {
+            coalescer.latest.lock().unwrap().take();
+            (coalescer.inner)(text, progress, is_error);
+        } else {
+            *coalescer.latest.lock().unwrap() = Some((text, progress, is_error));
+        }
+    })
+}
+
+/// Folder/file counters for the scan phase, reported as their own status stage so a large
+/// tree doesn't look frozen before the first upload.
+pub struct ScanProgress {
+    status: StatusFn,
+    folders: Cell<u64>,
+    files: Cell<u64>,
+    filtered: Cell<u64>,
+    last_report: Cell<Instant>,
+}
+
+impl ScanProgress {
+    pub fn new(status: StatusFn) -> Self {
+        let scan = Self {
+            status,
+            folders: Cell::new(0),
+            files: Cell::new(0),
+            filtered: Cell::new(0),
+            last_report: Cell::new(Instant::now()),
+        };
+        scan.report();
+        scan
+    }
+
+    pub fn folder(&self) {
+        self.folders.set(self.folders.get() + 1);
+        self.maybe_report();
+    }
+
+    pub fn file(&self) {
+        self.files.set(self.files.get() + 1);
+        self.maybe_report();
+    }
+
+    pub fn filtered(&self) {
+        self.filtered.set(self.filtered.get() + 1);
+        self.maybe_report();
+    }
+
+    pub fn filtered_count(&self) -> u64 {
+        self.filtered.get()
+    }
+
+    fn maybe_report(&self) {
+        if self.last_report.get().elapsed() >= UI_UPDATE_INTERVAL {
+            self.report();
+        }
+    }
+
+    pub fn report(&self) {
+        self.last_report.set(Instant::now());
+        (self.status)(
+            format!(
+                "Đang quét: {} thư mục, {} file, đã lọc {}",
+                self.folders.get(),
+                self.files.get(),
+                self.filtered.get()
+            ),
+            INDETERMINATE,
+            false,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_coalesce_keeps_latest_and_errors() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let inner: StatusFn = {
+            let seen = Arc::clone(&seen);
+            Arc::new(move |text, _, is_error| seen.lock().unwrap().push((text, is_error)))
+        };
+        let status = coalesce(inner, Duration::from_secs(3600));
+        for i in 0..100 {
+            status(format!("file {}", i), i as f32 / 100.0, false);
+        }
+        status("failed".to_string(), 0.0, true);
+        status("done".to_string(), 1.0, false);
+        drop(status);
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![("failed".to_string(), true), ("done".to_string(), false)]
+        );
+    }
+
+    #[test]
+    fn test_channel_sink_delivers_updates_in_order() {
+        let (status, mut rx) = channel();
+        status("scan".to_string(), INDETERMINATE, false);
+        status("failed".to_string(), 0.0, true);
+        drop(status);
+        assert_eq!(rx.try_recv().unwrap(), ProgressUpdate { text: "scan".to_string(), progress: INDETERMINATE, is_error: false });
+        assert!(rx.try_recv().unwrap().is_error);
+        assert!(rx.try_recv().is_err());
+    }
+}
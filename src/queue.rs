@@ -0,0 +1,153 @@
+use once_cell::sync::Lazy;
+use std::sync::Mutex;
+
+/// Everything needed to run one sync job independently of the current UI selection.
+#[derive(Debug, Clone)]
+pub struct SyncJob {
+    pub id: u64,
+    pub acc_key: String,
+    pub sec_key: String,
+    pub sess_token: String,
+    pub region: String,
+    pub bucket: String,
+    /// (local_path, s3_path)
+    pub mappings: Vec<(String, String)>,
+    pub log_path: String,
+    pub limits: crate::scheduler::JobLimits,
+    /// Scheduler handle id while the job is running.
+    pub sched_id: Option<u64>,
+    pub status: JobStatus,
+    pub progress: f32,
+    /// Latest status text reported by the sync engine.
+    pub message: String,
+}
+
+impl SyncJob {
+    /// Short label shown in the queue list, e.g. "site, docs → my-bucket".
+    pub fn title(&self) -> String {
+        let names: Vec<String> = self
+            .mappings
+            .iter()
+            .map(|(local, _)| {
+                std::path::Path::new(local)
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_else(|| local.clone())
+            })
+            .collect();
+        format!("{} → {}", names.join(", "), self.bucket)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Done,
+    Failed(String),
+}
+
+impl JobStatus {
+    pub fn label(&self) -> &'static str {
+        match self {
+            JobStatus::Queued => "queued",
+            JobStatus::Running => "running",
+            JobStatus::Done => "done",
+            JobStatus::Failed(_) => "failed",
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        matches!(self, JobStatus::Done | JobStatus::Failed(_))
+    }
+}
+
+struct Queue {
+    next_id: u64,
+    jobs: Vec<SyncJob>,
+}
+
+static QUEUE: Lazy<Mutex<Queue>> = Lazy::new(|| Mutex::new(Queue { next_id: 1, jobs: Vec::new() }));
+
+/// Adds a job to the end of the queue and returns its id.
+pub fn enqueue(mut job: SyncJob) -> u64 {
+    let mut queue = QUEUE.lock().unwrap();
+    job.id = queue.next_id;
+    job.status = JobStatus::Queued;
+    queue.next_id += 1;
+    let id = job.id;
+    queue.jobs.push(job);
+    id
+}
+
+/// All jobs in queue order.
+pub fn snapshot() -> Vec<SyncJob> {
+    QUEUE.lock().unwrap().jobs.clone()
+}
+
+/// Marks every queued job as running and returns them, highest priority first
+/// (queue order within the same priority).
+pub fn take_queued() -> Vec<SyncJob> {
+    let mut queue = QUEUE.lock().unwrap();
+    let mut jobs: Vec<SyncJob> = queue
+        .jobs
+        .iter_mut()
+        .filter(|j| j.status == JobStatus::Queued)
+        .map(|j| {
+            j.status = JobStatus::Running;
+            j.clone()
+        })
+        .collect();
+    jobs.sort_by_key(|j| std::cmp::Reverse(j.limits.priority.weight()));
+    jobs
+}
+
+pub fn get(id: u64) -> Option<SyncJob> {
+    QUEUE.lock().unwrap().jobs.iter().find(|j| j.id == id).cloned()
+}
+
+pub fn set_status(id: u64, status: JobStatus) {
+    if let Some(job) = QUEUE.lock().unwrap().jobs.iter_mut().find(|j| j.id == id) {
+        job.status = status;
+    }
+}
+
+pub fn set_sched_id(id: u64, sched_id: Option<u64>) {
+    if let Some(job) = QUEUE.lock().unwrap().jobs.iter_mut().find(|j| j.id == id) {
+        job.sched_id = sched_id;
+    }
+}
+
+/// Changes a job's limits; a running job is rebalanced in the scheduler right away.
+pub fn set_limits(id: u64, limits: crate::scheduler::JobLimits) {
+    let sched_id = {
+        let mut queue = QUEUE.lock().unwrap();
+        let Some(job) = queue.jobs.iter_mut().find(|j| j.id == id) else { return };
+        job.limits = limits;
+        job.sched_id
+    };
+    if let Some(sched_id) = sched_id {
+        crate::scheduler::update_limits(sched_id, limits);
+    }
+}
+
+pub fn set_progress(id: u64, progress: f32, message: String) {
+    if let Some(job) = QUEUE.lock().unwrap().jobs.iter_mut().find(|j| j.id == id) {
+        job.progress = progress;
+        job.message = message;
+    }
+}
+
+/// Removes a job that is not running.
+pub fn remove(id: u64) {
+    QUEUE
+        .lock()
+        .unwrap()
+        .jobs
+        .retain(|j| j.id != id || j.status == JobStatus::Running);
+}
+
+/// Drops finished (done or failed) jobs.
+pub fn clear_finished() {
+    QUEUE.lock().unwrap().jobs.retain(|j| !j.status.is_finished());
+}
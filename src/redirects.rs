@@ -0,0 +1,114 @@
+use aws_sdk_s3::Client;
+use aws_sdk_s3::primitives::ByteStream;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tracing::info;
+
+/// Parses a redirect mapping file: one `old/path new-location` pair per line
+/// (separated by whitespace or `->`). Blank lines and `#` comments are ignored.
+/// Returns (key, location) pairs; keys never start with `/`.
+pub fn parse_redirects(text: &str) -> Result<Vec<(String, String)>, String> {
+    let mut redirects = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let parts: Vec<&str> = line.split_whitespace().filter(|p| *p != "->").collect();
+        let [old, new] = parts.as_slice() else {
+            return Err(format!("Dòng {}: cần đúng 2 cột 'đường dẫn cũ  URL mới'", i + 1));
+        };
+        let key = old.trim_start_matches('/');
+        if key.is_empty() {
+            return Err(format!("Dòng {}: đường dẫn cũ trống", i + 1));
+        }
+        // S3 only accepts absolute URLs or paths starting with '/'
+        if !(new.starts_with('/') || new.starts_with("http://") || new.starts_with("https://")) {
+            return Err(format!("Dòng {}: đích phải bắt đầu bằng '/' hoặc http(s)://", i + 1));
+        }
+        redirects.push((key.to_string(), new.to_string()));
+    }
+    Ok(redirects)
+}
+
+/// Reads and parses a redirect mapping file.
+pub fn load_redirects(path: &Path) -> Result<Vec<(String, String)>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("Không thể đọc file redirect {}: {}", path.display(), e))?;
+    parse_redirects(&text)
+}
+
+/// Creates zero-byte website redirect objects. Stops at the first failure.
+pub async fn put_redirects(
+    client: Arc<Client>,
+    bucket: &str,
+    redirects: Vec<(String, String)>,
+    on_progress: impl Fn(usize, usize),
+) -> Result<usize, String> {
+    let total = redirects.len();
+    let semaphore = Arc::new(Semaphore::new(20));
+    let mut set = JoinSet::new();
+
+    for (key, location) in redirects {
+        let client = Arc::clone(&client);
+        let semaphore = Arc::clone(&semaphore);
+        let bucket = bucket.to_string();
+
+        set.spawn(async move {
+            let _permit = semaphore.acquire().await.unwrap();
+            client
+                .put_object()
+                .bucket(&bucket)
+                .key(&key)
+                .website_redirect_location(&location)
+                .body(ByteStream::from_static(b""))
+                .send()
+                .await
+                .map(|_| ())
+                .map_err(|e| format!("Lỗi tạo redirect {} -> {}: {}", key, location, e))
+        });
+    }
+
+    let mut created = 0usize;
+    while let Some(res) = set.join_next().await {
+        match res {
+            Ok(Ok(())) => {
+                created += 1;
+                on_progress(created, total);
+            }
+            Ok(Err(e)) => {
+                set.abort_all();
+                return Err(e);
+            }
+            Err(e) => {
+                set.abort_all();
+                return Err(format!("Task lỗi: {}", e));
+            }
+        }
+    }
+
+    info!("Created {} redirect objects in {}", created, bucket);
+    Ok(created)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_redirects() {
+        let text = "# moved pages\n/old/about.html /about/\nblog/2020.html -> https://blog.example.com/2020\n\n";
+        let redirects = parse_redirects(text).unwrap();
+        assert_eq!(
+            redirects,
+            vec![
+                ("old/about.html".to_string(), "/about/".to_string()),
+                ("blog/2020.html".to_string(), "https://blog.example.com/2020".to_string()),
+            ]
+        );
+
+        assert!(parse_redirects("old.html").is_err());
+        assert!(parse_redirects("old.html about/").is_err());
+    }
+}
@@ -0,0 +1,67 @@
+use aws_sdk_s3::types::BucketLocationConstraint;
+
+/// Regions S3 serves that the SDK's `BucketLocationConstraint` doesn't list: us-east-1 is the
+/// implicit default there, and the China partition has its own endpoints.
+const EXTRA_REGIONS: &[&str] = &["us-east-1", "cn-north-1", "cn-northwest-1"];
+
+/// Partitions in the order their regions are listed.
+const PARTITIONS: &[&str] = &["aws", "aws-us-gov", "aws-cn"];
+
+/// AWS partition of a region code. Regions outside `aws` need credentials from that partition.
+pub fn partition(region: &str) -> &'static str {
+    if region.starts_with("cn-") {
+        "aws-cn"
+    } else if region.starts_with("us-gov-") {
+        "aws-us-gov"
+    } else {
+        "aws"
+    }
+}
+
+/// Every region the SDK knows S3 buckets can live in, plus [`EXTRA_REGIONS`], grouped by
+/// partition and sorted within it.
+pub fn known_regions() -> Vec<String> {
+    let mut regions: Vec<&str> = BucketLocationConstraint::values()
+        .iter()
+        .copied()
+        // "EU" is the legacy alias of eu-west-1
+        .filter(|r| r.contains('-'))
+        .chain(EXTRA_REGIONS.iter().copied())
+        .collect();
+    regions.sort_by_key(|r| (PARTITIONS.iter().position(|p| *p == partition(r)), *r));
+    regions.dedup();
+    regions.into_iter().map(String::from).collect()
+}
+
+/// The region choices shown in the UI: the user's own list first, in their order (it may hold
+/// regions newer than this build), then the remaining known regions.
+pub fn region_choices(pinned: &[String]) -> Vec<String> {
+    let mut choices = pinned.to_vec();
+    choices.extend(known_regions().into_iter().filter(|r| !pinned.contains(r)));
+    choices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_regions_and_choices() {
+        let known = known_regions();
+        for region in ["us-east-1", "ap-northeast-1", "eu-west-1", "us-gov-west-1", "cn-north-1"] {
+            assert!(known.iter().any(|r| r == region), "{} missing", region);
+        }
+        assert!(!known.iter().any(|r| r == "EU"));
+        // aws first, then GovCloud, then China
+        let first_gov = known.iter().position(|r| partition(r) == "aws-us-gov").unwrap();
+        let first_cn = known.iter().position(|r| partition(r) == "aws-cn").unwrap();
+        assert!(known[..first_gov].iter().all(|r| partition(r) == "aws"));
+        assert!(first_gov < first_cn);
+
+        let pinned = vec!["ap-southeast-1".to_string(), "xx-test-9".to_string()];
+        let choices = region_choices(&pinned);
+        assert_eq!(&choices[..2], &pinned[..]);
+        assert_eq!(choices.iter().filter(|r| *r == "ap-southeast-1").count(), 1);
+        assert_eq!(choices.len(), known.len() + 1);
+    }
+}
@@ -0,0 +1,233 @@
+use std::sync::Arc;
+use tracing::{error, info, warn};
+
+use crate::config::{ReplicaMode, ReplicaTarget};
+use crate::progress::StatusFn;
+use crate::s3_client::SyncSummary;
+
+/// The finished main-bucket sync that replica targets are fed from.
+pub struct ReplicaSource<'a> {
+    pub acc_key: &'a str,
+    pub sec_key: &'a str,
+    pub sess_token: &'a str,
+    /// Region of the main bucket
+    pub region: &'a str,
+    /// (local_path, s3_path), as passed to the main sync
+    pub mappings: &'a [(String, String)],
+    pub summary: &'a SyncSummary,
+    /// The user confirmed this sync in the dialog, which lists a Batch Operations copy; queued
+    /// runs leave Batch jobs waiting for confirmation in the S3 console.
+    pub confirmed: bool,
+}
+
+/// Outcome for one replica target.
+#[derive(Debug, Clone)]
+pub struct ReplicaResult {
+    pub target: ReplicaTarget,
+    pub transferred: usize,
+    /// Objects handed to an S3 Batch job still running when the sync ended; not in `transferred`.
+    pub submitted: usize,
+    pub error: Option<String>,
+}
+
+impl ReplicaResult {
+    pub fn is_success(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// "b1@ap-northeast-1: 120 file · b2@us-east-1: lỗi ..." line for the status bar and queue.
+pub fn format_results(results: &[ReplicaResult]) -> String {
+    results
+        .iter()
+        .map(|r| match &r.error {
+            None if r.submitted > 0 => format!("{}: {} object trong S3 Batch job (đang chạy)", r.target, r.submitted),
+            None => format!("{}: {} file", r.target, r.transferred),
+            Some(e) => format!("{}: lỗi {}", r.target, e),
+        })
+        .collect::<Vec<_>>()
+        .join(" · ")
+}
+
+/// Brings every configured replica target up to date with the main sync, one target at a time.
+/// A failing target doesn't stop the others. Returns an empty list when no targets are set, or
+/// when the main sync had errors: replicas would then copy a half-updated destination.
+pub async fn sync_replicas(
+    source: ReplicaSource<'_>,
+    status: &StatusFn,
+    log_path: &str,
+    throttle: Arc<crate::scheduler::JobThrottle>,
+) -> Vec<ReplicaResult> {
+    let options = crate::config::load_config().sync_options;
+    if !source.summary.is_success() && !options.replica_targets.is_empty() {
+        warn!("Skipping {} replica targets: the main sync had errors", options.replica_targets.len());
+        status("Bỏ qua replica vì sync chính có lỗi".to_string(), 0.0, true);
+        return Vec::new();
+    }
+    let mut results = Vec::new();
+    for (i, target) in options.replica_targets.iter().enumerate() {
+        let label = format!("[{}/{} {}]", i + 1, options.replica_targets.len(), target);
+        let target_status: StatusFn = {
+            let status = Arc::clone(status);
+            let label = label.clone();
+            Arc::new(move |text, progress, is_error| status(format!("{} {}", label, text), progress, is_error))
+        };
+        let result = sync_target(&source, target, &options, &target_status, log_path, Arc::clone(&throttle)).await;
+        match &result {
+            Ok((n, 0)) => info!("Replica {} done: {} objects ({:?})", target, n, options.replica_mode),
+            Ok((_, n)) => info!("Replica {}: {} objects submitted to S3 Batch", target, n),
+            Err(e) => {
+                error!("Replica {} failed: {}", target, e);
+                status(format!("{} {}", label, e), 0.0, true);
+            }
+        }
+        results.push(ReplicaResult {
+            target: target.clone(),
+            transferred: result.as_ref().map_or(0, |r| r.0),
+            submitted: result.as_ref().map_or(0, |r| r.1),
+            error: result.err(),
+        });
+    }
+    results
+}
+
+async fn sync_target(
+    source: &ReplicaSource<'_>,
+    target: &ReplicaTarget,
+    options: &crate::config::SyncOptions,
+    status: &StatusFn,
+    log_path: &str,
+    throttle: Arc<crate::scheduler::JobThrottle>,
+) -> Result<(usize, usize), String> {
+    let client = crate::s3_client::create_s3_client(
+        source.acc_key.to_string(),
+        source.sec_key.to_string(),
+        (!source.sess_token.is_empty()).then(|| source.sess_token.to_string()),
+        target.region.clone(),
+    )
+    .await
+    .map_err(|e| format!("Lỗi tạo client: {}", e))?;
+    let client = Arc::new(client);
+
+    match options.replica_mode {
+        ReplicaMode::Upload => {
+            let summary = crate::s3_client::sync_to_s3(
+                client,
+                target.bucket.clone(),
+                source.mappings.to_vec(),
+                Arc::clone(status),
+                log_path.to_string(),
+                throttle,
+            )
+            .await?;
+            match summary.errors.first() {
+                Some(e) if !summary.is_success() => Err(format!("{} file lỗi ({})", summary.failed, e)),
+                _ => Ok((summary.uploaded, 0)),
+            }
+        }
+        ReplicaMode::Copy
+            if crate::batch_ops::use_batch(
+                source.summary.uploaded_keys.len(),
+                options.batch_threshold,
+                &options.batch_role_arn,
+                &options.batch_manifest_bucket,
+            ) =>
+        {
+            copy_with_batch(source, target, options, status).await.map(|n| (0, n))
+        }
+        ReplicaMode::Copy => {
+            // The request goes to the target's region; S3 reads the source bucket cross-region
+            let pairs: Vec<(String, String)> = source.summary.uploaded_keys.iter().map(|k| (k.clone(), k.clone())).collect();
+            let total = pairs.len();
+            let progress_status = Arc::clone(status);
+            let copied = crate::s3_client::copy_objects(client, &source.summary.bucket, &target.bucket, pairs, move |done, _| {
+                progress_status(format!("Đang copy ({}/{})", done, total), done as f32 / total.max(1) as f32, false);
+            })
+            .await?;
+            status(format!("Đã copy {} object", copied), 1.0, false);
+            Ok((copied, 0))
+        }
+    }
+}
+
+/// Hands a large replica copy to S3 Batch Operations and tracks the job in the background.
+/// Returns the number of objects in the submitted job, which is still running.
+async fn copy_with_batch(
+    source: &ReplicaSource<'_>,
+    target: &ReplicaTarget,
+    options: &crate::config::SyncOptions,
+    status: &StatusFn,
+) -> Result<usize, String> {
+    let keys = &source.summary.uploaded_keys;
+    status(format!("Đang tạo S3 Batch job copy {} object...", keys.len()), crate::progress::INDETERMINATE, false);
+    let sess_token = (!source.sess_token.is_empty()).then(|| source.sess_token.to_string());
+    let account = crate::s3_client::caller_identity(
+        source.acc_key.to_string(),
+        source.sec_key.to_string(),
+        sess_token.clone(),
+        source.region.to_string(),
+    )
+    .await?
+    .account;
+    // The job and its manifest live in the main bucket's region
+    let s3 = crate::s3_client::create_s3_client(
+        source.acc_key.to_string(),
+        source.sec_key.to_string(),
+        sess_token.clone(),
+        source.region.to_string(),
+    )
+    .await
+    .map(Arc::new)
+    .map_err(|e| format!("Lỗi tạo client: {}", e))?;
+    let control = Arc::new(
+        crate::s3_client::create_s3control_client(
+            source.acc_key.to_string(),
+            source.sec_key.to_string(),
+            sess_token,
+            source.region.to_string(),
+        )
+        .await,
+    );
+    let spec = crate::batch_ops::CopyJobSpec {
+        account_id: &account,
+        region: source.region,
+        source_bucket: &source.summary.bucket,
+        target_bucket: &target.bucket,
+        manifest_bucket: options.batch_manifest_bucket.trim(),
+        keys,
+        role_arn: &options.batch_role_arn,
+    };
+    let job = crate::batch_ops::start_copy_job(&s3, &control, spec).await?;
+    if source.confirmed {
+        crate::batch_ops::confirm(&control, &job).await?;
+        status(format!("Đã tạo S3 Batch job {} ({} object), đang theo dõi...", job.job_id, job.objects), 1.0, false);
+    } else {
+        status(format!("Đã tạo S3 Batch job {} ({} object), chờ xác nhận trên S3 console", job.job_id, job.objects), 1.0, false);
+    }
+    let objects = job.objects;
+    crate::batch_ops::track(control, s3, job, Arc::clone(status));
+    Ok(objects)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_format_targets() {
+        let targets = ReplicaTarget::parse_list("site-eu@eu-west-1, site-us\n", "ap-northeast-1").unwrap();
+        assert_eq!(targets[0], ReplicaTarget { bucket: "site-eu".into(), region: "eu-west-1".into() });
+        assert_eq!(targets[1].to_string(), "site-us@ap-northeast-1");
+        assert!(ReplicaTarget::parse_list("bad bucket@x", "us-east-1").is_err());
+
+        let results = vec![
+            ReplicaResult { target: targets[0].clone(), transferred: 3, submitted: 0, error: None },
+            ReplicaResult { target: targets[1].clone(), transferred: 0, submitted: 0, error: Some("AccessDenied".into()) },
+            ReplicaResult { target: targets[1].clone(), transferred: 0, submitted: 20_000, error: None },
+        ];
+        assert_eq!(
+            format_results(&results),
+            "site-eu@eu-west-1: 3 file · site-us@ap-northeast-1: lỗi AccessDenied · site-us@ap-northeast-1: 20000 object trong S3 Batch job (đang chạy)"
+        );
+    }
+}
@@ -0,0 +1,276 @@
+use aws_sdk_s3::Client;
+use aws_sdk_s3::types::{GlacierJobParameters, RestoreRequest, Tier};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::progress::StatusFn;
+use crate::two_way::{BaselineRef, LocalFile};
+
+const STATE_FILE_NAME: &str = "pending_restores.json";
+
+/// Retrieval tiers offered in the UI, cheapest-but-reasonable first.
+pub const RESTORE_TIERS: &[&str] = &["Standard", "Bulk", "Expedited"];
+
+/// Storage classes whose objects must be restored before they can be read.
+/// Glacier Instant Retrieval is readable directly and isn't included.
+pub fn is_archived(storage_class: &str) -> bool {
+    matches!(storage_class, "GLACIER" | "DEEP_ARCHIVE")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestoreStatus {
+    /// No restore requested, or the restored copy has expired.
+    NotRestored,
+    InProgress,
+    Available,
+}
+
+/// Parses the `x-amz-restore` header of HeadObject:
+/// `ongoing-request="true"` or `ongoing-request="false", expiry-date="..."`.
+pub fn parse_restore_header(header: Option<&str>) -> RestoreStatus {
+    match header {
+        Some(h) if h.contains("ongoing-request=\"true\"") => RestoreStatus::InProgress,
+        Some(h) if h.contains("ongoing-request=\"false\"") => RestoreStatus::Available,
+        _ => RestoreStatus::NotRestored,
+    }
+}
+
+/// A restore being waited on. Kept in a state file next to the config so restores requested
+/// before the app was closed (Deep Archive can take 48h) are picked up again.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingRestore {
+    pub bucket: String,
+    pub key: String,
+    /// Local file to download the object to once it is available.
+    #[serde(default)]
+    pub download_to: Option<PathBuf>,
+    /// That file when the restore was requested (`None`: there was none). If it changed since,
+    /// the download goes next to it instead of over the edits.
+    #[serde(default)]
+    pub local_before: Option<LocalFile>,
+    /// Two-way baseline entry updated after the download.
+    #[serde(default)]
+    pub baseline: Option<BaselineRef>,
+    pub tier: String,
+    /// Unix timestamp (seconds).
+    pub requested_at: i64,
+}
+
+static PENDING: Lazy<Mutex<Vec<PendingRestore>>> = Lazy::new(|| Mutex::new(load_pending()));
+/// (bucket, key) with a poll task running in this process.
+static POLLING: Lazy<Mutex<HashSet<(String, String)>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+fn parse_tier(tier: &str) -> Tier {
+    match tier {
+        "Expedited" => Tier::Expedited,
+        "Bulk" => Tier::Bulk,
+        _ => Tier::Standard,
+    }
+}
+
+/// How often a restore is checked; matched to how long the tier usually takes.
+fn poll_interval(tier: &str) -> Duration {
+    match tier {
+        "Expedited" => Duration::from_secs(60),
+        "Bulk" => Duration::from_secs(30 * 60),
+        _ => Duration::from_secs(10 * 60),
+    }
+}
+
+/// Issues RestoreObject for an archived object, keeping the restored copy for `days` days.
+/// A restore that is already running counts as success.
+pub async fn request_restore(client: &Client, bucket: &str, key: &str, tier: &str, days: i32) -> Result<(), String> {
+    let request = RestoreRequest::builder()
+        .days(days)
+        .glacier_job_parameters(
+            GlacierJobParameters::builder()
+                .tier(parse_tier(tier))
+                .build()
+                .map_err(|e| e.to_string())?,
+        )
+        .build();
+    match client.restore_object().bucket(bucket).key(key).restore_request(request).send().await {
+        Ok(_) => {
+            info!("Requested {} restore of s3://{}/{} for {} days", tier, bucket, key, days);
+            Ok(())
+        }
+        Err(e) if e.as_service_error().and_then(|s| s.meta().code()) == Some("RestoreAlreadyInProgress") => Ok(()),
+        Err(e) => Err(format!("Lỗi restore {}: {}", key, e)),
+    }
+}
+
+/// Where a restored object is downloaded once available.
+#[derive(Debug, Clone)]
+pub struct RestoreDownload {
+    pub path: PathBuf,
+    /// The file at `path` now, to tell later whether it was edited while waiting.
+    pub local_before: Option<LocalFile>,
+    pub baseline: Option<BaselineRef>,
+}
+
+/// Requests a restore and starts waiting for it; `download` is fetched once available.
+pub async fn restore_and_track(
+    client: &Arc<Client>,
+    bucket: &str,
+    key: &str,
+    download: Option<RestoreDownload>,
+    status: &StatusFn,
+) -> Result<(), String> {
+    let options = crate::config::load_config().restore;
+    request_restore(client, bucket, key, &options.tier, options.days as i32).await?;
+    let (download_to, local_before, baseline) = match download {
+        Some(d) => (Some(d.path), d.local_before, d.baseline),
+        None => (None, None, None),
+    };
+    let pending = PendingRestore {
+        bucket: bucket.to_string(),
+        key: key.to_string(),
+        download_to,
+        local_before,
+        baseline,
+        tier: options.tier,
+        requested_at: chrono::Utc::now().timestamp(),
+    };
+    {
+        let mut all = PENDING.lock().unwrap();
+        all.retain(|p| !(p.bucket == pending.bucket && p.key == pending.key));
+        all.push(pending.clone());
+        save_pending(&all);
+    }
+    spawn_poll(Arc::clone(client), pending, Arc::clone(status));
+    Ok(())
+}
+
+/// Restarts polling for restores saved by an earlier run (same bucket only, since the
+/// credentials may not reach other buckets).
+pub fn resume(client: &Arc<Client>, bucket: &str, status: &StatusFn) {
+    let pending: Vec<PendingRestore> = PENDING.lock().unwrap().iter().filter(|p| p.bucket == bucket).cloned().collect();
+    for restore in pending {
+        spawn_poll(Arc::clone(client), restore, Arc::clone(status));
+    }
+}
+
+/// Number of restores still being waited on.
+pub fn pending_count() -> usize {
+    PENDING.lock().unwrap().len()
+}
+
+fn spawn_poll(client: Arc<Client>, restore: PendingRestore, status: StatusFn) {
+    if !POLLING.lock().unwrap().insert((restore.bucket.clone(), restore.key.clone())) {
+        return;
+    }
+    tokio::spawn(async move {
+        let interval = poll_interval(&restore.tier);
+        let outcome = loop {
+            let head = client.head_object().bucket(&restore.bucket).key(&restore.key).send().await;
+            match head {
+                Ok(head) => match parse_restore_header(head.restore()) {
+                    RestoreStatus::InProgress => {}
+                    RestoreStatus::Available => break finish(&client, &restore).await,
+                    RestoreStatus::NotRestored => break Err(format!("Restore {} đã hết hạn hoặc bị hủy", restore.key)),
+                },
+                Err(e) if crate::connectivity::is_connection_error(&e) => {}
+                Err(e) => break Err(format!("Không kiểm tra được restore {}: {}", restore.key, e)),
+            }
+            tokio::time::sleep(interval).await;
+        };
+        match outcome {
+            Ok(message) => status(message, 0.0, false),
+            Err(e) => {
+                warn!("{}", e);
+                status(e, 0.0, true);
+            }
+        }
+        POLLING.lock().unwrap().remove(&(restore.bucket.clone(), restore.key.clone()));
+        let mut all = PENDING.lock().unwrap();
+        all.retain(|p| p != &restore);
+        save_pending(&all);
+    });
+}
+
+async fn finish(client: &Client, restore: &PendingRestore) -> Result<String, String> {
+    let Some(path) = &restore.download_to else {
+        info!("Restore of s3://{}/{} is available", restore.bucket, restore.key);
+        return Ok(format!("Restore xong: {} (có thể tải về)", restore.key));
+    };
+    // Hours or days may have passed; edits made meanwhile are kept and the restored copy goes next to them
+    if crate::two_way::local_file_at(path) != restore.local_before {
+        let sibling = restored_sibling(path);
+        crate::two_way::download_object(client, &restore.bucket, &restore.key, &sibling).await?;
+        warn!("{} changed while s3://{}/{} was restoring; downloaded to {}", path.display(), restore.bucket, restore.key, sibling.display());
+        return Ok(format!("Restore xong: {} đã thay đổi nên bản restore được lưu ở {}", path.display(), sibling.display()));
+    }
+    let etag = crate::two_way::download_object(client, &restore.bucket, &restore.key, path).await?;
+    if let Some(baseline) = &restore.baseline {
+        crate::two_way::record_download(baseline, path, etag);
+    }
+    info!("Downloaded restored s3://{}/{} to {}", restore.bucket, restore.key, path.display());
+    Ok(format!("Restore xong và đã tải {} về {}", restore.key, path.display()))
+}
+
+/// `report.restored.txt` next to `report.txt`.
+fn restored_sibling(path: &Path) -> PathBuf {
+    let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let name = match path.extension() {
+        Some(ext) => format!("{}.restored.{}", stem, ext.to_string_lossy()),
+        None => format!("{}.restored", stem),
+    };
+    path.with_file_name(name)
+}
+
+/// State file lives next to the confy config file.
+fn state_path() -> Option<PathBuf> {
+    crate::config::get_config_path().and_then(|p| p.parent().map(|d| d.join(STATE_FILE_NAME)))
+}
+
+fn load_pending() -> Vec<PendingRestore> {
+    state_path()
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+fn save_pending(pending: &[PendingRestore]) {
+    let Some(path) = state_path() else { return };
+    let result = serde_json::to_string(pending)
+        .map_err(|e| e.to_string())
+        .and_then(|text| std::fs::write(&path, text).map_err(|e| e.to_string()));
+    if let Err(e) = result {
+        warn!("Không thể ghi danh sách restore {:?}: {}", path, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_restore_header() {
+        assert_eq!(parse_restore_header(None), RestoreStatus::NotRestored);
+        assert_eq!(parse_restore_header(Some("ongoing-request=\"true\"")), RestoreStatus::InProgress);
+        assert_eq!(
+            parse_restore_header(Some("ongoing-request=\"false\", expiry-date=\"Fri, 21 Dec 2012 00:00:00 GMT\"")),
+            RestoreStatus::Available
+        );
+        assert!(is_archived("DEEP_ARCHIVE") && !is_archived("GLACIER_IR"));
+    }
+
+    #[test]
+    fn test_restored_sibling_and_old_state_file() {
+        assert_eq!(restored_sibling(Path::new("/d/report.txt")), PathBuf::from("/d/report.restored.txt"));
+        assert_eq!(restored_sibling(Path::new("/d/Makefile")), PathBuf::from("/d/Makefile.restored"));
+
+        // Saved before the local state was recorded: any existing file counts as changed
+        let old: PendingRestore = serde_json::from_str(
+            r#"{"bucket":"b","key":"k","download_to":"/d/k","tier":"Standard","requested_at":0}"#,
+        )
+        .unwrap();
+        assert_eq!(old.local_before, None);
+        assert_eq!(old.baseline, None);
+    }
+}
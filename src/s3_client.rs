@@ -1,20 +1,22 @@
-use crate::*;
+use aws_config::retry::RetryConfig;
+use aws_config::timeout::TimeoutConfig;
 use aws_sdk_s3::Client;
 use aws_sdk_s3::config::{Credentials, Region};
 use aws_sdk_s3::primitives::ByteStream;
-use chrono::{Local, Datelike};
-use slint::Weak;
+use chrono::Local;
 use std::collections::{HashMap, HashSet};
-use std::fs::OpenOptions;
-use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::{Mutex, Semaphore};
 use tokio::task::JoinSet;
 use tracing::{debug, error, info, warn};
 use walkdir::WalkDir;
 
-use crate::utils::{get_mime_type, update_status};
+use crate::config::{DatePartition, DedupMode, PlaceholderPolicy};
+use crate::object_store::ObjectStore;
+use crate::sync_error::SyncError;
+use crate::utils::get_mime_type;
 
 /// Creates an S3 client with provided credentials and region.
 pub async fn create_s3_client(
@@ -23,24 +25,183 @@ pub async fn create_s3_client(
     sess_token: Option<String>,
     region: String,
 ) -> Result<Client, aws_sdk_s3::Error> {
+    let config = load_sdk_config(acc_key, sec_key, sess_token, region).await;
+    Ok(Client::new(&config))
+}
+
+async fn load_sdk_config(acc_key: String, sec_key: String, sess_token: Option<String>, region: String) -> aws_config::SdkConfig {
+    crate::redact::register_secrets(&[&acc_key, &sec_key, sess_token.as_deref().unwrap_or_default()]);
     let credentials = Credentials::new(acc_key, sec_key, sess_token, None, "manual");
-    let config = aws_config::from_env()
+    let settings = crate::config::settings();
+    let mut timeouts = TimeoutConfig::builder().connect_timeout(Duration::from_secs(settings.connect_timeout_secs));
+    if settings.operation_timeout_secs > 0 {
+        timeouts = timeouts.operation_timeout(Duration::from_secs(settings.operation_timeout_secs));
+    }
+    aws_config::from_env()
         .credentials_provider(credentials)
         .region(Region::new(region))
+        .retry_config(RetryConfig::standard().with_max_attempts(settings.max_attempts))
+        .timeout_config(timeouts.build())
         .load()
-        .await;
-    Ok(Client::new(&config))
+        .await
 }
 
-/// Tests access to S3 bucket by attempting to head the bucket.
-pub async fn test_bucket_access(client: &Client, bucket: &str) -> Result<(), aws_sdk_s3::Error> {
-    client.head_bucket().bucket(bucket).send().await?;
-    Ok(())
+/// Client for the S3 Control API (Batch Operations jobs) in `region`.
+pub async fn create_s3control_client(
+    acc_key: String,
+    sec_key: String,
+    sess_token: Option<String>,
+    region: String,
+) -> aws_sdk_s3control::Client {
+    let config = load_sdk_config(acc_key, sec_key, sess_token, region).await;
+    aws_sdk_s3control::Client::new(&config)
+}
+
+/// Who the credentials belong to, as reported by STS GetCallerIdentity.
+#[derive(Debug, Clone)]
+pub struct CallerIdentity {
+    pub account: String,
+    pub arn: String,
+    /// IAM user name, or the session name for assumed roles.
+    pub user_name: String,
+}
+
+impl std::fmt::Display for CallerIdentity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.account, self.arn)
+    }
+}
+
+/// Last segment of the ARN: `arn:aws:iam::1:user/dev/alice` → `alice`,
+/// `arn:aws:sts::1:assumed-role/Deploy/ci-session` → `ci-session`.
+fn user_name_from_arn(arn: &str) -> String {
+    let resource = arn.splitn(6, ':').nth(5).unwrap_or(arn);
+    resource.rsplit('/').next().unwrap_or(resource).to_string()
+}
+
+/// Looks up the account and caller ARN for the credentials via STS GetCallerIdentity.
+pub async fn caller_identity(
+    acc_key: String,
+    sec_key: String,
+    sess_token: Option<String>,
+    region: String,
+) -> Result<CallerIdentity, String> {
+    let config = load_sdk_config(acc_key, sec_key, sess_token, region).await;
+    let identity = aws_sdk_sts::Client::new(&config)
+        .get_caller_identity()
+        .send()
+        .await
+        .map_err(|e| format!("STS GetCallerIdentity lỗi: {}", e))?;
+    let arn = identity.arn().unwrap_or("?").to_string();
+    Ok(CallerIdentity {
+        account: identity.account().unwrap_or("?").to_string(),
+        user_name: user_name_from_arn(&arn),
+        arn,
+    })
+}
+
+/// Clock difference to AWS beyond which requests are rejected as RequestTimeTooSkewed.
+const MAX_CLOCK_SKEW_SECS: i64 = 15 * 60;
+
+/// Failed Test Access, with the local clock offset when it is the likely cause.
+#[derive(Debug)]
+pub struct AccessError {
+    pub message: String,
+    /// Seconds the local clock is behind (negative) or ahead (positive) of AWS.
+    pub clock_skew_secs: Option<i64>,
+}
+
+impl std::fmt::Display for AccessError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.clock_skew_secs {
+            Some(skew) => write!(
+                f,
+                "Đồng hồ máy lệch{} so với AWS (RequestTimeTooSkewed). \
+                 Hãy bật đồng bộ giờ tự động (Windows: Settings › Time & language › Sync now; \
+                 Linux: timedatectl set-ntp true) rồi thử lại",
+                match skew {
+                    0 => String::new(),
+                    s if s > 0 => format!(" (nhanh {} giây)", s),
+                    s => format!(" (chậm {} giây)", -s),
+                }
+            ),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// Tests access to S3 bucket by attempting to head the bucket, returning the round-trip latency.
+pub async fn test_bucket_access(client: &Client, bucket: &str) -> Result<std::time::Duration, AccessError> {
+    use aws_sdk_s3::error::ProvideErrorMetadata;
+
+    let started = std::time::Instant::now();
+    match client.head_bucket().bucket(bucket).send().await {
+        Ok(_) => Ok(started.elapsed()),
+        Err(e) => {
+            // The error body carries the code for GET requests; HEAD only has the server Date header
+            let server_offset = e
+                .raw_response()
+                .and_then(|r| r.headers().get("date"))
+                .and_then(|d| chrono::DateTime::parse_from_rfc2822(d).ok())
+                .map(|server| Local::now().timestamp() - server.timestamp());
+            let skewed = e.code() == Some("RequestTimeTooSkewed")
+                || server_offset.is_some_and(|o| o.abs() >= MAX_CLOCK_SKEW_SECS);
+            Err(AccessError {
+                message: format!("Lỗi: {}", aws_sdk_s3::Error::from(e)),
+                clock_skew_secs: if skewed { Some(server_offset.unwrap_or_default()) } else { None },
+            })
+        }
+    }
+}
+
+/// HeadBucket result: the HTTP status of a failure with its message, and the region S3
+/// reported (`x-amz-bucket-region`), which it sends even on 301 and 403.
+async fn head_bucket_region(client: &Client, bucket: &str) -> (Result<(), (Option<u16>, String)>, Option<String>) {
+    match client.head_bucket().bucket(bucket).send().await {
+        Ok(out) => (Ok(()), out.bucket_region().map(String::from)),
+        Err(e) => {
+            let region = e
+                .raw_response()
+                .and_then(|r| r.headers().get("x-amz-bucket-region"))
+                .map(String::from);
+            let status = e.raw_response().map(|r| r.status().as_u16());
+            (Err((status, aws_sdk_s3::Error::from(e).to_string())), region)
+        }
+    }
+}
+
+/// Checks that `bucket` exists and the credentials can reach it, and finds its region.
+/// A bucket in another region is asked again through that region, so the status is real.
+pub async fn check_bucket(client: &Client, bucket: &str) -> crate::config::BucketCheck {
+    use crate::config::{BucketCheck, BucketStatus};
+
+    let client_region = client.config().region().map(|r| r.to_string()).unwrap_or_default();
+    let (mut result, region) = head_bucket_region(client, bucket).await;
+    if let Some(other) = region.as_deref().filter(|r| *r != client_region)
+        && matches!(result, Err((Some(301 | 400), _)))
+    {
+        let regional = Client::from_conf(client.config().to_builder().region(Region::new(other.to_string())).build());
+        result = head_bucket_region(&regional, bucket).await.0;
+    }
+
+    let status = match &result {
+        Ok(()) => BucketStatus::Reachable,
+        Err((Some(403), _)) => BucketStatus::AccessDenied,
+        Err((Some(404), _)) => BucketStatus::NotFound,
+        Err((_, message)) => {
+            warn!("Bucket check {} failed: {}", bucket, message);
+            BucketStatus::Error
+        }
+    };
+    let region = region.unwrap_or_else(|| if result.is_ok() { client_region } else { String::new() });
+    BucketCheck { status, region, checked_at: Local::now().timestamp() }
 }
 
 /// Cache structure for S3 prefix lookups to avoid redundant requests
 pub struct PrefixCache {
     pub prefixes: HashSet<String>,
+    /// Nested prefixes probed directly (prefix -> has objects), dropped with the listing.
+    pub probed: HashMap<String, bool>,
     pub cache_time: std::time::Instant,
 }
 
@@ -48,6 +209,7 @@ impl PrefixCache {
     fn new() -> Self {
         Self {
             prefixes: HashSet::new(),
+            probed: HashMap::new(),
             cache_time: std::time::Instant::now(),
         }
     }
@@ -60,109 +222,85 @@ impl PrefixCache {
 /// Global cache for S3 prefixes per bucket
 pub type GlobalPrefixCache = Arc<Mutex<HashMap<String, PrefixCache>>>;
 
+/// Lists the bucket into `cache` unless its entry is still within the cache TTL.
+async fn refresh_prefix_cache(store: &dyn ObjectStore, bucket: &str, cache: &mut HashMap<String, PrefixCache>) {
+    let ttl_secs = crate::config::settings().cache_ttl_secs;
+    let needs_refresh = cache.get(bucket).is_none_or(|entry| entry.is_expired(ttl_secs));
+
+    if needs_refresh && let Ok(resp) = store.list(bucket, "", true).await {
+        let mut new_cache = PrefixCache::new();
+        for prefix in &resp.prefixes {
+            new_cache.prefixes.insert(
+                prefix
+                    .trim_end_matches('/')
+                    .trim_start_matches('/')
+                    .to_string(),
+            );
+        }
+        for (key, _) in &resp.objects {
+            if let Some((parent, _)) = key.rsplit_once('/') {
+                new_cache.prefixes.insert(
+                    parent
+                        .trim_end_matches('/')
+                        .trim_start_matches('/')
+                        .to_string(),
+                );
+            }
+        }
+        cache.insert(bucket.to_string(), new_cache);
+    }
+}
+
 /// Checks if a prefix (folder) exists in S3 bucket using cache.
 pub async fn is_s3_prefix_exists_cached(
-    client: &Client,
+    store: &dyn ObjectStore,
     bucket: &str,
     prefix: &str,
     cache: &GlobalPrefixCache,
 ) -> bool {
-    let prefix_normalized = if prefix.ends_with('/') || prefix.is_empty() {
-        prefix.to_string()
-    } else {
-        format!("{}/", prefix)
-    };
-
     let mut cache_guard = cache.lock().await;
-
-    let cache_entry = cache_guard.get(bucket);
-    
-    // FIXED: Use configurable TTL from env var, default to 5 minutes
-    let ttl_secs = std::env::var("S3_CACHE_TTL_SECS")
-        .ok()
-        .and_then(|s| s.parse().ok())
-        .unwrap_or(300);
-    let needs_refresh = cache_entry.is_none() || cache_entry.unwrap().is_expired(ttl_secs);
-
-    if needs_refresh {
-        if let Ok(resp) = client
-            .list_objects_v2()
-            .bucket(bucket)
-            .delimiter("/")
-            .max_keys(1000)
-            .send()
-            .await
-        {
-            let mut new_cache = PrefixCache::new();
-            for cp in resp.common_prefixes() {
-                if let Some(prefix) = cp.prefix() {
-                    new_cache.prefixes.insert(
-                        prefix
-                            .trim_end_matches('/')
-                            .trim_start_matches('/')
-                            .to_string(),
-                    );
-                }
-            }
-            for obj in resp.contents() {
-                if let Some(key) = obj.key() {
-                    if let Some((parent, _)) = key.rsplit_once('/') {
-                        new_cache.prefixes.insert(
-                            parent
-                                .trim_end_matches('/')
-                                .trim_start_matches('/')
-                                .to_string(),
-                        );
-                    }
-                }
-            }
-            cache_guard.insert(bucket.to_string(), new_cache);
-        }
-    }
+    refresh_prefix_cache(store, bucket, &mut cache_guard).await;
 
     if let Some(entry) = cache_guard.get(bucket) {
-        let trimmed = prefix_normalized.trim_end_matches('/');
+        let trimmed = prefix.trim_end_matches('/');
         return entry.prefixes.contains(trimmed);
     }
 
     false
 }
 
-/// Normalizes a path for S3 use by filtering out system and user-specific directories.
-pub fn normalize_path_parts(path: &std::path::Path) -> Vec<String> {
+/// All prefixes (folders) known in the bucket, sorted, from the cache when it's fresh.
+pub async fn cached_prefixes(store: &dyn ObjectStore, bucket: &str, cache: &GlobalPrefixCache) -> Vec<String> {
+    let mut cache_guard = cache.lock().await;
+    refresh_prefix_cache(store, bucket, &mut cache_guard).await;
+
+    let mut prefixes: Vec<String> = cache_guard
+        .get(bucket)
+        .map(|entry| entry.prefixes.iter().filter(|p| !p.is_empty()).cloned().collect())
+        .unwrap_or_default();
+    prefixes.sort();
+    prefixes
+}
+
+/// Normalizes a path for S3 use by dropping the drive and the leading system and user-specific
+/// directories (`rules.system_folders`); everything from the first other folder on is kept.
+pub fn normalize_path_parts(path: &std::path::Path, rules: &crate::config::PrefixRules) -> Vec<String> {
     let normalized = path.to_string_lossy().replace('\\', "/");
-    normalized
-        .split('/')
-        .filter_map(|s| {
-            let s = s.trim();
-            let s_lower = s.to_lowercase();
-            // Filter out drive letters, system folders, and common user folders
-            if s.is_empty()
-                || s.contains(':')
-                || [
-                    "users",
-                    "home",
-                    "desktop",
-                    "documents",
-                    "downloads",
-                    "appdata",
-                    "local",
-                    "temp",
-                    "admin",
-                ]
-                .contains(&s_lower.as_str())
-            {
-                None
-            } else {
-                Some(s.to_string())
-            }
-        })
-        .collect()
+    let mut parts = normalized.split('/').map(str::trim).filter(|s| !s.is_empty()).peekable();
+    while let Some(part) = parts.peek() {
+        let lower = part.to_lowercase();
+        if !part.contains(':') && !rules.system_folders.iter().any(|f| f.trim().to_lowercase() == lower) {
+            break;
+        }
+        parts.next();
+    }
+    parts.map(String::from).collect()
 }
 
-/// Simple preview: usually takes last 2-3 folder levels to provide safe context.
-pub fn get_preview_prefix(path: &std::path::Path) -> String {
-    let parts = normalize_path_parts(path);
+/// Simple preview: takes the last `depth` folder levels (1 = folder name only). With depth 0
+/// it takes the last 2-3 levels to provide safe context.
+pub fn get_preview_prefix(path: &std::path::Path, rules: &crate::config::PrefixRules, depth: u8) -> String {
+    let parts = normalize_path_parts(path, rules);
     if parts.is_empty() {
         return path
             .file_name()
@@ -171,105 +309,864 @@ pub fn get_preview_prefix(path: &std::path::Path) -> String {
             .to_string();
     }
 
-    // Take last 2-3 levels to provide enough context
-    let n = parts.len();
-    if n >= 3 {
-        format!("{}/{}/{}", parts[n - 3], parts[n - 2], parts[n - 1])
-    } else if n >= 2 {
-        format!("{}/{}", parts[n - 2], parts[n - 1])
-    } else {
-        parts[0].clone()
+    let levels = match depth {
+        0 => 3,
+        depth => depth.min(crate::config::MAX_PREVIEW_DEPTH) as usize,
+    };
+    parts[parts.len().saturating_sub(levels)..].join("/")
+}
+
+/// Where a mapping's S3 path came from, shown on its row so a guess isn't mistaken for a
+/// prefix that was found on S3.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrefixSource {
+    /// Relative path below the configured S3 base path.
+    BasePath,
+    /// Found on S3; `matched` is how many folders of the candidate exist there.
+    SmartMatch { matched: usize },
+    /// Last folders of the local path, not checked against S3.
+    Preview,
+}
+
+impl PrefixSource {
+    pub fn label(&self) -> String {
+        match self {
+            PrefixSource::BasePath => "base path".to_string(),
+            PrefixSource::SmartMatch { matched } => format!("smart match · {} cấp có trên S3", matched),
+            PrefixSource::Preview => "đoán · chưa kiểm tra trên S3".to_string(),
+        }
+    }
+
+    /// Whether the path is only a guess the user should double-check.
+    pub fn is_guess(&self) -> bool {
+        *self == PrefixSource::Preview
     }
 }
 
 /// Robust prefix detection: uses normalized path, and expands/merges
 /// based on actual S3 structure to prevent production path errors.
+///
+/// The cached listing only knows top-level prefixes, so nested candidates whose first folder
+/// exists there are probed directly (one key each, in parallel). Probe results are cached
+/// with the listing.
 pub async fn find_best_s3_prefix(
-    client: &Client,
+    store: Arc<dyn ObjectStore>,
     bucket: &str,
     local_path: &Path,
+    rules: &crate::config::PrefixRules,
+    preview_depth: u8,
     cache: &GlobalPrefixCache,
-) -> String {
-    let default_prefix = get_preview_prefix(local_path);
+) -> (String, PrefixSource) {
+    let default_prefix = get_preview_prefix(local_path, rules, preview_depth);
 
     // Try to find a longer match on S3 if possible, with FIXED logic
     let normalized = local_path.to_string_lossy().replace('\\', "/");
     let parts: Vec<&str> = normalized.split('/').filter(|s: &&str| !s.is_empty() && !s.contains(':')).collect();
-    let n = parts.len();
-    
-    for i in 0..n {
-        let candidate = parts[i..].join("/");
-
-        if is_s3_prefix_exists_cached(client, bucket, &candidate, cache).await {
-            // FIXED: Check if candidate is a PROPER prefix of default
-if candidate.split('/').count() == 1 && default_prefix.contains('/')
-                && !default_prefix.starts_with(&candidate) && !default_prefix.contains(&format!("{}/", candidate)) {
-                    continue;
-                }
-            info!("Smart Match found on S3: '{}'", candidate);
-            return candidate;
+    // Longest first
+    let candidates: Vec<String> = (0..parts.len())
+        .map(|i| parts[i..].join("/"))
+        // FIXED: a single folder only counts if it's a PROPER prefix of default
+        .filter(|candidate| {
+            candidate.contains('/')
+                || !default_prefix.contains('/')
+                || default_prefix.starts_with(candidate.as_str())
+                || default_prefix.contains(&format!("{}/", candidate))
+        })
+        .collect();
+
+    let mut known: HashMap<String, bool> = HashMap::new();
+    let mut to_probe: Vec<String> = Vec::new();
+    {
+        let mut cache_guard = cache.lock().await;
+        refresh_prefix_cache(store.as_ref(), bucket, &mut cache_guard).await;
+        let Some(entry) = cache_guard.get(bucket) else {
+            info!("Using prefix: '{}'", default_prefix);
+            return (default_prefix, PrefixSource::Preview);
+        };
+        for candidate in &candidates {
+            let top = candidate.split('/').next().unwrap_or_default();
+            if entry.prefixes.contains(candidate.as_str()) {
+                known.insert(candidate.clone(), true);
+            } else if let Some(&exists) = entry.probed.get(candidate) {
+                known.insert(candidate.clone(), exists);
+            } else if candidate.contains('/') && entry.prefixes.contains(top) {
+                to_probe.push(candidate.clone());
+            }
+        }
+    }
+
+    if !to_probe.is_empty() {
+        let mut set = JoinSet::new();
+        for candidate in to_probe {
+            let store = Arc::clone(&store);
+            let bucket = bucket.to_string();
+            set.spawn(async move {
+                let exists = store.prefix_exists(&bucket, &format!("{}/", candidate)).await;
+                (candidate, exists)
+            });
+        }
+        let mut probed = Vec::new();
+        while let Some(result) = set.join_next().await {
+            match result {
+                Ok((candidate, Ok(exists))) => probed.push((candidate, exists)),
+                Ok((candidate, Err(e))) => warn!("Prefix probe '{}' failed: {}", candidate, e),
+                Err(e) => warn!("Prefix probe task failed: {}", e),
+            }
         }
+        debug!("Probed {} prefixes in {}", probed.len(), bucket);
+        let mut cache_guard = cache.lock().await;
+        if let Some(entry) = cache_guard.get_mut(bucket) {
+            entry.probed.extend(probed.iter().cloned());
+        }
+        known.extend(probed);
+    }
+
+    if let Some(candidate) = candidates.into_iter().find(|c| known.get(c) == Some(&true)) {
+        info!("Smart Match found on S3: '{}'", candidate);
+        let matched = candidate.split('/').count();
+        return (candidate, PrefixSource::SmartMatch { matched });
     }
 
     info!("Using prefix: '{}'", default_prefix);
-    default_prefix
+    (default_prefix, PrefixSource::Preview)
+}
+
+/// One level of a bucket listing: sub-prefixes and objects directly under a prefix.
+#[derive(Debug, Clone, Default)]
+pub struct BucketLevel {
+    pub prefixes: Vec<String>,
+    /// (key, size in bytes)
+    pub objects: Vec<(String, u64)>,
+}
+
+/// Lists sub-prefixes and objects directly under `prefix` (delimiter `/`), following pagination.
+pub async fn list_bucket_level(store: &dyn ObjectStore, bucket: &str, prefix: &str) -> Result<BucketLevel, String> {
+    let list = store
+        .list(bucket, prefix, true)
+        .await
+        .map_err(|e| format!("Lỗi list objects: {}", e))?;
+    let objects = list
+        .objects
+        .into_iter()
+        // Skip the folder marker object of the current prefix itself
+        .filter(|(key, _)| key != prefix)
+        .map(|(key, object)| (key, object.size))
+        .collect();
+    Ok(BucketLevel { prefixes: list.prefixes, objects })
+}
+
+/// Downloads a single object into a fresh folder under the system temp directory, decrypting
+/// client-side encrypted objects, and returns the local file path.
+pub async fn download_object_to_temp(client: &Client, bucket: &str, key: &str) -> Result<PathBuf, String> {
+    let file_name = key.rsplit('/').next().filter(|n| !n.is_empty()).unwrap_or("object");
+    let dir = std::env::temp_dir()
+        .join("s3sync_preview")
+        .join(Local::now().format("%Y%m%d_%H%M%S%3f").to_string());
+    tokio::fs::create_dir_all(&dir)
+        .await
+        .map_err(|e| format!("Không thể tạo thư mục tạm: {}", e))?;
+    let local_path = dir.join(file_name);
+    // Same path as restores and two-way downloads, so encrypted objects open as plaintext
+    crate::two_way::download_object(client, bucket, key, &local_path).await?;
+
+    info!("Downloaded s3://{}/{} -> {}", bucket, key, local_path.display());
+    Ok(local_path)
+}
+
+/// Normalizes a user-entered prefix for destructive operations: forward slashes,
+/// no leading slash, trailing slash so `assets` never matches `assets-old/`.
+/// Returns None for an empty prefix (the whole bucket), which is never allowed.
+pub fn normalize_delete_prefix(prefix: &str) -> Option<String> {
+    let trimmed = prefix.trim().replace('\\', "/");
+    let trimmed = trimmed.trim_matches('/');
+    if trimmed.is_empty() {
+        None
+    } else {
+        Some(format!("{}/", trimmed))
+    }
+}
+
+/// Deletes every object under `prefix` using paginated listing and batched `delete_objects`
+/// (max 1000 keys per request). Returns the number of deleted objects.
+pub async fn delete_prefix(
+    store: &dyn ObjectStore,
+    bucket: &str,
+    prefix: &str,
+    mut on_progress: impl FnMut(u64),
+) -> Result<u64, String> {
+    let prefix = normalize_delete_prefix(prefix)
+        .ok_or_else(|| "Không được xóa toàn bộ bucket (prefix rỗng)".to_string())?;
+
+    let keys: Vec<String> = store
+        .list(bucket, &prefix, false)
+        .await
+        .map_err(|e| format!("Lỗi list objects: {}", e))?
+        .objects
+        .into_iter()
+        .map(|(key, _)| key)
+        .collect();
+
+    let mut deleted = 0u64;
+    for batch in keys.chunks(1000) {
+        store
+            .delete(bucket, batch)
+            .await
+            .map_err(|e| format!("Lỗi xóa objects: {}", e))?;
+
+        deleted += batch.len() as u64;
+        on_progress(deleted);
+        debug!("Deleted {} objects under '{}'", deleted, prefix);
+    }
+
+    info!("Deleted prefix '{}' in bucket {}: {} objects", prefix, bucket, deleted);
+    Ok(deleted)
+}
+
+/// Maps a key into a destination prefix (empty prefix keeps the key unchanged).
+pub fn prefixed_key(prefix: &str, key: &str) -> String {
+    let prefix = prefix.trim().trim_matches('/');
+    if prefix.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}/{}", prefix, key.trim_start_matches('/'))
+    }
+}
+
+/// Server-side copies `keys` from `src_bucket` into `dst_bucket` under `dst_prefix`.
+/// Stops at the first failure. Returns the number of copied objects.
+pub async fn copy_keys(
+    store: Arc<dyn ObjectStore>,
+    src_bucket: &str,
+    keys: Vec<String>,
+    dst_bucket: &str,
+    dst_prefix: &str,
+    on_progress: impl Fn(usize, usize),
+) -> Result<usize, String> {
+    let pairs = keys
+        .into_iter()
+        .map(|key| {
+            let dst_key = prefixed_key(dst_prefix, &key);
+            (key, dst_key)
+        })
+        .collect();
+    let copied = copy_objects(store, src_bucket, dst_bucket, pairs, on_progress).await?;
+    info!("Copied {} objects from {} to {}/{}", copied, src_bucket, dst_bucket, dst_prefix);
+    Ok(copied)
+}
+
+/// Server-side copies each `(src_key, dst_key)` pair from `src_bucket` into `dst_bucket`.
+/// Stops at the first failure. Returns the number of copied objects.
+pub async fn copy_objects(
+    store: Arc<dyn ObjectStore>,
+    src_bucket: &str,
+    dst_bucket: &str,
+    pairs: Vec<(String, String)>,
+    on_progress: impl Fn(usize, usize),
+) -> Result<usize, String> {
+    copy_objects_with_class(store, src_bucket, dst_bucket, pairs, None, on_progress).await
+}
+
+/// Changes the storage class of `keys` by copying each object onto itself.
+/// Metadata and content headers are kept. Stops at the first failure.
+pub async fn transition_storage_class(
+    store: Arc<dyn ObjectStore>,
+    bucket: &str,
+    keys: Vec<String>,
+    storage_class: &str,
+    on_progress: impl Fn(usize, usize),
+) -> Result<usize, String> {
+    let pairs = keys.into_iter().map(|key| (key.clone(), key)).collect();
+    let moved = copy_objects_with_class(store, bucket, bucket, pairs, Some(storage_class), on_progress).await?;
+    info!("Transitioned {} objects in {} to {}", moved, bucket, storage_class);
+    Ok(moved)
+}
+
+async fn copy_objects_with_class(
+    store: Arc<dyn ObjectStore>,
+    src_bucket: &str,
+    dst_bucket: &str,
+    pairs: Vec<(String, String)>,
+    storage_class: Option<&str>,
+    on_progress: impl Fn(usize, usize),
+) -> Result<usize, String> {
+    let total = pairs.len();
+    let semaphore = Arc::new(Semaphore::new(20));
+    let mut set = JoinSet::new();
+
+    for (src_key, dst_key) in pairs {
+        let store = Arc::clone(&store);
+        let semaphore = Arc::clone(&semaphore);
+        let src_bucket = src_bucket.to_string();
+        let dst_bucket = dst_bucket.to_string();
+        let storage_class = storage_class.map(String::from);
+
+        set.spawn(async move {
+            let _permit = semaphore.acquire().await.unwrap();
+            store
+                .copy(&src_bucket, &src_key, &dst_bucket, &dst_key, storage_class.as_deref())
+                .await
+                .map_err(|e| format!("Lỗi copy {}/{} -> {}: {}", src_bucket, src_key, dst_key, e))
+        });
+    }
+
+    let mut copied = 0usize;
+    while let Some(res) = set.join_next().await {
+        match res {
+            Ok(Ok(())) => {
+                copied += 1;
+                on_progress(copied, total);
+            }
+            Ok(Err(e)) => {
+                set.abort_all();
+                return Err(e);
+            }
+            Err(e) => {
+                set.abort_all();
+                return Err(format!("Task lỗi: {}", e));
+            }
+        }
+    }
+
+    Ok(copied)
+}
+
+/// Creates zero-byte `folder/` marker objects. Stops at the first failure.
+pub async fn put_folder_markers(store: &dyn ObjectStore, bucket: &str, keys: &[String]) -> Result<usize, String> {
+    for key in keys {
+        store
+            .put(bucket, key, Vec::new())
+            .await
+            .map_err(|e| format!("Lỗi tạo thư mục {}: {}", key, e))?;
+        debug!("Created folder marker: {}", key);
+    }
+    Ok(keys.len())
+}
+
+/// Root prefix for staged deploys; each deploy uploads into `.staging/<timestamp>/` first.
+pub const STAGING_PREFIX: &str = ".staging/";
+
+/// Checks that every uploaded (key, size) exists with the same size under `staging_prefix`.
+async fn verify_staged(store: &dyn ObjectStore, bucket: &str, staging_prefix: &str, uploaded: &[(String, u64)]) -> Result<(), String> {
+    let staged = crate::diff::list_remote_objects(store, bucket, staging_prefix).await?;
+    for (key, size) in uploaded {
+        match staged.get(key) {
+            Some(obj) if obj.size == *size => {}
+            Some(obj) => return Err(format!("Staging sai kích thước {}: {} ≠ {}", key, obj.size, size)),
+            None => return Err(format!("Staging thiếu {}", key)),
+        }
+    }
+    Ok(())
+}
+
+/// Root prefix holding backups of objects overwritten by a sync.
+pub const TRASH_PREFIX: &str = ".trash/";
+
+/// Root prefix of other objects the app writes for itself (batch manifests, ...).
+pub const APP_PREFIX: &str = ".s3sync/";
+
+/// Every root prefix holding the app's own bookkeeping rather than synced content.
+pub const BOOKKEEPING_PREFIXES: [&str; 3] = [APP_PREFIX, STAGING_PREFIX, TRASH_PREFIX];
+
+/// Whether `key` lies under one of [`BOOKKEEPING_PREFIXES`].
+pub fn is_bookkeeping_key(key: &str) -> bool {
+    BOOKKEEPING_PREFIXES.iter().any(|prefix| key.starts_with(prefix))
+}
+
+/// Trash folder for one sync session: `.trash/<timestamp>/`.
+pub fn trash_session_prefix(time: &chrono::DateTime<Local>) -> String {
+    format!("{}{}/", TRASH_PREFIX, time.format("%Y%m%d_%H%M%S"))
+}
+
+/// Returns true if `prefix` is exactly one trash session folder (`.trash/<timestamp>/`).
+pub fn is_trash_session_prefix(prefix: &str) -> bool {
+    prefix
+        .strip_prefix(TRASH_PREFIX)
+        .and_then(|rest| rest.strip_suffix('/'))
+        .is_some_and(|ts| !ts.is_empty() && !ts.contains('/'))
+}
+
+/// Server-side copies the current object at `key` into `trash_prefix` before it gets overwritten.
+/// Returns Ok(false) if the key does not exist yet.
+pub async fn backup_existing_object(store: &dyn ObjectStore, bucket: &str, key: &str, trash_prefix: &str) -> Result<bool, String> {
+    match store.head(bucket, key).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return Ok(false),
+        Err(e) => return Err(format!("Lỗi kiểm tra {}: {}", key, e)),
+    }
+
+    let trash_key = format!("{}{}", trash_prefix, key);
+    store
+        .copy(bucket, key, bucket, &trash_key, None)
+        .await
+        .map_err(|e| format!("Lỗi backup {} -> {}: {}", key, trash_key, e))?;
+    debug!("Backed up {} -> {}", key, trash_key);
+    Ok(true)
+}
+
+/// Copies every object of a trash session back to its original key. Returns the number restored.
+pub async fn restore_trash_session(
+    store: Arc<dyn ObjectStore>,
+    bucket: &str,
+    trash_prefix: &str,
+    on_progress: impl Fn(usize, usize),
+) -> Result<usize, String> {
+    let listing = crate::diff::list_remote_objects(store.as_ref(), bucket, trash_prefix).await?;
+    let prefix = crate::diff::normalize_list_prefix(trash_prefix);
+    let pairs = listing
+        .into_keys()
+        .map(|rel| (format!("{}{}", prefix, rel), rel))
+        .collect();
+    let restored = copy_objects(store, bucket, bucket, pairs, on_progress).await?;
+    info!("Restored {} objects from s3://{}/{}", restored, bucket, prefix);
+    Ok(restored)
+}
+
+/// Outcome of a sync session, used for the completion report.
+#[derive(Debug, Clone, Default)]
+pub struct SyncSummary {
+    pub bucket: String,
+    pub total_files: usize,
+    pub uploaded: usize,
+    pub bytes_uploaded: u64,
+    /// Keys uploaded successfully, in completion order.
+    pub uploaded_keys: Vec<String>,
+    pub failed: usize,
+    pub filtered: u64,
+    /// Duplicate files not uploaded directly (skipped or copied server-side).
+    pub deduplicated: usize,
+    /// Junctions, reparse points and cloud placeholders skipped during the walk (Windows).
+    pub skipped_special: usize,
+    pub duration: std::time::Duration,
+    pub errors: Vec<String>,
+    /// Number of errors per [`SyncError::category`].
+    pub error_counts: std::collections::BTreeMap<&'static str, usize>,
+    /// Time each successful upload took, from acquiring its slot to the end of PutObject.
+    pub upload_timings: Vec<UploadTiming>,
+    /// Problems that don't fail the sync, e.g. a missing website index document.
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct UploadTiming {
+    pub key: String,
+    pub bytes: u64,
+    pub duration: std::time::Duration,
+    /// Headers sent with the object, checked again by the HeadObject sample afterwards.
+    pub content_type: String,
+    pub cache_control: Option<String>,
+}
+
+impl SyncSummary {
+    /// A sync that stopped before uploading anything (client, preflight, redirects, key policy),
+    /// so it still reaches the report and the failure counters.
+    pub fn aborted(bucket: &str, error: String) -> Self {
+        let mut summary = SyncSummary { bucket: bucket.to_string(), ..Default::default() };
+        summary.record_error(SyncError::Other(error));
+        summary
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.failed == 0
+    }
+
+    /// Counts a failed step and keeps its message for the report.
+    pub fn record_error(&mut self, error: SyncError) {
+        self.failed += 1;
+        *self.error_counts.entry(error.category()).or_default() += 1;
+        self.errors.push(error.to_string());
+    }
+
+    /// The `n` uploads that took longest, slowest first.
+    pub fn slowest_uploads(&self, n: usize) -> Vec<&UploadTiming> {
+        let mut timings: Vec<&UploadTiming> = self.upload_timings.iter().collect();
+        timings.sort_by_key(|t| std::cmp::Reverse(t.duration));
+        timings.truncate(n);
+        timings
+    }
+}
+
+/// Uploads listed in the "slowest uploads" section of session reports.
+pub const SLOWEST_UPLOADS_REPORTED: usize = 20;
+
+/// "key - 12.3s (4.5 MB, 372.0 KB/s)" line for the slowest uploads report.
+pub fn format_upload_timing(timing: &UploadTiming) -> String {
+    let secs = timing.duration.as_secs_f64();
+    let speed = if secs > 0.0 { (timing.bytes as f64 / secs) as u64 } else { timing.bytes };
+    format!(
+        "{} - {:.1}s ({}, {}/s)",
+        timing.key,
+        secs,
+        crate::utils::format_size(timing.bytes),
+        crate::utils::format_size(speed)
+    )
+}
+
+pub use crate::progress::StatusFn;
+
+/// Extra attempts for an upload S3 keeps throttling after the SDK's own retries.
+const MAX_THROTTLED_RETRIES: u32 = 3;
+/// Wait before the first extra attempt; doubles, then triples, ... with each one.
+const THROTTLED_RETRY_DELAY: Duration = Duration::from_secs(2);
+
+/// Appends the session header and mapping lines to the daily sync log.
+fn write_session_header(log: &crate::sync_log::SessionLog, bucket_name: &str, lines: &[String]) {
+    let mut block = vec![
+        "--------------------------------------------------".to_string(),
+        format!("Sync Session Started - Bucket: {}", bucket_name),
+    ];
+    block.extend(lines.iter().map(|line| crate::redact::redact(line).into_owned()));
+    log.write(block);
+}
+
+/// Makes uploads to the same key run one after another, in upload-list order.
+/// The turn passes on when the upload finishes or fails.
+struct KeyTurn {
+    turns: Arc<tokio::sync::watch::Sender<usize>>,
+    mine: usize,
+}
+
+impl KeyTurn {
+    async fn wait(&self) {
+        let _ = self.turns.subscribe().wait_for(|turn| *turn == self.mine).await;
+    }
+}
+
+/// Hands out [`KeyTurn`]s for the keys several files are mapped onto, in upload-list order.
+struct KeyTurns(HashMap<String, (Arc<tokio::sync::watch::Sender<usize>>, usize)>);
+
+impl KeyTurns {
+    fn new(keys: impl IntoIterator<Item = String>) -> Self {
+        Self(keys.into_iter().map(|key| (key, (Arc::new(tokio::sync::watch::Sender::new(0)), 0))).collect())
+    }
+
+    /// The next turn for `key`, or `None` if only one file is mapped onto it.
+    fn next(&mut self, key: &str) -> Option<KeyTurn> {
+        self.0.get_mut(key).map(|(turns, next)| {
+            *next += 1;
+            KeyTurn { turns: Arc::clone(turns), mine: *next - 1 }
+        })
+    }
+}
+
+/// Backs up the object at `key` before the first upload to it in this run. A later turn would
+/// back up this run's own upload over the original already in the trash.
+async fn backup_before_upload(
+    store: &dyn ObjectStore,
+    bucket: &str,
+    key: &str,
+    trash_prefix: &str,
+    turn: Option<&KeyTurn>,
+) -> Result<bool, String> {
+    if turn.is_some_and(|turn| turn.mine > 0) {
+        return Ok(false);
+    }
+    backup_existing_object(store, bucket, key, trash_prefix).await
+}
+
+impl Drop for KeyTurn {
+    fn drop(&mut self) {
+        self.turns.send_modify(|turn| *turn += 1);
+    }
 }
 
 /// Performs sync operation: uploads all files from the provided mappings to the S3 bucket.
+/// Upload slots come from the shared scheduler through `throttle`, whose job id is also the
+/// session an over-budget approval was given to.
 pub async fn sync_to_s3(
     client: Arc<Client>,
     bucket_name: String,
     mappings: Vec<(String, String)>, // (local_path, s3_path)
-    ui_handle: Weak<AppWindow>,
+    status: StatusFn,
     log_path: String,
-) -> Result<(), String> {
-    update_status(&ui_handle, "Khởi tạo Sync...".to_string(), 0.0, false);
+    throttle: Arc<crate::scheduler::JobThrottle>,
+) -> Result<SyncSummary, String> {
+    status("Khởi tạo Sync...".to_string(), 0.0, false);
+
+    let shown: Vec<String> = mappings.iter().take(5).map(|(local, s3)| format!("{} -> {}", local, s3)).collect();
+    let more = mappings.len().saturating_sub(shown.len());
+    let _crash_context = crate::crash::enter_sync(format!(
+        "{}: {}{}",
+        bucket_name,
+        shown.join(", "),
+        if more > 0 { format!(" (+{} nữa)", more) } else { String::new() }
+    ));
 
     let should_log = !log_path.is_empty();
     let start_time = Local::now();
+    let started = std::time::Instant::now();
     let mut log_mappings: Vec<String> = Vec::new();
     
-    // Pre-compute log file path to avoid duplication
-    let log_file_path = if should_log {
-        Some(format!(
-            "{}/sync_log_{:02}_{:02}_{}.log",
-            log_path,
-            start_time.day(),
-            start_time.month(),
-            start_time.year()
-        ))
+    // Load filter config
+    let app_config = crate::config::load_config();
+    // Written through the shared log writer so concurrent sessions don't interleave
+    let session_log = should_log
+        .then(|| crate::sync_log::SessionLog::new(&log_path, &bucket_name, &start_time, app_config.log_file_mode));
+    let filter_config = match crate::config::filter_override() {
+        Some(live) => {
+            log_mappings.push("Bộ lọc: bản chỉnh sửa chưa lưu (áp dụng ngay)".to_string());
+            live
+        }
+        None => app_config.filter_config,
+    };
+    let dedup_mode = app_config.sync_options.dedup_mode;
+    let key_policy = app_config.sync_options.key_policy;
+    let header_rules = Arc::new(app_config.sync_options.header_rules);
+    let disposition_rules = Arc::new(app_config.sync_options.disposition_rules);
+    let entry_patterns = app_config.sync_options.entry_patterns;
+    let fingerprint_patterns = app_config.sync_options.fingerprint_patterns;
+    let fingerprint_manifest_key = app_config.sync_options.fingerprint_manifest;
+    let checksum_manifest_key = app_config.sync_options.checksum_manifest.trim().to_string();
+    // Shared by dedup, fingerprinting and the checksum manifest so each file is hashed once
+    let hash_cache = Arc::new(crate::dedup::HashCache::default());
+    let create_folder_markers = app_config.sync_options.create_folder_markers;
+    let placeholder_policy = app_config.sync_options.placeholder_policy;
+    let date_partition = app_config.sync_options.date_partition;
+    let sniff_mime = app_config.sync_options.sniff_mime;
+    let store_ctime = app_config.sync_options.store_ctime;
+    let partition_date = |path: &Path| -> Option<chrono::NaiveDate> {
+        match date_partition {
+            DatePartition::Off => None,
+            DatePartition::Sync => Some(start_time.date_naive()),
+            DatePartition::Mtime => Some(
+                std::fs::metadata(path)
+                    .and_then(|m| m.modified())
+                    .map(|t| chrono::DateTime::<Local>::from(t).date_naive())
+                    .unwrap_or_else(|_| start_time.date_naive()),
+            ),
+        }
+    };
+    // Files at or above the threshold use their own lane so they can't take every shared slot
+    let large_file_threshold = app_config.sync_options.large_file_threshold_mb * 1024 * 1024;
+    let large_lane = (app_config.sync_options.large_file_lane > 0)
+        .then(|| Arc::new(Semaphore::new(app_config.sync_options.large_file_lane)));
+    let mut to_hydrate: Vec<PathBuf> = Vec::new();
+    let mut folder_markers: Vec<String> = Vec::new();
+    let redirects = if app_config.sync_options.redirects_file.is_empty() {
+        Vec::new()
+    } else {
+        crate::redirects::load_redirects(Path::new(&app_config.sync_options.redirects_file)).inspect_err(|e| {
+            status(e.clone(), 0.0, true);
+        })?
+    };
+    if !redirects.is_empty() {
+        log_mappings.push(format!(
+            "Redirects: {} từ {}",
+            redirects.len(),
+            app_config.sync_options.redirects_file
+        ));
+    }
+    let trash_prefix = app_config
+        .sync_options
+        .trash_overwritten
+        .then(|| trash_session_prefix(&start_time));
+    let staging_prefix = app_config
+        .sync_options
+        .staged_deploy
+        .then(|| format!("{}{}/", STAGING_PREFIX, start_time.format("%Y%m%d_%H%M%S")));
+    let staged_cleanup = app_config.sync_options.staged_cleanup;
+    let encryptor = if app_config.sync_options.client_side_encryption {
+        let passphrase = crate::encryption::passphrase().ok_or_else(|| {
+            let message = "Mã hóa phía client đang bật nhưng chưa nhập passphrase (Sync Options)".to_string();
+            status(message.clone(), 0.0, true);
+            message
+        })?;
+        let encryptor = tokio::task::spawn_blocking(move || crate::encryption::Encryptor::new(&passphrase))
+            .await
+            .map_err(|e| format!("Lỗi khởi tạo mã hóa: {}", e))?
+            .inspect_err(|e| status(e.clone(), 0.0, true))?;
+        log_mappings.push("Mã hóa phía client: AES-256-GCM".to_string());
+        Some(Arc::new(encryptor))
     } else {
         None
     };
 
-    // Load filter config
-    let filter_config = crate::config::load_config().filter_config;
+    // Preflight: report missing folders, a full log disk or a too-low open file limit up front
+    let problems = crate::preflight::run(
+        &mappings,
+        &log_path,
+        crate::scheduler::total_concurrency() + app_config.sync_options.large_file_lane,
+    );
+    if !problems.is_empty() {
+        for problem in &problems {
+            error!("Preflight: {}", problem);
+        }
+        let message = format!("Không thể bắt đầu sync: {}", problems.join("; "));
+        status(message.clone(), 0.0, true);
+        return Err(message);
+    }
+    // A policy demanding encryption or ACL headers may reject the uploads. The statement may
+    // not apply to this caller, so it is a warning (also shown in the confirmation), not a failure.
+    let dest_prefixes: Vec<String> = mappings
+        .iter()
+        .filter_map(|(local, s3)| crate::utils::expand_path_template(s3, Path::new(local), &start_time).ok())
+        .map(|prefix| crate::diff::normalize_list_prefix(&prefix))
+        .chain(staging_prefix.clone())
+        .collect();
+    match crate::bucket_policy::check_policy(&client, &bucket_name, &dest_prefixes).await {
+        Ok(conflicts) => {
+            for conflict in conflicts {
+                warn!("Preflight: {}", conflict);
+                log_mappings.push(format!("Cảnh báo: {}", conflict));
+            }
+        }
+        Err(e) => warn!("Preflight: {}", e),
+    }
+
+    if app_config.sync_options.archive_upload {
+        let _permit = throttle.acquire().await;
+        let budget = crate::budget::budget_bytes(app_config.sync_options.session_budget_mb);
+        let overage_approved = crate::budget::is_approved(throttle.id());
+        return sync_archives(&client, bucket_name, mappings, status, session_log, filter_config, start_time, (budget, overage_approved))
+            .await;
+    }
+
+    // Logs, config and state written during the sync would otherwise be uploaded every run
+    let app_owned: Vec<PathBuf> = crate::config::app_owned_paths(&log_path, &app_config.metrics_path)
+        .iter()
+        .filter_map(|p| p.canonicalize().ok())
+        .collect();
+    // A log folder that is the synced folder itself can't be left out as a folder; its log
+    // files are skipped by name instead
+    let log_dir = (!log_path.is_empty())
+        .then(|| PathBuf::from(crate::config::resolve_user_path(&log_path)).canonicalize().ok())
+        .flatten();
+
     let mut all_files: Vec<(PathBuf, PathBuf, String)> = Vec::new();
-    let mut filtered_files = 0u64;
+    let mut skipped_special = 0usize;
+    // Folder prefixes checked for their website index document after the sync
+    let mut folder_prefixes: Vec<String> = Vec::new();
+    // (local folder, S3 prefix) of folder mappings, for generated index pages
+    let mut index_roots: Vec<(PathBuf, String)> = Vec::new();
+    let scan = crate::progress::ScanProgress::new(Arc::clone(&status));
+    let scan_max_depth = app_config.sync_options.scan_max_depth;
+    let scan_max_files = app_config.sync_options.scan_max_files;
+    let flatten_paths = &app_config.sync_options.flatten_paths;
     
-    for (local_path, s3_prefix) in mappings {
+    for (local_path, s3_template) in mappings {
         let local_path_buf = PathBuf::from(&local_path);
+        let s3_prefix = crate::utils::expand_path_template(&s3_template, &local_path_buf, &start_time)
+            .and_then(|prefix| crate::utils::validate_s3_path(&prefix).map(|_| prefix))
+            .inspect_err(|e| {
+                status(e.clone(), 0.0, true);
+            })?;
+        // "/" or an empty path uploads into the bucket root
+        let s3_prefix = if crate::utils::is_bucket_root(&s3_prefix) { String::new() } else { s3_prefix };
 
         if local_path_buf.is_file() {
-            if crate::utils::should_include_file(&local_path_buf, local_path_buf.parent().unwrap_or(&local_path_buf), &filter_config) {
-                log_mappings.push(format!("File: {} -> S3: {}", local_path, s3_prefix));
-                all_files.push((local_path_buf.clone(), local_path_buf.clone(), s3_prefix));
+            scan.file();
+            let placeholder = std::fs::symlink_metadata(&local_path_buf).is_ok_and(|m| crate::utils::is_cloud_placeholder(&m));
+            if placeholder && placeholder_policy == PlaceholderPolicy::Skip {
+                skipped_special += 1;
+                warn!("Skipped online-only placeholder: {}", local_path);
+            } else if crate::utils::should_include_file(&local_path_buf, local_path_buf.parent().unwrap_or(&local_path_buf), &filter_config) {
+                // A single file mapped to the root keeps its own name
+                let target = if s3_prefix.is_empty() {
+                    local_path_buf.file_name().unwrap_or_default().to_string_lossy().to_string()
+                } else {
+                    s3_prefix
+                };
+                log_mappings.push(format!("File: {} -> S3: {}", local_path, target));
+                if placeholder {
+                    to_hydrate.push(local_path_buf.clone());
+                }
+                let key = match partition_date(&local_path_buf) {
+                    Some(date) => {
+                        let (dir, name) = target.rsplit_once('/').unwrap_or(("", &target));
+                        crate::utils::partitioned_key(dir, name, date)
+                    }
+                    None => target,
+                };
+                all_files.push((local_path_buf.clone(), local_path_buf.clone(), key));
             } else {
-                filtered_files += 1;
+                scan.filtered();
                 info!("Filtered out file: {}", local_path);
             }
         } else {
-            log_mappings.push(format!("Folder: {} -> S3 Folder: {}", local_path, s3_prefix));
+            let flatten = flatten_paths.iter().any(|p| p == &local_path);
+            log_mappings.push(format!(
+                "Folder: {} -> S3 Folder: {}{}",
+                local_path,
+                if s3_prefix.is_empty() { "(gốc bucket)" } else { &s3_prefix },
+                if flatten { " (phẳng)" } else { "" }
+            ));
+            folder_prefixes.push(s3_prefix.clone());
+            index_roots.push((local_path_buf.clone(), s3_prefix.clone()));
+            let canonical_root = local_path_buf.canonicalize().ok();
+            let own_paths = canonical_root
+                .as_ref()
+                .map(|root| crate::utils::owned_paths_under(root, &local_path_buf, &app_owned))
+                .unwrap_or_default();
+            let logs_at_root = canonical_root.is_some() && canonical_root == log_dir;
+            if !own_paths.is_empty() || logs_at_root {
+                let mut shown: Vec<String> = own_paths.iter().map(|p| p.display().to_string()).collect();
+                if logs_at_root {
+                    shown.push(local_path_buf.join("sync_*.log").display().to_string());
+                }
+                let notice = format!("Tự động bỏ qua file của ứng dụng (log/cấu hình): {}", shown.join(", "));
+                warn!("{}", notice);
+                status(notice.clone(), 0.0, false);
+                log_mappings.push(notice);
+            }
+            // Set when the walk breaks the depth or file-count limit; the rest of the tree is pruned
+            let mut limit_error: Option<String> = None;
+            let mut files_seen = 0u64;
             let files = WalkDir::new(&local_path_buf)
                 .into_iter()
+                .filter_entry(|e| {
+                    if limit_error.is_some() {
+                        return false;
+                    }
+                    let Ok(meta) = e.metadata() else { return true };
+                    if meta.is_dir() {
+                        scan.folder();
+                    }
+                    if e.depth() == 0 {
+                        return true;
+                    }
+                    if !meta.is_dir() {
+                        files_seen += 1;
+                    }
+                    limit_error = crate::utils::scan_limit_error(&local_path_buf, e.depth(), meta.is_dir(), files_seen, scan_max_depth, scan_max_files);
+                    if limit_error.is_some() {
+                        return false;
+                    }
+                    let is_log = || e.depth() == 1 && crate::sync_log::is_log_file_name(&e.file_name().to_string_lossy());
+                    if own_paths.iter().any(|p| p == e.path()) || (logs_at_root && is_log()) {
+                        return false;
+                    }
+                    if crate::utils::is_cloud_placeholder(&meta) {
+                        if placeholder_policy == PlaceholderPolicy::Skip {
+                            skipped_special += 1;
+                            info!("Skipped online-only placeholder: {}", e.path().display());
+                            return false;
+                        }
+                        if meta.is_file() {
+                            to_hydrate.push(e.path().to_path_buf());
+                        }
+                        return true;
+                    }
+                    // Don't descend into junctions
+                    if crate::utils::is_reparse_point(&meta) {
+                        skipped_special += 1;
+                        info!("Skipped reparse point: {}", e.path().display());
+                        return false;
+                    }
+                    let relative = e.path().strip_prefix(&local_path_buf).unwrap_or(e.path());
+                    if meta.is_dir() && crate::utils::is_skipped_hidden(relative, Some(&meta), &filter_config) {
+                        info!("Skipped hidden folder: {}", e.path().display());
+                        return false;
+                    }
+                    true
+                })
                 .filter_map(|e| e.ok())
                 .filter(|e| e.file_type().is_file())
                 .filter_map(|e| {
+                    scan.file();
                     let file_path = e.path().to_path_buf();
                     if crate::utils::should_include_file(&file_path, &local_path_buf, &filter_config) {
                         Some(e)
                     } else {
-                        filtered_files += 1;
+                        scan.filtered();
                         info!("Filtered out file: {}", file_path.display());
                         None
                     }
@@ -277,159 +1174,1063 @@ pub async fn sync_to_s3(
                 .map(|e| {
                     let file_path = e.path().to_path_buf();
                     let relative = file_path.strip_prefix(&local_path_buf).unwrap_or(&file_path);
-                    let clean_rel = relative.to_string_lossy().replace('\\', "/");
-                    let final_key = if clean_rel.is_empty() {
-                        s3_prefix.clone()
+                    // A flattened folder drops the subfolders and keeps only the file name
+                    let clean_rel = if flatten {
+                        e.file_name().to_string_lossy().to_string()
+                    } else {
+                        relative.to_string_lossy().replace('\\', "/")
+                    };
+                    let final_key = if let Some(date) = partition_date(&file_path) {
+                        crate::utils::partitioned_key(&s3_prefix, &clean_rel, date)
                     } else {
-                        format!("{}/{}", s3_prefix.trim_end_matches('/'), clean_rel.trim_start_matches('/'))
+                        crate::utils::join_key(&s3_prefix, &clean_rel)
                     };
                     (file_path, local_path_buf.clone(), final_key)
                 });
+            let first_new = all_files.len();
             all_files.extend(files);
+            if let Some(message) = limit_error {
+                error!("{}", message);
+                status(message.clone(), 0.0, true);
+                return Err(message);
+            }
+            if flatten {
+                let rels: Vec<String> = all_files[first_new..]
+                    .iter()
+                    .map(|(path, root, _)| path.strip_prefix(root).unwrap_or(path).to_string_lossy().replace('\\', "/"))
+                    .collect();
+                let collisions = crate::utils::flatten_collisions(&rels);
+                if !collisions.is_empty() {
+                    let shown: Vec<String> =
+                        collisions.iter().take(5).map(|(name, paths)| format!("{} ({})", name, paths.join(", "))).collect();
+                    let message = format!(
+                        "Không thể upload phẳng {}: {} tên file bị trùng: {}",
+                        local_path,
+                        collisions.len(),
+                        shown.join("; ")
+                    );
+                    error!("{}", message);
+                    status(message.clone(), 0.0, true);
+                    return Err(message);
+                }
+            }
+
+            if create_folder_markers && !flatten {
+                let markers = WalkDir::new(&local_path_buf)
+                    .min_depth(1)
+                    .into_iter()
+                    .filter_entry(|e| {
+                        let relative = e.path().strip_prefix(&local_path_buf).unwrap_or(e.path());
+                        !own_paths.iter().any(|p| p == e.path())
+                            && !e.metadata().is_ok_and(|m| {
+                                (crate::utils::is_reparse_point(&m) && !crate::utils::is_cloud_placeholder(&m))
+                                    || crate::utils::is_skipped_hidden(relative, Some(&m), &filter_config)
+                            })
+                    })
+                    .filter_map(|e| e.ok())
+                    .filter(|e| e.file_type().is_dir())
+                    .filter(|e| std::fs::read_dir(e.path()).is_ok_and(|mut d| d.next().is_none()))
+                    .filter_map(|e| {
+                        let relative = e.path().strip_prefix(&local_path_buf).ok()?;
+                        let clean_rel = relative.to_string_lossy().replace('\\', "/");
+                        if filter_config.enable_filtering
+                            && crate::utils::matches_any_pattern(&clean_rel, &filter_config.exclude_patterns)
+                        {
+                            return None;
+                        }
+                        let key = format!("{}/{}/", s3_prefix.trim_end_matches('/'), clean_rel);
+                        Some(key.trim_start_matches('/').to_string())
+                    });
+                folder_markers.extend(markers);
+            }
         }
     }
 
+    scan.report();
+    let filtered_files = scan.filtered_count();
+    drop(scan);
+
+    if skipped_special > 0 {
+        let message = format!("Bỏ qua {} junction/placeholder", skipped_special);
+        status(message.clone(), 0.05, false);
+        log_mappings.push(message);
+    }
+
     // Update status if files were filtered
     if filtered_files > 0 {
-        update_status(
-            &ui_handle,
+        status(
             format!("Đã lọc {} files, chuẩn bị upload {} files...", filtered_files, all_files.len()),
             0.05,
             false,
         );
     }
 
-    if should_log && !log_mappings.is_empty() {
-        if let Some(ref log_file) = log_file_path {
-            match OpenOptions::new().create(true).append(true).open(log_file) {
-                Ok(mut file) => {
-                    if writeln!(file, "--------------------------------------------------").is_err()
-                        || writeln!(file, "Sync Session Started - Bucket: {}", bucket_name).is_err()
-                    {
-                        warn!("Failed to write sync session header to log file: {}", log_file);
-                    }
-                    for mapping in &log_mappings {
-                        if writeln!(file, "{}", mapping).is_err() {
-                            warn!("Failed to write mapping to log file: {}", log_file);
-                            break;
-                        }
-                    }
+    // Sparse files (VM disks, databases) would stream their full logical size. Online-only
+    // placeholders are sparse on disk too, but are handled by the placeholder policy.
+    let mut sparse_warning = None;
+    let sparse: HashSet<PathBuf> = all_files
+        .iter()
+        .filter(|(path, _, _)| !to_hydrate.contains(path))
+        .filter(|(path, _, _)| std::fs::metadata(path).is_ok_and(|m| crate::utils::is_sparse_file(&m)))
+        .map(|(path, _, _)| path.clone())
+        .collect();
+    if !sparse.is_empty() {
+        let logical: u64 = sparse.iter().filter_map(|p| std::fs::metadata(p).ok()).map(|m| m.len()).sum();
+        let skip = app_config.sync_options.sparse_policy == crate::config::SparsePolicy::Skip;
+        for path in &sparse {
+            info!("Sparse file{}: {}", if skip { " skipped" } else { "" }, path.display());
+        }
+        if skip {
+            all_files.retain(|(path, _, _)| !sparse.contains(path));
+        }
+        let message = format!(
+            "{} {} file sparse (kích thước logic {})",
+            if skip { "Bỏ qua" } else { "Sẽ upload đủ kích thước của" },
+            sparse.len(),
+            crate::utils::format_size(logical)
+        );
+        warn!("{}", message);
+        status(message.clone(), 0.05, false);
+        log_mappings.push(message.clone());
+        sparse_warning = Some(message);
+    }
+
+    // Over the session budget: upload only what fits, by mapping order, unless the user allowed it
+    let budget = crate::budget::budget_bytes(app_config.sync_options.session_budget_mb);
+    let overage_approved = crate::budget::is_approved(throttle.id());
+    let mut budget_warning = None;
+    if budget > 0 {
+        // Append-only runs upload just the tail each file grew by since it was last shipped
+        let append_only = app_config.sync_options.append_only;
+        let size_of = |(path, _, key): &(PathBuf, PathBuf, String)| {
+            let len = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+            if append_only {
+                crate::append_log::pending_range(crate::append_log::shipped(&bucket_name, key), len).map_or(0, |r| r.end - r.start)
+            } else {
+                len
+            }
+        };
+        let planned: u64 = all_files.iter().map(size_of).sum();
+        if let Some(overage) = crate::budget::overage_text(planned, budget) {
+            if overage_approved {
+                log_mappings.push(format!("{} (đã xác nhận)", overage));
+            } else {
+                let (kept, dropped, dropped_bytes) = crate::budget::trim_to_budget(all_files, budget, size_of);
+                all_files = kept;
+                let message = format!(
+                    "{}: bỏ qua {} file ({}) theo thứ tự ưu tiên",
+                    overage,
+                    dropped.len(),
+                    crate::utils::format_size(dropped_bytes)
+                );
+                for (path, _, key) in &dropped {
+                    info!("Over budget, not uploaded: {} -> {}", path.display(), key);
                 }
-                Err(e) => {
-                    warn!("Failed to open log file '{}': {}", log_file, e);
+                warn!("{}", message);
+                status(message.clone(), 0.05, false);
+                log_mappings.push(message.clone());
+                budget_warning = Some(message);
+            }
+        }
+    }
+
+    // Download online-only files before anything reads them, so progress is visible
+    let included: HashSet<&PathBuf> = all_files.iter().map(|(p, _, _)| p).collect();
+    to_hydrate.retain(|p| included.contains(p));
+    if !to_hydrate.is_empty() {
+        let total = to_hydrate.len();
+        log_mappings.push(format!("Hydrate {} file online", total));
+        for (i, path) in to_hydrate.into_iter().enumerate() {
+            let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+            status(format!("Đang tải file online: {} ({}/{})", name, i + 1, total), i as f32 / total as f32, false);
+            let hydrated = tokio::task::spawn_blocking(move || crate::utils::hydrate_file(&path).map_err(|e| (path, e)))
+                .await
+                .map_err(|e| format!("Lỗi tải file online: {}", e))?;
+            if let Err((path, e)) = hydrated {
+                let message = format!("Không tải được file online {}: {}", path.display(), e);
+                status(message.clone(), 0.0, true);
+                return Err(message);
+            }
+        }
+    }
+
+    if app_config.sync_options.append_only {
+        let mut summary = SyncSummary {
+            bucket: bucket_name.clone(),
+            total_files: all_files.len(),
+            filtered: filtered_files,
+            skipped_special,
+            warnings: budget_warning.into_iter().collect(),
+            ..Default::default()
+        };
+        ship_appended(&client, &bucket_name, all_files, &status, &throttle, &start_time, &mut summary).await;
+        summary.duration = started.elapsed();
+        if summary.is_success() {
+            status(format!("Đã upload phần mới của {} file", summary.uploaded), 1.0, false);
+        }
+        if let Some(ref log) = session_log {
+            log_mappings.push(format!("Append-only: {} file có dữ liệu mới", summary.uploaded));
+            write_session_header(log, &bucket_name, &log_mappings);
+            write_session_footer(log, &summary, summary.is_success());
+        }
+        return Ok(summary);
+    }
+
+    // Cache-busting: rename matching keys to include a short content hash
+    let mut fingerprint_manifest = std::collections::BTreeMap::new();
+    if !fingerprint_patterns.is_empty() {
+        status("Đang tính hash cho fingerprint...".to_string(), 0.05, false);
+        let hash_cache = Arc::clone(&hash_cache);
+        let (files, manifest) = tokio::task::spawn_blocking(move || {
+            let mut files = all_files;
+            crate::fingerprint::fingerprint_files(&mut files, &fingerprint_patterns, &hash_cache).map(|manifest| (files, manifest))
+        })
+        .await
+        .map_err(|e| format!("Lỗi fingerprint: {}", e))?
+        .inspect_err(|e| status(e.clone(), 0.0, true))?;
+        all_files = files;
+        if !manifest.is_empty() {
+            log_mappings.push(format!("Fingerprint: {} file, manifest {}", manifest.len(), fingerprint_manifest_key));
+        }
+        fingerprint_manifest = manifest;
+    }
+
+    // Preflight: block the sync if any computed key breaks the key policy
+    if key_policy.enabled {
+        let violations = crate::key_policy::check_keys(&key_policy, all_files.iter().map(|(_, _, k)| k.as_str()))
+            .map_err(|e| {
+                status(format!("Key policy lỗi: {}", e), 0.0, true);
+                e
+            })?;
+        if !violations.is_empty() {
+            let report = crate::key_policy::format_violations(&violations, 50);
+            error!("Key policy preflight failed:\n{}", report);
+            if let Some(ref log) = session_log {
+                log_mappings.push(format!("Preflight FAILED - {}", report.trim_end()));
+                write_session_header(log, &bucket_name, &log_mappings);
+            }
+            let first = &violations[0];
+            let message = format!(
+                "Sync bị chặn: {} key vi phạm quy tắc (vd: {} - {})",
+                violations.len(),
+                first.key,
+                first.reason
+            );
+            status(message.clone(), 0.0, true);
+            return Err(message);
+        }
+    }
+
+    // Encrypted objects are bound to their key, so a server-side copy to another key couldn't be decrypted
+    let dedup_mode = if dedup_mode == DedupMode::Copy && encryptor.is_some() {
+        log_mappings.push("Dedup copy không dùng được khi mã hóa phía client, chỉ báo cáo file trùng".to_string());
+        DedupMode::Report
+    } else {
+        dedup_mode
+    };
+    // Dedup scan: (source key, duplicate key) pairs to copy server-side after upload
+    let mut dedup_copies: Vec<(String, String)> = Vec::new();
+    let mut deduplicated = 0usize;
+    if dedup_mode != DedupMode::Off && all_files.len() > 1 {
+        status("Đang kiểm tra file trùng lặp...".to_string(), 0.05, false);
+        let paths: Vec<PathBuf> = all_files.iter().map(|(p, _, _)| p.clone()).collect();
+        let hash_cache = Arc::clone(&hash_cache);
+        let groups = tokio::task::spawn_blocking(move || crate::dedup::find_duplicate_groups(&paths, &hash_cache))
+            .await
+            .map_err(|e| format!("Lỗi kiểm tra trùng lặp: {}", e))?;
+
+        let mut duplicate_indices: HashSet<usize> = HashSet::new();
+        let mut duplicate_bytes = 0u64;
+        for group in &groups {
+            let keep_key = &all_files[group[0]].2;
+            for &i in &group[1..] {
+                info!("Duplicate: {} == {} ({})", all_files[i].2, keep_key, all_files[i].0.display());
+                duplicate_bytes += std::fs::metadata(&all_files[i].0).map(|m| m.len()).unwrap_or(0);
+                duplicate_indices.insert(i);
+                if dedup_mode == DedupMode::Copy {
+                    dedup_copies.push((keep_key.clone(), all_files[i].2.clone()));
                 }
             }
         }
+
+        if !duplicate_indices.is_empty() {
+            let report = format!(
+                "Phát hiện {} file trùng lặp trong {} nhóm ({})",
+                duplicate_indices.len(),
+                groups.len(),
+                crate::utils::format_size(duplicate_bytes)
+            );
+            info!("{}", report);
+            status(report.clone(), 0.05, false);
+            log_mappings.push(format!("Dedup ({}): {}", dedup_mode.as_str(), report));
+
+            if matches!(dedup_mode, DedupMode::Skip | DedupMode::Copy) {
+                deduplicated = duplicate_indices.len();
+                all_files = all_files
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(i, _)| !duplicate_indices.contains(i))
+                    .map(|(_, f)| f)
+                    .collect();
+            }
+        }
+    }
+
+    // Several files mapped onto one key: their uploads take turns, the last one in the list wins
+    let duplicate_keys = crate::utils::find_duplicate_keys(&all_files);
+    let duplicate_key_warning = (!duplicate_keys.is_empty()).then(|| {
+        let listed: Vec<String> = duplicate_keys
+            .iter()
+            .map(|(key, paths)| {
+                let paths: Vec<String> = paths.iter().map(|p| p.display().to_string()).collect();
+                format!("{} ({})", key, paths.join(", "))
+            })
+            .collect();
+        format!("{} key được nhiều file ghi vào, file cuối cùng được giữ: {}", duplicate_keys.len(), listed.join("; "))
+    });
+    if let Some(ref warning) = duplicate_key_warning {
+        warn!("{}", warning);
+        log_mappings.push(warning.clone());
+    }
+    let mut key_turns = KeyTurns::new(duplicate_keys.into_iter().map(|(key, _)| key));
+
+    if let Some(ref trash_prefix) = trash_prefix {
+        log_mappings.push(format!("Trash: ghi đè sẽ được backup vào {}", trash_prefix));
+    }
+    if let Some(ref staging_prefix) = staging_prefix {
+        log_mappings.push(format!("Staged deploy qua {}", staging_prefix));
+    }
+    if should_log
+        && !log_mappings.is_empty()
+        && let Some(ref log) = session_log
+    {
+        write_session_header(log, &bucket_name, &log_mappings);
     }
 
     let total_files = all_files.len();
-    if total_files == 0 {
-        update_status(&ui_handle, "Không có file nào để upload!".to_string(), 1.0, false);
-        return Ok(());
+    let mut summary = SyncSummary {
+        bucket: bucket_name.clone(),
+        total_files,
+        filtered: filtered_files,
+        deduplicated,
+        skipped_special,
+        warnings: sparse_warning.into_iter().chain(budget_warning).chain(duplicate_key_warning).collect(),
+        ..Default::default()
+    };
+    if total_files == 0 && folder_markers.is_empty() {
+        status("Không có file nào để upload!".to_string(), 1.0, false);
+        summary.duration = started.elapsed();
+        return Ok(summary);
     }
 
-    let concurrency = std::env::var("S3_SYNC_CONCURRENCY")
-        .unwrap_or_else(|_| "50".to_string())
-        .parse()
-        .unwrap_or(50);
-    let semaphore = Arc::new(Semaphore::new(concurrency));
-    let mut set = JoinSet::new();
     let completed_count = Arc::new(tokio::sync::Mutex::new(0));
+    let checksum_files: Vec<(PathBuf, String)> = if checksum_manifest_key.is_empty() {
+        Vec::new()
+    } else {
+        all_files.iter().map(|(path, _, key)| (path.clone(), key.clone())).collect()
+    };
+    let checksum_copies = dedup_copies.clone();
+    let smoke_test_urls = app_config.sync_options.smoke_test_urls;
+    let verify_sample = app_config.sync_options.verify_sample;
+    let smoke_files: HashMap<String, PathBuf> = if smoke_test_urls.is_empty() {
+        HashMap::new()
+    } else {
+        all_files.iter().map(|(path, _, key)| (key.clone(), path.clone())).collect()
+    };
 
-    for (path, _base_path, key) in all_files {
-        let client = Arc::clone(&client);
-        let semaphore = Arc::clone(&semaphore);
-        let ui_handle = ui_handle.clone();
-        let bucket_name = bucket_name.clone();
-        let completed_count = Arc::clone(&completed_count);
+    // Entry points (e.g. index.html) go in a second phase, once every asset they reference exists.
+    // Assets in nested directories are started first.
+    let (entry_files, mut asset_files): (Vec<_>, Vec<_>) = all_files
+        .into_iter()
+        .partition(|(_, _, key)| crate::utils::matches_any_pattern(key, &entry_patterns));
+    if !entry_patterns.is_empty() {
+        asset_files.sort_by_key(|(_, _, key)| std::cmp::Reverse(key.matches('/').count()));
+    }
+    if !entry_files.is_empty() && !asset_files.is_empty() {
+        info!("Uploading {} entry point files after {} assets", entry_files.len(), asset_files.len());
+    }
 
-        set.spawn(async move {
-            let _permit = semaphore.acquire().await.unwrap();
+    // Live per-file table, rows in upload order (assets, then entry points)
+    let transfer_base = crate::transfers::begin(asset_files.iter().chain(&entry_files).map(|(path, _, key)| {
+        (key.clone(), std::fs::metadata(path).map(|m| m.len()).unwrap_or(0))
+    }));
+    let mut transfer_index = transfer_base;
+
+    let connectivity = Arc::new(crate::connectivity::Connectivity::default());
+    let mut has_error = false;
+    // (key, size) uploaded per phase, needed to verify and promote a staged deploy
+    let mut phase_uploads: Vec<Vec<(String, u64)>> = Vec::new();
+    for phase in [asset_files, entry_files] {
+        phase_uploads.push(Vec::new());
+        let phase_base = transfer_index;
+        transfer_index += phase.len();
+        if has_error || phase.is_empty() {
+            continue;
+        }
+        let mut set = JoinSet::new();
+        for (i, (path, _base_path, key)) in phase.into_iter().enumerate() {
+            let transfer = phase_base + i;
+            let client = Arc::clone(&client);
+            let throttle = Arc::clone(&throttle);
+            let status = Arc::clone(&status);
+            let bucket_name = bucket_name.clone();
+            let completed_count = Arc::clone(&completed_count);
+            let trash_prefix = trash_prefix.clone();
+            let header_rules = Arc::clone(&header_rules);
+            let disposition_rules = Arc::clone(&disposition_rules);
+            let connectivity = Arc::clone(&connectivity);
+            let encryptor = encryptor.clone();
+            let upload_key = match &staging_prefix {
+                Some(staging) => format!("{}{}", staging, key),
+                None => key.clone(),
+            };
+            let lane = large_lane
+                .as_ref()
+                .filter(|_| std::fs::metadata(&path).is_ok_and(|m| m.len() >= large_file_threshold))
+                .map(Arc::clone);
+            let key_turn = key_turns.next(&key);
+
+            set.spawn(async move {
+                // Waited for before the upload slot so a queued write doesn't hold one
+                if let Some(turn) = &key_turn {
+                    turn.wait().await;
+                }
+                let (_permit, _lane_permit) = match lane {
+                    Some(lane) => (None, Some(lane.acquire_owned().await.map_err(|_| SyncError::Cancelled)?)),
+                    None => (Some(throttle.acquire().await), None),
+                };
+                let in_flight = crate::transfers::start(transfer);
+                let upload_started = std::time::Instant::now();
 
-            info!("Map local file: {:?} -> S3 Key: {}", path, key);
-            let display_name = path
-                .file_name()
-                .unwrap_or_default()
-                .to_string_lossy()
-                .to_string();
-            let mime_type = get_mime_type(&path);
+                info!("Map local file: {:?} -> S3 Key: {}", path, key);
+                if let Some(trash_prefix) = &trash_prefix {
+                    backup_before_upload(&*client, &bucket_name, &key, trash_prefix, key_turn.as_ref())
+                        .await
+                        .map_err(SyncError::Other)?;
+                }
+                let (content_type, content_language) =
+                    crate::utils::content_headers(&key, get_mime_type(&path, sniff_mime), &header_rules);
+                let content_disposition = crate::utils::content_disposition(&key, &disposition_rules);
+                // Lets a later download put the original timestamps and permissions back
+                let file_metadata = std::fs::metadata(&path)
+                    .map(|m| crate::file_meta::attribute_metadata(&m, store_ctime))
+                    .unwrap_or_default();
 
-            match ByteStream::from_path(&path).await {
-                Ok(stream) => {
+                let mut throttled_retries = 0;
+                loop {
+                    connectivity
+                        .wait_online()
+                        .await
+                        .map_err(|message| SyncError::NetworkTimeout { key: key.clone(), message })?;
+                    // Encrypted files are read whole into memory and stored as opaque bytes, a
+                    // few at a time whatever the upload concurrency
+                    let (stream, metadata, stored_type, _memory_slot) = match &encryptor {
+                        Some(encryptor) => {
+                            let size = tokio::fs::metadata(&path).await.map_err(|e| SyncError::io_open(&path, e))?.len();
+                            let slot = crate::encryption::memory_slot(size, &key).await?;
+                            let data = tokio::fs::read(&path).await.map_err(|e| SyncError::io_open(&path, e))?;
+                            let (data, metadata) = crate::encryption::encrypt_blocking(encryptor, data, &content_type, &key).await?;
+                            (ByteStream::from(data), Some(metadata), "application/octet-stream".to_string(), Some(slot))
+                        }
+                        None => {
+                            let stream = ByteStream::from_path(&path).await.map_err(|e| SyncError::io_open(&path, e))?;
+                            (stream, None, content_type.clone(), None)
+                        }
+                    };
+                    let mut metadata = metadata.unwrap_or_default();
+                    metadata.extend(file_metadata.clone());
+                    let size = stream.size_hint().0;
                     match client
                         .put_object()
                         .bucket(&bucket_name)
-                        .key(&key)
-                        .content_type(mime_type)
+                        .key(&upload_key)
+                        .content_type(stored_type.clone())
+                        .set_content_language(content_language.clone())
+                        .set_content_disposition(content_disposition.clone())
+                        .set_metadata((!metadata.is_empty()).then_some(metadata))
                         .cache_control("no-cache")
                         .body(stream)
                         .send()
                         .await
                     {
                         Ok(_) => {
+                            connectivity.record_success();
                             let mut count = completed_count.lock().await;
                             *count += 1;
                             let progress = *count as f32 / total_files as f32;
-                            update_status(
-                                &ui_handle,
-                                format!(
-                                    "Đang upload: {} ({}/{})",
-                                    display_name, *count, total_files
-                                ),
+                            status(
+                                format!("Đang upload {}/{} file", *count, total_files),
                                 progress,
                                 false,
                             );
                             debug!("Uploaded: {}", key);
-                            Ok(())
+                            in_flight.done();
+                            return Ok(UploadTiming {
+                                key,
+                                bytes: size,
+                                duration: upload_started.elapsed(),
+                                content_type: stored_type,
+                                cache_control: Some("no-cache".to_string()),
+                            });
+                        }
+                        // Network gone: wait for it to come back and retry instead of failing the sync
+                        Err(e) if crate::connectivity::is_connection_error(&e) => {
+                            warn!("Connection error uploading {}: {}", key, e);
+                            connectivity.on_connection_error(&client, &bucket_name, &status);
+                        }
+                        Err(e) => {
+                            let error = SyncError::from_sdk(&key, &e);
+                            // The SDK already backed off; give throttled uploads a few more slower tries
+                            if matches!(error, SyncError::UploadThrottled { .. }) && throttled_retries < MAX_THROTTLED_RETRIES {
+                                throttled_retries += 1;
+                                warn!("{} (retry {}/{})", error, throttled_retries, MAX_THROTTLED_RETRIES);
+                                tokio::time::sleep(THROTTLED_RETRY_DELAY * throttled_retries).await;
+                                continue;
+                            }
+                            return Err(error);
                         }
-                        Err(e) => Err(format!("Lỗi upload {}: {}", key, e)),
                     }
                 }
-                Err(e) => Err(format!("Lỗi mở file {}: {}", path.display(), e)),
+            });
+        }
+
+        while let Some(res) = set.join_next().await {
+            match res {
+                Ok(Ok(timing)) => {
+                    crate::throughput::record(timing.bytes);
+                    summary.bytes_uploaded += timing.bytes;
+                    if let Some(uploads) = phase_uploads.last_mut() {
+                        uploads.push((timing.key.clone(), timing.bytes));
+                    }
+                    summary.uploaded_keys.push(timing.key.clone());
+                    summary.upload_timings.push(timing);
+                }
+                Ok(Err(e)) => {
+                    error!("{}", e);
+                    status(format!("Lỗi: {}", e), 0.0, true);
+                    summary.record_error(e);
+                    has_error = true;
+                    set.abort_all();
+                    break;
+                }
+                Err(_) => {}
             }
-        });
+        }
+    }
+    if has_error {
+        crate::transfers::abandon(transfer_base..transfer_index);
     }
 
-    let mut has_error = false;
-    while let Some(res) = set.join_next().await {
-        if let Ok(Err(e)) = res {
-            error!("{}", e);
-            update_status(&ui_handle, format!("Lỗi: {}", e), 0.0, true);
-            has_error = true;
-            set.abort_all();
-            break;
+    if !has_error && let Some(ref staging) = staging_prefix {
+        let all_uploaded: Vec<(String, u64)> = phase_uploads.iter().flatten().cloned().collect();
+        status("Đang kiểm tra bản staging...".to_string(), 0.9, false);
+        let mut result = verify_staged(&*client, &bucket_name, staging, &all_uploaded).await;
+
+        // Promote assets first, then entry points
+        for uploads in &phase_uploads {
+            if result.is_err() || uploads.is_empty() {
+                continue;
+            }
+            let pairs = uploads
+                .iter()
+                .map(|(key, _)| (format!("{}{}", staging, key), key.clone()))
+                .collect();
+            let promote_status = Arc::clone(&status);
+            result = copy_objects(client.clone(), &bucket_name, &bucket_name, pairs, move |done, total| {
+                promote_status(format!("Đang đưa lên live ({}/{})", done, total), done as f32 / total as f32, false);
+            })
+            .await
+            .map(|_| ());
+        }
+
+        match result {
+            Ok(()) => {
+                info!("Staged deploy promoted {} objects from {}", all_uploaded.len(), staging);
+                if staged_cleanup
+                    && let Err(e) = delete_prefix(&*client, &bucket_name, staging, |_| {}).await
+                {
+                    warn!("Failed to clean up staging prefix {}: {}", staging, e);
+                }
+            }
+            Err(e) => {
+                // The staging copy is kept so the failed deploy can be inspected
+                error!("Staged deploy failed: {}", e);
+                status(format!("Lỗi: {}", e), 0.0, true);
+                summary.record_error(e.into());
+                has_error = true;
+            }
         }
     }
 
-    if !has_error {
-        update_status(&ui_handle, "Đồng bộ hoàn tất!".to_string(), 1.0, false);
-    }
-
-    if should_log {
-        if let Some(ref log_file) = log_file_path {
-            let end_time = Local::now();
-            let status = if !has_error { "success" } else { "failed" };
-            match OpenOptions::new().create(true).append(true).open(log_file) {
-                Ok(mut file) => {
-                    if writeln!(
-                        file,
-                        "Time Upload: {}, Bucket: {}, Status: {}",
-                        end_time.format("%Y-%m-%d %H:%M:%S"),
-                        bucket_name,
-                        status
-                    )
-                    .is_err()
-                        || writeln!(file, "--------------------------------------------------").is_err()
-                    {
-                        warn!("Failed to write sync completion to log file: {}", log_file);
+    if !has_error && !fingerprint_manifest.is_empty() && !fingerprint_manifest_key.is_empty() {
+        let body = serde_json::to_vec_pretty(&fingerprint_manifest).unwrap_or_default();
+        match client
+            .put_object()
+            .bucket(&bucket_name)
+            .key(&fingerprint_manifest_key)
+            .content_type("application/json")
+            .cache_control("no-cache")
+            .body(ByteStream::from(body))
+            .send()
+            .await
+        {
+            Ok(_) => {
+                info!("Uploaded fingerprint manifest {} ({} entries)", fingerprint_manifest_key, fingerprint_manifest.len());
+                summary.uploaded_keys.push(fingerprint_manifest_key.clone());
+            }
+            Err(e) => {
+                let e = format!("Lỗi upload manifest {}: {}", fingerprint_manifest_key, e);
+                error!("{}", e);
+                status(format!("Lỗi: {}", e), 0.0, true);
+                summary.record_error(e.into());
+                has_error = true;
+            }
+        }
+    }
+
+    if !has_error && !checksum_manifest_key.is_empty() {
+        status("Đang tạo manifest checksum...".to_string(), crate::progress::INDETERMINATE, false);
+        let hash_cache = Arc::clone(&hash_cache);
+        let manifest = tokio::task::spawn_blocking(move || {
+            crate::checksums::build_manifest(&checksum_files, &checksum_copies, &hash_cache)
+        })
+        .await
+        .map_err(|e| format!("Lỗi tạo manifest checksum: {}", e))
+        .and_then(|m| m);
+        let uploaded = match manifest {
+            Ok(manifest) => client
+                .put_object()
+                .bucket(&bucket_name)
+                .key(&checksum_manifest_key)
+                .content_type("text/plain; charset=utf-8")
+                .cache_control("no-cache")
+                .body(ByteStream::from(crate::checksums::format_manifest(&manifest).into_bytes()))
+                .send()
+                .await
+                .map(|_| manifest.len())
+                .map_err(|e| format!("Lỗi upload manifest {}: {}", checksum_manifest_key, e)),
+            Err(e) => Err(e),
+        };
+        match uploaded {
+            Ok(entries) => {
+                info!("Uploaded checksum manifest {} ({} entries)", checksum_manifest_key, entries);
+                summary.uploaded_keys.push(checksum_manifest_key.clone());
+            }
+            Err(e) => {
+                error!("{}", e);
+                status(format!("Lỗi: {}", e), 0.0, true);
+                summary.record_error(e.into());
+                has_error = true;
+            }
+        }
+    }
+
+    if !has_error && !folder_markers.is_empty() {
+        match put_folder_markers(&*client, &bucket_name, &folder_markers).await {
+            Ok(created) => {
+                info!("Created {} empty folder markers", created);
+                summary.uploaded_keys.extend(folder_markers);
+            }
+            Err(e) => {
+                error!("{}", e);
+                status(format!("Lỗi: {}", e), 0.0, true);
+                summary.record_error(e.into());
+                has_error = true;
+            }
+        }
+    }
+
+    let mut dedup_copied = 0usize;
+    if !has_error && !dedup_copies.is_empty() {
+        let copy_status = Arc::clone(&status);
+        let copied_keys: Vec<String> = dedup_copies.iter().map(|(_, dst)| dst.clone()).collect();
+        match copy_objects(client.clone(), &bucket_name, &bucket_name, dedup_copies, move |done, total| {
+            copy_status(format!("Đang copy file trùng lặp ({}/{})", done, total), done as f32 / total as f32, false);
+        })
+        .await
+        {
+            Ok(copied) => {
+                info!("Copied {} duplicate keys server-side", copied);
+                dedup_copied = copied;
+                summary.uploaded_keys.extend(copied_keys);
+            }
+            Err(e) => {
+                error!("{}", e);
+                status(format!("Lỗi: {}", e), 0.0, true);
+                summary.record_error(e.into());
+                has_error = true;
+            }
+        }
+    }
+
+    if !has_error && !redirects.is_empty() {
+        let redirect_status = Arc::clone(&status);
+        let redirect_keys: Vec<String> = redirects.iter().map(|(key, _)| key.clone()).collect();
+        match crate::redirects::put_redirects(Arc::clone(&client), &bucket_name, redirects, move |done, total| {
+            redirect_status(format!("Đang tạo redirect ({}/{})", done, total), done as f32 / total as f32, false);
+        })
+        .await
+        {
+            Ok(created) => {
+                debug!("Created {} redirect objects", created);
+                summary.uploaded_keys.extend(redirect_keys);
+            }
+            Err(e) => {
+                error!("{}", e);
+                status(format!("Lỗi: {}", e), 0.0, true);
+                summary.record_error(e.into());
+                has_error = true;
+            }
+        }
+    }
+
+    if !has_error && app_config.sync_options.dir_index && !index_roots.is_empty() {
+        status("Đang tạo trang index cho thư mục...".to_string(), crate::progress::INDETERMINATE, false);
+        let template_path = &app_config.sync_options.dir_index_template;
+        let template = if template_path.is_empty() {
+            Ok(crate::dir_index::DEFAULT_TEMPLATE.to_string())
+        } else {
+            std::fs::read_to_string(template_path).map_err(|e| format!("Không đọc được template index {}: {}", template_path, e))
+        };
+        // A missing page is a warning; the files themselves are already uploaded
+        match template {
+            Ok(template) => {
+                for (root, prefix) in &index_roots {
+                    match crate::dir_index::upload_indexes(&client, &bucket_name, prefix, root, &template).await {
+                        Ok(keys) => summary.uploaded_keys.extend(keys),
+                        Err(e) => {
+                            warn!("{}", e);
+                            summary.warnings.push(e);
+                        }
                     }
                 }
-                Err(e) => {
-                    warn!("Failed to open log file '{}': {}", log_file, e);
-                }
+            }
+            Err(e) => {
+                warn!("{}", e);
+                summary.warnings.push(e);
             }
         }
     }
+    if !has_error && !folder_prefixes.is_empty() {
+        let warnings = crate::website::validate(&client, &bucket_name, &folder_prefixes, &summary.uploaded_keys).await;
+        summary.warnings.extend(warnings);
+    }
+    if !has_error && verify_sample > 0 && !summary.upload_timings.is_empty() {
+        status("Đang kiểm tra mẫu file đã upload...".to_string(), crate::progress::INDETERMINATE, false);
+        let mismatches = crate::verify::verify_sample(&client, &bucket_name, &summary.upload_timings, verify_sample).await;
+        summary.warnings.extend(mismatches);
+    }
+    if !has_error && !smoke_test_urls.is_empty() {
+        let failures =
+            crate::smoke_test::run(&client, &bucket_name, &smoke_test_urls, &smoke_files, &hash_cache, &status).await;
+        summary.warnings.extend(failures);
+    }
 
-    Ok(())
+    if !has_error {
+        let message = match summary.warnings.as_slice() {
+            [] => "Đồng bộ hoàn tất!".to_string(),
+            warnings => format!("Đồng bộ hoàn tất! Cảnh báo: {}", warnings.join("; ")),
+        };
+        status(message, 1.0, false);
+    }
+    summary.uploaded = *completed_count.lock().await + dedup_copied;
+    summary.duration = started.elapsed();
+
+    if should_log && let Some(ref log) = session_log {
+        write_session_footer(log, &summary, !has_error);
+    }
+
+    Ok(summary)
+}
+
+/// Appends the slowest uploads and the completion line to the daily sync log.
+fn write_session_footer(log: &crate::sync_log::SessionLog, summary: &SyncSummary, success: bool) {
+    let end_time = Local::now();
+    let status = if success { "success" } else { "failed" };
+    let mut lines: Vec<String> = Vec::new();
+    let slowest = summary.slowest_uploads(SLOWEST_UPLOADS_REPORTED);
+    if !slowest.is_empty() {
+        lines.push("Slowest uploads:".to_string());
+        lines.extend(slowest.iter().map(|t| format!("  {}", format_upload_timing(t))));
+    }
+    lines.push(format!(
+        "Time Upload: {}, Bucket: {}, Status: {}",
+        end_time.format("%Y-%m-%d %H:%M:%S"),
+        summary.bucket,
+        status
+    ));
+    lines.push("--------------------------------------------------".to_string());
+    log.write(lines);
+}
+
+/// Append-only mode for growing log files: only the bytes added since the last sync are
+/// uploaded, each chunk as its own dated object next to the file's key.
+async fn ship_appended(
+    client: &Client,
+    bucket_name: &str,
+    files: Vec<(PathBuf, PathBuf, String)>,
+    status: &StatusFn,
+    throttle: &crate::scheduler::JobThrottle,
+    start_time: &chrono::DateTime<Local>,
+    summary: &mut SyncSummary,
+) {
+    let total = files.len();
+    for (i, (path, _, key)) in files.into_iter().enumerate() {
+        let len = match std::fs::metadata(&path) {
+            Ok(meta) => meta.len(),
+            Err(e) => {
+                summary.record_error(SyncError::io_open(&path, e));
+                continue;
+            }
+        };
+        let shipped = crate::append_log::shipped(bucket_name, &key);
+        let Some(range) = crate::append_log::pending_range(shipped, len) else { continue };
+        if shipped.is_some_and(|offset| offset > len) {
+            warn!("{} is shorter than the shipped offset, assuming it was rotated", path.display());
+        }
+        let chunk_key = crate::append_log::chunk_key(&key, start_time, range.start);
+        let bytes = range.end - range.start;
+        status(
+            format!("Đang upload phần mới {} ({}) - {}/{}", key, crate::utils::format_size(bytes), i + 1, total),
+            i as f32 / total as f32,
+            false,
+        );
+
+        let _permit = throttle.acquire().await;
+        let upload_started = std::time::Instant::now();
+        // Only the appended range is read; the file may keep growing meanwhile
+        let result = match ByteStream::read_from()
+            .path(&path)
+            .offset(range.start)
+            .length(aws_sdk_s3::primitives::Length::Exact(bytes))
+            .build()
+            .await
+        {
+            Ok(body) => client
+                .put_object()
+                .bucket(bucket_name)
+                .key(&chunk_key)
+                .content_type("text/plain; charset=utf-8")
+                .body(body)
+                .send()
+                .await
+                .map_err(|e| SyncError::from_sdk(&chunk_key, &e)),
+            Err(e) => Err(SyncError::io_open(&path, e)),
+        };
+        match result {
+            Ok(_) => {
+                info!("Shipped {} bytes of {} as {}", bytes, path.display(), chunk_key);
+                crate::append_log::record_shipped(bucket_name, &key, range.end);
+                summary.uploaded += 1;
+                summary.bytes_uploaded += bytes;
+                summary.upload_timings.push(UploadTiming {
+                    key: chunk_key.clone(),
+                    bytes,
+                    duration: upload_started.elapsed(),
+                    content_type: "text/plain; charset=utf-8".to_string(),
+                    cache_control: None,
+                });
+                summary.uploaded_keys.push(chunk_key);
+            }
+            Err(e) => {
+                error!("{}", e);
+                status(format!("Lỗi: {}", e), 0.0, true);
+                summary.record_error(e);
+            }
+        }
+    }
+}
+
+/// Archive mode: each mapped folder becomes one dated `.tar.gz` object instead of being mirrored.
+/// `budget` is the session budget in bytes and whether its overage was approved.
+#[allow(clippy::too_many_arguments)]
+async fn sync_archives(
+    client: &Client,
+    bucket_name: String,
+    mappings: Vec<(String, String)>,
+    status: StatusFn,
+    session_log: Option<crate::sync_log::SessionLog>,
+    filter_config: crate::config::FilterConfig,
+    start_time: chrono::DateTime<Local>,
+    budget: (u64, bool),
+) -> Result<SyncSummary, String> {
+    let started = std::time::Instant::now();
+    let mut log_mappings = Vec::new();
+    let (mappings, budget_warning) = archives_within_budget(mappings, &filter_config, budget, &mut log_mappings).await;
+    let mut summary = SyncSummary { bucket: bucket_name.clone(), total_files: mappings.len(), ..Default::default() };
+    if let Some(message) = budget_warning {
+        warn!("{}", message);
+        status(message.clone(), 0.0, false);
+        log_mappings.push(message.clone());
+        summary.warnings.push(message);
+    }
+    for (local_path, s3_template) in &mappings {
+        let local_dir = PathBuf::from(local_path);
+        let s3_prefix = crate::utils::expand_path_template(s3_template, &local_dir, &start_time).inspect_err(|e| {
+            status(e.clone(), 0.0, true);
+        })?;
+        if !local_dir.is_dir() {
+            let message = format!("Chế độ nén chỉ hỗ trợ thư mục: {}", local_path);
+            status(message.clone(), 0.0, true);
+            summary.record_error(message.into());
+            continue;
+        }
+        let key = crate::archive::archive_key(&s3_prefix, &local_dir, &start_time);
+        let upload_started = std::time::Instant::now();
+        match crate::archive::upload_archive(client, &bucket_name, &key, &local_dir, filter_config.clone(), &status).await {
+            Ok((files, bytes)) => {
+                log_mappings.push(format!("Archive: {} ({} file) -> S3: {} ({})", local_path, files, key, crate::utils::format_size(bytes)));
+                summary.uploaded += 1;
+                summary.bytes_uploaded += bytes;
+                summary.uploaded_keys.push(key.clone());
+                summary.upload_timings.push(UploadTiming {
+                    key,
+                    bytes,
+                    duration: upload_started.elapsed(),
+                    content_type: "application/gzip".to_string(),
+                    cache_control: None,
+                });
+            }
+            Err(e) => {
+                error!("{}", e);
+                status(format!("Lỗi: {}", e), 0.0, true);
+                log_mappings.push(format!("Archive FAILED: {} - {}", local_path, e));
+                summary.record_error(e.into());
+            }
+        }
+    }
+    summary.duration = started.elapsed();
+    if summary.is_success() {
+        status(format!("Đã upload {} file nén!", summary.uploaded), 1.0, false);
+    }
+    if let Some(ref log) = session_log {
+        write_session_header(log, &bucket_name, &log_mappings);
+        write_session_footer(log, &summary, summary.is_success());
+    }
+    Ok(summary)
+}
+
+/// Drops whole folders, last mapping first, until the archive run fits the session budget. A
+/// folder is sized before compression, so the estimate errs on the side of the budget.
+async fn archives_within_budget(
+    mappings: Vec<(String, String)>,
+    filter_config: &crate::config::FilterConfig,
+    (budget, overage_approved): (u64, bool),
+    log_mappings: &mut Vec<String>,
+) -> (Vec<(String, String)>, Option<String>) {
+    if budget == 0 {
+        return (mappings, None);
+    }
+    let filter_config = filter_config.clone();
+    let sized = tokio::task::spawn_blocking(move || {
+        mappings
+            .into_iter()
+            .map(|mapping| {
+                let (_, bytes) = crate::utils::mapping_totals(Path::new(&mapping.0), &filter_config).unwrap_or((0, 0));
+                (mapping, bytes)
+            })
+            .collect::<Vec<_>>()
+    })
+    .await
+    .unwrap_or_default();
+    let planned: u64 = sized.iter().map(|(_, bytes)| bytes).sum();
+    let overage = match crate::budget::overage_text(planned, budget) {
+        Some(overage) if !overage_approved => overage,
+        approved => {
+            log_mappings.extend(approved.map(|overage| format!("{} (đã xác nhận)", overage)));
+            return (sized.into_iter().map(|(mapping, _)| mapping).collect(), None);
+        }
+    };
+    let (kept, dropped, dropped_bytes) = crate::budget::trim_to_budget(sized, budget, |(_, bytes)| *bytes);
+    for ((local_path, _), _) in &dropped {
+        info!("Over budget, not archived: {}", local_path);
+    }
+    let message = format!(
+        "{}: bỏ qua {} thư mục ({}) theo thứ tự ưu tiên",
+        overage,
+        dropped.len(),
+        crate::utils::format_size(dropped_bytes)
+    );
+    (kept.into_iter().map(|(mapping, _)| mapping).collect(), Some(message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_path_parts_strips_only_leading_system_folders() {
+        let rules = crate::config::PrefixRules::default();
+        assert_eq!(
+            normalize_path_parts(Path::new("C:\\Users\\Desktop\\site\\local\\temp"), &rules),
+            vec!["site", "local", "temp"]
+        );
+        assert_eq!(normalize_path_parts(Path::new("C:\\Users\\Ngoc\\Proj\\X"), &rules), vec!["Ngoc", "Proj", "X"]);
+        assert_eq!(normalize_path_parts(Path::new("/home/ann/projects/app"), &rules), vec!["ann", "projects", "app"]);
+        assert_eq!(get_preview_prefix(Path::new("D:/work/temp"), &rules, 0), "work/temp");
+
+        let rules = crate::config::PrefixRules { system_folders: vec!["Work".to_string()] };
+        assert_eq!(get_preview_prefix(Path::new("D:/work/temp"), &rules, 0), "temp");
+    }
+
+    #[test]
+    fn test_preview_prefix_depth() {
+        let rules = crate::config::PrefixRules::default();
+        let path = Path::new("/srv/a/b/c/d/e/f");
+        assert_eq!(get_preview_prefix(path, &rules, 0), "d/e/f");
+        assert_eq!(get_preview_prefix(path, &rules, 1), "f");
+        assert_eq!(get_preview_prefix(path, &rules, 5), "b/c/d/e/f");
+        assert_eq!(get_preview_prefix(path, &rules, 9), "b/c/d/e/f");
+        assert_eq!(get_preview_prefix(Path::new("/srv/site"), &rules, 4), "srv/site");
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_key_backs_up_the_original_once() {
+        let store = crate::object_store::MemoryStore::default();
+        store.put("site", "index.html", b"original".to_vec()).await.unwrap();
+        let trash = ".trash/20250101_120000/";
+        // Two mappings whose files land on the same key
+        let files = vec![
+            (PathBuf::from("/a/index.html"), PathBuf::from("/a"), "index.html".to_string()),
+            (PathBuf::from("/b/index.html"), PathBuf::from("/b"), "index.html".to_string()),
+        ];
+        let mut turns = KeyTurns::new(crate::utils::find_duplicate_keys(&files).into_iter().map(|(key, _)| key));
+
+        let mut backed_up = Vec::new();
+        for (path, _, key) in &files {
+            let turn = turns.next(key);
+            turn.as_ref().unwrap().wait().await;
+            backed_up.push(backup_before_upload(&store, "site", key, trash, turn.as_ref()).await.unwrap());
+            store.put("site", key, path.to_string_lossy().as_bytes().to_vec()).await.unwrap();
+        }
+        assert_eq!(backed_up, vec![true, false]);
+        assert_eq!(store.body("site", ".trash/20250101_120000/index.html").unwrap(), b"original");
+        assert_eq!(store.body("site", "index.html").unwrap(), b"/b/index.html");
+    }
+
+    #[tokio::test]
+    async fn test_archives_trimmed_to_budget_by_mapping_order() {
+        let root = std::env::temp_dir().join(format!("s3sync_archive_budget_{}", std::process::id()));
+        let mut mappings = Vec::new();
+        for (name, size) in [("first", 600 * 1024), ("second", 600 * 1024), ("third", 300 * 1024)] {
+            let dir = root.join(name);
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(dir.join("data.txt"), vec![b'x'; size]).unwrap();
+            mappings.push((dir.to_string_lossy().to_string(), name.to_string()));
+        }
+        let filter = crate::config::FilterConfig { enable_filtering: false, ..Default::default() };
+        let budget = crate::budget::budget_bytes(1);
+
+        let mut log = Vec::new();
+        let (kept, warning) = archives_within_budget(mappings.clone(), &filter, (budget, false), &mut log).await;
+        assert_eq!(kept.iter().map(|(_, s3)| s3.as_str()).collect::<Vec<_>>(), vec!["first", "third"]);
+        assert!(warning.unwrap().contains("bỏ qua 1 thư mục"));
+
+        let mut log = Vec::new();
+        let (kept, warning) = archives_within_budget(mappings.clone(), &filter, (budget, true), &mut log).await;
+        assert_eq!(kept, mappings);
+        assert!(warning.is_none());
+        assert!(log[0].ends_with("(đã xác nhận)"));
+        let _ = std::fs::remove_dir_all(&root);
+    }
 }
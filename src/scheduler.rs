@@ -0,0 +1,200 @@
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tokio::sync::Notify;
+
+/// Relative share of the upload slots a job gets while other jobs are active.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Priority {
+    High,
+    #[default]
+    Normal,
+    Low,
+}
+
+impl Priority {
+    pub fn weight(&self) -> usize {
+        match self {
+            Priority::High => 8,
+            Priority::Normal => 3,
+            Priority::Low => 1,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Priority::High => "high",
+            Priority::Normal => "normal",
+            Priority::Low => "low",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "high" => Some(Priority::High),
+            "normal" => Some(Priority::Normal),
+            "low" => Some(Priority::Low),
+            _ => None,
+        }
+    }
+}
+
+/// Per-job limits: priority and a hard cap on concurrent uploads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JobLimits {
+    pub priority: Priority,
+    pub max_concurrency: usize,
+}
+
+impl Default for JobLimits {
+    fn default() -> Self {
+        Self {
+            priority: Priority::Normal,
+            max_concurrency: total_concurrency(),
+        }
+    }
+}
+
+/// Total upload slots shared by all active jobs (Settings window, default 50).
+pub fn total_concurrency() -> usize {
+    crate::config::settings().concurrency.max(1)
+}
+
+struct ActiveJob {
+    limits: JobLimits,
+    in_flight: usize,
+}
+
+struct Scheduler {
+    next_id: u64,
+    jobs: HashMap<u64, ActiveJob>,
+}
+
+impl Scheduler {
+    /// Slots currently granted to `id`: its weighted share of the total, at least 1, at most its cap.
+    fn share(&self, id: u64, total: usize) -> usize {
+        let Some(job) = self.jobs.get(&id) else { return 0 };
+        let weight_sum: usize = self.jobs.values().map(|j| j.limits.priority.weight()).sum();
+        let share = total * job.limits.priority.weight() / weight_sum.max(1);
+        share.clamp(1, job.limits.max_concurrency.max(1))
+    }
+}
+
+static SCHEDULER: Lazy<Mutex<Scheduler>> = Lazy::new(|| {
+    Mutex::new(Scheduler {
+        next_id: 1,
+        jobs: HashMap::new(),
+    })
+});
+static CHANGED: Lazy<Notify> = Lazy::new(Notify::new);
+
+/// Registers an active job. Slots are rebalanced whenever jobs start, finish or change limits.
+pub fn register(limits: JobLimits) -> JobThrottle {
+    register_job(limits, false)
+}
+
+/// Registers a job from the queue; it only gets slots inside the sync window.
+pub fn register_queued(limits: JobLimits) -> JobThrottle {
+    register_job(limits, true)
+}
+
+fn register_job(limits: JobLimits, windowed: bool) -> JobThrottle {
+    let id = {
+        let mut scheduler = SCHEDULER.lock().unwrap();
+        let id = scheduler.next_id;
+        scheduler.next_id += 1;
+        scheduler.jobs.insert(id, ActiveJob { limits, in_flight: 0 });
+        id
+    };
+    CHANGED.notify_waiters();
+    JobThrottle { id, windowed }
+}
+
+/// Changes the limits of a running job; other jobs are rebalanced immediately.
+pub fn update_limits(id: u64, limits: JobLimits) {
+    if let Some(job) = SCHEDULER.lock().unwrap().jobs.get_mut(&id) {
+        job.limits = limits;
+    }
+    CHANGED.notify_waiters();
+}
+
+/// Handle of a registered job. Unregisters on drop.
+#[derive(Debug)]
+pub struct JobThrottle {
+    id: u64,
+    /// Waits for the sync window before each slot (queued jobs).
+    windowed: bool,
+}
+
+impl JobThrottle {
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Waits until this job is below its current share, then takes a slot.
+    pub async fn acquire(&self) -> JobPermit {
+        if self.windowed {
+            crate::sync_window::wait_until_open(None).await;
+        }
+        loop {
+            let notified = CHANGED.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+            {
+                let mut scheduler = SCHEDULER.lock().unwrap();
+                let share = scheduler.share(self.id, total_concurrency());
+                if let Some(job) = scheduler.jobs.get_mut(&self.id)
+                    && job.in_flight < share
+                {
+                    job.in_flight += 1;
+                    return JobPermit { id: self.id };
+                }
+            }
+            notified.await;
+        }
+    }
+}
+
+impl Drop for JobThrottle {
+    fn drop(&mut self) {
+        SCHEDULER.lock().unwrap().jobs.remove(&self.id);
+        CHANGED.notify_waiters();
+    }
+}
+
+/// One upload slot; released on drop.
+pub struct JobPermit {
+    id: u64,
+}
+
+impl Drop for JobPermit {
+    fn drop(&mut self) {
+        if let Some(job) = SCHEDULER.lock().unwrap().jobs.get_mut(&self.id) {
+            job.in_flight = job.in_flight.saturating_sub(1);
+        }
+        CHANGED.notify_waiters();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_share_rebalances_by_priority() {
+        let mut scheduler = Scheduler {
+            next_id: 3,
+            jobs: HashMap::new(),
+        };
+        let limits = |priority, max_concurrency| JobLimits { priority, max_concurrency };
+        scheduler.jobs.insert(1, ActiveJob { limits: limits(Priority::Low, 50), in_flight: 0 });
+        assert_eq!(scheduler.share(1, 45), 45);
+
+        scheduler.jobs.insert(2, ActiveJob { limits: limits(Priority::High, 50), in_flight: 0 });
+        assert_eq!(scheduler.share(1, 45), 5);
+        assert_eq!(scheduler.share(2, 45), 40);
+
+        scheduler.jobs.get_mut(&2).unwrap().limits.max_concurrency = 10;
+        assert_eq!(scheduler.share(2, 45), 10);
+    }
+}
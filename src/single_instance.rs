@@ -0,0 +1,185 @@
+use fs2::FileExt;
+use ring::rand::{SecureRandom, SystemRandom};
+use std::fs::File;
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// First line of a hand-off message, so stray connections to the port are ignored.
+const HANDOFF_HEADER: &str = "S3SYNC-OPEN";
+/// Random bytes in the token that proves a hand-off comes from someone who can read the port file.
+const TOKEN_BYTES: usize = 16;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Held by the running instance for its whole lifetime: the locked file keeps other launches
+/// out, the listener receives their hand-off.
+pub struct InstanceLock {
+    _file: File,
+    listener: TcpListener,
+    token: String,
+}
+
+pub enum Startup {
+    /// This is the only instance; keep the lock alive until exit.
+    First(InstanceLock),
+    /// Another instance was running and has been told to come to the front.
+    HandedOff,
+    /// The lock couldn't be set up (no config folder, file or port); run without the guard.
+    /// Carries the reason for the caller to log once logging is up.
+    Unguarded(String),
+}
+
+fn instance_dir() -> Option<PathBuf> {
+    crate::config::get_config_path()?.parent().map(PathBuf::from)
+}
+
+fn format_message(token: &str, paths: &[String]) -> String {
+    let mut message = format!("{}\n{}\n", HANDOFF_HEADER, token);
+    for path in paths {
+        message.push_str(path);
+        message.push('\n');
+    }
+    message
+}
+
+/// The paths of a hand-off message, or `None` if it isn't one or carries the wrong token.
+fn parse_message(message: &str, token: &str) -> Option<Vec<String>> {
+    let mut lines = message.lines();
+    (lines.next()? == HANDOFF_HEADER && lines.next()? == token)
+        .then(|| lines.filter(|l| !l.trim().is_empty()).map(String::from).collect())
+}
+
+fn new_token() -> Result<String, String> {
+    let mut bytes = [0u8; TOKEN_BYTES];
+    SystemRandom::new().fill(&mut bytes).map_err(|_| "Không tạo được token ngẫu nhiên".to_string())?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Writes the port file readable by the current user only, since its token authorizes hand-offs.
+fn write_port_file(path: &std::path::Path, contents: &str) -> std::io::Result<()> {
+    let _ = std::fs::remove_file(path);
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)?.write_all(contents.as_bytes())
+}
+
+/// The port and token of the running instance, as written by [`take_lock`].
+fn read_port_file(port_file: &std::path::Path) -> Result<(u16, String), String> {
+    let contents = std::fs::read_to_string(port_file).map_err(|e| format!("Không đọc được {}: {}", port_file.display(), e))?;
+    let mut lines = contents.lines();
+    let port = lines
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .parse()
+        .map_err(|e| format!("Cổng không hợp lệ trong {}: {}", port_file.display(), e))?;
+    let token = lines.next().map(str::trim).filter(|t| !t.is_empty());
+    let token = token.ok_or_else(|| format!("Thiếu token trong {}", port_file.display()))?;
+    Ok((port, token.to_string()))
+}
+
+fn hand_off(port_file: &std::path::Path, paths: &[String]) -> Result<(), String> {
+    let (port, token) = read_port_file(port_file)?;
+    let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+    let mut stream = TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT).map_err(|e| format!("Không kết nối được bản đang chạy: {}", e))?;
+    stream
+        .write_all(format_message(&token, paths).as_bytes())
+        .map_err(|e| format!("Không gửi được đến bản đang chạy: {}", e))
+}
+
+fn take_lock(dir: &std::path::Path, file: File) -> Result<InstanceLock, String> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).map_err(|e| format!("Không mở được cổng nội bộ: {}", e))?;
+    let port = listener.local_addr().map_err(|e| e.to_string())?.port();
+    let token = new_token()?;
+    let port_file = dir.join("instance.port");
+    write_port_file(&port_file, &format!("{}\n{}\n", port, token))
+        .map_err(|e| format!("Không ghi được {}: {}", port_file.display(), e))?;
+    Ok(InstanceLock { _file: file, listener, token })
+}
+
+/// Takes the single-instance lock, or passes `paths` to the instance already holding it.
+/// Errs only if another instance holds the lock but can't be reached; problems setting up
+/// the lock itself give [`Startup::Unguarded`] so the app still opens. Runs before the config
+/// is loaded and logging is set up, so a second launch touches neither; it logs nothing itself.
+pub fn start(paths: &[String]) -> Result<Startup, String> {
+    let Some(dir) = instance_dir() else {
+        return Ok(Startup::Unguarded("No config folder".to_string()));
+    };
+    let _ = std::fs::create_dir_all(&dir);
+    let file = match File::create(dir.join("instance.lock")) {
+        Ok(file) => file,
+        Err(e) => return Ok(Startup::Unguarded(format!("Cannot create instance lock file: {}", e))),
+    };
+
+    if file.try_lock_exclusive().is_err() {
+        hand_off(&dir.join("instance.port"), paths)?;
+        return Ok(Startup::HandedOff);
+    }
+    match take_lock(&dir, file) {
+        Ok(lock) => Ok(Startup::First(lock)),
+        Err(e) => Ok(Startup::Unguarded(e)),
+    }
+}
+
+impl InstanceLock {
+    /// Calls `on_open` with the paths of every later launch, on a background thread.
+    pub fn listen(self, on_open: impl Fn(Vec<String>) + Send + 'static) {
+        let InstanceLock { _file: file, listener, token } = self;
+        std::thread::spawn(move || {
+            // Owned by this thread so the file stays locked for as long as the app runs
+            let _file = file;
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { continue };
+                let _ = stream.set_read_timeout(Some(CONNECT_TIMEOUT));
+                let mut message = String::new();
+                if stream.read_to_string(&mut message).is_err() {
+                    continue;
+                }
+                match parse_message(&message, &token) {
+                    Some(paths) => {
+                        info!("Second launch handed off {} path(s)", paths.len());
+                        on_open(paths);
+                    }
+                    None => warn!("Ignored unexpected connection on the single-instance port"),
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handoff_message_round_trip() {
+        let paths = vec!["C:\\site".to_string(), "/home/a/docs dir".to_string()];
+        assert_eq!(parse_message(&format_message("abc", &paths), "abc"), Some(paths.clone()));
+        assert_eq!(parse_message(&format_message("abc", &[]), "abc"), Some(vec![]));
+        assert_eq!(parse_message("GET / HTTP/1.1\r\n", "abc"), None);
+        // Knowing the header isn't enough without the token from the port file
+        assert_eq!(parse_message(&format_message("guess", &paths), "abc"), None);
+        assert_eq!(parse_message(&format!("{}\n{}\n", HANDOFF_HEADER, paths[0]), "abc"), None);
+    }
+
+    #[test]
+    fn test_port_file_round_trip() {
+        let path = std::env::temp_dir().join(format!("s3sync_instance_port_{}", std::process::id()));
+        let token = new_token().unwrap();
+        assert_eq!(token.len(), TOKEN_BYTES * 2);
+        write_port_file(&path, &format!("4242\n{}\n", token)).unwrap();
+        assert_eq!(read_port_file(&path), Ok((4242, token)));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+        std::fs::write(&path, "4242\n").unwrap();
+        assert!(read_port_file(&path).is_err());
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,128 @@
+use aws_sdk_s3::Client;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::dedup::HashCache;
+use crate::progress::StatusFn;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// One URL to fetch after a sync and the key it should serve.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SmokeTarget {
+    pub url: String,
+    pub key: String,
+}
+
+/// Parses one `URL [key]` line. Without a key, the URL path is used (`/docs/` → `docs/index.html`).
+pub fn parse_line(line: &str) -> Result<SmokeTarget, String> {
+    let mut parts = line.split_whitespace();
+    let url = parts.next().unwrap_or_default();
+    let Some(rest) = url.strip_prefix("https://").or_else(|| url.strip_prefix("http://")) else {
+        return Err(format!("URL phải bắt đầu bằng http(s)://: '{}'", url));
+    };
+    let key = match parts.next() {
+        Some(key) => key.trim_start_matches('/').to_string(),
+        None => {
+            let path = rest.split(['?', '#']).next().unwrap_or_default();
+            let path = path.split_once('/').map(|(_, p)| p).unwrap_or("");
+            if path.is_empty() || path.ends_with('/') { format!("{}index.html", path) } else { path.to_string() }
+        }
+    };
+    Ok(SmokeTarget { url: url.to_string(), key })
+}
+
+/// Validates the configured lines; returns the first error.
+pub fn validate_lines(lines: &[String]) -> Result<(), String> {
+    lines.iter().try_for_each(|line| parse_line(line).map(|_| ()))
+}
+
+/// Fetches every target and checks it returns 2xx with the content just uploaded: the ETag
+/// must match the object's, or else the body must hash like the local file. Targets whose key
+/// wasn't part of this upload are only checked for the status code. Returns one message per
+/// failed target.
+pub async fn run(
+    client: &Client,
+    bucket: &str,
+    lines: &[String],
+    uploaded: &HashMap<String, PathBuf>,
+    cache: &HashCache,
+    status: &StatusFn,
+) -> Vec<String> {
+    let http = match reqwest::Client::builder().timeout(REQUEST_TIMEOUT).build() {
+        Ok(http) => http,
+        Err(e) => return vec![format!("Smoke test: không tạo được HTTP client: {}", e)],
+    };
+    let mut failures = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        status(format!("Smoke test ({}/{})", i + 1, lines.len()), (i + 1) as f32 / lines.len() as f32, false);
+        let result = match parse_line(line) {
+            Ok(target) => check(&http, client, bucket, &target, uploaded.get(&target.key), cache).await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(()) => info!("Smoke test passed: {}", line),
+            Err(e) => {
+                warn!("Smoke test failed: {}: {}", line, e);
+                failures.push(format!("Smoke test {}: {}", line, e));
+            }
+        }
+    }
+    failures
+}
+
+async fn check(
+    http: &reqwest::Client,
+    client: &Client,
+    bucket: &str,
+    target: &SmokeTarget,
+    local: Option<&PathBuf>,
+    cache: &HashCache,
+) -> Result<(), String> {
+    let response = http.get(&target.url).send().await.map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("HTTP {}", response.status()));
+    }
+    let Some(local) = local else { return Ok(()) };
+
+    let served_etag = response.headers().get("etag").and_then(|v| v.to_str().ok()).map(normalize_etag);
+    if let Some(served) = &served_etag {
+        let object_etag = client.head_object().bucket(bucket).key(&target.key).send().await.ok().and_then(|h| h.e_tag);
+        if object_etag.as_deref().map(normalize_etag).as_ref() == Some(served) {
+            return Ok(());
+        }
+    }
+    // CDNs may rewrite the ETag; fall back to comparing the content itself
+    let body = response.bytes().await.map_err(|e| e.to_string())?;
+    let served_hash: String = Sha256::digest(&body).iter().map(|b| format!("{:02x}", b)).collect();
+    let local_hash = cache.sha256(local).map_err(|e| format!("Lỗi tính hash {}: {}", local.display(), e))?;
+    if served_hash == local_hash {
+        Ok(())
+    } else {
+        Err("nội dung khác bản vừa upload (CDN có thể còn cache bản cũ)".to_string())
+    }
+}
+
+/// `W/"abc"` → `abc`
+fn normalize_etag(etag: &str) -> String {
+    etag.trim_start_matches("W/").trim_matches('"').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_line() {
+        assert_eq!(parse_line("https://cdn.example.com/").unwrap().key, "index.html");
+        assert_eq!(parse_line("https://cdn.example.com/docs/?v=2").unwrap().key, "docs/index.html");
+        assert_eq!(parse_line("https://example.com").unwrap().key, "index.html");
+        let target = parse_line("https://example.com/app  /site/app.js").unwrap();
+        assert_eq!(target, SmokeTarget { url: "https://example.com/app".into(), key: "site/app.js".into() });
+        assert!(parse_line("example.com/a.html").is_err());
+        assert_eq!(normalize_etag("W/\"abc\""), "abc");
+    }
+}
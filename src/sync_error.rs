@@ -0,0 +1,122 @@
+use aws_sdk_s3::config::http::HttpResponse;
+use aws_sdk_s3::error::{ProvideErrorMetadata, SdkError};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Why one step of a sync failed. The user-facing text comes from `Display`, so the status bar,
+/// the log and the email report all word the same error the same way.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyncError {
+    /// A local file could not be opened or read.
+    IoOpen { path: PathBuf, message: String },
+    /// S3 asked us to slow down (SlowDown, throttling, HTTP 503).
+    UploadThrottled { key: String, message: String },
+    /// The credentials may not write this key (AccessDenied, HTTP 403).
+    AccessDenied { key: String, message: String },
+    /// No response: DNS failure, refused or reset connection, timeout.
+    NetworkTimeout { key: String, message: String },
+    /// Any other error returned by S3.
+    Upload { key: String, message: String },
+    /// The task was stopped before it finished.
+    Cancelled,
+    /// Failures outside a single upload (staging, manifests, encryption, ...), already worded.
+    Other(String),
+}
+
+impl SyncError {
+    pub fn io_open(path: &Path, error: impl fmt::Display) -> Self {
+        SyncError::IoOpen { path: path.to_path_buf(), message: error.to_string() }
+    }
+
+    /// Classifies an S3 SDK error for `key`.
+    pub fn from_sdk<E>(key: &str, error: &SdkError<E, HttpResponse>) -> Self
+    where
+        E: ProvideErrorMetadata + std::error::Error + 'static,
+    {
+        let connection = crate::connectivity::is_connection_error(error);
+        let http_status = error.raw_response().map(|r| r.status().as_u16());
+        match Self::classify(key, error.code(), http_status, connection, error.to_string()) {
+            // S3's message says whose policy denied it; turn that into what to fix
+            SyncError::AccessDenied { key, message } => match error.message().and_then(crate::bucket_policy::denied_hint) {
+                Some(hint) => SyncError::AccessDenied { key, message: format!("{} ({})", message, hint) },
+                None => SyncError::AccessDenied { key, message },
+            },
+            classified => classified,
+        }
+    }
+
+    fn classify(key: &str, code: Option<&str>, http_status: Option<u16>, connection: bool, message: String) -> Self {
+        let key = key.to_string();
+        if connection {
+            return SyncError::NetworkTimeout { key, message };
+        }
+        match (code, http_status) {
+            (Some("SlowDown" | "Throttling" | "ThrottlingException" | "RequestLimitExceeded" | "TooManyRequests"), _)
+            | (_, Some(429 | 503)) => SyncError::UploadThrottled { key, message },
+            (Some("AccessDenied" | "AllAccessDisabled" | "InvalidAccessKeyId" | "SignatureDoesNotMatch"), _)
+            | (_, Some(403)) => SyncError::AccessDenied { key, message },
+            (Some("RequestTimeout"), _) => SyncError::NetworkTimeout { key, message },
+            _ => SyncError::Upload { key, message },
+        }
+    }
+
+    /// Worth trying again later: the request itself was fine.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, SyncError::UploadThrottled { .. } | SyncError::NetworkTimeout { .. })
+    }
+
+    /// Short name used to count errors by type in reports.
+    pub fn category(&self) -> &'static str {
+        match self {
+            SyncError::IoOpen { .. } => "io_open",
+            SyncError::UploadThrottled { .. } => "throttled",
+            SyncError::AccessDenied { .. } => "access_denied",
+            SyncError::NetworkTimeout { .. } => "network",
+            SyncError::Upload { .. } => "upload",
+            SyncError::Cancelled => "cancelled",
+            SyncError::Other(_) => "other",
+        }
+    }
+}
+
+impl fmt::Display for SyncError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SyncError::IoOpen { path, message } => write!(f, "Lỗi mở file {}: {}", path.display(), message),
+            SyncError::UploadThrottled { key, message } => {
+                write!(f, "S3 giới hạn tốc độ khi upload {}: {}", key, message)
+            }
+            SyncError::AccessDenied { key, message } => write!(f, "Không có quyền upload {}: {}", key, message),
+            SyncError::NetworkTimeout { key, message } => write!(f, "Lỗi mạng khi upload {}: {}", key, message),
+            SyncError::Upload { key, message } => write!(f, "Lỗi upload {}: {}", key, message),
+            SyncError::Cancelled => write!(f, "Đã hủy"),
+            SyncError::Other(message) => f.write_str(message),
+        }
+    }
+}
+
+impl From<String> for SyncError {
+    fn from(message: String) -> Self {
+        SyncError::Other(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        let classify = |code, status, connection| SyncError::classify("a.txt", code, status, connection, "x".to_string());
+
+        assert_eq!(classify(Some("SlowDown"), Some(503), false).category(), "throttled");
+        assert_eq!(classify(None, Some(503), false).category(), "throttled");
+        assert_eq!(classify(Some("AccessDenied"), Some(403), false).category(), "access_denied");
+        assert_eq!(classify(None, None, true).category(), "network");
+        assert_eq!(classify(Some("NoSuchBucket"), Some(404), false).category(), "upload");
+
+        assert!(classify(Some("SlowDown"), None, false).is_retryable());
+        assert!(!classify(Some("AccessDenied"), None, false).is_retryable());
+        assert_eq!(classify(Some("InvalidBucketName"), Some(400), false).to_string(), "Lỗi upload a.txt: x");
+    }
+}
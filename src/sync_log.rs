@@ -0,0 +1,212 @@
+//! The sync log in the chosen log folder: either one daily file (`sync_log_DD_MM_YYYY.log`)
+//! or one file per session (`sync_YYYYMMDD_HHMM_<bucket>.log`). All sessions send their lines
+//! to one writer thread, so each block lands whole even when syncs run side by side, and a
+//! session that runs past midnight continues in the new day's file.
+
+use chrono::{DateTime, Local, NaiveDate};
+use once_cell::sync::OnceCell;
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::{mpsc, oneshot};
+use tracing::warn;
+
+use crate::config::LogFileMode;
+
+enum Command {
+    Write { session: u64, dir: PathBuf, file: Option<String>, bucket: String, started: NaiveDate, lines: Vec<String> },
+    Flush(oneshot::Sender<()>),
+}
+
+static WRITER: OnceCell<mpsc::UnboundedSender<Command>> = OnceCell::new();
+static NEXT_SESSION: AtomicU64 = AtomicU64::new(1);
+
+/// File name of the log for `date`.
+pub fn daily_file_name(date: NaiveDate) -> String {
+    date.format("sync_log_%d_%m_%Y.log").to_string()
+}
+
+/// File name of a per-session log.
+pub fn session_file_name(started: &DateTime<Local>, bucket: &str) -> String {
+    format!("sync_{}_{}.log", started.format("%Y%m%d_%H%M"), bucket)
+}
+
+/// Whether `name` is a log file written here, daily or per session. Used to leave the logs out
+/// when the log folder is itself a synced folder.
+pub fn is_log_file_name(name: &str) -> bool {
+    let Some(stem) = name.strip_prefix("sync_").and_then(|n| n.strip_suffix(".log")) else { return false };
+    if NaiveDate::parse_from_str(stem.strip_prefix("log_").unwrap_or_default(), "%d_%m_%Y").is_ok() {
+        return true;
+    }
+    // <YYYYMMDD>_<HHMM or HHMMSS>_<bucket>
+    let mut parts = stem.splitn(3, '_');
+    let digits = |part: Option<&str>, lens: &[usize]| {
+        part.is_some_and(|p| lens.contains(&p.len()) && p.bytes().all(|b| b.is_ascii_digit()))
+    };
+    digits(parts.next(), &[8]) && digits(parts.next(), &[4, 6]) && parts.next().is_some_and(|bucket| !bucket.is_empty())
+}
+
+/// One sync session's handle on the log. Cheap to create; writes never block the caller.
+#[derive(Debug, Clone)]
+pub struct SessionLog {
+    session: u64,
+    dir: PathBuf,
+    /// Fixed file name in per-session mode; `None` follows the daily file.
+    file: Option<String>,
+    bucket: String,
+    started: NaiveDate,
+}
+
+impl SessionLog {
+    pub fn new(dir: &str, bucket: &str, started: &DateTime<Local>, mode: LogFileMode) -> Self {
+        Self {
+            session: NEXT_SESSION.fetch_add(1, Ordering::Relaxed),
+            dir: PathBuf::from(dir),
+            file: (mode == LogFileMode::Session).then(|| session_file_name(started, bucket)),
+            bucket: bucket.to_string(),
+            started: started.date_naive(),
+        }
+    }
+
+    /// Queues `lines` to be written together, without another session's lines in between.
+    pub fn write(&self, lines: Vec<String>) {
+        let command = Command::Write {
+            session: self.session,
+            dir: self.dir.clone(),
+            file: self.file.clone(),
+            bucket: self.bucket.clone(),
+            started: self.started,
+            lines,
+        };
+        if writer().send(command).is_err() {
+            warn!("Sync log writer has stopped; dropped log lines for {}", self.bucket);
+        }
+    }
+}
+
+fn writer() -> &'static mpsc::UnboundedSender<Command> {
+    WRITER.get_or_init(|| {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        std::thread::Builder::new()
+            .name("sync-log-writer".to_string())
+            .spawn(move || {
+                let mut writer = DailyWriter::default();
+                while let Some(command) = rx.blocking_recv() {
+                    match command {
+                        Command::Write { dir, file: Some(file), lines, .. } => {
+                            writer.write_to(&dir.join(file), &lines);
+                        }
+                        Command::Write { session, dir, file: None, bucket, started, lines } => {
+                            writer.write(session, &dir, &bucket, started, &lines, Local::now().date_naive());
+                        }
+                        Command::Flush(done) => {
+                            writer.flush();
+                            let _ = done.send(());
+                        }
+                    }
+                }
+            })
+            .expect("failed to start the sync log writer");
+        tx
+    })
+}
+
+/// Waits until everything queued so far is on disk. Called on shutdown.
+pub async fn flush() {
+    let Some(tx) = WRITER.get() else { return };
+    let (done, wait) = oneshot::channel();
+    if tx.send(Command::Flush(done)).is_ok() {
+        let _ = wait.await;
+    }
+}
+
+/// Keeps the current file open and switches when the date, folder or session file changes.
+#[derive(Default)]
+struct DailyWriter {
+    file: Option<(PathBuf, BufWriter<File>)>,
+    /// Sessions that already got the continuation note in the file of a later day.
+    continued: HashSet<(u64, NaiveDate)>,
+}
+
+impl DailyWriter {
+    fn open(&mut self, path: &Path) -> Option<&mut BufWriter<File>> {
+        if self.file.as_ref().is_none_or(|(current, _)| current != path) {
+            self.flush();
+            self.file = match OpenOptions::new().create(true).append(true).open(path) {
+                Ok(file) => Some((path.to_path_buf(), BufWriter::new(file))),
+                Err(e) => {
+                    warn!("Failed to open log file '{}': {}", path.display(), e);
+                    None
+                }
+            };
+        }
+        self.file.as_mut().map(|(_, file)| file)
+    }
+
+    /// Writes to the daily file for `today`, noting where a session that started earlier continues.
+    fn write(&mut self, session: u64, dir: &Path, bucket: &str, started: NaiveDate, lines: &[String], today: NaiveDate) {
+        if today != started && self.continued.insert((session, today)) {
+            let note = format!("(Tiếp tục phiên sync bắt đầu ngày {} - Bucket: {})", started.format("%d/%m/%Y"), bucket);
+            let mut block = vec![note];
+            block.extend_from_slice(lines);
+            self.write_to(&dir.join(daily_file_name(today)), &block);
+        } else {
+            self.write_to(&dir.join(daily_file_name(today)), lines);
+        }
+    }
+
+    fn write_to(&mut self, path: &Path, lines: &[String]) {
+        let Some(file) = self.open(path) else { return };
+        let written = lines.iter().try_for_each(|line| writeln!(file, "{}", line)).and_then(|_| file.flush());
+        if let Err(e) = written {
+            warn!("Failed to write to log file '{}': {}", path.display(), e);
+        }
+    }
+
+    fn flush(&mut self) {
+        if let Some((path, file)) = &mut self.file
+            && let Err(e) = file.flush()
+        {
+            warn!("Failed to flush log file '{}': {}", path.display(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_day_rollover() {
+        let dir = std::env::temp_dir().join(format!("s3sync_sync_log_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let day1 = NaiveDate::from_ymd_opt(2024, 1, 31).unwrap();
+        let day2 = NaiveDate::from_ymd_opt(2024, 2, 1).unwrap();
+        assert_eq!(daily_file_name(day1), "sync_log_31_01_2024.log");
+
+        let mut writer = DailyWriter::default();
+        writer.write(1, &dir, "prod", day1, &["header".to_string()], day1);
+        writer.write(1, &dir, "prod", day1, &["a".to_string()], day2);
+        writer.write(1, &dir, "prod", day1, &["footer".to_string()], day2);
+        writer.flush();
+
+        let first = std::fs::read_to_string(dir.join(daily_file_name(day1))).unwrap();
+        let second = std::fs::read_to_string(dir.join(daily_file_name(day2))).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(first, "header\n");
+        assert_eq!(second, "(Tiếp tục phiên sync bắt đầu ngày 31/01/2024 - Bucket: prod)\na\nfooter\n");
+
+        let started = Local.with_ymd_and_hms(2024, 1, 1, 15, 30, 0).unwrap();
+        assert_eq!(session_file_name(&started, "prodbucket"), "sync_20240101_1530_prodbucket.log");
+
+        assert!(is_log_file_name("sync_log_31_01_2024.log"));
+        assert!(is_log_file_name("sync_20240101_1530_prodbucket.log"));
+        assert!(is_log_file_name("sync_20240101_153012_my_bucket.log"));
+        assert!(!is_log_file_name("sync_notes.log"));
+        assert!(!is_log_file_name("sync_log_31_01_2024.txt"));
+        assert!(!is_log_file_name("sync_20240101_1530_.log"));
+    }
+}
@@ -0,0 +1,174 @@
+//! When the job queue may upload: only inside the configured hours (e.g. `22:00-06:00`) and,
+//! if asked, not while the connection is metered. Outside the window queued jobs wait instead
+//! of using office bandwidth during work hours; "Sync Now" is not affected.
+
+use chrono::{Local, NaiveTime};
+use once_cell::sync::Lazy;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::info;
+
+use crate::progress::StatusFn;
+
+/// How often a waiting queue checks the window again.
+const RECHECK_INTERVAL: Duration = Duration::from_secs(30);
+/// How long a metered-connection answer is reused; asking the OS spawns a process.
+const METERED_CACHE: Duration = Duration::from_secs(60);
+
+/// Daily hours during which the queue uploads. `start > end` wraps past midnight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyncWindow {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl SyncWindow {
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+impl fmt::Display for SyncWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.start.format("%H:%M"), self.end.format("%H:%M"))
+    }
+}
+
+/// Parses `HH:MM-HH:MM`; an empty text means no window (any time).
+pub fn parse_window(text: &str) -> Result<Option<SyncWindow>, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Ok(None);
+    }
+    let invalid = || format!("Khung giờ không hợp lệ: '{}' (dạng 22:00-06:00)", text);
+    let (start, end) = text.split_once('-').ok_or_else(invalid)?;
+    let parse = |t: &str| NaiveTime::parse_from_str(t.trim(), "%H:%M").map_err(|_| invalid());
+    let window = SyncWindow { start: parse(start)?, end: parse(end)? };
+    if window.start == window.end {
+        return Err(format!("Khung giờ {} rỗng: giờ bắt đầu trùng giờ kết thúc", text));
+    }
+    Ok(Some(window))
+}
+
+/// Reads `NetworkCostType` from Windows (`Fixed`/`Variable` are metered) or the
+/// `GENERAL.METERED` lines of `nmcli` (`yes`, `yes (guessed)`).
+pub fn is_metered_output(output: &str) -> bool {
+    output.lines().map(str::trim).any(|line| {
+        let value = line.rsplit(':').next().unwrap_or(line).trim();
+        value.eq_ignore_ascii_case("fixed") || value.eq_ignore_ascii_case("variable") || value.starts_with("yes")
+    })
+}
+
+#[cfg(windows)]
+fn query_metered() -> Option<String> {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+    let script = "[void][Windows.Networking.Connectivity.NetworkInformation,Windows.Networking.Connectivity,ContentType=WindowsRuntime]; \
+                  $p = [Windows.Networking.Connectivity.NetworkInformation]::GetInternetConnectionProfile(); \
+                  if ($p) { $p.GetConnectionCost().NetworkCostType }";
+    let output = std::process::Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", script])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .ok()?;
+    Some(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+#[cfg(not(windows))]
+fn query_metered() -> Option<String> {
+    let output = std::process::Command::new("nmcli").args(["-t", "-f", "GENERAL.METERED", "device", "show"]).output().ok()?;
+    Some(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+static METERED: Lazy<Mutex<Option<(Instant, bool)>>> = Lazy::new(|| Mutex::new(None));
+
+/// Whether the current connection is metered. Unknown (no OS support) counts as not metered.
+pub async fn is_metered() -> bool {
+    if let Some((checked, metered)) = *METERED.lock().unwrap()
+        && checked.elapsed() < METERED_CACHE
+    {
+        return metered;
+    }
+    let metered = tokio::task::spawn_blocking(query_metered)
+        .await
+        .ok()
+        .flatten()
+        .is_some_and(|output| is_metered_output(&output));
+    *METERED.lock().unwrap() = Some((Instant::now(), metered));
+    metered
+}
+
+/// Why queued uploads must wait right now, or `None` when they may run.
+pub async fn blocked_reason() -> Option<String> {
+    let options = crate::config::load_config().sync_options;
+    if let Ok(Some(window)) = parse_window(&options.sync_window)
+        && !window.contains(Local::now().time())
+    {
+        return Some(format!("Ngoài khung giờ sync {}", window));
+    }
+    if options.pause_on_metered && is_metered().await {
+        return Some("Mạng đang tính phí theo dung lượng (metered)".to_string());
+    }
+    None
+}
+
+/// Waits until the queue may upload, reporting the pause once through `status`.
+pub async fn wait_until_open(status: Option<&StatusFn>) {
+    let mut paused = false;
+    while let Some(reason) = blocked_reason().await {
+        if !paused {
+            info!("Queue paused: {}", reason);
+            if let Some(status) = status {
+                status(format!("{}, tạm dừng hàng đợi...", reason), crate::progress::INDETERMINATE, false);
+            }
+            paused = true;
+        }
+        tokio::time::sleep(RECHECK_INTERVAL).await;
+    }
+    if paused {
+        info!("Queue resumed: inside the sync window");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(h: u32, m: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(h, m, 0).unwrap()
+    }
+
+    #[test]
+    fn test_window_wraps_midnight() {
+        assert_eq!(parse_window("  "), Ok(None));
+        assert!(parse_window("22:00").is_err());
+        assert!(parse_window("25:00-06:00").is_err());
+        assert!(parse_window("06:00-06:00").is_err());
+
+        let night = parse_window("22:00-06:00").unwrap().unwrap();
+        assert_eq!(night.to_string(), "22:00-06:00");
+        assert!(night.contains(at(23, 30)));
+        assert!(night.contains(at(5, 59)));
+        assert!(!night.contains(at(6, 0)));
+        assert!(!night.contains(at(12, 0)));
+
+        let lunch = parse_window("12:00 - 13:30").unwrap().unwrap();
+        assert!(lunch.contains(at(12, 45)));
+        assert!(!lunch.contains(at(13, 30)));
+    }
+
+    #[test]
+    fn test_metered_output() {
+        assert!(is_metered_output("Variable\r\n"));
+        assert!(is_metered_output("Fixed"));
+        assert!(!is_metered_output("Unrestricted"));
+        assert!(is_metered_output("GENERAL.METERED:no\nGENERAL.METERED:yes (guessed)\n"));
+        assert!(!is_metered_output("GENERAL.METERED:no\nGENERAL.METERED:unknown\n"));
+        assert!(!is_metered_output(""));
+    }
+}
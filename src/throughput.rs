@@ -0,0 +1,78 @@
+use once_cell::sync::Lazy;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Seconds of history kept for the throughput chart.
+pub const WINDOW_SECS: usize = 60;
+
+static BYTES: AtomicU64 = AtomicU64::new(0);
+static FILES: AtomicU64 = AtomicU64::new(0);
+static HISTORY: Lazy<Mutex<History>> = Lazy::new(|| Mutex::new(History::default()));
+
+/// Counts one finished upload. Bytes are credited when the file completes.
+pub fn record(bytes: u64) {
+    BYTES.fetch_add(bytes, Ordering::Relaxed);
+    FILES.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Per-second samples of the last [`WINDOW_SECS`] seconds, oldest first.
+#[derive(Debug, Default, Clone)]
+pub struct History {
+    pub mb_per_sec: VecDeque<f32>,
+    pub files_per_sec: VecDeque<f32>,
+}
+
+impl History {
+    fn push(&mut self, bytes: u64, files: u64) {
+        self.mb_per_sec.push_back(bytes as f32 / (1024.0 * 1024.0));
+        self.files_per_sec.push_back(files as f32);
+        while self.mb_per_sec.len() > WINDOW_SECS {
+            self.mb_per_sec.pop_front();
+            self.files_per_sec.pop_front();
+        }
+    }
+
+    /// True if anything was uploaded within the window.
+    pub fn is_active(&self) -> bool {
+        self.files_per_sec.iter().any(|f| *f > 0.0)
+    }
+}
+
+/// Moves the counters accumulated since the last call into the history. Call once per second.
+pub fn sample() -> History {
+    let bytes = BYTES.swap(0, Ordering::Relaxed);
+    let files = FILES.swap(0, Ordering::Relaxed);
+    let mut history = HISTORY.lock().unwrap();
+    history.push(bytes, files);
+    history.clone()
+}
+
+/// Scales samples to 0..1 against their peak, padded on the left to a full window.
+pub fn normalized(samples: &VecDeque<f32>) -> Vec<f32> {
+    let peak = samples.iter().cloned().fold(0.0f32, f32::max);
+    let mut bars = vec![0.0; WINDOW_SECS.saturating_sub(samples.len())];
+    bars.extend(samples.iter().map(|v| if peak > 0.0 { v / peak } else { 0.0 }));
+    bars
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_window() {
+        let mut history = History::default();
+        assert!(!history.is_active());
+        for i in 0..(WINDOW_SECS as u64 + 5) {
+            history.push(i * 1024 * 1024, i % 2);
+        }
+        assert_eq!(history.mb_per_sec.len(), WINDOW_SECS);
+        assert_eq!(history.mb_per_sec.front(), Some(&5.0));
+        assert!(history.is_active());
+
+        let bars = normalized(&VecDeque::from(vec![1.0, 4.0]));
+        assert_eq!(bars.len(), WINDOW_SECS);
+        assert_eq!(&bars[WINDOW_SECS - 2..], &[0.25, 1.0]);
+    }
+}
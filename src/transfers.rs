@@ -0,0 +1,198 @@
+use once_cell::sync::Lazy;
+use std::collections::BTreeSet;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Live table of the files in the current upload set, read row by row by the UI model
+/// so only visible rows are ever converted.
+static TABLE: Lazy<Mutex<Table>> = Lazy::new(|| Mutex::new(Table::default()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferState {
+    Queued,
+    Uploading(Instant),
+    Done(Duration),
+    Failed,
+}
+
+#[derive(Debug, Clone)]
+pub struct Transfer {
+    pub name: String,
+    pub size: u64,
+    pub state: TransferState,
+}
+
+impl Transfer {
+    pub fn state_label(&self) -> &'static str {
+        match self.state {
+            TransferState::Queued => "Chờ",
+            TransferState::Uploading(_) => "Đang upload",
+            TransferState::Done(_) => "Xong",
+            TransferState::Failed => "Lỗi",
+        }
+    }
+
+    /// Average speed for finished files, elapsed time for in-flight ones.
+    pub fn speed_label(&self) -> String {
+        match self.state {
+            TransferState::Uploading(started) => format!("{}s", started.elapsed().as_secs()),
+            TransferState::Done(took) if took.as_secs_f64() > 0.0 => {
+                format!("{}/s", crate::utils::format_size((self.size as f64 / took.as_secs_f64()) as u64))
+            }
+            _ => String::new(),
+        }
+    }
+}
+
+/// What changed since the UI last looked.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Changes {
+    None,
+    /// Rows were added or removed
+    Reset,
+    Rows(Vec<usize>),
+}
+
+#[derive(Default)]
+struct Table {
+    rows: Vec<Transfer>,
+    dirty: BTreeSet<usize>,
+    uploading: BTreeSet<usize>,
+    reset: bool,
+}
+
+/// Adds a sync's files as queued rows and returns the index of the first one. The previous
+/// upload set is cleared unless another sync (e.g. a queue job) still has files in it.
+pub fn begin(files: impl IntoIterator<Item = (String, u64)>) -> usize {
+    let mut table = TABLE.lock().unwrap();
+    let busy = table
+        .rows
+        .iter()
+        .any(|r| matches!(r.state, TransferState::Queued | TransferState::Uploading(_)));
+    if !busy {
+        table.rows.clear();
+        table.uploading.clear();
+    }
+    let base = table.rows.len();
+    table.rows.extend(files.into_iter().map(|(name, size)| Transfer { name, size, state: TransferState::Queued }));
+    table.reset = true;
+    base
+}
+
+/// Marks a row as in flight once its upload slot is acquired. The row turns failed if the
+/// returned guard is dropped without [`InFlight::done`] (error or aborted task).
+pub fn start(index: usize) -> InFlight {
+    let mut table = TABLE.lock().unwrap();
+    if let Some(row) = table.rows.get_mut(index) {
+        row.state = TransferState::Uploading(Instant::now());
+        table.uploading.insert(index);
+        table.dirty.insert(index);
+    }
+    InFlight { index, done: false }
+}
+
+pub struct InFlight {
+    index: usize,
+    done: bool,
+}
+
+impl InFlight {
+    pub fn done(mut self) {
+        self.done = true;
+        finish(self.index, true);
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        if !self.done {
+            finish(self.index, false);
+        }
+    }
+}
+
+fn finish(index: usize, ok: bool) {
+    let mut table = TABLE.lock().unwrap();
+    let Some(row) = table.rows.get_mut(index) else { return };
+    row.state = match (ok, row.state) {
+        (true, TransferState::Uploading(started)) => TransferState::Done(started.elapsed()),
+        (true, _) => TransferState::Done(Duration::ZERO),
+        (false, _) => TransferState::Failed,
+    };
+    table.uploading.remove(&index);
+    table.dirty.insert(index);
+}
+
+/// Marks rows still queued or in flight when a sync stopped early as failed.
+pub fn abandon(range: std::ops::Range<usize>) {
+    let mut table = TABLE.lock().unwrap();
+    let end = range.end.min(table.rows.len());
+    for index in range.start..end {
+        if matches!(table.rows[index].state, TransferState::Queued | TransferState::Uploading(_)) {
+            table.rows[index].state = TransferState::Failed;
+            table.uploading.remove(&index);
+            table.dirty.insert(index);
+        }
+    }
+}
+
+/// Changed rows since the last call. In-flight rows always count as changed, since their
+/// elapsed time moves.
+pub fn take_changes() -> Changes {
+    let mut table = TABLE.lock().unwrap();
+    if std::mem::take(&mut table.reset) {
+        table.dirty.clear();
+        return Changes::Reset;
+    }
+    let mut rows = std::mem::take(&mut table.dirty);
+    rows.extend(table.uploading.iter().copied());
+    if rows.is_empty() { Changes::None } else { Changes::Rows(rows.into_iter().collect()) }
+}
+
+pub fn len() -> usize {
+    TABLE.lock().unwrap().rows.len()
+}
+
+pub fn row(index: usize) -> Option<Transfer> {
+    TABLE.lock().unwrap().rows.get(index).cloned()
+}
+
+/// "done/total · uploading · failed" line shown above the table.
+pub fn summary() -> String {
+    let table = TABLE.lock().unwrap();
+    let (mut done, mut uploading, mut failed) = (0, 0, 0);
+    for row in &table.rows {
+        match row.state {
+            TransferState::Done(_) => done += 1,
+            TransferState::Uploading(_) => uploading += 1,
+            TransferState::Failed => failed += 1,
+            TransferState::Queued => {}
+        }
+    }
+    let mut text = format!("{}/{} file xong · {} đang upload", done, table.rows.len(), uploading);
+    if failed > 0 {
+        text.push_str(&format!(" · {} lỗi", failed));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changes() {
+        let base = begin(vec![("a.txt".to_string(), 10), ("b.txt".to_string(), 20)]);
+        assert_eq!(take_changes(), Changes::Reset);
+        let in_flight = start(base + 1);
+        assert_eq!(take_changes(), Changes::Rows(vec![base + 1]));
+        // In-flight rows keep refreshing
+        assert_eq!(take_changes(), Changes::Rows(vec![base + 1]));
+        in_flight.done();
+        assert_eq!(take_changes(), Changes::Rows(vec![base + 1]));
+        assert_eq!(take_changes(), Changes::None);
+        assert_eq!(row(base + 1).unwrap().state_label(), "Xong");
+        abandon(base..base + 2);
+        assert_eq!(row(base).unwrap().state, TransferState::Failed);
+    }
+}
@@ -0,0 +1,687 @@
+use aws_sdk_s3::Client;
+use aws_sdk_s3::primitives::ByteStream;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::task::JoinSet;
+use tracing::{info, warn};
+use walkdir::WalkDir;
+
+use crate::config::ConflictPolicy;
+use crate::diff::{RemoteListing, RemoteObject};
+use crate::encryption::Encryptor;
+use crate::object_store::ObjectStore;
+use crate::progress::StatusFn;
+
+const STATE_FILE_NAME: &str = "two_way_state.json";
+
+/// Local file metadata used to detect changes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct LocalFile {
+    pub size: u64,
+    /// Unix timestamp (seconds).
+    pub modified: i64,
+}
+
+/// Files under a local folder, keyed by forward-slash path relative to that folder.
+pub type LocalListing = BTreeMap<String, LocalFile>;
+
+/// State of a file right after the last two-way sync, used to tell which side changed since.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BaselineEntry {
+    pub size: u64,
+    pub local_modified: i64,
+    pub etag: String,
+}
+
+pub type Baseline = BTreeMap<String, BaselineEntry>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Local,
+    Remote,
+}
+
+/// A file changed on both sides since the last sync.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict {
+    pub key: String,
+    pub local: LocalFile,
+    pub remote: RemoteObject,
+    /// Winning side, or None while waiting for the user (policy `ask`).
+    pub resolution: Option<Side>,
+}
+
+/// What a two-way sync will do. Conflicts are applied according to their resolution.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TwoWayPlan {
+    pub uploads: Vec<String>,
+    pub downloads: Vec<String>,
+    pub conflicts: Vec<Conflict>,
+    /// Files already identical on both sides, with their baseline entry.
+    pub in_sync: Baseline,
+}
+
+impl TwoWayPlan {
+    pub fn unresolved(&self) -> usize {
+        self.conflicts.iter().filter(|c| c.resolution.is_none()).count()
+    }
+
+    /// Applies user choices to conflicts by key.
+    pub fn resolve(&mut self, choices: &HashMap<String, Side>) {
+        for conflict in &mut self.conflicts {
+            if let Some(side) = choices.get(&conflict.key) {
+                conflict.resolution = Some(*side);
+            }
+        }
+    }
+
+    /// Every upload including conflicts resolved to the local side.
+    fn all_uploads(&self) -> Vec<String> {
+        let resolved = self.conflicts.iter().filter(|c| c.resolution == Some(Side::Local));
+        self.uploads.iter().cloned().chain(resolved.map(|c| c.key.clone())).collect()
+    }
+
+    /// Every download including conflicts resolved to the remote side.
+    fn all_downloads(&self) -> Vec<String> {
+        let resolved = self.conflicts.iter().filter(|c| c.resolution == Some(Side::Remote));
+        self.downloads.iter().cloned().chain(resolved.map(|c| c.key.clone())).collect()
+    }
+}
+
+/// Lists every file under `dir` with size and modification time.
+pub fn scan_local(dir: &Path) -> LocalListing {
+    WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| {
+            let rel = e.path().strip_prefix(dir).ok()?.to_string_lossy().replace('\\', "/");
+            let meta = e.metadata().ok()?;
+            Some((rel, local_file_from_meta(&meta)))
+        })
+        .collect()
+}
+
+/// Size and mtime of the file at `path`, or `None` if there is none.
+pub fn local_file_at(path: &Path) -> Option<LocalFile> {
+    std::fs::metadata(path).ok().filter(|m| m.is_file()).map(|m| local_file_from_meta(&m))
+}
+
+fn local_file_from_meta(meta: &std::fs::Metadata) -> LocalFile {
+    let modified = meta
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    LocalFile { size: meta.len(), modified }
+}
+
+/// Hex MD5 of a file, compared with single-part ETags.
+fn file_md5(path: &Path) -> std::io::Result<String> {
+    use md5::{Digest, Md5};
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Md5::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Files with no baseline entry whose content matches the object: equal size and an MD5 equal
+/// to its ETag. Multipart ETags (`...-N`) aren't an MD5 of the content and never match.
+/// Reads the files, so call it off the async runtime.
+pub fn unchanged_without_baseline(dir: &Path, local: &LocalListing, remote: &RemoteListing, baseline: &Baseline) -> HashSet<String> {
+    local
+        .iter()
+        .filter(|(key, _)| !baseline.contains_key(*key))
+        .filter_map(|(key, l)| {
+            let r = remote.get(key)?;
+            if l.size != r.size || r.etag.contains('-') {
+                return None;
+            }
+            let md5 = file_md5(&dir.join(key)).ok()?;
+            (md5 == r.etag.trim_matches('"')).then(|| key.clone())
+        })
+        .collect()
+}
+
+/// Lists the remote side of a two-way sync. At the bucket root the app's own trash, staging and
+/// manifest objects are left out, so they are never downloaded into the folder.
+pub async fn list_remote(store: &dyn ObjectStore, bucket: &str, prefix: &str) -> Result<RemoteListing, String> {
+    let mut listing = crate::diff::list_remote_objects(store, bucket, prefix).await?;
+    if crate::diff::normalize_list_prefix(prefix).is_empty() {
+        listing.retain(|key, _| !crate::s3_client::is_bookkeeping_key(key));
+    }
+    Ok(listing)
+}
+
+/// Decides the direction of every file. Deletions are not propagated: a file missing on
+/// one side is copied from the other.
+/// Without a baseline entry, only files in `unchanged` (checked by content) count as in sync.
+/// Remote keys that would land outside the folder (`..`, absolute paths) are left out.
+pub fn plan_two_way(
+    local: &LocalListing,
+    remote: &RemoteListing,
+    baseline: &Baseline,
+    unchanged: &HashSet<String>,
+    policy: ConflictPolicy,
+) -> TwoWayPlan {
+    let mut plan = TwoWayPlan::default();
+
+    for (key, l) in local {
+        let Some(r) = remote.get(key) else {
+            plan.uploads.push(key.clone());
+            continue;
+        };
+        let (local_changed, remote_changed) = match baseline.get(key) {
+            Some(b) => (l.size != b.size || l.modified != b.local_modified, r.etag != b.etag),
+            None if unchanged.contains(key) => (false, false),
+            None => (true, true),
+        };
+        match (local_changed, remote_changed) {
+            (false, false) => {
+                plan.in_sync.insert(
+                    key.clone(),
+                    BaselineEntry { size: l.size, local_modified: l.modified, etag: r.etag.clone() },
+                );
+            }
+            (true, false) => plan.uploads.push(key.clone()),
+            (false, true) => plan.downloads.push(key.clone()),
+            (true, true) => {
+                let resolution = match policy {
+                    ConflictPolicy::Newest if l.modified >= r.modified_time() => Some(Side::Local),
+                    ConflictPolicy::Newest => Some(Side::Remote),
+                    ConflictPolicy::Local => Some(Side::Local),
+                    ConflictPolicy::Remote => Some(Side::Remote),
+                    ConflictPolicy::Ask => None,
+                };
+                plan.conflicts.push(Conflict {
+                    key: key.clone(),
+                    local: *l,
+                    remote: r.clone(),
+                    resolution,
+                });
+            }
+        }
+    }
+    for key in remote.keys().filter(|k| !local.contains_key(*k)) {
+        match crate::utils::safe_join(Path::new(""), key) {
+            Ok(_) => plan.downloads.push(key.clone()),
+            Err(e) => warn!("{}", e),
+        }
+    }
+
+    plan
+}
+
+/// Reads the stored file mtime of every conflicting object, so Newest compares file times
+/// rather than the upload time. Returns whether anything was found; objects that can't be read
+/// keep their upload time.
+pub async fn fetch_conflict_mtimes(
+    store: &dyn ObjectStore,
+    bucket: &str,
+    prefix: &str,
+    plan: &TwoWayPlan,
+    remote: &mut RemoteListing,
+) -> bool {
+    let prefix = crate::diff::normalize_list_prefix(prefix);
+    let mut found = false;
+    for conflict in &plan.conflicts {
+        match store.head(bucket, &format!("{}{}", prefix, conflict.key)).await {
+            Ok(Some(head)) if head.file_modified.is_some() => {
+                if let Some(object) = remote.get_mut(&conflict.key) {
+                    object.file_modified = head.file_modified;
+                    found = true;
+                }
+            }
+            Ok(_) => {}
+            Err(e) => warn!("HeadObject {}{}: {}", prefix, conflict.key, e),
+        }
+    }
+    found
+}
+
+/// Result of executing a plan.
+#[derive(Debug, Clone, Default)]
+pub struct TwoWayResult {
+    pub uploaded: usize,
+    pub downloaded: usize,
+    /// Archived objects whose restore was started; they are downloaded once available.
+    pub restoring: usize,
+}
+
+enum Transfer {
+    Up(String),
+    Down(String),
+}
+
+/// Executes a plan with no unresolved conflicts and records the new baseline.
+/// Stops at the first failure; files transferred before it stay in the baseline.
+/// Transfer slots come from the shared scheduler through `throttle`.
+#[allow(clippy::too_many_arguments)]
+pub async fn execute_plan(
+    client: Arc<Client>,
+    bucket: &str,
+    prefix: &str,
+    dir: &Path,
+    plan: &TwoWayPlan,
+    status: &StatusFn,
+    throttle: Arc<crate::scheduler::JobThrottle>,
+    on_progress: impl Fn(usize, usize),
+) -> Result<TwoWayResult, String> {
+    if plan.unresolved() > 0 {
+        return Err(format!("Còn {} xung đột chưa xử lý", plan.unresolved()));
+    }
+    let prefix = crate::diff::normalize_list_prefix(prefix);
+    let transfers: Vec<Transfer> = plan
+        .all_uploads()
+        .into_iter()
+        .map(Transfer::Up)
+        .chain(plan.all_downloads().into_iter().map(Transfer::Down))
+        .collect();
+    let total = transfers.len();
+    let encryptor = if !plan.all_uploads().is_empty() && crate::config::load_config().sync_options.client_side_encryption {
+        let passphrase =
+            crate::encryption::passphrase().ok_or("Mã hóa phía client đang bật nhưng chưa nhập passphrase (Sync Options)")?;
+        let encryptor = tokio::task::spawn_blocking(move || Encryptor::new(&passphrase))
+            .await
+            .map_err(|e| format!("Lỗi khởi tạo mã hóa: {}", e))??;
+        Some(Arc::new(encryptor))
+    } else {
+        None
+    };
+
+    let mut set = JoinSet::new();
+    for transfer in transfers {
+        let client = Arc::clone(&client);
+        let throttle = Arc::clone(&throttle);
+        let bucket = bucket.to_string();
+        let prefix = prefix.clone();
+        let dir = dir.to_path_buf();
+        let status = Arc::clone(status);
+        let encryptor = encryptor.clone();
+
+        set.spawn(async move {
+            let _permit = throttle.acquire().await;
+            match transfer {
+                Transfer::Up(rel) => {
+                    upload_file(&client, &bucket, &prefix, &dir, rel, encryptor.as_ref()).await.map(|e| (Side::Local, Some(e)))
+                }
+                Transfer::Down(rel) => download_file(&client, &bucket, &prefix, &dir, rel, &status).await.map(|e| (Side::Remote, e)),
+            }
+        });
+    }
+
+    let state_key = state_key(bucket, &prefix, dir);
+    let mut baseline = load_baseline(&state_key);
+    let mut result = TwoWayResult::default();
+    let mut failure = None;
+    while let Some(res) = set.join_next().await {
+        match res {
+            Ok(Ok((side, transferred))) => {
+                match (side, transferred) {
+                    (_, None) => result.restoring += 1,
+                    (Side::Local, Some((rel, entry))) => {
+                        result.uploaded += 1;
+                        baseline.insert(rel, entry);
+                    }
+                    (Side::Remote, Some((rel, entry))) => {
+                        result.downloaded += 1;
+                        baseline.insert(rel, entry);
+                    }
+                }
+                on_progress(result.uploaded + result.downloaded + result.restoring, total);
+            }
+            Ok(Err(e)) => {
+                failure = Some(e);
+                set.abort_all();
+                break;
+            }
+            Err(e) => {
+                failure = Some(format!("Task lỗi: {}", e));
+                set.abort_all();
+                break;
+            }
+        }
+    }
+
+    // Files that were already identical become part of the baseline too
+    baseline.extend(plan.in_sync.clone());
+    save_baseline(&state_key, baseline);
+
+    match failure {
+        Some(e) => Err(e),
+        None => {
+            info!("Two-way sync {} <-> s3://{}/{}: {} up, {} down", dir.display(), bucket, prefix, result.uploaded, result.downloaded);
+            Ok(result)
+        }
+    }
+}
+
+async fn upload_file(
+    client: &Client,
+    bucket: &str,
+    prefix: &str,
+    dir: &Path,
+    rel: String,
+    encryptor: Option<&Arc<Encryptor>>,
+) -> Result<(String, BaselineEntry), String> {
+    let path = dir.join(&rel);
+    let key = format!("{}{}", prefix, rel);
+    let meta = std::fs::metadata(&path).map_err(|e| format!("Lỗi đọc file {}: {}", path.display(), e))?;
+    let options = crate::config::load_config().sync_options;
+    let content_type = crate::utils::get_mime_type(&path, options.sniff_mime);
+    let mut metadata = crate::file_meta::attribute_metadata(&meta, options.store_ctime);
+    // Stored the same way as one-way uploads, so the download side can decrypt either
+    let (stream, stored_type, _memory_slot) = match encryptor {
+        Some(encryptor) => {
+            let slot = crate::encryption::memory_slot(meta.len(), &key).await?;
+            let data = tokio::fs::read(&path).await.map_err(|e| format!("Lỗi mở file {}: {}", path.display(), e))?;
+            let (data, encryption) = crate::encryption::encrypt_blocking(encryptor, data, content_type, &key).await?;
+            metadata.extend(encryption);
+            (ByteStream::from(data), "application/octet-stream", Some(slot))
+        }
+        None => {
+            let stream = ByteStream::from_path(&path)
+                .await
+                .map_err(|e| format!("Lỗi mở file {}: {}", path.display(), e))?;
+            (stream, content_type, None)
+        }
+    };
+    let resp = client
+        .put_object()
+        .bucket(bucket)
+        .key(&key)
+        .content_type(stored_type)
+        .set_metadata(Some(metadata))
+        .body(stream)
+        .send()
+        .await
+        .map_err(|e| format!("Lỗi upload {}: {}", key, e))?;
+
+    let local = local_file_from_meta(&meta);
+    let etag = resp.e_tag().unwrap_or_default().trim_matches('"').to_string();
+    Ok((rel, BaselineEntry { size: local.size, local_modified: local.modified, etag }))
+}
+
+/// Downloads one object, or starts a restore when it is archived in Glacier / Deep Archive;
+/// the restore tracker downloads it once available. `None` means a restore was started.
+async fn download_file(
+    client: &Arc<Client>,
+    bucket: &str,
+    prefix: &str,
+    dir: &Path,
+    rel: String,
+    status: &StatusFn,
+) -> Result<Option<(String, BaselineEntry)>, String> {
+    let path: PathBuf = crate::utils::safe_join(dir, &rel)?;
+    let key = format!("{}{}", prefix, rel);
+    let etag = match download_object(client, bucket, &key, &path).await {
+        Ok(etag) => etag,
+        Err(DownloadError::Archived) => {
+            let download = crate::restore::RestoreDownload {
+                local_before: local_file_at(&path),
+                path,
+                baseline: Some(BaselineRef { state_key: state_key(bucket, prefix, dir), rel }),
+            };
+            crate::restore::restore_and_track(client, bucket, &key, Some(download), status).await?;
+            return Ok(None);
+        }
+        Err(DownloadError::Other(e)) => return Err(e),
+    };
+    let meta = std::fs::metadata(&path).map_err(|e| format!("Lỗi đọc file {}: {}", path.display(), e))?;
+    let local = local_file_from_meta(&meta);
+    Ok(Some((rel, BaselineEntry { size: local.size, local_modified: local.modified, etag })))
+}
+
+pub enum DownloadError {
+    /// The object is in Glacier / Deep Archive and has to be restored first.
+    Archived,
+    Other(String),
+}
+
+impl From<String> for DownloadError {
+    fn from(e: String) -> Self {
+        DownloadError::Other(e)
+    }
+}
+
+impl From<DownloadError> for String {
+    fn from(e: DownloadError) -> Self {
+        match e {
+            DownloadError::Archived => "Object đang ở Glacier, cần restore trước khi tải".to_string(),
+            DownloadError::Other(e) => e,
+        }
+    }
+}
+
+/// Downloads `key` to `path`, decrypting client-side encrypted objects. Returns the ETag.
+pub async fn download_object(client: &Client, bucket: &str, key: &str, path: &Path) -> Result<String, DownloadError> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .map_err(|e| DownloadError::Other(format!("Không thể tạo thư mục {}: {}", parent.display(), e)))?;
+    }
+    let resp = match client.get_object().bucket(bucket).key(key).send().await {
+        Ok(resp) => resp,
+        Err(e) if e.as_service_error().is_some_and(|s| s.is_invalid_object_state()) => return Err(DownloadError::Archived),
+        Err(e) => return Err(DownloadError::Other(format!("Lỗi tải {}: {}", key, e))),
+    };
+    let etag = resp.e_tag().unwrap_or_default().trim_matches('"').to_string();
+    let stored_metadata = resp.metadata().cloned();
+
+    // Written next to the target and renamed over it, so a failed download leaves the old file intact
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let part = path.with_file_name(format!(".{}.s3sync-part", name));
+    let written = write_body(resp, key, &part).await.and_then(|_| {
+        std::fs::rename(&part, path).map_err(|e| format!("Lỗi ghi file {}: {}", path.display(), e))
+    });
+    if let Err(e) = written {
+        let _ = std::fs::remove_file(&part);
+        return Err(DownloadError::Other(e));
+    }
+    // The content is in place; a timestamp or mode that can't be set isn't worth failing over
+    if let Err(e) = crate::file_meta::restore_attributes(path, stored_metadata.as_ref()) {
+        warn!("{}", e);
+    }
+    Ok(etag)
+}
+
+/// Writes an object body to `path`, decrypting client-side encrypted objects first.
+async fn write_body(resp: aws_sdk_s3::operation::get_object::GetObjectOutput, key: &str, path: &Path) -> Result<(), String> {
+    if let Some(metadata) = resp.metadata().filter(|m| m.contains_key(crate::encryption::META_ALGORITHM)).cloned() {
+        let passphrase = crate::encryption::passphrase().ok_or_else(|| format!("{} đã mã hóa phía client, cần passphrase để tải", key))?;
+        let size = resp.content_length().and_then(|len| u64::try_from(len).ok()).unwrap_or_default();
+        let _memory_slot = crate::encryption::memory_slot(size, key).await?;
+        let data = resp.body.collect().await.map_err(|e| format!("Lỗi tải {}: {}", key, e))?.to_vec();
+        let object_key = key.to_string();
+        let data = tokio::task::spawn_blocking(move || crate::encryption::decrypt(&passphrase, &metadata, data, &object_key))
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| format!("Lỗi giải mã {}: {}", key, e))?;
+        tokio::fs::write(path, data)
+            .await
+            .map_err(|e| format!("Lỗi ghi file {}: {}", path.display(), e))
+    } else {
+        let mut reader = resp.body.into_async_read();
+        let mut file = tokio::fs::File::create(path)
+            .await
+            .map_err(|e| format!("Không thể tạo file {}: {}", path.display(), e))?;
+        tokio::io::copy(&mut reader, &mut file)
+            .await
+            .map_err(|e| format!("Lỗi ghi file {}: {}", path.display(), e))?;
+        file.sync_all().await.map_err(|e| format!("Lỗi ghi file {}: {}", path.display(), e))
+    }
+}
+
+/// A file's entry in one two-way baseline, for downloads that finish after the sync (restores).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BaselineRef {
+    pub state_key: String,
+    pub rel: String,
+}
+
+/// Records a file downloaded after its two-way sync ended as in sync.
+pub fn record_download(target: &BaselineRef, path: &Path, etag: String) {
+    let Some(local) = local_file_at(path) else { return };
+    let mut baseline = load_baseline(&target.state_key);
+    baseline.insert(target.rel.clone(), BaselineEntry { size: local.size, local_modified: local.modified, etag });
+    save_baseline(&target.state_key, baseline);
+}
+
+fn state_key(bucket: &str, prefix: &str, dir: &Path) -> String {
+    format!("{}|{}|{}", bucket, prefix, dir.display())
+}
+
+/// State file lives next to the confy config file.
+fn state_path() -> Option<PathBuf> {
+    crate::config::get_config_path().and_then(|p| p.parent().map(|d| d.join(STATE_FILE_NAME)))
+}
+
+fn load_all_baselines() -> HashMap<String, Baseline> {
+    state_path()
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+/// Loads the baseline recorded for a local folder / bucket prefix pair.
+fn load_baseline(state_key: &str) -> Baseline {
+    load_all_baselines().remove(state_key).unwrap_or_default()
+}
+
+/// Convenience wrapper building the state key from its parts.
+pub fn load_baseline_for(bucket: &str, prefix: &str, dir: &Path) -> Baseline {
+    load_baseline(&state_key(bucket, &crate::diff::normalize_list_prefix(prefix), dir))
+}
+
+fn save_baseline(state_key: &str, baseline: Baseline) {
+    let Some(path) = state_path() else { return };
+    let mut all = load_all_baselines();
+    all.insert(state_key.to_string(), baseline);
+    let result = serde_json::to_string(&all)
+        .map_err(|e| e.to_string())
+        .and_then(|text| std::fs::write(&path, text).map_err(|e| e.to_string()));
+    if let Err(e) = result {
+        warn!("Không thể ghi trạng thái two-way sync {:?}: {}", path, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local(size: u64, modified: i64) -> LocalFile {
+        LocalFile { size, modified }
+    }
+
+    fn remote(size: u64, etag: &str, last_modified: i64) -> RemoteObject {
+        RemoteObject {
+            size,
+            etag: etag.to_string(),
+            last_modified,
+            ..Default::default()
+        }
+    }
+
+    fn base(size: u64, local_modified: i64, etag: &str) -> BaselineEntry {
+        BaselineEntry { size, local_modified, etag: etag.to_string() }
+    }
+
+    #[test]
+    fn test_plan_two_way() {
+        let mut l = LocalListing::new();
+        l.insert("same.txt".into(), local(1, 100));
+        l.insert("local_new.txt".into(), local(1, 100));
+        l.insert("local_edit.txt".into(), local(2, 200));
+        l.insert("remote_edit.txt".into(), local(1, 100));
+        l.insert("both_edit.txt".into(), local(3, 300));
+
+        let mut r = RemoteListing::new();
+        r.insert("same.txt".into(), remote(1, "a", 50));
+        r.insert("remote_new.txt".into(), remote(1, "b", 50));
+        r.insert("local_edit.txt".into(), remote(1, "c", 50));
+        r.insert("remote_edit.txt".into(), remote(4, "d2", 150));
+        r.insert("both_edit.txt".into(), remote(5, "e2", 400));
+
+        let mut b = Baseline::new();
+        b.insert("same.txt".into(), base(1, 100, "a"));
+        b.insert("local_edit.txt".into(), base(1, 100, "c"));
+        b.insert("remote_edit.txt".into(), base(1, 100, "d"));
+        b.insert("both_edit.txt".into(), base(1, 100, "e"));
+
+        let none = HashSet::new();
+        let plan = plan_two_way(&l, &r, &b, &none, ConflictPolicy::Newest);
+        assert_eq!(plan.in_sync.len(), 1);
+        assert_eq!(plan.uploads, vec!["local_edit.txt", "local_new.txt"]);
+        assert_eq!(plan.downloads, vec!["remote_edit.txt", "remote_new.txt"]);
+        assert_eq!(plan.conflicts.len(), 1);
+        assert_eq!(plan.conflicts[0].resolution, Some(Side::Remote));
+
+        let mut plan = plan_two_way(&l, &r, &b, &none, ConflictPolicy::Ask);
+        assert_eq!(plan.unresolved(), 1);
+        plan.resolve(&HashMap::from([("both_edit.txt".to_string(), Side::Local)]));
+        assert_eq!(plan.unresolved(), 0);
+        assert!(plan.all_uploads().contains(&"both_edit.txt".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_newest_uses_stored_mtime() {
+        let l = LocalListing::from([("report.txt".to_string(), local(3, 300))]);
+        let b = Baseline::from([("report.txt".to_string(), base(1, 100, "e"))]);
+        // Uploaded after the local edit, from a file that was last changed before it
+        let mut r = RemoteListing::from([("report.txt".to_string(), remote(5, "e2", 400))]);
+        let none = HashSet::new();
+        let plan = plan_two_way(&l, &r, &b, &none, ConflictPolicy::Newest);
+        assert_eq!(plan.conflicts[0].resolution, Some(Side::Remote));
+
+        // Objects without the metadata keep the upload time
+        let store = crate::object_store::MemoryStore::default();
+        store.put("b", "docs/report.txt", b"remote".to_vec()).await.unwrap();
+        assert!(!fetch_conflict_mtimes(&store, "b", "docs", &plan, &mut r).await);
+
+        r.get_mut("report.txt").unwrap().file_modified = Some(200);
+        assert_eq!(r["report.txt"].modified_time(), 200);
+        assert_eq!(plan_two_way(&l, &r, &b, &none, ConflictPolicy::Newest).conflicts[0].resolution, Some(Side::Local));
+    }
+
+    #[tokio::test]
+    async fn test_root_listing_skips_bookkeeping() {
+        let store = crate::object_store::MemoryStore::default();
+        for key in ["site/index.html", ".trash/20240101_000000/site/index.html", ".staging/20240101_000000/a.txt", ".s3sync/manifest.csv"] {
+            store.put("b", key, b"x".to_vec()).await.unwrap();
+        }
+        let root = list_remote(&store, "b", "").await.unwrap();
+        assert_eq!(root.keys().collect::<Vec<_>>(), vec!["site/index.html"]);
+
+        let plan = plan_two_way(&LocalListing::new(), &root, &Baseline::new(), &HashSet::new(), ConflictPolicy::Ask);
+        assert_eq!(plan.downloads, vec!["site/index.html"]);
+        // Asked for explicitly, the trash is listed like any other prefix
+        assert_eq!(list_remote(&store, "b", ".trash").await.unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_no_baseline_needs_matching_content() {
+        let dir = std::env::temp_dir().join(format!("s3sync_two_way_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("same.txt"), b"hello").unwrap();
+        std::fs::write(dir.join("edited.txt"), b"HELLO").unwrap();
+        let l = scan_local(&dir);
+
+        let mut r = RemoteListing::new();
+        // MD5 of "hello"
+        r.insert("same.txt".into(), remote(5, "5d41402abc4b2a76b9719d911017c592", 50));
+        r.insert("edited.txt".into(), remote(5, "5d41402abc4b2a76b9719d911017c592", 50));
+        r.insert("../outside.txt".into(), remote(1, "x", 50));
+
+        let unchanged = unchanged_without_baseline(&dir, &l, &r, &Baseline::new());
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(unchanged, HashSet::from(["same.txt".to_string()]));
+
+        let plan = plan_two_way(&l, &r, &Baseline::new(), &unchanged, ConflictPolicy::Ask);
+        assert_eq!(plan.in_sync.keys().collect::<Vec<_>>(), vec!["same.txt"]);
+        assert_eq!(plan.unresolved(), 1);
+        assert!(plan.downloads.is_empty());
+    }
+}
@@ -1,38 +1,110 @@
 use crate::*;
+use crate::app_state::AppState;
+use crate::progress::{ProgressSink, ProgressUpdate, StatusFn};
 use aws_sdk_s3::config::Credentials;
 use once_cell::sync::Lazy;
 use slint::{Model, ModelRc, VecModel};
 use std::rc::Rc;
 use tokio::time;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 static BUCKET_NAME_REGEX: Lazy<regex::Regex> =
     Lazy::new(|| regex::Regex::new(r"^[a-z0-9][a-z0-9.-]*[a-z0-9]$").unwrap());
 
 static REGION_NAME_REGEX: Lazy<regex::Regex> = Lazy::new(|| regex::Regex::new(r"^[a-z0-9-]+$").unwrap());
 
-use crate::s3_client::{create_s3_client, sync_to_s3, test_bucket_access, find_best_s3_prefix, get_preview_prefix};
+use crate::s3_client::{create_s3_client, sync_to_s3, test_bucket_access, find_best_s3_prefix, get_preview_prefix, PrefixSource};
+
+/// AWS connection parameters captured from the UI.
+#[derive(Clone)]
+pub struct AwsParams {
+    pub acc_key: String,
+    pub sec_key: String,
+    pub sess_token: String,
+    pub region: String,
+    pub bucket: String,
+}
+
+impl AwsParams {
+    pub fn from_ui(ui: &AppWindow) -> Self {
+        Self {
+            acc_key: ui.get_access_key().to_string(),
+            sec_key: ui.get_secret_key().to_string(),
+            sess_token: ui.get_session_token().to_string(),
+            region: ui.get_region().to_string(),
+            bucket: ui.get_bucket_name().to_string(),
+        }
+    }
+
+    /// Returns a user-facing error if credentials or bucket are missing/invalid.
+    pub fn validate(&self) -> Option<String> {
+        crate::utils::validate_credentials(&self.acc_key, &self.sec_key, &self.sess_token, &self.bucket)
+    }
+
+    pub async fn create_client(&self) -> Result<aws_sdk_s3::Client, aws_sdk_s3::Error> {
+        create_s3_client(
+            self.acc_key.clone(),
+            self.sec_key.clone(),
+            if self.sess_token.is_empty() { None } else { Some(self.sess_token.clone()) },
+            self.region.clone(),
+        )
+        .await
+    }
+}
+
+/// Shows sync engine progress in the status bar of the main window.
+struct UiProgress(slint::Weak<AppWindow>);
+
+impl ProgressSink for UiProgress {
+    fn report(&self, update: ProgressUpdate) {
+        update_status(&self.0, update.text, update.progress, update.is_error);
+    }
+}
+
+/// Updates the UI status text and progress bar.
+/// Must be called from within an event loop.
+pub fn update_status(
+    ui_handle: &slint::Weak<AppWindow>,
+    text: String,
+    progress: f32,
+    is_error: bool,
+) {
+    let text = crate::redact::redact(&text).into_owned();
+    let _ = ui_handle.upgrade_in_event_loop(move |ui| {
+        ui.set_status_text(text.into());
+        ui.set_progress(progress);
+        ui.set_is_error(is_error);
+    });
+}
+
+/// Progress callback for background sync tasks that reports to the main window.
+/// Updates are coalesced so concurrent uploads don't flood the event loop.
+pub fn status_fn(ui_handle: &slint::Weak<AppWindow>) -> StatusFn {
+    crate::progress::coalesce(
+        crate::progress::sink_fn(UiProgress(ui_handle.clone())),
+        crate::progress::UI_UPDATE_INTERVAL,
+    )
+}
 
 /// Sets up the test access handler for the UI.
-pub fn setup_test_access_handler(ui: &AppWindow) {
+pub fn setup_test_access_handler(ui: &AppWindow, state: &AppState) {
     ui.on_test_access({
         let ui_handle = ui.as_weak();
+        let state = state.clone();
         move |acc_key, sec_key, sess_token, region, bucket| {
             let bucket_name = bucket.to_string();
             let region_str = region.to_string();
 
             // Save selected bucket and region to config
-            let mut config = crate::config::load_config();
-            config.selected_bucket = bucket_name.clone();
-            config.selected_region = region_str.clone();
-            if let Err(e) = crate::config::save_config(&config) {
-                error!("Failed to save config: {:?}", e);
-            }
+            state.update(|config| {
+                config.selected_bucket = bucket_name.clone();
+                config.selected_region = region_str.clone();
+            });
 
             // Validate inputs
-            if let Some(err) = crate::utils::validate_credentials(&acc_key, &sec_key, &bucket_name)
+            if let Some(err) = crate::utils::validate_credentials(&acc_key, &sec_key, &sess_token, &bucket_name)
             {
-                crate::utils::update_status(&ui_handle, err.clone(), 0.0, true);
+                update_status(&ui_handle, err.clone(), 0.0, true);
                 let _ = ui_handle.upgrade_in_event_loop(|ui| ui.set_test_access_error(err.into()));
                 return;
             }
@@ -52,13 +124,17 @@ pub fn setup_test_access_handler(ui: &AppWindow) {
             let ui_handle_cloned = ui_handle.clone();
             
             tokio::spawn(async move {
-                crate::utils::update_status(
+                update_status(
                     &ui_handle_cloned,
                     "Đang kiểm tra kết nối...".to_string(),
                     0.1,
                     false,
                 );
-                let _ = ui_handle_cloned.upgrade_in_event_loop(|ui| ui.set_test_access_error("".into()));
+                let _ = ui_handle_cloned.upgrade_in_event_loop(|ui| {
+                    ui.set_test_access_error("".into());
+                    ui.set_caller_identity("".into());
+                });
+                let sts_token = if sess_token.is_empty() { None } else { Some(sess_token.to_string()) };
                 match create_s3_client(
                     acc_key.to_string(),
                     sec_key.to_string(),
@@ -67,18 +143,31 @@ pub fn setup_test_access_handler(ui: &AppWindow) {
                     } else {
                         Some(sess_token.to_string())
                     },
-                    region_str,
+                    region_str.clone(),
                 )
                 .await
                 {
                     Ok(client) => match test_bucket_access(&client, &bucket_name).await {
-                        Ok(_) => {
-                            info!("Test Access thành công: {}", bucket_name);
+                        Ok(latency) => {
+                            let latency_text = format!("độ trễ tới {}: {} ms", region_str, latency.as_millis());
+                            info!("Test Access thành công: {} ({})", bucket_name, latency_text);
+                            // Show who we are connected as, so the wrong account is spotted before syncing
+                            match crate::s3_client::caller_identity(acc_key.to_string(), sec_key.to_string(), sts_token, region_str).await {
+                                Ok(identity) => {
+                                    info!("Caller identity: {}", identity);
+                                    let text = format!(
+                                        "Account: {} · User: {}\nARN: {}",
+                                        identity.account, identity.user_name, identity.arn
+                                    );
+                                    let _ = ui_handle_cloned.upgrade_in_event_loop(move |ui| ui.set_caller_identity(text.into()));
+                                }
+                                Err(e) => warn!("{}", e),
+                            }
                             let _ = ui_handle_cloned
                                 .upgrade_in_event_loop(|ui| ui.set_show_config(false));
-                            crate::utils::update_status(
+                            update_status(
                                 &ui_handle_cloned,
-                                "Kết nối thành công!".to_string(),
+                                format!("Kết nối thành công! ({})", latency_text),
                                 1.0,
                                 false,
                             );
@@ -86,24 +175,160 @@ pub fn setup_test_access_handler(ui: &AppWindow) {
                         }
                         Err(e) => {
                             error!("Test Access thất bại: {:?}", e);
-                            crate::utils::update_status(
-                                &ui_handle_cloned,
-                                format!("Lỗi: {}", e),
-                                0.0,
-                                true,
-                            );
-                            let _ = ui_handle_cloned.upgrade_in_event_loop(move |ui| ui.set_test_access_error(format!("Lỗi: {}", e).into()));
+                            let message = e.to_string();
+                            update_status(&ui_handle_cloned, message.clone(), 0.0, true);
+                            let _ = ui_handle_cloned.upgrade_in_event_loop(move |ui| ui.set_test_access_error(crate::redact::redact(&message).as_ref().into()));
                         }
                     },
                     Err(e) => {
                         error!("Failed to create S3 client: {:?}", e);
-                        crate::utils::update_status(
+                        update_status(
                             &ui_handle_cloned,
                             format!("Lỗi tạo client: {}", e),
                             0.0,
                             true,
                         );
-                        let _ = ui_handle_cloned.upgrade_in_event_loop(move |ui| ui.set_test_access_error(format!("Lỗi tạo client: {}", e).into()));
+                        let _ = ui_handle_cloned.upgrade_in_event_loop(move |ui| ui.set_test_access_error(crate::redact::redact(&format!("Lỗi tạo client: {}", e)).as_ref().into()));
+                    }
+                }
+            });
+        }
+    });
+}
+
+/// Computes S3 paths for `paths` (BasePath, then existing S3 structure unless Smart Match is
+/// off, then a preview guess) and appends them to the mapping list.
+fn add_local_paths(ui: &AppWindow, state: &AppState, paths: Vec<std::path::PathBuf>) {
+    // Get current AWS config
+    let acc_key = ui.get_access_key().to_string();
+    let sec_key = ui.get_secret_key().to_string();
+    let sess_token = ui.get_session_token().to_string();
+    let region = ui.get_region().to_string();
+    let bucket = ui.get_bucket_name().to_string();
+    let s3_base_path = ui.get_s3_base_path().to_string();
+    let (defaults, prefix_rules, flatten_paths) = {
+        let config = state.read();
+        (
+            config.bucket_defaults.get(&bucket).cloned().unwrap_or_default(),
+            config.prefix_rules.clone(),
+            config.sync_options.flatten_paths.clone(),
+        )
+    };
+    let smart_match = crate::config::settings().smart_match;
+
+    ui.set_is_selecting_folder(true);
+    let ui_handle_task = ui.as_weak();
+    let state = state.clone();
+    tokio::spawn(async move {
+        let mut results = Vec::new();
+        let base_path_buf = std::path::PathBuf::from(&s3_base_path);
+
+        // Try to create S3 client for accurate calculation
+        let client = if smart_match && !acc_key.is_empty() && !sec_key.is_empty() && !bucket.is_empty() {
+            match create_s3_client(
+                acc_key,
+                sec_key,
+                if sess_token.is_empty() { None } else { Some(sess_token) },
+                region
+            ).await {
+                Ok(c) => Some(std::sync::Arc::new(c) as std::sync::Arc<dyn crate::object_store::ObjectStore>),
+                Err(e) => {
+                    error!("Failed to create S3 client for path preview: {:?}", e);
+                    update_status(&ui_handle_task, "Cảnh báo: Không thể kết nối S3, sử dụng đường dẫn xem trước".to_string(), 0.0, false);
+                    time::sleep(time::Duration::from_secs(2)).await; // Show message briefly
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        let cache = &*PREFIX_CACHE;
+
+        for p in paths {
+            let local_path = p.to_string_lossy().to_string();
+            
+            let (s3_path, source) = if !base_path_buf.as_os_str().is_empty() && p.starts_with(&base_path_buf) {
+                let rel = p.strip_prefix(&base_path_buf).unwrap_or(&p);
+                let rel_str = rel.to_string_lossy().replace('\\', "/");
+                let s3_path = if rel_str.is_empty() {
+                    p.file_name().unwrap_or_default().to_string_lossy().to_string()
+                } else {
+                    rel_str
+                };
+                (s3_path, PrefixSource::BasePath)
+            } else if let Some(ref c) = client {
+                find_best_s3_prefix(std::sync::Arc::clone(c), &bucket, p.as_path(), &prefix_rules, defaults.preview_depth, cache).await
+            } else {
+                (get_preview_prefix(&p, &prefix_rules, defaults.preview_depth), PrefixSource::Preview)
+            };
+            let s3_path = if defaults.is_empty() {
+                s3_path
+            } else {
+                let today = chrono::Local::now().date_naive();
+                crate::utils::apply_bucket_defaults(&defaults.base_prefix, &defaults.key_template, &s3_path, &p, today)
+            };
+
+            results.push(PathItem {
+                flatten: flatten_paths.contains(&local_path),
+                local_path: local_path.into(),
+                s3_path: s3_path.into(),
+                enabled: true,
+                stats: "đang tính...".into(),
+                conflict: "".into(),
+                origin: source.label().into(),
+                origin_guess: source.is_guess(),
+            });
+        }
+
+        let paths = results.iter().map(|item| item.local_path.to_string()).collect();
+        let ui_handle_stats = ui_handle_task.clone();
+        let _ = ui_handle_task.upgrade_in_event_loop(move |ui| {
+            let mut current_items: Vec<PathItem> = ui.get_local_paths().iter().collect();
+            current_items.extend(results);
+            let model = Rc::new(VecModel::from(current_items));
+            ui.set_local_paths(ModelRc::from(model));
+            refresh_path_collisions(&ui);
+            ui.set_is_selecting_folder(false);
+        });
+        compute_path_stats(ui_handle_stats, &state, paths);
+    });
+}
+
+/// Marks rows whose S3 path is also targeted by another enabled row, since their files
+/// would overwrite each other.
+fn refresh_path_collisions(ui: &AppWindow) {
+    let model = ui.get_local_paths();
+    let items: Vec<PathItem> = model.iter().collect();
+    let mappings: Vec<(bool, &str)> = items.iter().map(|item| (item.enabled, item.s3_path.as_str())).collect();
+    for (i, other) in crate::utils::find_path_collisions(&mappings).into_iter().enumerate() {
+        let conflict: slint::SharedString = match other {
+            Some(j) => format!("⚠ Trùng đường dẫn S3 với dòng {}", j + 1).into(),
+            None => "".into(),
+        };
+        if items[i].conflict != conflict {
+            let mut item = items[i].clone();
+            item.conflict = conflict;
+            model.set_row_data(i, item);
+        }
+    }
+}
+
+/// Counts files and size (after filters) for each path in the background and fills in the
+/// matching rows' stats as each one finishes.
+fn compute_path_stats(ui_handle: slint::Weak<AppWindow>, state: &AppState, paths: Vec<String>) {
+    let filter_config = crate::config::effective_filter(state.read().filter_config.clone());
+    tokio::task::spawn_blocking(move || {
+        for path in paths {
+            let label = crate::utils::mapping_stats_label(std::path::Path::new(&path), &filter_config);
+            let _ = ui_handle.upgrade_in_event_loop(move |ui| {
+                let model = ui.get_local_paths();
+                for i in 0..model.row_count() {
+                    if let Some(mut item) = model.row_data(i)
+                        && item.local_path == path.as_str()
+                    {
+                        item.stats = label.clone().into();
+                        model.set_row_data(i, item);
                     }
                 }
             });
@@ -111,188 +336,333 @@ pub fn setup_test_access_handler(ui: &AppWindow) {
     });
 }
 
+/// Set while a file dialog is open. The dialogs no longer block the window, so this keeps a
+/// second click from stacking another one on top.
+static FILE_DIALOG_OPEN: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+/// Shows a file dialog without blocking the event loop. `open` builds the dialog on the UI
+/// thread (macOS requires it), the runtime awaits it, and `on_done` gets the result (`None` if
+/// cancelled) back on the event loop. Ignored while another dialog is open.
+fn spawn_file_dialog<T, F>(ui_handle: &slint::Weak<AppWindow>, open: impl FnOnce() -> F, on_done: impl FnOnce(&AppWindow, Option<T>) + Send + 'static)
+where
+    T: Send + 'static,
+    F: std::future::Future<Output = Option<T>> + Send + 'static,
+{
+    use std::sync::atomic::Ordering;
+    if FILE_DIALOG_OPEN.swap(true, Ordering::AcqRel) {
+        return;
+    }
+    let dialog = open();
+    let ui_handle = ui_handle.clone();
+    tokio::spawn(async move {
+        let picked = dialog.await;
+        FILE_DIALOG_OPEN.store(false, Ordering::Release);
+        let _ = ui_handle.upgrade_in_event_loop(move |ui| on_done(&ui, picked));
+    });
+}
+
+/// Local paths of the entries picked in a file dialog.
+fn handle_paths(handles: Vec<rfd::FileHandle>) -> Vec<std::path::PathBuf> {
+    handles.iter().map(|h| h.path().to_path_buf()).collect()
+}
+
 /// Sets up the folder selection handler.
-pub fn setup_select_folder_handler(ui: &AppWindow) {
+pub fn setup_select_folder_handler(ui: &AppWindow, state: &AppState) {
     ui.on_select_folder({
         let ui_handle = ui.as_weak();
+        let state = state.clone();
         move || {
-            let ui = match ui_handle.upgrade() {
-                Some(ui) => ui,
-                None => return,
-            };
+            let state = state.clone();
+            spawn_file_dialog(&ui_handle, || rfd::AsyncFileDialog::new().pick_folders(), move |ui, picked| {
+                if let Some(handles) = picked {
+                    add_local_paths(ui, &state, handle_paths(handles));
+                }
+            });
+        }
+    });
+}
 
-            // Get current AWS config
-            let acc_key = ui.get_access_key().to_string();
-            let sec_key = ui.get_secret_key().to_string();
-            let sess_token = ui.get_session_token().to_string();
-            let region = ui.get_region().to_string();
-            let bucket = ui.get_bucket_name().to_string();
-            let s3_base_path = ui.get_s3_base_path().to_string();
+/// Adds folders/files passed on the command line (e.g. dropped onto the exe), skipping ones
+/// that don't exist.
+pub fn add_launch_paths(ui: &AppWindow, state: &AppState, paths: Vec<String>) {
+    let (found, missing): (Vec<_>, Vec<_>) = paths.into_iter().map(std::path::PathBuf::from).partition(|p| p.exists());
+    for path in &missing {
+        warn!("Launch path not found: {:?}", path);
+    }
+    if !found.is_empty() {
+        add_local_paths(ui, state, found);
+    }
+}
 
-            let ui_handle_cloned = ui_handle.clone();
-            let _ = ui_handle_cloned.upgrade_in_event_loop(|ui| {
-                ui.set_is_selecting_folder(true);
+/// Brings the window forward for a second launch and adds the paths it was given.
+pub fn handle_second_launch(ui: &AppWindow, state: &AppState, paths: Vec<String>) {
+    ui.window().set_minimized(false);
+    if let Err(e) = ui.window().show() {
+        warn!("Failed to show window: {}", e);
+    }
+    add_launch_paths(ui, state, paths);
+}
+
+/// Sets up the file selection handler.
+pub fn setup_select_files_handler(ui: &AppWindow, state: &AppState) {
+    ui.on_select_files({
+        let ui_handle = ui.as_weak();
+        let state = state.clone();
+        move || {
+            let state = state.clone();
+            spawn_file_dialog(&ui_handle, || rfd::AsyncFileDialog::new().pick_files(), move |ui, picked| {
+                if let Some(handles) = picked {
+                    add_local_paths(ui, &state, handle_paths(handles));
+                }
             });
+        }
+    });
+}
 
-            if let Some(paths) = rfd::FileDialog::new().pick_folders() {
-                let ui_handle_task = ui_handle.clone();
-                tokio::spawn(async move {
-                    let mut results = Vec::new();
-                    let base_path_buf = std::path::PathBuf::from(&s3_base_path);
-
-                    // Try to create S3 client for accurate calculation
-                    let client = if !acc_key.is_empty() && !sec_key.is_empty() && !bucket.is_empty() {
-                        match create_s3_client(
-                            acc_key,
-                            sec_key,
-                            if sess_token.is_empty() { None } else { Some(sess_token) },
-                            region
-                        ).await {
-                            Ok(c) => Some(c),
-                            Err(e) => {
-                                error!("Failed to create S3 client for path preview: {:?}", e);
-                                crate::utils::update_status(&ui_handle_task, "Cảnh báo: Không thể kết nối S3, sử dụng đường dẫn xem trước".to_string(), 0.0, false);
-                                time::sleep(time::Duration::from_secs(2)).await; // Show message briefly
-                                None
-                            }
-                        }
-                    } else {
-                        None
-                    };
+/// Most rejected lines named in the paste status message.
+const PASTE_REJECTS_SHOWN: usize = 3;
 
-                    let cache: crate::s3_client::GlobalPrefixCache = std::sync::Arc::new(tokio::sync::Mutex::new(std::collections::HashMap::new()));
-
-                    for p in paths {
-                        let local_path = p.to_string_lossy().to_string();
-                        
-                        let s3_path = if !base_path_buf.as_os_str().is_empty() && p.starts_with(&base_path_buf) {
-                            let rel = p.strip_prefix(&base_path_buf).unwrap_or(&p);
-                            let rel_str = rel.to_string_lossy().replace('\\', "/");
-                            if rel_str.is_empty() {
-                                p.file_name().unwrap_or_default().to_string_lossy().to_string()
-                            } else {
-                                rel_str
-                            }
-                        } else if let Some(ref c) = client {
-                            find_best_s3_prefix(c, &bucket, p.as_path(), &cache).await
-                        } else {
-                            get_preview_prefix(&p)
-                        };
+/// Sets up pasting a list of absolute paths (one per line, e.g. from a ticket) from the
+/// clipboard into the mapping list. Relative, missing and already listed paths are skipped.
+pub fn setup_paste_paths_handler(ui: &AppWindow, state: &AppState) {
+    ui.on_paste_paths({
+        let ui_handle = ui.as_weak();
+        let state = state.clone();
+        move || {
+            use copypasta::ClipboardProvider;
+            let Some(ui) = ui_handle.upgrade() else { return; };
+            let text = match copypasta::ClipboardContext::new().and_then(|mut clipboard| clipboard.get_contents()) {
+                Ok(text) => text,
+                Err(e) => {
+                    error!("Failed to read clipboard: {}", e);
+                    update_status(&ui_handle, format!("Không đọc được clipboard: {}", e), 0.0, true);
+                    return;
+                }
+            };
 
-                        results.push(PathItem {
-                            local_path: local_path.into(),
-                            s3_path: s3_path.into(),
-                        });
-                    }
+            let listed: std::collections::HashSet<std::path::PathBuf> =
+                ui.get_local_paths().iter().map(|item| std::path::PathBuf::from(item.local_path.as_str())).collect();
+            let (mut valid, mut rejected, mut duplicates) = (Vec::new(), Vec::new(), 0);
+            for path in crate::utils::parse_pasted_paths(&text) {
+                if listed.contains(&path) {
+                    duplicates += 1;
+                } else if path.is_absolute() && path.exists() {
+                    valid.push(path);
+                } else {
+                    rejected.push(path.to_string_lossy().to_string());
+                }
+            }
 
-                    let _ = ui_handle_task.upgrade_in_event_loop(move |ui| {
-                        let mut current_items: Vec<PathItem> = ui.get_local_paths().iter().collect();
-                        current_items.extend(results);
-                        let model = Rc::new(VecModel::from(current_items));
-                        ui.set_local_paths(ModelRc::from(model));
-                        ui.set_is_selecting_folder(false);
-                    });
-                });
+            let mut message = if valid.is_empty() {
+                "Clipboard không có đường dẫn hợp lệ".to_string()
             } else {
-                let _ = ui_handle_cloned.upgrade_in_event_loop(|ui| {
-                    ui.set_is_selecting_folder(false);
-                });
+                format!("Đã dán {} đường dẫn", valid.len())
+            };
+            if duplicates > 0 {
+                message.push_str(&format!(", {} đã có trong danh sách", duplicates));
+            }
+            if !rejected.is_empty() {
+                let shown: Vec<&str> = rejected.iter().take(PASTE_REJECTS_SHOWN).map(String::as_str).collect();
+                let more = if rejected.len() > PASTE_REJECTS_SHOWN { ", ..." } else { "" };
+                message.push_str(&format!(
+                    ", bỏ qua {} dòng không phải đường dẫn tuyệt đối tồn tại: {}{}",
+                    rejected.len(),
+                    shown.join(", "),
+                    more
+                ));
+                warn!("Pasted paths rejected: {:?}", rejected);
+            }
+            update_status(&ui_handle, message, 0.0, valid.is_empty());
+            if !valid.is_empty() {
+                add_local_paths(&ui, &state, valid);
             }
         }
     });
 }
 
-/// Sets up the file selection handler.
-pub fn setup_select_files_handler(ui: &AppWindow) {
-    ui.on_select_files({
+/// Sets up the recent folders quick-add handler.
+pub fn setup_recent_folders_handler(ui: &AppWindow, state: &AppState) {
+    ui.on_add_recent_folder({
         let ui_handle = ui.as_weak();
-        move || {
-            let ui = match ui_handle.upgrade() {
-                Some(ui) => ui,
-                None => return,
-            };
+        let state = state.clone();
+        move |path| {
+            let Some(ui) = ui_handle.upgrade() else { return; };
+            let path = std::path::PathBuf::from(path.as_str());
+            if !path.exists() {
+                update_status(&ui_handle, format!("Không tìm thấy: {}", path.display()), 0.0, true);
+                return;
+            }
+            add_local_paths(&ui, &state, vec![path]);
+        }
+    });
+}
 
-            // Get current AWS config
-            let acc_key = ui.get_access_key().to_string();
-            let sec_key = ui.get_secret_key().to_string();
-            let sess_token = ui.get_session_token().to_string();
-            let region = ui.get_region().to_string();
-            let bucket = ui.get_bucket_name().to_string();
-            let s3_base_path = ui.get_s3_base_path().to_string();
+/// Most palette entries shown at once.
+const PALETTE_LIMIT: usize = 50;
 
-            let ui_handle_cloned = ui_handle.clone();
-            let _ = ui_handle_cloned.upgrade_in_event_loop(|ui| {
-                ui.set_is_selecting_folder(true);
-            });
+/// Palette entries matching `query`, best match first: buckets, regions, then recent folders
+/// when scores tie.
+fn palette_items(ui: &AppWindow, config: &crate::config::AppConfig, query: &str) -> Vec<PaletteItem> {
+    let current_bucket = ui.get_bucket_name().to_string();
+    let current_region = ui.get_region().to_string();
+    let regions = crate::regions::region_choices(&config.regions);
+    let entries = config
+        .buckets
+        .iter()
+        .map(|b| ("bucket", "Bucket", b, *b == current_bucket))
+        .chain(regions.iter().map(|r| ("region", "Region", r, *r == current_region)))
+        .chain(config.recent_folders.iter().map(|f| ("folder", "Thư mục", f, false)));
+
+    let mut scored: Vec<(i32, PaletteItem)> = entries
+        .filter_map(|(kind, kind_name, value, current)| {
+            let score = crate::utils::fuzzy_score(query, &format!("{} {}", kind_name, value))?;
+            let detail = match kind {
+                "folder" => "thêm vào danh sách",
+                _ if current => "đang chọn",
+                _ => "",
+            };
+            let item = PaletteItem { kind: kind.into(), value: value.into(), label: value.into(), detail: detail.into() };
+            Some((score, item))
+        })
+        .collect();
+    // Stable, so equal scores keep the bucket / region / folder order
+    scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+    scored.into_iter().take(PALETTE_LIMIT).map(|(_, item)| item).collect()
+}
 
-            if let Some(paths) = rfd::FileDialog::new().pick_files() {
-                let ui_handle_task = ui_handle.clone();
-                tokio::spawn(async move {
-                    let mut results = Vec::new();
-                    let base_path_buf = std::path::PathBuf::from(&s3_base_path);
-
-                    // Try to create S3 client for accurate calculation
-                    let client = if !acc_key.is_empty() && !sec_key.is_empty() && !bucket.is_empty() {
-                        match create_s3_client(
-                            acc_key,
-                            sec_key,
-                            if sess_token.is_empty() { None } else { Some(sess_token) },
-                            region
-                        ).await {
-                            Ok(c) => Some(c),
-                            Err(e) => {
-                                error!("Failed to create S3 client for path preview: {:?}", e);
-                                crate::utils::update_status(&ui_handle_task, "Cảnh báo: Không thể kết nối S3, sử dụng đường dẫn xem trước".to_string(), 0.0, false);
-                                time::sleep(time::Duration::from_secs(2)).await; // Show message briefly
-                                None
-                            }
-                        }
-                    } else {
-                        None
-                    };
+/// Sets up the Ctrl+K palette for switching bucket or region and re-adding recent folders.
+pub fn setup_command_palette_handlers(ui: &AppWindow, state: &AppState) {
+    ui.on_open_palette({
+        let ui_handle = ui.as_weak();
+        let state = state.clone();
+        move || {
+            let Some(ui) = ui_handle.upgrade() else { return; };
+            let items = palette_items(&ui, &state.read(), "");
+            ui.set_palette_items(ModelRc::from(Rc::new(VecModel::from(items))));
+            ui.set_palette_query("".into());
+            ui.set_palette_selected(0);
+            ui.set_show_palette(true);
+        }
+    });
 
-                    let cache: crate::s3_client::GlobalPrefixCache = std::sync::Arc::new(tokio::sync::Mutex::new(std::collections::HashMap::new()));
-
-                    for p in paths {
-                        let local_path = p.to_string_lossy().to_string();
-                        
-                        let s3_path = if !base_path_buf.as_os_str().is_empty() && p.starts_with(&base_path_buf) {
-                            let rel = p.strip_prefix(&base_path_buf).unwrap_or(&p);
-                            let rel_str = rel.to_string_lossy().replace('\\', "/");
-                            if rel_str.is_empty() {
-                                p.file_name().unwrap_or_default().to_string_lossy().to_string()
-                            } else {
-                                rel_str
-                            }
-                        } else if let Some(ref c) = client {
-                            find_best_s3_prefix(c, &bucket, p.as_path(), &cache).await
-                        } else {
-                            get_preview_prefix(&p)
-                        };
+    ui.on_palette_search({
+        let ui_handle = ui.as_weak();
+        let state = state.clone();
+        move |query| {
+            let Some(ui) = ui_handle.upgrade() else { return; };
+            let items = palette_items(&ui, &state.read(), &query);
+            ui.set_palette_items(ModelRc::from(Rc::new(VecModel::from(items))));
+        }
+    });
 
-                        results.push(PathItem {
-                            local_path: local_path.into(),
-                            s3_path: s3_path.into(),
-                        });
+    ui.on_palette_activate({
+        let ui_handle = ui.as_weak();
+        let state = state.clone();
+        move |index| {
+            let Some(ui) = ui_handle.upgrade() else { return; };
+            let Some(item) = ui.get_palette_items().row_data(index as usize) else { return; };
+            ui.set_show_palette(false);
+            let value = item.value.to_string();
+            match item.kind.as_str() {
+                "bucket" => {
+                    ui.set_bucket_name(item.value.clone());
+                    state.update(|config| config.selected_bucket = value.clone());
+                    update_status(&ui_handle, format!("Đã chuyển sang bucket {}", value), 0.0, false);
+                }
+                "region" => {
+                    ui.set_region(item.value.clone());
+                    state.update(|config| config.selected_region = value.clone());
+                    update_status(&ui_handle, format!("Đã chuyển sang region {}", value), 0.0, false);
+                }
+                _ => {
+                    let path = std::path::PathBuf::from(&value);
+                    if path.exists() {
+                        add_local_paths(&ui, &state, vec![path]);
+                    } else {
+                        update_status(&ui_handle, format!("Không tìm thấy: {}", value), 0.0, true);
                     }
+                }
+            }
+        }
+    });
+}
 
-                    let _ = ui_handle_task.upgrade_in_event_loop(move |ui| {
-                        let mut current_items: Vec<PathItem> = ui.get_local_paths().iter().collect();
-                        current_items.extend(results);
-                        let model = Rc::new(VecModel::from(current_items));
-                        ui.set_local_paths(ModelRc::from(model));
-                        ui.set_is_selecting_folder(false);
-                    });
-                });
-            } else {
-                let _ = ui_handle_cloned.upgrade_in_event_loop(|ui| {
-                    ui.set_is_selecting_folder(false);
-                });
+/// Sets up the per-item checkbox that excludes a mapping from the next sync.
+pub fn setup_toggle_path_item_handler(ui: &AppWindow) {
+    ui.on_toggle_path_item({
+        let ui_handle = ui.as_weak();
+        move |index, enabled| {
+            let Some(ui) = ui_handle.upgrade() else { return; };
+            let model = ui.get_local_paths();
+            if let Some(mut item) = model.row_data(index as usize) {
+                item.enabled = enabled;
+                model.set_row_data(index as usize, item);
             }
+            refresh_path_collisions(&ui);
+        }
+    });
+}
+
+/// Sets up the per-row flatten toggle; the choice is remembered per local folder.
+pub fn setup_toggle_flatten_handler(ui: &AppWindow, state: &AppState) {
+    ui.on_toggle_flatten({
+        let ui_handle = ui.as_weak();
+        let state = state.clone();
+        move |index, flatten| {
+            let Some(ui) = ui_handle.upgrade() else { return; };
+            let model = ui.get_local_paths();
+            let Some(mut item) = model.row_data(index as usize) else { return; };
+            let local_path = item.local_path.to_string();
+            state.update(|config| {
+                let paths = &mut config.sync_options.flatten_paths;
+                paths.retain(|p| p != &local_path);
+                if flatten {
+                    paths.push(local_path.clone());
+                }
+            });
+            item.flatten = flatten;
+            model.set_row_data(index as usize, item);
         }
     });
 }
 
+/// Sets up the handler restoring the mapping list saved when the app last closed.
+pub fn setup_restore_session_handler(ui: &AppWindow, state: &AppState) {
+    ui.on_restore_session({
+        let ui_handle = ui.as_weak();
+        let state = state.clone();
+        move || {
+            let Some(ui) = ui_handle.upgrade() else { return; };
+            let flatten_paths = state.read().sync_options.flatten_paths.clone();
+            let items: Vec<PathItem> = crate::last_session::load_mappings()
+                .into_iter()
+                .map(|m| PathItem {
+                    flatten: flatten_paths.contains(&m.local_path),
+                    local_path: m.local_path.into(),
+                    s3_path: m.s3_path.into(),
+                    enabled: m.enabled,
+                    stats: "đang tính...".into(),
+                    conflict: "".into(),
+                    origin: "".into(),
+                    origin_guess: false,
+                })
+                .collect();
+            let paths = items.iter().map(|item| item.local_path.to_string()).collect();
+            info!("Restored {} mappings from last session", items.len());
+            ui.set_local_paths(ModelRc::new(VecModel::from(items)));
+            refresh_path_collisions(&ui);
+            ui.set_show_restore_session(false);
+            compute_path_stats(ui.as_weak(), &state, paths);
+        }
+    });
+}
+
+/// Shows the recently synced folders in the "Recent" menu.
+fn set_recent_folders(ui: &AppWindow, folders: &[String]) {
+    let model: Vec<slint::SharedString> = folders.iter().map(|f| f.into()).collect();
+    ui.set_recent_folders(ModelRc::new(VecModel::from(model)));
+}
+
 /// Sets up the clear folders handler.
 pub fn setup_clear_folders_handler(ui: &AppWindow) {
     ui.on_clear_folders({
@@ -327,103 +697,700 @@ pub fn setup_remove_folder_handler(ui: &AppWindow) {
                         ui.set_local_paths(ModelRc::from(new_model));
                     }
                 }
+                refresh_path_collisions(&ui);
             });
         }
     });
 }
 
+/// Bucket prefixes shared by S3 path detection and path suggestions, so typing doesn't relist.
+static PREFIX_CACHE: Lazy<crate::s3_client::GlobalPrefixCache> = Lazy::new(Default::default);
+/// Bumped per keystroke so a slow lookup can't overwrite newer suggestions.
+static SUGGEST_GENERATION: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+const MAX_PREFIX_SUGGESTIONS: usize = 8;
+
+/// Looks up existing bucket prefixes matching the typed S3 path of row `index` and shows them
+/// under it. Quietly shows nothing without credentials or when the bucket can't be listed.
+fn request_prefix_suggestions(ui: &AppWindow, index: i32, typed: String) {
+    use std::sync::atomic::Ordering;
+    let generation = SUGGEST_GENERATION.fetch_add(1, Ordering::Relaxed) + 1;
+    let params = AwsParams::from_ui(ui);
+    if params.validate().is_some() || typed.contains('{') {
+        ui.set_s3_suggest_index(-1);
+        return;
+    }
+    let ui_handle = ui.as_weak();
+    tokio::spawn(async move {
+        let suggestions = match params.create_client().await {
+            Ok(client) => {
+                let prefixes = crate::s3_client::cached_prefixes(&client, &params.bucket, &PREFIX_CACHE).await;
+                crate::utils::suggest_prefixes(&prefixes, &typed, MAX_PREFIX_SUGGESTIONS)
+            }
+            Err(e) => {
+                warn!("Prefix suggestions unavailable: {:?}", e);
+                Vec::new()
+            }
+        };
+        let _ = ui_handle.upgrade_in_event_loop(move |ui| {
+            if SUGGEST_GENERATION.load(Ordering::Relaxed) != generation {
+                return;
+            }
+            ui.set_s3_suggest_index(if suggestions.is_empty() { -1 } else { index });
+            let suggestions: Vec<slint::SharedString> = suggestions.into_iter().map(Into::into).collect();
+            ui.set_s3_suggestions(ModelRc::from(Rc::new(VecModel::from(suggestions))));
+        });
+    });
+}
+
+/// Sets up the handler for editing a mapping's S3 path (may contain template variables).
+pub fn setup_edit_s3_path_handler(ui: &AppWindow) {
+    ui.on_edit_s3_path({
+        let ui_handle = ui.as_weak();
+        move |index, s3_path| {
+            let Some(ui) = ui_handle.upgrade() else { return; };
+            let model = ui.get_local_paths();
+            if let Some(mut item) = model.row_data(index as usize) {
+                item.s3_path = s3_path.clone();
+                item.origin = "sửa tay".into();
+                item.origin_guess = false;
+                model.set_row_data(index as usize, item);
+            }
+            refresh_path_collisions(&ui);
+            request_prefix_suggestions(&ui, index, s3_path.to_string());
+        }
+    });
+}
+
+/// Post-sync bookkeeping shared by single syncs and queued jobs: history, metrics, email report.
+/// Syncs that failed before uploading come here too, as [`SyncSummary::aborted`](crate::s3_client::SyncSummary::aborted).
+async fn finish_sync(state: &AppState, summary: &crate::s3_client::SyncSummary, region: &str) {
+    let (metrics_path, smtp) = {
+        let config = state.read();
+        (config.metrics_path.clone(), config.smtp.clone())
+    };
+    if !summary.uploaded_keys.is_empty() {
+        let session = crate::history::SyncSession::from_summary(summary, region);
+        if let Err(e) = crate::history::record_session(session) {
+            error!("Failed to record sync session: {}", e);
+        }
+    }
+    let snapshot = crate::metrics::record_sync(summary);
+    if !metrics_path.is_empty() {
+        let path = std::path::Path::new(&metrics_path);
+        if let Err(e) = crate::metrics::write_metrics_file(path, &snapshot) {
+            error!("Failed to write metrics file '{}': {}", metrics_path, e);
+        }
+    }
+
+    if smtp.enabled {
+        match crate::notify::send_sync_report(&smtp, summary).await {
+            Ok(_) => info!("Sync report email sent to {}", smtp.to),
+            Err(e) => error!("Failed to send sync report: {}", e),
+        }
+    }
+}
+
 /// Sets up the start sync handler.
-pub fn setup_start_sync_handler(ui: &AppWindow) {
+pub fn setup_start_sync_handler(ui: &AppWindow, state: &AppState) {
     ui.on_start_sync({
         let ui_handle = ui.as_weak();
+        let state = state.clone();
         move |acc_key, sec_key, sess_token, region, bucket, local_dirs| {
             let bucket_name = bucket.to_string();
             let region_str = region.to_string();
             let mappings: Vec<(String, String)> = local_dirs
                 .iter()
+                .filter(|item: &PathItem| item.enabled)
                 .map(|item: PathItem| (item.local_path.to_string(), item.s3_path.to_string()))
                 .collect();
             let log_path = ui_handle.upgrade().map(|ui| ui.get_log_path().to_string()).unwrap_or_default();
 
             // Save selected bucket and region to config
-            let mut config = crate::config::load_config();
-            config.selected_bucket = bucket_name.clone();
-            config.selected_region = region_str.clone();
-            if let Err(e) = crate::config::save_config(&config) {
-                error!("Failed to save config: {:?}", e);
+            let recent_folders = state.update(|config| {
+                config.selected_bucket = bucket_name.clone();
+                config.selected_region = region_str.clone();
+                config.remember_recent_folders(mappings.iter().map(|(local, _)| local.as_str()));
+                config.recent_folders.clone()
+            });
+            if let Some(ui) = ui_handle.upgrade() {
+                set_recent_folders(&ui, &recent_folders);
             }
 
             // Validate inputs
-            if let Some(err) = crate::utils::validate_credentials(&acc_key, &sec_key, &bucket_name)
+            if let Some(err) = crate::utils::validate_credentials(&acc_key, &sec_key, &sess_token, &bucket_name)
             {
-                crate::utils::update_status(&ui_handle, err, 0.0, true);
+                update_status(&ui_handle, err, 0.0, true);
                 return;
             }
 
             if mappings.is_empty() {
-                crate::utils::update_status(
+                update_status(
                     &ui_handle,
-                    "Không có file hoặc thư mục nào để upload".to_string(),
+                    "Không có file hoặc thư mục nào được chọn để upload".to_string(),
                     0.0,
                     true,
                 );
                 return;
             }
 
+            let params = AwsParams {
+                acc_key: acc_key.to_string(),
+                sec_key: sec_key.to_string(),
+                sess_token: sess_token.to_string(),
+                region: region_str,
+                bucket: bucket_name,
+            };
+            show_sync_confirmation(&ui_handle, &state, PendingSync { params, mappings, log_path });
+        }
+    });
+
+    ui.on_confirm_sync({
+        let ui_handle = ui.as_weak();
+        let state = state.clone();
+        move |allow_over_budget| {
+            if let Some(ui) = ui_handle.upgrade() {
+                ui.set_show_sync_confirm(false);
+            }
+            let Some(pending) = PENDING_SYNC.lock().unwrap().take() else { return; };
             let ui_handle_cloned = ui_handle.clone();
+            let state = state.clone();
 
             tokio::spawn(async move {
-                match create_s3_client(
-                    acc_key.to_string(),
-                    sec_key.to_string(),
-                    if sess_token.is_empty() {
-                        None
-                    } else {
-                        Some(sess_token.to_string())
-                    },
-                    region_str,
-                )
-                .await
-                {
+                let PendingSync { params, mappings, log_path } = pending;
+                match params.create_client().await {
                     Ok(client) => {
                         let client = std::sync::Arc::new(client);
-                        if let Err(e) =
-                            sync_to_s3(client, bucket_name, mappings, ui_handle_cloned, log_path).await
-                        {
-                            error!("Sync failed: {}", e);
+                        let status = status_fn(&ui_handle_cloned);
+                        let throttle = std::sync::Arc::new(crate::scheduler::register(Default::default()));
+                        // The approval covers the replica uploads of this sync too
+                        let session = throttle.id();
+                        if allow_over_budget {
+                            crate::budget::approve_overage(session);
+                        }
+                        match sync_to_s3(client, params.bucket.clone(), mappings.clone(), std::sync::Arc::clone(&status), log_path.clone(), std::sync::Arc::clone(&throttle)).await {
+                            Ok(summary) => {
+                                finish_sync(&state, &summary, &params.region).await;
+                                let source = crate::replicas::ReplicaSource {
+                                    acc_key: &params.acc_key,
+                                    sec_key: &params.sec_key,
+                                    sess_token: &params.sess_token,
+                                    region: &params.region,
+                                    mappings: &mappings,
+                                    summary: &summary,
+                                    confirmed: true,
+                                };
+                                let results = crate::replicas::sync_replicas(source, &status, &log_path, throttle).await;
+                                if !results.is_empty() {
+                                    let failed = results.iter().any(|r| !r.is_success());
+                                    status(format!("Replica: {}", crate::replicas::format_results(&results)), if failed { 0.0 } else { 1.0 }, failed);
+                                }
+                            }
+                            Err(e) => {
+                                error!("Sync failed: {}", e);
+                                finish_sync(&state, &crate::s3_client::SyncSummary::aborted(&params.bucket, e), &params.region).await;
+                            }
                         }
+                        crate::budget::end_session(session);
                     }
                     Err(e) => {
                         error!("Failed to create S3 client for sync: {:?}", e);
-                        crate::utils::update_status(
-                            &ui_handle_cloned,
-                            format!("Lỗi tạo client: {}", e),
-                            0.0,
-                            true,
-                        );
+                        let message = format!("Lỗi tạo client: {}", e);
+                        update_status(&ui_handle_cloned, message.clone(), 0.0, true);
+                        finish_sync(&state, &crate::s3_client::SyncSummary::aborted(&params.bucket, message), &params.region).await;
                     }
                 }
             });
         }
     });
+
+    ui.on_cancel_sync_confirm({
+        let ui_handle = ui.as_weak();
+        move || {
+            PENDING_SYNC.lock().unwrap().take();
+            if let Some(ui) = ui_handle.upgrade() {
+                ui.set_show_sync_confirm(false);
+            }
+        }
+    });
 }
 
-pub fn setup_select_log_path_handler(ui: &AppWindow) {
-    let ui_handle = ui.as_weak();
-    ui.on_select_log_path(move || {
-        if let Some(path) = rfd::FileDialog::new().pick_folder() {
-            let path_str = path.to_string_lossy().to_string();
+/// A sync waiting for the user to confirm the pre-sync summary.
+struct PendingSync {
+    params: AwsParams,
+    mappings: Vec<(String, String)>,
+    log_path: String,
+}
 
-            // Validate that the path is writable
-            let test_file = path.join(".s3sync_write_test");
-            match std::fs::File::create(&test_file) {
-                Ok(_) => {
-                    // Clean up test file
-                    let _ = std::fs::remove_file(&test_file);
-                }
-                Err(e) => {
-                    error!("Log path is not writable: {:?}", e);
-                    crate::utils::update_status(
+static PENDING_SYNC: Lazy<std::sync::Mutex<Option<PendingSync>>> = Lazy::new(|| std::sync::Mutex::new(None));
+
+/// The S3 prefixes the mappings upload to, as of now; the bucket root is an empty prefix.
+fn destination_prefixes(mappings: &[(String, String)]) -> Vec<String> {
+    let now = chrono::Local::now();
+    mappings
+        .iter()
+        .filter_map(|(local, s3)| crate::utils::expand_path_template(s3, std::path::Path::new(local), &now).ok())
+        .map(|prefix| if crate::utils::is_bucket_root(&prefix) { String::new() } else { prefix })
+        .collect()
+}
+
+/// Queued jobs run without the confirmation dialog, so protected buckets can't be queued.
+fn protected_queue_error(bucket: &str) -> String {
+    format!("Bucket {} được bảo vệ, không thể chạy qua hàng đợi. Dùng Sync để xác nhận bằng tên bucket", bucket)
+}
+
+/// Gathers the pre-sync summary (file count and size after filters, account identity) in the
+/// background, then opens the confirmation dialog. Protected buckets require typing the
+/// bucket name to confirm; an upload over the session budget offers trimming instead.
+fn show_sync_confirmation(ui_handle: &slint::Weak<AppWindow>, state: &AppState, pending: PendingSync) {
+    let params = pending.params.clone();
+    let local_paths: Vec<String> = pending.mappings.iter().map(|(local, _)| local.clone()).collect();
+    let dest_prefixes = destination_prefixes(&pending.mappings);
+    let to_root = pending.mappings.iter().any(|(_, s3)| crate::utils::is_bucket_root(s3));
+    *PENDING_SYNC.lock().unwrap() = Some(pending);
+    update_status(ui_handle, "Đang chuẩn bị tóm tắt sync...".to_string(), 0.0, false);
+
+    let ui_handle = ui_handle.clone();
+    let config = state.config();
+    tokio::spawn(async move {
+        let filter_config = crate::config::effective_filter(config.filter_config.clone());
+        let totals = tokio::task::spawn_blocking(move || {
+            local_paths.iter().fold((0u64, 0u64), |(files, bytes), path| {
+                let (f, b) = crate::utils::mapping_totals(std::path::Path::new(path), &filter_config).unwrap_or((0, 0));
+                (files + f, bytes + b)
+            })
+        })
+        .await
+        .unwrap_or((0, 0));
+        let account = crate::s3_client::caller_identity(
+            params.acc_key.clone(),
+            params.sec_key.clone(),
+            if params.sess_token.is_empty() { None } else { Some(params.sess_token.clone()) },
+            params.region.clone(),
+        )
+        .await
+        .map(|identity| identity.to_string())
+        .unwrap_or_else(|e| {
+            error!("{}", e);
+            "không xác định".to_string()
+        });
+
+        let (lifecycle, public_access, policy) = destination_notes(&params, &dest_prefixes).await;
+
+        let protected = config.requires_typed_confirmation(&params.bucket);
+        let options = &config.sync_options;
+        let overage = crate::budget::overage_text(totals.1, crate::budget::budget_bytes(options.session_budget_mb)).unwrap_or_default();
+        let mut flags = Vec::new();
+        if to_root {
+            flags.push("upload vào gốc bucket");
+        }
+        if options.staged_deploy {
+            flags.push("staged deploy");
+        }
+        if options.trash_overwritten {
+            flags.push("backup vào .trash/");
+        }
+        if options.dedup_mode != crate::config::DedupMode::Off {
+            flags.push("dedup");
+        }
+        if options.client_side_encryption {
+            flags.push("mã hóa client");
+        }
+        if options.archive_upload {
+            flags.push("nén mỗi thư mục thành .tar.gz");
+        }
+        let partition = format!("phân vùng key theo ngày ({})", options.date_partition.as_str());
+        if options.date_partition != crate::config::DatePartition::Off {
+            flags.push(&partition);
+        }
+        if options.append_only {
+            flags.push("append-only: chỉ upload phần mới của file");
+        }
+        let replicas = (!options.replica_targets.is_empty()).then(|| {
+            let targets: Vec<String> = options.replica_targets.iter().map(|t| t.to_string()).collect();
+            let how = match options.replica_mode {
+                crate::config::ReplicaMode::Upload => "upload lại",
+                crate::config::ReplicaMode::Copy => "copy server-side",
+            };
+            format!("replica ({}): {}", how, targets.join(", "))
+        });
+        if let Some(replicas) = &replicas {
+            flags.push(replicas);
+        }
+        let batch = format!("replica qua S3 Batch Operations (> {} object)", options.batch_threshold);
+        if replicas.is_some()
+            && options.replica_mode == crate::config::ReplicaMode::Copy
+            && crate::batch_ops::use_batch(
+                totals.0 as usize,
+                options.batch_threshold,
+                &options.batch_role_arn,
+                &options.batch_manifest_bucket,
+            )
+        {
+            flags.push(&batch);
+        }
+        let options_text = format!(
+            "Xóa file thừa trên S3: không{} · {}",
+            if protected { " (bucket được bảo vệ)" } else { "" },
+            if flags.is_empty() { "không có tùy chọn đặc biệt".to_string() } else { flags.join(", ") }
+        );
+
+        let _ = ui_handle.upgrade_in_event_loop(move |ui| {
+            ui.set_confirm_bucket(params.bucket.clone().into());
+            ui.set_confirm_region(params.region.clone().into());
+            ui.set_confirm_files(format!("{} file · {}", totals.0, crate::utils::format_size(totals.1)).into());
+            ui.set_confirm_options(options_text.into());
+            ui.set_confirm_account(account.into());
+            ui.set_confirm_requires_typing(protected);
+            ui.set_confirm_budget_overage(overage.into());
+            ui.set_confirm_lifecycle(lifecycle.into());
+            ui.set_confirm_public_access(public_access.into());
+            ui.set_confirm_policy(policy.into());
+            ui.set_show_sync_confirm(true);
+            update_status(&ui.as_weak(), "Chờ xác nhận sync".to_string(), 0.0, false);
+        });
+    });
+}
+
+/// Notes about the target bucket for the confirmation: lifecycle rules acting on the destination
+/// prefixes (one per line), its Block Public Access state and bucket policy statements that would
+/// reject the uploads (one per line). Checks that can't be read leave their part empty; that is
+/// only logged.
+async fn destination_notes(params: &AwsParams, prefixes: &[String]) -> (String, String, String) {
+    let session = if params.sess_token.is_empty() { None } else { Some(params.sess_token.clone()) };
+    let Ok(client) =
+        crate::s3_client::create_s3_client(params.acc_key.clone(), params.sec_key.clone(), session, params.region.clone()).await
+    else {
+        return (String::new(), String::new(), String::new());
+    };
+    let lifecycle = match crate::lifecycle::fetch_rules(&client, &params.bucket).await {
+        Ok(rules) => {
+            let warnings = crate::lifecycle::warnings(&rules, prefixes);
+            if !warnings.is_empty() {
+                warn!("Lifecycle rules apply to the sync destination: {}", warnings.join("; "));
+            }
+            warnings.join("\n")
+        }
+        Err(e) => {
+            warn!("{}", e);
+            String::new()
+        }
+    };
+    let public_access = crate::bucket_policy::public_access_summary(&client, &params.bucket).await.unwrap_or_else(|e| {
+        warn!("{}", e);
+        "không xác định".to_string()
+    });
+    let policy = match crate::bucket_policy::check_policy(&client, &params.bucket, prefixes).await {
+        Ok(conflicts) => conflicts.join("\n"),
+        Err(e) => {
+            warn!("{}", e);
+            String::new()
+        }
+    };
+    (lifecycle, public_access, policy)
+}
+
+fn job_row(job: &crate::queue::SyncJob) -> JobRow {
+    let message = match &job.status {
+        crate::queue::JobStatus::Failed(e) => e.clone(),
+        _ => job.message.clone(),
+    };
+    JobRow {
+        id: job.id as i32,
+        title: job.title().into(),
+        status: job.status.label().into(),
+        progress: job.progress,
+        message: message.into(),
+        priority: job.limits.priority.as_str().into(),
+        concurrency: job.limits.max_concurrency.to_string().into(),
+    }
+}
+
+/// Rebuilds the queue list from the job queue.
+fn refresh_job_rows(ui: &AppWindow) {
+    let rows: Vec<JobRow> = crate::queue::snapshot().iter().map(job_row).collect();
+    ui.set_queue_jobs(ModelRc::from(Rc::new(VecModel::from(rows))));
+}
+
+/// Updates a single queue row in place (called for every progress tick).
+fn refresh_job_row(ui_handle: &slint::Weak<AppWindow>, id: u64) {
+    let Some(job) = crate::queue::get(id) else { return };
+    let _ = ui_handle.upgrade_in_event_loop(move |ui| {
+        let model = ui.get_queue_jobs();
+        if let Some(index) = model.iter().position(|r| r.id as u64 == job.id) {
+            model.set_row_data(index, job_row(&job));
+        }
+    });
+}
+
+/// Runs one queued job end to end and records its final status.
+async fn run_queued_job(job: crate::queue::SyncJob, ui_handle: slint::Weak<AppWindow>, state: AppState) {
+    let id = job.id;
+    refresh_job_row(&ui_handle, id);
+    // The bucket may have been protected after the job was queued
+    if state.read().requires_typed_confirmation(&job.bucket) {
+        crate::queue::set_status(id, crate::queue::JobStatus::Failed(protected_queue_error(&job.bucket)));
+        refresh_job_row(&ui_handle, id);
+        return;
+    }
+    let client = match create_s3_client(
+        job.acc_key.clone(),
+        job.sec_key.clone(),
+        if job.sess_token.is_empty() { None } else { Some(job.sess_token.clone()) },
+        job.region.clone(),
+    )
+    .await
+    {
+        Ok(client) => std::sync::Arc::new(client),
+        Err(e) => {
+            error!("Queue job #{}: failed to create S3 client: {:?}", id, e);
+            let message = format!("Lỗi tạo client: {}", e);
+            crate::queue::set_status(id, crate::queue::JobStatus::Failed(message.clone()));
+            refresh_job_row(&ui_handle, id);
+            finish_sync(&state, &crate::s3_client::SyncSummary::aborted(&job.bucket, message), &job.region).await;
+            return;
+        }
+    };
+
+    let throttle = std::sync::Arc::new(crate::scheduler::register_queued(job.limits));
+    crate::queue::set_sched_id(id, Some(throttle.id()));
+
+    let status: StatusFn = {
+        let ui_handle = ui_handle.clone();
+        crate::progress::coalesce(
+            std::sync::Arc::new(move |text: String, progress, is_error| {
+                crate::queue::set_progress(id, progress.max(0.0), text.clone());
+                refresh_job_row(&ui_handle, id);
+                update_status(&ui_handle, format!("[Job #{}] {}", id, text), progress, is_error);
+            }),
+            crate::progress::UI_UPDATE_INTERVAL,
+        )
+    };
+    // Outside the sync window the job waits here before scanning anything
+    crate::sync_window::wait_until_open(Some(&status)).await;
+
+    let final_status = match sync_to_s3(
+        client,
+        job.bucket.clone(),
+        job.mappings.clone(),
+        std::sync::Arc::clone(&status),
+        job.log_path.clone(),
+        std::sync::Arc::clone(&throttle),
+    )
+    .await
+    {
+        Ok(summary) => {
+            finish_sync(&state, &summary, &job.region).await;
+            let source = crate::replicas::ReplicaSource {
+                acc_key: &job.acc_key,
+                sec_key: &job.sec_key,
+                sess_token: &job.sess_token,
+                region: &job.region,
+                mappings: &job.mappings,
+                summary: &summary,
+                confirmed: false,
+            };
+            let replicas = crate::replicas::sync_replicas(source, &status, &job.log_path, throttle).await;
+            if !summary.is_success() {
+                crate::queue::JobStatus::Failed(summary.errors.first().cloned().unwrap_or_default())
+            } else if replicas.iter().any(|r| !r.is_success()) {
+                crate::queue::JobStatus::Failed(format!("Replica: {}", crate::replicas::format_results(&replicas)))
+            } else {
+                crate::queue::JobStatus::Done
+            }
+        }
+        Err(e) => {
+            error!("Queue job #{} failed: {}", id, e);
+            finish_sync(&state, &crate::s3_client::SyncSummary::aborted(&job.bucket, e.clone()), &job.region).await;
+            crate::queue::JobStatus::Failed(e)
+        }
+    };
+    info!("Queue job #{} finished: {}", id, final_status.label());
+    crate::queue::set_sched_id(id, None);
+    crate::queue::set_status(id, final_status);
+    refresh_job_row(&ui_handle, id);
+}
+
+/// Sets up the sync queue handlers (enqueue, run, remove, clear).
+pub fn setup_sync_queue_handlers(ui: &AppWindow, state: &AppState) {
+    ui.on_enqueue_sync({
+        let ui_handle = ui.as_weak();
+        let state = state.clone();
+        move |acc_key, sec_key, sess_token, region, bucket, local_dirs| {
+            let Some(ui) = ui_handle.upgrade() else { return; };
+            if let Some(err) = crate::utils::validate_credentials(&acc_key, &sec_key, &sess_token, &bucket) {
+                update_status(&ui_handle, err, 0.0, true);
+                return;
+            }
+            let mappings: Vec<(String, String)> = local_dirs
+                .iter()
+                .filter(|item: &PathItem| item.enabled)
+                .map(|item: PathItem| (item.local_path.to_string(), item.s3_path.to_string()))
+                .collect();
+            if mappings.is_empty() {
+                return;
+            }
+            if state.read().requires_typed_confirmation(&bucket) {
+                update_status(&ui_handle, protected_queue_error(&bucket), 0.0, true);
+                return;
+            }
+            let params = AwsParams {
+                acc_key: acc_key.to_string(),
+                sec_key: sec_key.to_string(),
+                sess_token: sess_token.to_string(),
+                region: region.to_string(),
+                bucket: bucket.to_string(),
+            };
+            let dest_prefixes = destination_prefixes(&mappings);
+
+            let id = crate::queue::enqueue(crate::queue::SyncJob {
+                id: 0,
+                acc_key: acc_key.to_string(),
+                sec_key: sec_key.to_string(),
+                sess_token: sess_token.to_string(),
+                region: region.to_string(),
+                bucket: bucket.to_string(),
+                mappings,
+                log_path: ui.get_log_path().to_string(),
+                limits: crate::scheduler::JobLimits::default(),
+                sched_id: None,
+                status: crate::queue::JobStatus::Queued,
+                progress: 0.0,
+                message: String::new(),
+            });
+            info!("Enqueued sync job #{} -> {}", id, bucket);
+            refresh_job_rows(&ui);
+            ui.set_show_sync_queue(true);
+            update_status(&ui_handle, format!("Đã thêm job #{} vào hàng đợi", id), 0.0, false);
+
+            // No confirmation dialog for queued jobs: the notes it would show go on the job row
+            let ui_handle = ui_handle.clone();
+            tokio::spawn(async move {
+                let (lifecycle, public_access, policy) = destination_notes(&params, &dest_prefixes).await;
+                let notes: Vec<String> = [policy, lifecycle]
+                    .into_iter()
+                    .filter(|note| !note.is_empty())
+                    .chain((!public_access.is_empty()).then(|| format!("Public: {}", public_access)))
+                    .collect();
+                if let Some(job) = crate::queue::get(id)
+                    && job.status == crate::queue::JobStatus::Queued
+                {
+                    crate::queue::set_progress(id, 0.0, notes.join("; ").replace('\n', "; "));
+                    refresh_job_row(&ui_handle, id);
+                }
+            });
+        }
+    });
+
+    ui.on_run_queue({
+        let ui_handle = ui.as_weak();
+        let state = state.clone();
+        move |parallel_text| {
+            let Some(ui) = ui_handle.upgrade() else { return; };
+            let parallel = match parallel_text.trim().parse::<usize>() {
+                Ok(n) if (1..=8).contains(&n) => n,
+                _ => {
+                    update_status(&ui_handle, "Số job song song phải từ 1 đến 8".to_string(), 0.0, true);
+                    return;
+                }
+            };
+            state.update(|config| config.sync_options.queue_parallel = parallel);
+
+            let jobs = crate::queue::take_queued();
+            if jobs.is_empty() {
+                return;
+            }
+            info!("Running {} queued jobs ({} at a time)", jobs.len(), parallel);
+            ui.set_is_queue_running(true);
+            refresh_job_rows(&ui);
+
+            let ui_handle = ui_handle.clone();
+            let state = state.clone();
+            tokio::spawn(async move {
+                let semaphore = std::sync::Arc::new(tokio::sync::Semaphore::new(parallel));
+                let mut set = tokio::task::JoinSet::new();
+                for job in jobs {
+                    let semaphore = std::sync::Arc::clone(&semaphore);
+                    let ui_handle = ui_handle.clone();
+                    let state = state.clone();
+                    set.spawn(async move {
+                        let _permit = semaphore.acquire().await.unwrap();
+                        run_queued_job(job, ui_handle, state).await;
+                    });
+                }
+                while set.join_next().await.is_some() {}
+
+                let failed = crate::queue::snapshot()
+                    .iter()
+                    .filter(|j| matches!(j.status, crate::queue::JobStatus::Failed(_)))
+                    .count();
+                let message = if failed == 0 {
+                    "Hàng đợi hoàn tất!".to_string()
+                } else {
+                    format!("Hàng đợi hoàn tất, {} job lỗi", failed)
+                };
+                update_status(&ui_handle, message, 1.0, failed > 0);
+                let _ = ui_handle.upgrade_in_event_loop(|ui| {
+                    ui.set_is_queue_running(false);
+                    refresh_job_rows(&ui);
+                });
+            });
+        }
+    });
+
+    ui.on_remove_job({
+        let ui_handle = ui.as_weak();
+        move |id| {
+            let Some(ui) = ui_handle.upgrade() else { return; };
+            crate::queue::remove(id as u64);
+            refresh_job_rows(&ui);
+        }
+    });
+
+    ui.on_set_job_limits({
+        let ui_handle = ui.as_weak();
+        move |id, priority, concurrency| {
+            let Some(priority) = crate::scheduler::Priority::parse(&priority) else { return; };
+            let max_concurrency = match concurrency.trim().parse::<usize>() {
+                Ok(n) if n > 0 => n.min(crate::scheduler::total_concurrency()),
+                _ => return,
+            };
+            let limits = crate::scheduler::JobLimits { priority, max_concurrency };
+            info!("Queue job #{} limits: {} / {}", id, priority.as_str(), max_concurrency);
+            crate::queue::set_limits(id as u64, limits);
+            refresh_job_row(&ui_handle, id as u64);
+        }
+    });
+
+    ui.on_clear_finished_jobs({
+        let ui_handle = ui.as_weak();
+        move || {
+            let Some(ui) = ui_handle.upgrade() else { return; };
+            crate::queue::clear_finished();
+            refresh_job_rows(&ui);
+        }
+    });
+}
+
+pub fn setup_select_log_path_handler(ui: &AppWindow, state: &AppState) {
+    let ui_handle = ui.as_weak();
+    let state = state.clone();
+    ui.on_select_log_path(move || {
+        let ui_handle = ui_handle.clone();
+        let state = state.clone();
+        spawn_file_dialog(&ui_handle.clone(), || rfd::AsyncFileDialog::new().pick_folder(), move |ui, picked| {
+            let Some(path) = picked.map(|h| h.path().to_path_buf()) else { return; };
+            let path_str = path.to_string_lossy().to_string();
+
+            // Validate that the path is writable
+            let test_file = path.join(".s3sync_write_test");
+            match std::fs::File::create(&test_file) {
+                Ok(_) => {
+                    // Clean up test file
+                    let _ = std::fs::remove_file(&test_file);
+                }
+                Err(e) => {
+                    error!("Log path is not writable: {:?}", e);
+                    update_status(
                         &ui_handle,
                         format!("Thư mục log không có quyền ghi: {}", e),
                         0.0,
@@ -433,107 +1400,1233 @@ pub fn setup_select_log_path_handler(ui: &AppWindow) {
                 }
             }
 
-            // Save to config file
-            let mut config = crate::config::load_config();
-            config.log_path = path_str.clone();
-            if let Err(e) = crate::config::save_config(&config) {
+            // Save to config file
+            let result = state.save(|config| {
+                config.log_path = crate::config::store_user_path(&path_str);
+            });
+            if let Err(e) = result {
+                error!("Failed to save config: {:?}", e);
+                update_status(&ui_handle, format!("Lỗi lưu cấu hình: {}", e), 0.0, true);
+            } else {
+                info!("Config saved: log_path = {}", path_str);
+                update_status(&ui_handle, "Đã lưu đường dẫn log".to_string(), 0.0, false);
+            }
+
+            ui.set_log_path(path_str.into());
+        });
+    });
+}
+
+pub fn setup_open_log_folder_handler(ui: &AppWindow) {
+    let ui_handle = ui.as_weak();
+    ui.on_open_log_folder(move || {
+        if let Some(ui) = ui_handle.upgrade() {
+            let log_path = ui.get_log_path().to_string();
+            if !log_path.is_empty() {
+                let ui_handle_cloned = ui_handle.clone();
+                let _ = ui_handle_cloned.upgrade_in_event_loop(|ui| {
+                    ui.set_is_opening_log(true);
+                });
+
+                let spawn_result = crate::utils::open_with_default_app(std::path::Path::new(&log_path));
+
+                // Reset button state immediately after spawn attempt
+                let ui_handle_for_reset = ui_handle.clone();
+                match spawn_result {
+                    Ok(_) => {
+                        info!("Opened log folder: {}", log_path);
+                    }
+                    Err(e) => {
+                        error!("Failed to open log folder: {:?}", e);
+                        update_status(
+                            &ui_handle_for_reset,
+                            format!("Không thể mở thư mục: {}", e),
+                            0.0,
+                            true,
+                        );
+                    }
+                }
+
+                // Reset is_opening_log immediately (no arbitrary delay)
+                let _ = ui_handle_for_reset.upgrade_in_event_loop(|ui| {
+                    ui.set_is_opening_log(false);
+                });
+            }
+        }
+    });
+}
+
+/// Sets up the log verbosity handler: applies the level at runtime and persists it.
+pub fn setup_log_level_handler(ui: &AppWindow, state: &AppState) {
+    ui.on_log_level_changed({
+        let ui_handle = ui.as_weak();
+        let state = state.clone();
+        move |level| {
+            if let Err(e) = crate::logging::set_log_level(&level) {
+                error!("{}", e);
+                update_status(&ui_handle, e, 0.0, true);
+                return;
+            }
+
+            let result = state.save(|config| {
+                config.log_level = level.to_string();
+            });
+            if let Err(e) = result {
+                error!("Failed to save config: {:?}", e);
+                update_status(&ui_handle, format!("Lỗi lưu cấu hình: {}", e), 0.0, true);
+            } else {
+                info!("Config saved: log_level = {}", level);
+                update_status(&ui_handle, format!("Log level: {}", level), 0.0, false);
+            }
+        }
+    });
+}
+
+/// Sets up the sync log file mode handler (one daily file or one file per session).
+pub fn setup_log_file_mode_handler(ui: &AppWindow, state: &AppState) {
+    ui.on_log_file_mode_changed({
+        let ui_handle = ui.as_weak();
+        let state = state.clone();
+        move |mode| {
+            let Some(mode) = crate::config::LogFileMode::parse(&mode) else { return };
+            let result = state.save(|config| {
+                config.log_file_mode = mode;
+            });
+            if let Err(e) = result {
+                error!("Failed to save config: {:?}", e);
+                update_status(&ui_handle, format!("Lỗi lưu cấu hình: {}", e), 0.0, true);
+            } else {
+                info!("Config saved: log_file_mode = {}", mode.as_str());
+                let text = match mode {
+                    crate::config::LogFileMode::Daily => "File log: một file mỗi ngày",
+                    crate::config::LogFileMode::Session => "File log: một file mỗi phiên sync",
+                };
+                update_status(&ui_handle, text.to_string(), 0.0, false);
+            }
+        }
+    });
+}
+
+/// Sets up the bucket storage usage handler.
+pub fn setup_bucket_usage_handler(ui: &AppWindow) {
+    ui.on_load_bucket_usage({
+        let ui_handle = ui.as_weak();
+        move || {
+            let Some(ui) = ui_handle.upgrade() else { return; };
+            let params = AwsParams::from_ui(&ui);
+
+            if let Some(err) = params.validate() {
+                ui.set_usage_summary(err.into());
+                return;
+            }
+
+            ui.set_is_loading_usage(true);
+            ui.set_usage_rows(ModelRc::from(Rc::new(VecModel::<UsageRow>::default())));
+            ui.set_usage_summary("Đang quét bucket...".into());
+
+            let ui_handle = ui_handle.clone();
+            tokio::spawn(async move {
+                let result = match params.create_client().await {
+                    Ok(client) => {
+                        let progress_handle = ui_handle.clone();
+                        crate::usage::collect_bucket_usage(&client, &params.bucket, move |seen| {
+                            let _ = progress_handle.upgrade_in_event_loop(move |ui| {
+                                ui.set_usage_summary(format!("Đang quét... {} objects", seen).into());
+                            });
+                        })
+                        .await
+                    }
+                    Err(e) => Err(format!("Lỗi tạo client: {}", e)),
+                };
+
+                match result {
+                    Ok(usage) => {
+                        let mut prefixes: Vec<_> = usage.prefixes.iter().collect();
+                        prefixes.sort_by_key(|(_, u)| std::cmp::Reverse(u.bytes));
+                        let rows: Vec<(String, String, String, String)> = prefixes
+                            .into_iter()
+                            .map(|(prefix, u)| {
+                                (
+                                    prefix.clone(),
+                                    u.objects.to_string(),
+                                    crate::utils::format_size(u.bytes),
+                                    format!("{:.2}", u.monthly_cost()),
+                                )
+                            })
+                            .collect();
+                        let total = usage.total();
+                        let summary = format!(
+                            "Tổng: {} objects | {} | ~${:.2}/tháng (ước tính, giá us-east-1)",
+                            total.objects,
+                            crate::utils::format_size(total.bytes),
+                            total.monthly_cost()
+                        );
+                        info!("Bucket usage for {}: {}", params.bucket, summary);
+
+                        let _ = ui_handle.upgrade_in_event_loop(move |ui| {
+                            let rows: Vec<UsageRow> = rows
+                                .into_iter()
+                                .map(|(prefix, objects, size, cost)| UsageRow {
+                                    prefix: prefix.into(),
+                                    objects: objects.into(),
+                                    size: size.into(),
+                                    cost: cost.into(),
+                                })
+                                .collect();
+                            ui.set_usage_rows(ModelRc::from(Rc::new(VecModel::from(rows))));
+                            ui.set_usage_summary(summary.into());
+                            ui.set_is_loading_usage(false);
+                        });
+                    }
+                    Err(e) => {
+                        error!("Bucket usage failed: {}", e);
+                        let _ = ui_handle.upgrade_in_event_loop(move |ui| {
+                            ui.set_usage_summary(e.into());
+                            ui.set_is_loading_usage(false);
+                        });
+                    }
+                }
+            });
+        }
+    });
+}
+
+/// Sets up the delete remote prefix handler.
+pub fn setup_delete_prefix_handler(ui: &AppWindow) {
+    ui.on_delete_prefix({
+        let ui_handle = ui.as_weak();
+        move |prefix| {
+            let Some(ui) = ui_handle.upgrade() else { return; };
+            let params = AwsParams::from_ui(&ui);
+
+            if let Some(err) = params.validate() {
+                ui.set_delete_prefix_status(err.into());
+                return;
+            }
+            // The dialog already requires the prefix to be typed twice; re-check here as the last guard
+            if prefix != ui.get_delete_prefix_confirm() {
+                ui.set_delete_prefix_status("Prefix xác nhận không khớp".into());
+                return;
+            }
+            let Some(normalized) = crate::s3_client::normalize_delete_prefix(&prefix) else {
+                ui.set_delete_prefix_status("Không được xóa toàn bộ bucket (prefix rỗng)".into());
+                return;
+            };
+
+            ui.set_is_deleting_prefix(true);
+            ui.set_delete_prefix_status(format!("Đang xóa '{}'...", normalized).into());
+            info!("Deleting prefix '{}' in bucket {}", normalized, params.bucket);
+
+            let ui_handle = ui_handle.clone();
+            tokio::spawn(async move {
+                let result = match params.create_client().await {
+                    Ok(client) => {
+                        let progress_handle = ui_handle.clone();
+                        crate::s3_client::delete_prefix(&client, &params.bucket, &normalized, move |deleted| {
+                            let _ = progress_handle.upgrade_in_event_loop(move |ui| {
+                                ui.set_delete_prefix_status(format!("Đã xóa {} objects...", deleted).into());
+                            });
+                        })
+                        .await
+                    }
+                    Err(e) => Err(format!("Lỗi tạo client: {}", e)),
+                };
+
+                let (message, is_error) = match result {
+                    Ok(0) => (format!("Không có object nào dưới '{}'", normalized), false),
+                    Ok(n) => (format!("Đã xóa {} objects dưới '{}'", n, normalized), false),
+                    Err(e) => {
+                        error!("Delete prefix failed: {}", e);
+                        (e, true)
+                    }
+                };
+                update_status(&ui_handle, message.clone(), 0.0, is_error);
+                let _ = ui_handle.upgrade_in_event_loop(move |ui| {
+                    ui.set_delete_prefix_status(message.into());
+                    ui.set_is_deleting_prefix(false);
+                    if !is_error {
+                        ui.set_delete_prefix_confirm("".into());
+                    }
+                });
+            });
+        }
+    });
+}
+
+/// Sets up the bucket browser handlers (navigate prefixes, open single objects).
+pub fn setup_bucket_browser_handlers(ui: &AppWindow) {
+    ui.on_browse_bucket({
+        let ui_handle = ui.as_weak();
+        move |prefix| {
+            let Some(ui) = ui_handle.upgrade() else { return; };
+            let params = AwsParams::from_ui(&ui);
+
+            if let Some(err) = params.validate() {
+                ui.set_browser_status(err.into());
+                return;
+            }
+
+            let prefix = prefix.to_string();
+            ui.set_is_browsing(true);
+            ui.set_browser_status("Đang tải...".into());
+
+            let ui_handle = ui_handle.clone();
+            tokio::spawn(async move {
+                let result = match params.create_client().await {
+                    Ok(client) => crate::s3_client::list_bucket_level(&client, &params.bucket, &prefix).await,
+                    Err(e) => Err(format!("Lỗi tạo client: {}", e)),
+                };
+
+                match result {
+                    Ok(level) => {
+                        let parent = prefix
+                            .trim_end_matches('/')
+                            .rsplit_once('/')
+                            .map(|(p, _)| format!("{}/", p))
+                            .unwrap_or_default();
+                        let mut entries: Vec<(String, String, bool, String)> = Vec::new();
+                        if !prefix.is_empty() {
+                            entries.push(("..".to_string(), parent, true, String::new()));
+                        }
+                        for p in &level.prefixes {
+                            let name = p.strip_prefix(&prefix).unwrap_or(p).to_string();
+                            entries.push((name, p.clone(), true, String::new()));
+                        }
+                        for (key, size) in &level.objects {
+                            let name = key.strip_prefix(&prefix).unwrap_or(key).to_string();
+                            entries.push((name, key.clone(), false, crate::utils::format_size(*size)));
+                        }
+                        let status = format!("{} thư mục, {} objects", level.prefixes.len(), level.objects.len());
+
+                        let _ = ui_handle.upgrade_in_event_loop(move |ui| {
+                            let entries: Vec<BrowserEntry> = entries
+                                .into_iter()
+                                .map(|(name, key, is_prefix, size)| BrowserEntry {
+                                    name: name.into(),
+                                    key: key.into(),
+                                    is_prefix,
+                                    size: size.into(),
+                                })
+                                .collect();
+                            ui.set_browser_entries(ModelRc::from(Rc::new(VecModel::from(entries))));
+                            ui.set_browser_can_restore(crate::s3_client::is_trash_session_prefix(&prefix));
+                            ui.set_browser_prefix(prefix.into());
+                            ui.set_browser_status(status.into());
+                            ui.set_is_browsing(false);
+                        });
+                    }
+                    Err(e) => {
+                        error!("Browse bucket failed: {}", e);
+                        let _ = ui_handle.upgrade_in_event_loop(move |ui| {
+                            ui.set_browser_status(e.into());
+                            ui.set_is_browsing(false);
+                        });
+                    }
+                }
+            });
+        }
+    });
+
+    ui.on_open_remote_object({
+        let ui_handle = ui.as_weak();
+        move |key| {
+            let Some(ui) = ui_handle.upgrade() else { return; };
+            let params = AwsParams::from_ui(&ui);
+            let key = key.to_string();
+
+            ui.set_is_browsing(true);
+            ui.set_browser_status(format!("Đang tải {}...", key).into());
+
+            let ui_handle = ui_handle.clone();
+            tokio::spawn(async move {
+                let result = match params.create_client().await {
+                    Ok(client) => crate::s3_client::download_object_to_temp(&client, &params.bucket, &key).await,
+                    Err(e) => Err(format!("Lỗi tạo client: {}", e)),
+                };
+
+                let status = match result {
+                    Ok(path) => match crate::utils::open_with_default_app(&path) {
+                        Ok(_) => format!("Đã mở {}", path.display()),
+                        Err(e) => {
+                            error!("Failed to open downloaded object: {:?}", e);
+                            format!("Đã tải về {} nhưng không thể mở: {}", path.display(), e)
+                        }
+                    },
+                    Err(e) => {
+                        error!("Download object failed: {}", e);
+                        e
+                    }
+                };
+                let _ = ui_handle.upgrade_in_event_loop(move |ui| {
+                    ui.set_browser_status(status.into());
+                    ui.set_is_browsing(false);
+                });
+            });
+        }
+    });
+
+    ui.on_restore_trash({
+        let ui_handle = ui.as_weak();
+        move |trash_prefix| {
+            let Some(ui) = ui_handle.upgrade() else { return; };
+            let params = AwsParams::from_ui(&ui);
+            let trash_prefix = trash_prefix.to_string();
+            if !crate::s3_client::is_trash_session_prefix(&trash_prefix) {
+                return;
+            }
+
+            ui.set_is_browsing(true);
+            ui.set_browser_status(format!("Đang khôi phục {}...", trash_prefix).into());
+            info!("Restoring trash session s3://{}/{}", params.bucket, trash_prefix);
+
+            let ui_handle = ui_handle.clone();
+            tokio::spawn(async move {
+                let result = match params.create_client().await {
+                    Ok(client) => {
+                        let progress_handle = ui_handle.clone();
+                        crate::s3_client::restore_trash_session(
+                            std::sync::Arc::new(client),
+                            &params.bucket,
+                            &trash_prefix,
+                            move |done, total| {
+                                let _ = progress_handle.upgrade_in_event_loop(move |ui| {
+                                    ui.set_browser_status(format!("Đã khôi phục {}/{}", done, total).into());
+                                });
+                            },
+                        )
+                        .await
+                    }
+                    Err(e) => Err(format!("Lỗi tạo client: {}", e)),
+                };
+
+                let status = match result {
+                    Ok(n) => format!("Đã khôi phục {} objects về key gốc", n),
+                    Err(e) => {
+                        error!("Restore trash failed: {}", e);
+                        e
+                    }
+                };
+                let _ = ui_handle.upgrade_in_event_loop(move |ui| {
+                    ui.set_browser_status(status.into());
+                    ui.set_is_browsing(false);
+                });
+            });
+        }
+    });
+}
+
+/// (bucket, full key) of unchanged STANDARD objects found by the last comparison.
+static COLD_CANDIDATES: Lazy<std::sync::Mutex<Vec<(String, String)>>> = Lazy::new(|| std::sync::Mutex::new(Vec::new()));
+/// (bucket, full key) of Glacier / Deep Archive objects found by the last comparison.
+static ARCHIVED_CANDIDATES: Lazy<std::sync::Mutex<Vec<(String, String)>>> = Lazy::new(|| std::sync::Mutex::new(Vec::new()));
+
+/// Sets up the remote-to-remote prefix comparison handler. Objects on either side that haven't
+/// changed for the given number of months are listed as "Cold" and can be moved to IA/Glacier.
+pub fn setup_compare_prefixes_handler(ui: &AppWindow) {
+    ui.on_compare_prefixes({
+        let ui_handle = ui.as_weak();
+        move |bucket_a, prefix_a, bucket_b, prefix_b, cold_months| {
+            let Some(ui) = ui_handle.upgrade() else { return; };
+            let params = AwsParams::from_ui(&ui);
+            let (bucket_a, prefix_a) = (bucket_a.to_string(), prefix_a.to_string());
+            let (bucket_b, prefix_b) = (bucket_b.to_string(), prefix_b.to_string());
+            // Inventory manifest / saved listing used instead of a live listing, if chosen
+            let source_a = ui.get_compare_source_a().to_string();
+            let source_b = ui.get_compare_source_b().to_string();
+            let cold_months = match cold_months.trim() {
+                "" => None,
+                text => match text.parse::<u32>() {
+                    Ok(months) if months > 0 => Some(months),
+                    _ => {
+                        ui.set_compare_summary("Số tháng phải là số nguyên dương".into());
+                        return;
+                    }
+                },
+            };
+
+            for bucket in [&bucket_a, &bucket_b] {
+                if let Some(err) = crate::utils::validate_credentials(&params.acc_key, &params.sec_key, &params.sess_token, bucket) {
+                    ui.set_compare_summary(err.into());
+                    return;
+                }
+            }
+
+            ui.set_is_comparing(true);
+            ui.set_cold_count(0);
+            ui.set_archived_count(0);
+            COLD_CANDIDATES.lock().unwrap().clear();
+            ARCHIVED_CANDIDATES.lock().unwrap().clear();
+            ui.set_compare_rows(ModelRc::from(Rc::new(VecModel::<DiffRow>::default())));
+            ui.set_compare_summary("Đang list hai prefix...".into());
+
+            let ui_handle = ui_handle.clone();
+            tokio::spawn(async move {
+                let result = match params.create_client().await {
+                    Ok(client) => {
+                        let load = |bucket: &str, prefix: &str, source: &str| {
+                            let (client, bucket, prefix, source) =
+                                (&client, bucket.to_string(), prefix.to_string(), source.to_string());
+                            async move {
+                                if source.is_empty() {
+                                    crate::diff::list_remote_objects(client, &bucket, &prefix).await
+                                } else {
+                                    crate::inventory::load_listing(client, std::path::Path::new(&source), &prefix).await
+                                }
+                            }
+                        };
+                        let (a, b) = tokio::join!(
+                            load(&bucket_a, &prefix_a, &source_a),
+                            load(&bucket_b, &prefix_b, &source_b)
+                        );
+                        a.and_then(|a| b.map(|b| (a, b)))
+                    }
+                    Err(e) => Err(format!("Lỗi tạo client: {}", e)),
+                };
+
+                match result {
+                    Ok((listing_a, listing_b)) => {
+                        let (count_a, count_b) = (listing_a.len(), listing_b.len());
+                        let diff = crate::diff::diff_listings(&listing_a, &listing_b);
+                        let mut summary = if diff.is_identical() {
+                            format!("Giống hệt nhau: {} objects", diff.identical)
+                        } else {
+                            format!(
+                                "A: {} | B: {} | Chỉ A: {} | Chỉ B: {} | Khác: {} | Giống: {}",
+                                count_a,
+                                count_b,
+                                diff.only_a.len(),
+                                diff.only_b.len(),
+                                diff.changed.len(),
+                                diff.identical
+                            )
+                        };
+                        info!(
+                            "Compare s3://{}/{} vs s3://{}/{}: {}",
+                            bucket_a, prefix_a, bucket_b, prefix_b, summary
+                        );
+
+                        let mut rows: Vec<(String, String, String)> = Vec::new();
+                        rows.extend(diff.only_a.into_iter().map(|k| ("Only A".to_string(), k, String::new())));
+                        rows.extend(diff.only_b.into_iter().map(|k| ("Only B".to_string(), k, String::new())));
+                        rows.extend(diff.changed.into_iter().map(|(k, why)| ("Changed".to_string(), k, why)));
+
+                        if let Some(months) = cold_months {
+                            let now = chrono::Utc::now().timestamp();
+                            let mut candidates = Vec::new();
+                            for (side, bucket, prefix, listing) in
+                                [("A", &bucket_a, &prefix_a, &listing_a), ("B", &bucket_b, &prefix_b, &listing_b)]
+                            {
+                                // Comparing a prefix with itself would list every object twice
+                                if side == "B"
+                                    && bucket_a == bucket_b
+                                    && crate::diff::normalize_list_prefix(&prefix_a) == crate::diff::normalize_list_prefix(prefix)
+                                {
+                                    continue;
+                                }
+                                let full_prefix = crate::diff::normalize_list_prefix(prefix);
+                                for (key, age) in crate::diff::cold_objects(listing, now, months) {
+                                    candidates.push((bucket.clone(), format!("{}{}", full_prefix, key)));
+                                    rows.push((format!("Cold {}", side), key, format!("{} tháng không đổi", age)));
+                                }
+                            }
+                            if !candidates.is_empty() {
+                                summary.push_str(&format!(" | Cũ hơn {} tháng: {}", months, candidates.len()));
+                            }
+                            *COLD_CANDIDATES.lock().unwrap() = candidates;
+                        }
+                        let cold_count = COLD_CANDIDATES.lock().unwrap().len() as i32;
+
+                        // Archived objects can't be read or copied until restored
+                        let mut archived = Vec::new();
+                        for (side, bucket, prefix, listing) in
+                            [("A", &bucket_a, &prefix_a, &listing_a), ("B", &bucket_b, &prefix_b, &listing_b)]
+                        {
+                            let full_prefix = crate::diff::normalize_list_prefix(prefix);
+                            for (key, object) in listing.iter().filter(|(_, o)| crate::restore::is_archived(&o.storage_class)) {
+                                if !archived.iter().any(|(b, k)| b == bucket && *k == format!("{}{}", full_prefix, key)) {
+                                    archived.push((bucket.clone(), format!("{}{}", full_prefix, key)));
+                                    rows.push((format!("Archived {}", side), key.clone(), object.storage_class.clone()));
+                                }
+                            }
+                        }
+                        if !archived.is_empty() {
+                            summary.push_str(&format!(" | Glacier: {}", archived.len()));
+                        }
+                        let archived_count = archived.len() as i32;
+                        *ARCHIVED_CANDIDATES.lock().unwrap() = archived;
+
+                        let _ = ui_handle.upgrade_in_event_loop(move |ui| {
+                            let rows: Vec<DiffRow> = rows
+                                .into_iter()
+                                .map(|(kind, key, detail)| DiffRow {
+                                    kind: kind.into(),
+                                    key: key.into(),
+                                    detail: detail.into(),
+                                })
+                                .collect();
+                            ui.set_compare_rows(ModelRc::from(Rc::new(VecModel::from(rows))));
+                            ui.set_compare_summary(summary.into());
+                            ui.set_cold_count(cold_count);
+                            ui.set_archived_count(archived_count);
+                            ui.set_is_comparing(false);
+                        });
+                    }
+                    Err(e) => {
+                        error!("Compare prefixes failed: {}", e);
+                        let _ = ui_handle.upgrade_in_event_loop(move |ui| {
+                            ui.set_compare_summary(e.into());
+                            ui.set_is_comparing(false);
+                        });
+                    }
+                }
+            });
+        }
+    });
+}
+
+/// Sets up choosing an inventory manifest / saved listing as a comparison side, and exporting
+/// a live listing for later comparisons.
+pub fn setup_listing_source_handlers(ui: &AppWindow) {
+    ui.on_pick_listing_source({
+        let ui_handle = ui.as_weak();
+        move |side| {
+            let open = || rfd::AsyncFileDialog::new().add_filter("Inventory / listing", &["json", "csv", "gz"]).pick_file();
+            spawn_file_dialog(&ui_handle, open, move |ui, picked| {
+                let Some(handle) = picked else { return; };
+                let path: slint::SharedString = handle.path().to_string_lossy().to_string().into();
+                if side == 0 {
+                    ui.set_compare_source_a(path);
+                } else {
+                    ui.set_compare_source_b(path);
+                }
+            });
+        }
+    });
+
+    ui.on_export_listing({
+        let ui_handle = ui.as_weak();
+        move |bucket, prefix| {
+            let Some(ui) = ui_handle.upgrade() else { return; };
+            let params = AwsParams::from_ui(&ui);
+            let (bucket, prefix) = (bucket.to_string(), prefix.to_string());
+            if let Some(err) = crate::utils::validate_credentials(&params.acc_key, &params.sec_key, &params.sess_token, &bucket) {
+                ui.set_compare_summary(err.into());
+                return;
+            }
+            let file_name = format!("listing-{}.json", bucket);
+            let open = || rfd::AsyncFileDialog::new().set_file_name(file_name).add_filter("JSON", &["json"]).save_file();
+            spawn_file_dialog(&ui_handle, open, move |ui, picked| {
+                let Some(path) = picked.map(|h| h.path().to_path_buf()) else { return; };
+                ui.set_is_comparing(true);
+                ui.set_compare_summary(format!("Đang list s3://{}/{}...", bucket, prefix).into());
+
+                let ui_handle = ui.as_weak();
+                tokio::spawn(async move {
+                    let result = match params.create_client().await {
+                        Ok(client) => crate::diff::list_remote_objects(&client, &bucket, &prefix).await.and_then(|listing| {
+                            crate::inventory::save_listing(&path, &listing).map(|_| listing.len())
+                        }),
+                        Err(e) => Err(format!("Lỗi tạo client: {}", e)),
+                    };
+                    let summary = match result {
+                        Ok(count) => format!("Đã lưu {} objects vào {}", count, path.display()),
+                        Err(e) => {
+                            error!("Export listing failed: {}", e);
+                            e
+                        }
+                    };
+                    let _ = ui_handle.upgrade_in_event_loop(move |ui| {
+                        ui.set_compare_summary(summary.into());
+                        ui.set_is_comparing(false);
+                    });
+                });
+            });
+        }
+    });
+}
+
+/// Sets up the handler that moves the cold objects of the last comparison to another storage class.
+pub fn setup_transition_cold_handler(ui: &AppWindow) {
+    ui.set_transition_classes(ModelRc::from(Rc::new(VecModel::from(
+        crate::diff::TRANSITION_CLASSES.iter().map(|c| slint::SharedString::from(*c)).collect::<Vec<_>>(),
+    ))));
+    ui.on_transition_cold({
+        let ui_handle = ui.as_weak();
+        move |storage_class| {
+            let Some(ui) = ui_handle.upgrade() else { return; };
+            let candidates = std::mem::take(&mut *COLD_CANDIDATES.lock().unwrap());
+            if candidates.is_empty() {
+                return;
+            }
+            let params = AwsParams::from_ui(&ui);
+            let storage_class = storage_class.to_string();
+            ui.set_is_comparing(true);
+            ui.set_cold_count(0);
+
+            let ui_handle = ui_handle.clone();
+            tokio::spawn(async move {
+                let total = candidates.len();
+                let mut by_bucket: std::collections::BTreeMap<String, Vec<String>> = std::collections::BTreeMap::new();
+                for (bucket, key) in candidates {
+                    by_bucket.entry(bucket).or_default().push(key);
+                }
+                let result = match params.create_client().await {
+                    Ok(client) => {
+                        let client = std::sync::Arc::new(client);
+                        let mut moved = 0usize;
+                        let mut result = Ok(());
+                        for (bucket, keys) in by_bucket {
+                            let progress_handle = ui_handle.clone();
+                            let already = moved;
+                            match crate::s3_client::transition_storage_class(
+                                client.clone(),
+                                &bucket,
+                                keys,
+                                &storage_class,
+                                move |done, _| {
+                                    let text = format!("Đang chuyển storage class ({}/{})", already + done, total);
+                                    let _ = progress_handle.upgrade_in_event_loop(move |ui| ui.set_compare_summary(text.into()));
+                                },
+                            )
+                            .await
+                            {
+                                Ok(n) => moved += n,
+                                Err(e) => {
+                                    result = Err(e);
+                                    break;
+                                }
+                            }
+                        }
+                        result.map(|_| moved)
+                    }
+                    Err(e) => Err(format!("Lỗi tạo client: {}", e)),
+                };
+                let summary = match result {
+                    Ok(moved) => format!("Đã chuyển {} object sang {}", moved, storage_class),
+                    Err(e) => {
+                        error!("Storage class transition failed: {}", e);
+                        e
+                    }
+                };
+                let _ = ui_handle.upgrade_in_event_loop(move |ui| {
+                    ui.set_compare_summary(summary.into());
+                    ui.set_is_comparing(false);
+                });
+            });
+        }
+    });
+}
+
+/// Sets up restoring the Glacier / Deep Archive objects of the last comparison. Restores are
+/// tracked until available, also across restarts of the app.
+pub fn setup_restore_archived_handler(ui: &AppWindow, state: &AppState) {
+    let config = state.config();
+    ui.set_restore_tiers(ModelRc::from(Rc::new(VecModel::from(
+        crate::restore::RESTORE_TIERS.iter().map(|t| slint::SharedString::from(*t)).collect::<Vec<_>>(),
+    ))));
+    ui.set_restore_tier(config.restore.tier.clone().into());
+    ui.set_restore_days(config.restore.days.to_string().into());
+
+    ui.on_restore_archived({
+        let ui_handle = ui.as_weak();
+        let state = state.clone();
+        move |tier, days| {
+            let Some(ui) = ui_handle.upgrade() else { return; };
+            let days = match days.trim().parse::<u32>() {
+                Ok(days) if days > 0 => days,
+                _ => {
+                    ui.set_compare_summary("Số ngày giữ bản restore phải là số nguyên dương".into());
+                    return;
+                }
+            };
+            state.update(|config| config.restore = crate::config::RestoreOptions { tier: tier.to_string(), days });
+            let candidates = std::mem::take(&mut *ARCHIVED_CANDIDATES.lock().unwrap());
+            if candidates.is_empty() {
+                return;
+            }
+            let params = AwsParams::from_ui(&ui);
+            ui.set_is_comparing(true);
+            ui.set_archived_count(0);
+
+            let ui_handle = ui_handle.clone();
+            tokio::spawn(async move {
+                let total = candidates.len();
+                let status = status_fn(&ui_handle);
+                let result = match params.create_client().await {
+                    Ok(client) => {
+                        let client = std::sync::Arc::new(client);
+                        let buckets: std::collections::BTreeSet<&String> = candidates.iter().map(|(b, _)| b).collect();
+                        for bucket in buckets {
+                            crate::restore::resume(&client, bucket, &status);
+                        }
+                        let mut result = Ok(());
+                        for (i, (bucket, key)) in candidates.iter().enumerate() {
+                            if let Err(e) = crate::restore::restore_and_track(&client, bucket, key, None, &status).await {
+                                result = Err(e);
+                                break;
+                            }
+                            let text = format!("Đang gửi yêu cầu restore ({}/{})", i + 1, total);
+                            let _ = ui_handle.upgrade_in_event_loop(move |ui| ui.set_compare_summary(text.into()));
+                        }
+                        result
+                    }
+                    Err(e) => Err(format!("Lỗi tạo client: {}", e)),
+                };
+                let summary = match result {
+                    Ok(()) => format!(
+                        "Đã gửi restore {} object ({}, giữ {} ngày) · đang chờ: {}",
+                        total,
+                        tier,
+                        days,
+                        crate::restore::pending_count()
+                    ),
+                    Err(e) => {
+                        error!("Restore request failed: {}", e);
+                        e
+                    }
+                };
+                let _ = ui_handle.upgrade_in_event_loop(move |ui| {
+                    ui.set_compare_summary(summary.into());
+                    ui.set_is_comparing(false);
+                });
+            });
+        }
+    });
+}
+
+/// Sets up the session history handlers (list sessions, replay a session to another bucket).
+pub fn setup_session_history_handlers(ui: &AppWindow) {
+    ui.on_load_sessions({
+        let ui_handle = ui.as_weak();
+        move || {
+            let Some(ui) = ui_handle.upgrade() else { return; };
+            let rows: Vec<SessionRow> = crate::history::load_sessions()
+                .into_iter()
+                .map(|s| SessionRow {
+                    id: s.id.into(),
+                    time: s.started_at.into(),
+                    bucket: s.bucket.into(),
+                    files: s.files as i32,
+                    status: if s.success { "success".into() } else { "failed".into() },
+                })
+                .collect();
+            if rows.is_empty() {
+                ui.set_replay_status("Chưa có session nào được ghi lại".into());
+            }
+            ui.set_session_rows(ModelRc::from(Rc::new(VecModel::from(rows))));
+        }
+    });
+
+    ui.on_replay_session({
+        let ui_handle = ui.as_weak();
+        move |id, target_bucket, target_prefix| {
+            let Some(ui) = ui_handle.upgrade() else { return; };
+            let params = AwsParams::from_ui(&ui);
+            let target_bucket = target_bucket.to_string();
+            let target_prefix = target_prefix.to_string();
+
+            if let Some(err) = crate::utils::validate_credentials(&params.acc_key, &params.sec_key, &params.sess_token, &target_bucket) {
+                ui.set_replay_status(err.into());
+                return;
+            }
+            let Some(session) = crate::history::find_session(&id) else {
+                ui.set_replay_status("Không tìm thấy session".into());
+                return;
+            };
+            if !session.success {
+                ui.set_replay_status("Chỉ replay được session thành công".into());
+                return;
+            }
+            if session.bucket == target_bucket && target_prefix.trim_matches('/').is_empty() {
+                ui.set_replay_status("Bucket đích trùng bucket nguồn, cần chọn prefix khác".into());
+                return;
+            }
+
+            ui.set_is_replaying(true);
+            ui.set_replay_status(format!("Đang copy {} objects...", session.keys.len()).into());
+            info!(
+                "Replaying session {} ({} keys) from {} to {}/{}",
+                session.id,
+                session.keys.len(),
+                session.bucket,
+                target_bucket,
+                target_prefix
+            );
+
+            let ui_handle = ui_handle.clone();
+            tokio::spawn(async move {
+                let result = match params.create_client().await {
+                    Ok(client) => {
+                        let progress_handle = ui_handle.clone();
+                        crate::s3_client::copy_keys(
+                            std::sync::Arc::new(client),
+                            &session.bucket,
+                            session.keys,
+                            &target_bucket,
+                            &target_prefix,
+                            move |done, total| {
+                                let _ = progress_handle.upgrade_in_event_loop(move |ui| {
+                                    ui.set_replay_status(format!("Đã copy {}/{}", done, total).into());
+                                });
+                            },
+                        )
+                        .await
+                    }
+                    Err(e) => Err(format!("Lỗi tạo client: {}", e)),
+                };
+
+                let (message, is_error) = match result {
+                    Ok(n) => (format!("Đã replay {} objects sang {}", n, target_bucket), false),
+                    Err(e) => {
+                        error!("Replay session failed: {}", e);
+                        (e, true)
+                    }
+                };
+                update_status(&ui_handle, message.clone(), if is_error { 0.0 } else { 1.0 }, is_error);
+                let _ = ui_handle.upgrade_in_event_loop(move |ui| {
+                    ui.set_replay_status(message.into());
+                    ui.set_is_replaying(false);
+                });
+            });
+        }
+    });
+}
+
+/// Sets up the metrics file location handler.
+pub fn setup_select_metrics_path_handler(ui: &AppWindow, state: &AppState) {
+    let ui_handle = ui.as_weak();
+    let state = state.clone();
+    ui.on_select_metrics_path(move || {
+        let ui_handle = ui_handle.clone();
+        let state = state.clone();
+        spawn_file_dialog(&ui_handle.clone(), || rfd::AsyncFileDialog::new().pick_folder(), move |_, picked| {
+            let Some(dir) = picked.map(|h| h.path().to_path_buf()) else { return; };
+            let path = dir.join(crate::metrics::METRICS_FILE_NAME);
+
+            // Write the current counters right away so the path is validated and collectors see the file
+            if let Err(e) = crate::metrics::write_metrics_file(&path, &crate::metrics::snapshot()) {
+                error!("Metrics path is not writable: {:?}", e);
+                update_status(&ui_handle, format!("Không thể ghi file metrics: {}", e), 0.0, true);
+                return;
+            }
+
+            let path_str = path.to_string_lossy().to_string();
+            let result = state.save(|config| {
+                config.metrics_path = path_str.clone();
+            });
+            if let Err(e) = result {
                 error!("Failed to save config: {:?}", e);
-                crate::utils::update_status(&ui_handle, format!("Lỗi lưu cấu hình: {}", e), 0.0, true);
+                update_status(&ui_handle, format!("Lỗi lưu cấu hình: {}", e), 0.0, true);
             } else {
-                info!("Config saved: log_path = {}", path_str);
-                crate::utils::update_status(&ui_handle, "Đã lưu đường dẫn log".to_string(), 0.0, false);
+                info!("Config saved: metrics_path = {}", path_str);
+                update_status(&ui_handle, format!("Metrics: {}", path_str), 0.0, false);
             }
+        });
+    });
+}
 
-            let _ = ui_handle.upgrade_in_event_loop(move |ui| {
-                ui.set_log_path(path_str.into());
+/// Sets up the email report settings handler.
+/// Fills the Settings window fields.
+pub fn set_app_settings(ui: &AppWindow, settings: &crate::config::AdvancedSettings) {
+    ui.set_concurrency_text(settings.concurrency.to_string().into());
+    ui.set_cache_ttl_text(settings.cache_ttl_secs.to_string().into());
+    ui.set_max_attempts_text(settings.max_attempts.to_string().into());
+    ui.set_connect_timeout_text(settings.connect_timeout_secs.to_string().into());
+    ui.set_operation_timeout_text(settings.operation_timeout_secs.to_string().into());
+    ui.set_check_updates(settings.check_updates);
+    ui.set_smart_match(settings.smart_match);
+}
+
+/// Fills the Settings window's S3 path suggestion fields.
+pub fn set_prefix_rules(ui: &AppWindow, rules: &crate::config::PrefixRules) {
+    ui.set_system_folders_text(rules.system_folders.join(", ").into());
+}
+
+pub fn setup_app_settings_handler(ui: &AppWindow, state: &AppState) {
+    ui.on_save_app_settings({
+        let ui_handle = ui.as_weak();
+        let state = state.clone();
+        move || {
+            let Some(ui) = ui_handle.upgrade() else { return; };
+
+            let (Ok(concurrency), Ok(cache_ttl_secs), Ok(max_attempts), Ok(connect_timeout_secs), Ok(operation_timeout_secs)) = (
+                ui.get_concurrency_text().trim().parse::<usize>(),
+                ui.get_cache_ttl_text().trim().parse::<u64>(),
+                ui.get_max_attempts_text().trim().parse::<u32>(),
+                ui.get_connect_timeout_text().trim().parse::<u64>(),
+                ui.get_operation_timeout_text().trim().parse::<u64>(),
+            ) else {
+                ui.set_app_settings_error("Tất cả các giá trị phải là số nguyên không âm".into());
+                return;
+            };
+            let settings = crate::config::AdvancedSettings {
+                concurrency,
+                cache_ttl_secs,
+                max_attempts,
+                connect_timeout_secs,
+                operation_timeout_secs,
+                check_updates: ui.get_check_updates(),
+                smart_match: ui.get_smart_match(),
+            };
+            if let Err(e) = settings.validate() {
+                ui.set_app_settings_error(e.into());
+                return;
+            }
+
+            let prefix_rules = crate::config::PrefixRules {
+                system_folders: ui
+                    .get_system_folders_text()
+                    .split(',')
+                    .map(|f| f.trim().trim_matches(['/', '\\']).to_string())
+                    .filter(|f| !f.is_empty())
+                    .collect(),
+            };
+
+            let result = state.save(|config| {
+                config.settings = settings;
+                config.prefix_rules = prefix_rules;
             });
+            if let Err(e) = result {
+                error!("Failed to save settings: {:?}", e);
+                ui.set_app_settings_error(format!("Lỗi lưu cấu hình: {}", e).into());
+                return;
+            }
+            crate::config::apply_settings(settings);
+
+            info!("Settings saved: {:?}", settings);
+            ui.set_app_settings_error("".into());
+            ui.set_show_app_settings(false);
+            update_status(&ui_handle, "Đã lưu Settings".to_string(), 0.0, false);
         }
     });
 }
 
-pub fn setup_open_log_folder_handler(ui: &AppWindow) {
+/// Sets up the first-run wizard. Its steps reuse the main window's handlers (test access,
+/// log path, filters); finishing only records that it ran and saves the tested bucket.
+pub fn setup_onboarding_handler(ui: &AppWindow, state: &AppState) {
     let ui_handle = ui.as_weak();
-    ui.on_open_log_folder(move || {
-        if let Some(ui) = ui_handle.upgrade() {
-            let log_path = ui.get_log_path().to_string();
-            if !log_path.is_empty() {
-                let ui_handle_cloned = ui_handle.clone();
-                let _ = ui_handle_cloned.upgrade_in_event_loop(|ui| {
-                    ui.set_is_opening_log(true);
-                });
+    let state = state.clone();
+    ui.on_finish_onboarding(move |finished| {
+        let Some(ui) = ui_handle.upgrade() else { return };
+        ui.set_show_onboarding(false);
+
+        let bucket = ui.get_bucket_name().trim().to_string();
+        let region = ui.get_region().to_string();
+        if finished && ui.get_onboarding_save_bucket() && !bucket.is_empty() {
+            if !state.read().buckets.contains(&bucket) {
+                ui.invoke_add_bucket(bucket.clone().into());
+            }
+            state.update(|config| {
+                config.selected_bucket = bucket.clone();
+                config.selected_region = region.clone();
+            });
+        }
+        state.update(|config| config.onboarding_done = true);
+        info!("Onboarding {}", if finished { "finished" } else { "skipped" });
+    });
+}
 
-                let spawn_result;
-                #[cfg(target_os = "windows")]
-                {
-                    spawn_result = std::process::Command::new("explorer").arg(&log_path).spawn();
+/// Opens the crash report shown on startup.
+pub fn setup_crash_report_handler(ui: &AppWindow) {
+    ui.on_open_crash_report({
+        let ui_handle = ui.as_weak();
+        move || {
+            let Some(ui) = ui_handle.upgrade() else { return; };
+            let path = ui.get_crash_report_path().to_string();
+            match crate::utils::open_with_default_app(std::path::Path::new(&path)) {
+                Ok(_) => ui.set_show_crash_report(false),
+                Err(e) => {
+                    error!("Failed to open crash report {}: {}", path, e);
+                    update_status(&ui_handle, format!("Không mở được {}: {}", path, e), 0.0, true);
                 }
-                #[cfg(target_os = "macos")]
-                {
-                    spawn_result = std::process::Command::new("open").arg(&log_path).spawn();
+            }
+        }
+    });
+}
+
+/// Release found by the last update check, for the download and release page buttons.
+static PENDING_UPDATE: Lazy<std::sync::Mutex<Option<updates::Release>>> = Lazy::new(|| std::sync::Mutex::new(None));
+
+/// Checks GitHub for a newer release and shows the update dialog if there is one. A `manual`
+/// check (Settings → Kiểm tra ngay) also reports "up to date" and errors in the Settings window.
+fn run_update_check(ui_handle: slint::Weak<AppWindow>, manual: bool) {
+    tokio::spawn(async move {
+        let result = updates::check().await;
+        let _ = ui_handle.upgrade_in_event_loop(move |ui| match result {
+            Ok(Some(release)) => {
+                info!("Update available: {} ({})", release.version, release.page_url);
+                ui.set_update_version(release.version.clone().into());
+                ui.set_update_changelog(release.changelog.clone().into());
+                ui.set_update_has_installer(release.installer.is_some());
+                ui.set_update_status("".into());
+                *PENDING_UPDATE.lock().unwrap() = Some(release);
+                ui.set_show_update(true);
+            }
+            Ok(None) => {
+                info!("No update available");
+                if manual {
+                    ui.set_update_status("Đang dùng bản mới nhất".into());
                 }
-                #[cfg(target_os = "linux")]
-                {
-                    spawn_result = std::process::Command::new("xdg-open").arg(&log_path).spawn();
+            }
+            Err(e) => {
+                warn!("Update check failed: {}", e);
+                if manual {
+                    ui.set_update_status(format!("Lỗi kiểm tra: {}", e).into());
                 }
+            }
+        });
+    });
+}
 
-                // Reset button state immediately after spawn attempt
-                let ui_handle_for_reset = ui_handle.clone();
-                match spawn_result {
-                    Ok(_) => {
-                        info!("Opened log folder: {}", log_path);
+/// Sets up the update check: on startup when enabled in Settings, on demand from Settings,
+/// and the download / release page buttons of the update dialog.
+pub fn setup_update_handlers(ui: &AppWindow) {
+    ui.set_app_version(env!("CARGO_PKG_VERSION").into());
+
+    ui.on_check_updates_now({
+        let ui_handle = ui.as_weak();
+        move || {
+            if let Some(ui) = ui_handle.upgrade() {
+                ui.set_update_status("Đang kiểm tra...".into());
+            }
+            run_update_check(ui_handle.clone(), true);
+        }
+    });
+
+    ui.on_open_release_page({
+        let ui_handle = ui.as_weak();
+        move || {
+            let Some(url) = PENDING_UPDATE.lock().unwrap().as_ref().map(|r| r.page_url.clone()) else { return; };
+            if let Err(e) = crate::utils::open_with_default_app(std::path::Path::new(&url)) {
+                error!("Failed to open release page: {}", e);
+                if let Some(ui) = ui_handle.upgrade() {
+                    ui.set_update_status(format!("Không mở được trình duyệt: {}", e).into());
+                }
+            }
+        }
+    });
+
+    ui.on_download_update({
+        let ui_handle = ui.as_weak();
+        move || {
+            let Some(ui) = ui_handle.upgrade() else { return; };
+            let Some(installer) = PENDING_UPDATE.lock().unwrap().as_ref().and_then(|r| r.installer.clone()) else { return; };
+            ui.set_is_downloading_update(true);
+            ui.set_update_status(format!("Đang tải {}...", installer.name).into());
+
+            let ui_handle = ui_handle.clone();
+            tokio::spawn(async move {
+                let status = match updates::download(&installer).await {
+                    Ok(path) => {
+                        info!("Downloaded update to {:?}", path);
+                        match crate::utils::open_with_default_app(&path) {
+                            Ok(_) => format!("Đã tải về {}. Đóng ứng dụng để hoàn tất cài đặt.", path.display()),
+                            Err(e) => format!("Đã tải về {} nhưng không mở được: {}", path.display(), e),
+                        }
                     }
                     Err(e) => {
-                        error!("Failed to open log folder: {:?}", e);
-                        crate::utils::update_status(
-                            &ui_handle_for_reset,
-                            format!("Không thể mở thư mục: {}", e),
-                            0.0,
-                            true,
-                        );
+                        error!("Update download failed: {}", e);
+                        e
                     }
+                };
+                let _ = ui_handle.upgrade_in_event_loop(move |ui| {
+                    ui.set_is_downloading_update(false);
+                    ui.set_update_status(status.into());
+                });
+            });
+        }
+    });
+
+    if crate::config::settings().check_updates {
+        run_update_check(ui.as_weak(), false);
+    }
+}
+
+pub fn setup_email_settings_handler(ui: &AppWindow, state: &AppState) {
+    ui.on_save_email_settings({
+        let ui_handle = ui.as_weak();
+        let state = state.clone();
+        move || {
+            let Some(ui) = ui_handle.upgrade() else { return; };
+
+            let port = match ui.get_smtp_port_text().trim().parse::<u16>() {
+                Ok(p) if p > 0 => p,
+                _ => {
+                    ui.set_email_settings_error("Port phải là số từ 1 đến 65535".into());
+                    return;
                 }
+            };
 
-                // Reset is_opening_log immediately (no arbitrary delay)
-                let _ = ui_handle_for_reset.upgrade_in_event_loop(|ui| {
-                    ui.set_is_opening_log(false);
-                });
+            let smtp = crate::config::SmtpConfig {
+                enabled: ui.get_smtp_enabled(),
+                host: ui.get_smtp_host().trim().to_string(),
+                port,
+                username: ui.get_smtp_username().trim().to_string(),
+                from: ui.get_smtp_from().trim().to_string(),
+                to: ui.get_smtp_to().trim().to_string(),
+                max_errors: state.read().smtp.max_errors,
+            };
+
+            if let Some(err) = crate::notify::validate_smtp_config(&smtp) {
+                ui.set_email_settings_error(err.into());
+                return;
+            }
+            let password = ui.get_smtp_password();
+            if !password.is_empty() {
+                crate::notify::set_smtp_password(&password);
+                ui.set_smtp_password("".into());
+            }
+            if smtp.enabled && !smtp.username.is_empty() && crate::notify::smtp_password().is_none() {
+                ui.set_email_settings_error("Cần nhập mật khẩu SMTP hoặc đặt biến môi trường S3_SYNC_SMTP_PASSWORD".into());
+                return;
             }
+
+            let enabled = smtp.enabled;
+            if let Err(e) = state.save(|config| config.smtp = smtp) {
+                error!("Failed to save email settings: {:?}", e);
+                ui.set_email_settings_error(format!("Lỗi lưu cấu hình: {}", e).into());
+                return;
+            }
+
+            info!("Email report settings saved (enabled: {})", enabled);
+            ui.set_email_settings_error("".into());
+            ui.set_show_email_settings(false);
+            update_status(&ui_handle, "Đã lưu cấu hình email".to_string(), 0.0, false);
         }
     });
 }
 
 /// Sets up the base path selection handler.
-pub fn setup_select_base_path_handler(ui: &AppWindow) {
+pub fn setup_select_base_path_handler(ui: &AppWindow, state: &AppState) {
     ui.on_select_base_path({
         let ui_handle = ui.as_weak();
+        let state = state.clone();
         move || {
-            let ui_handle_cloned = ui_handle.clone();
-            let _ = ui_handle_cloned.upgrade_in_event_loop(|ui| {
+            let Some(ui) = ui_handle.upgrade() else { return; };
+            let open = || {
                 ui.set_is_selecting_base_path(true);
-            });
-
-            if let Some(path) = rfd::FileDialog::new().pick_folder() {
+                rfd::AsyncFileDialog::new().pick_folder()
+            };
+            let ui_handle = ui_handle.clone();
+            let state = state.clone();
+            spawn_file_dialog(&ui_handle.clone(), open, move |ui, picked| {
+                ui.set_is_selecting_base_path(false);
+                let Some(path) = picked.map(|h| h.path().to_path_buf()) else { return; };
                 let path_str = path.to_string_lossy().to_string();
 
                 // Save to config file
-                let mut config = crate::config::load_config();
-                config.s3_base_path = path_str.clone();
-                if let Err(e) = crate::config::save_config(&config) {
+                let result = state.save(|config| {
+                    config.s3_base_path = path_str.clone();
+                });
+                if let Err(e) = result {
                     error!("Failed to save config: {:?}", e);
-                    crate::utils::update_status(&ui_handle_cloned, format!("Lỗi lưu cấu hình: {}", e), 0.0, true);
+                    update_status(&ui_handle, format!("Lỗi lưu cấu hình: {}", e), 0.0, true);
                 } else {
                     info!("Config saved: s3_base_path = {}", path_str);
                 }
 
-                let _ = ui_handle_cloned.upgrade_in_event_loop(move |ui| {
-                    ui.set_s3_base_path(path_str.into());
-                    ui.set_is_selecting_base_path(false);
-                });
-            } else {
-                let _ = ui_handle_cloned.upgrade_in_event_loop(|ui| {
-                    ui.set_is_selecting_base_path(false);
-                });
-            }
+                ui.set_s3_base_path(path_str.into());
+            });
         }
     });
 }
@@ -550,109 +2643,222 @@ pub fn setup_toggle_filter_config_handler(ui: &AppWindow) {
     });
 }
 
+/// The filter described by the filter panel fields.
+fn filter_from_ui(ui: &AppWindow) -> Result<crate::config::FilterConfig, String> {
+    let mut filter_config = crate::utils::parse_filter_fields(
+        ui.get_enable_filtering(),
+        &ui.get_max_file_size_text(),
+        &ui.get_exclude_patterns_text(),
+        &ui.get_include_patterns_text(),
+        &ui.get_exclude_mime_text(),
+        &ui.get_include_mime_text(),
+    )?;
+    filter_config.skip_hidden = ui.get_skip_hidden();
+    filter_config.hidden_allow = ui
+        .get_hidden_allow_text()
+        .split(',')
+        .map(|name| name.trim().trim_matches('/').to_string())
+        .filter(|name| !name.is_empty())
+        .collect();
+    Ok(filter_config)
+}
+
 /// Sets up the save filter configuration handler.
-pub fn setup_save_filter_config_handler(ui: &AppWindow) {
+pub fn setup_save_filter_config_handler(ui: &AppWindow, state: &AppState) {
     ui.on_save_filter_config({
         let ui_handle = ui.as_weak();
+        let state = state.clone();
         move || {
             let ui = match ui_handle.upgrade() {
                 Some(ui) => ui,
                 None => return,
             };
 
-            // Get current values from UI
-            let enable_filtering = ui.get_enable_filtering();
-            let exclude_patterns_text = ui.get_exclude_patterns_text().to_string();
-            let include_patterns_text = ui.get_include_patterns_text().to_string();
-            let max_file_size_text = ui.get_max_file_size_text().to_string();
-
-            // Parse and validate max file size
-            let max_file_size_mb = match max_file_size_text.parse::<u64>() {
-                Ok(val) if val > 0 && val <= 10240 => val, // Giới hạn từ 1MB đến 10GB
-                _ => {
-                    crate::utils::update_status(&ui_handle, "Max file size phải là số từ 1 đến 10240 MB".to_string(), 0.0, true);
+            let filter_config = match filter_from_ui(&ui) {
+                Ok(filter_config) => filter_config,
+                Err(e) => {
+                    update_status(&ui_handle, e, 0.0, true);
                     return;
                 }
             };
-            let max_file_size = max_file_size_mb.saturating_mul(1024 * 1024);
-
-            // Validate exclude patterns
-            let invalid_exclude = crate::utils::validate_glob_patterns(&exclude_patterns_text);
-            if !invalid_exclude.is_empty() {
-                crate::utils::update_status(&ui_handle, format!("Pattern không hợp lệ trong Exclude: {}", invalid_exclude.join(", ")), 0.0, true);
-                return;
-            }
-
-            // Validate include patterns
-            let invalid_include = crate::utils::validate_glob_patterns(&include_patterns_text);
-            if !invalid_include.is_empty() {
-                crate::utils::update_status(&ui_handle, format!("Pattern không hợp lệ trong Include: {}", invalid_include.join(", ")), 0.0, true);
-                return;
-            }
-
-            // Parse patterns (comma-separated)
-            let exclude_patterns: Vec<String> = exclude_patterns_text
-                .split(',')
-                .map(|line| line.trim().to_string())
-                .filter(|line| !line.is_empty())
-                .collect();
-
-            let include_patterns: Vec<String> = include_patterns_text
-                .split(',')
-                .map(|line| line.trim().to_string())
-                .filter(|line| !line.is_empty())
-                .collect();
-
-            // Create new filter config
-            let filter_config = crate::config::FilterConfig {
-                enable_filtering,
-                exclude_patterns,
-                include_patterns,
-                max_file_size,
-            };
 
             // Save to config
-            let mut app_config = crate::config::load_config();
-            app_config.filter_config = filter_config.clone();
-            
-            if let Err(e) = crate::config::save_config(&app_config) {
+            let result = state.save(|config| {
+                config.filter_config = filter_config.clone();
+            });
+            if let Err(e) = result {
                 error!("Failed to save filter config: {:?}", e);
-                crate::utils::update_status(&ui_handle, format!("Lỗi lưu cấu hình lọc: {}", e), 0.0, true);
+                update_status(&ui_handle, format!("Lỗi lưu cấu hình lọc: {}", e), 0.0, true);
             } else {
                 info!("Filter config saved successfully");
-                crate::utils::update_status(&ui_handle, "Đã lưu cấu hình lọc file".to_string(), 0.0, false);
-                
+                update_status(&ui_handle, "Đã lưu cấu hình lọc file".to_string(), 0.0, false);
+
+                // The saved filter now matches the live edits
+                crate::config::set_filter_override(None);
+                ui.set_live_filtering(false);
                 // Hide config section after successful save
-                let ui_handle_clone = ui_handle.clone();
-                let _ = ui_handle_clone.upgrade_in_event_loop(|ui| {
-                    ui.set_show_filter_config(false);
-                });
+                ui.set_show_filter_config(false);
             }
         }
     });
 }
 
 /// Sets up the reset filter configuration handler.
-pub fn setup_reset_filter_config_handler(ui: &AppWindow) {
+pub fn setup_reset_filter_config_handler(ui: &AppWindow, state: &AppState) {
     ui.on_reset_filter_config({
         let ui_handle = ui.as_weak();
+        let state = state.clone();
         move || {
             let default_config = crate::config::FilterConfig::default();
             let exclude_text = default_config.exclude_patterns.join(", ");
             let include_text = default_config.include_patterns.join(", ");
             let max_size_text = (default_config.max_file_size / (1024 * 1024)).to_string();
             let enable_filtering = default_config.enable_filtering;
+            let skip_hidden = default_config.skip_hidden;
+            let hidden_allow_text = default_config.hidden_allow.join(", ");
             
+            let state = state.clone();
             let _ = ui_handle.upgrade_in_event_loop(move |ui| {
                 ui.set_enable_filtering(enable_filtering);
                 ui.set_exclude_patterns_text(exclude_text.into());
                 ui.set_include_patterns_text(include_text.into());
+                ui.set_exclude_mime_text("".into());
+                ui.set_include_mime_text("".into());
+                ui.set_skip_hidden(skip_hidden);
+                ui.set_hidden_allow_text(hidden_allow_text.into());
                 ui.set_max_file_size_text(max_size_text.into());
                 ui.set_filter_stats("".into());
+                if ui.get_live_filtering() {
+                    apply_live_filter(&ui, &state);
+                }
             });
             
-            crate::utils::update_status(&ui_handle, "Đã reset cấu hình lọc file".to_string(), 0.0, false);
+            update_status(&ui_handle, "Đã reset cấu hình lọc file".to_string(), 0.0, false);
+        }
+    });
+}
+
+/// Bumped per live filter edit so only the last of a burst of keystrokes runs the preview.
+static LIVE_FILTER_GENERATION: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+/// Quiet time after the last edit before the live preview walks the folders again.
+const LIVE_FILTER_DELAY: time::Duration = time::Duration::from_millis(400);
+
+/// Makes the filter panel's current fields apply to the next sync without saving them, then
+/// refreshes the preview and per-row stats. Invalid fields keep the last valid live filter.
+fn apply_live_filter(ui: &AppWindow, state: &AppState) {
+    let filter_config = match filter_from_ui(ui) {
+        Ok(filter_config) => filter_config,
+        Err(e) => {
+            ui.set_filter_stats(format!("⚠ {} (vẫn dùng bộ lọc hợp lệ trước đó)", e).into());
+            return;
+        }
+    };
+    crate::config::set_filter_override(Some(filter_config.clone()));
+
+    use std::sync::atomic::Ordering;
+    let generation = LIVE_FILTER_GENERATION.fetch_add(1, Ordering::Relaxed) + 1;
+    let ui_handle = ui.as_weak();
+    let state = state.clone();
+    tokio::spawn(async move {
+        time::sleep(LIVE_FILTER_DELAY).await;
+        if LIVE_FILTER_GENERATION.load(Ordering::Relaxed) != generation {
+            return;
+        }
+        let _ = ui_handle.clone().upgrade_in_event_loop(move |ui| {
+            let paths: Vec<String> = ui.get_local_paths().iter().map(|item| item.local_path.to_string()).collect();
+            if paths.is_empty() {
+                ui.set_filter_stats("Bộ lọc tạm đang áp dụng cho lần sync tới".into());
+                return;
+            }
+            run_filter_preview(ui_handle.clone(), paths.clone(), filter_config);
+            compute_path_stats(ui_handle, &state, paths);
+        });
+    });
+}
+
+/// Sets up live filtering: while on, filter edits apply to previews and syncs right away but
+/// are only written to the config by Save.
+pub fn setup_live_filter_handlers(ui: &AppWindow, state: &AppState) {
+    ui.on_live_filtering_toggled({
+        let ui_handle = ui.as_weak();
+        let state = state.clone();
+        move |enabled| {
+            let Some(ui) = ui_handle.upgrade() else { return; };
+            if enabled {
+                apply_live_filter(&ui, &state);
+            } else {
+                crate::config::set_filter_override(None);
+                ui.set_filter_stats("".into());
+                update_status(&ui_handle, "Sync dùng lại bộ lọc đã lưu".to_string(), 0.0, false);
+                let paths: Vec<String> = ui.get_local_paths().iter().map(|item| item.local_path.to_string()).collect();
+                compute_path_stats(ui_handle.clone(), &state, paths);
+            }
+        }
+    });
+
+    ui.on_filters_edited({
+        let ui_handle = ui.as_weak();
+        let state = state.clone();
+        move || {
+            let Some(ui) = ui_handle.upgrade() else { return; };
+            if ui.get_live_filtering() {
+                apply_live_filter(&ui, &state);
+            }
+        }
+    });
+}
+
+/// Counts what `filter_config` keeps of `paths` in the background and shows it under the
+/// filter panel.
+fn run_filter_preview(ui_handle: slint::Weak<AppWindow>, paths: Vec<String>, filter_config: crate::config::FilterConfig) {
+    tokio::spawn(async move {
+        let mut total_stats = crate::utils::FilteringStats {
+            total_files: 0,
+            included_files: 0,
+            excluded_files: 0,
+            total_size: 0,
+            excluded_size: 0,
+        };
+
+        for local_path_str in &paths {
+            let path = std::path::Path::new(local_path_str);
+            if path.is_dir() {
+                if let Ok(stats) = crate::utils::get_filtering_stats(path, &filter_config) {
+                    total_stats.total_files += stats.total_files;
+                    total_stats.included_files += stats.included_files;
+                    total_stats.excluded_files += stats.excluded_files;
+                    total_stats.total_size += stats.total_size;
+                    total_stats.excluded_size += stats.excluded_size;
+                }
+            } else if path.is_file() {
+                total_stats.total_files += 1;
+                if crate::utils::should_include_file(path, path.parent().unwrap_or(path), &filter_config) {
+                    total_stats.included_files += 1;
+                    if let Ok(metadata) = std::fs::metadata(path) {
+                        total_stats.total_size += metadata.len();
+                    }
+                } else {
+                    total_stats.excluded_files += 1;
+                    if let Ok(metadata) = std::fs::metadata(path) {
+                        total_stats.excluded_size += metadata.len();
+                    }
+                }
+            }
         }
+
+        let stats_text = format!(
+            "Tổng: {} files | Bao gồm: {} files | Loại trừ: {} files\nTổng kích thước: {} MB | Tiết kiệm: {} MB ({:.1}%)",
+            total_stats.total_files,
+            total_stats.included_files,
+            total_stats.excluded_files,
+            total_stats.total_size / (1024 * 1024),
+            total_stats.excluded_size / (1024 * 1024),
+            total_stats.exclusion_rate() * 100.0
+        );
+
+        let _ = ui_handle.upgrade_in_event_loop(|ui| {
+            ui.set_filter_stats(stats_text.into());
+        });
     });
 }
 
@@ -667,129 +2873,58 @@ pub fn setup_preview_filtering_handler(ui: &AppWindow) {
             };
 
             // Get current paths
-            let local_paths: Vec<crate::PathItem> = ui.get_local_paths().iter().collect();
+            let local_paths: Vec<String> = ui.get_local_paths().iter().map(|item| item.local_path.to_string()).collect();
             
             if local_paths.is_empty() {
-                crate::utils::update_status(&ui_handle, "Vui lòng chọn thư mục/file trước".to_string(), 0.0, true);
+                update_status(&ui_handle, "Vui lòng chọn thư mục/file trước".to_string(), 0.0, true);
                 return;
             }
 
             // Get current filter config from UI
-            let enable_filtering = ui.get_enable_filtering();
-            let exclude_patterns_text = ui.get_exclude_patterns_text().to_string();
-            let include_patterns_text = ui.get_include_patterns_text().to_string();
-            let max_file_size_text = ui.get_max_file_size_text().to_string();
-
-            // Parse max file size
-            let max_file_size = max_file_size_text.parse::<u64>()
-                .unwrap_or(100)
-                .saturating_mul(1024 * 1024);
-
-            // Parse patterns (comma-separated)
-            let exclude_patterns: Vec<String> = exclude_patterns_text
-                .split(',')
-                .map(|line| line.trim().to_string())
-                .filter(|line| !line.is_empty())
-                .collect();
-
-            let include_patterns: Vec<String> = include_patterns_text
-                .split(',')
-                .map(|line| line.trim().to_string())
-                .filter(|line| !line.is_empty())
-                .collect();
-
-            let filter_config = crate::config::FilterConfig {
-                enable_filtering,
-                exclude_patterns,
-                include_patterns,
-                max_file_size,
-            };
-
-            let ui_handle_task = ui_handle.clone();
-            tokio::spawn(async move {
-                let mut total_stats = crate::utils::FilteringStats {
-                    total_files: 0,
-                    included_files: 0,
-                    excluded_files: 0,
-                    total_size: 0,
-                    excluded_size: 0,
-                };
-
-                for item in &local_paths {
-                    let local_path_str = item.local_path.to_string();
-                    let path = std::path::Path::new(&local_path_str);
-                    if path.is_dir() {
-                        if let Ok(stats) = crate::utils::get_filtering_stats(path, &filter_config) {
-                            total_stats.total_files += stats.total_files;
-                            total_stats.included_files += stats.included_files;
-                            total_stats.excluded_files += stats.excluded_files;
-                            total_stats.total_size += stats.total_size;
-                            total_stats.excluded_size += stats.excluded_size;
-                        }
-                    } else if path.is_file() {
-                        total_stats.total_files += 1;
-                        if crate::utils::should_include_file(path, path.parent().unwrap_or(path), &filter_config) {
-                            total_stats.included_files += 1;
-                            if let Ok(metadata) = std::fs::metadata(path) {
-                                total_stats.total_size += metadata.len();
-                            }
-                        } else {
-                            total_stats.excluded_files += 1;
-                            if let Ok(metadata) = std::fs::metadata(path) {
-                                total_stats.excluded_size += metadata.len();
-                            }
-                        }
-                    }
-                }
-
-                let stats_text = format!(
-                    "Tổng: {} files | Bao gồm: {} files | Loại trừ: {} files\nTổng kích thước: {} MB | Tiết kiệm: {} MB ({:.1}%)",
-                    total_stats.total_files,
-                    total_stats.included_files,
-                    total_stats.excluded_files,
-                    total_stats.total_size / (1024 * 1024),
-                    total_stats.excluded_size / (1024 * 1024),
-                    total_stats.exclusion_rate() * 100.0
-                );
-
-                let _ = ui_handle_task.upgrade_in_event_loop(|ui| {
-                    ui.set_filter_stats(stats_text.into());
-                });
-            });
+            match filter_from_ui(&ui) {
+                Ok(filter_config) => run_filter_preview(ui_handle.clone(), local_paths, filter_config),
+                Err(e) => update_status(&ui_handle, e, 0.0, true),
+            }
         }
     });
 }
 
-pub fn setup_bucket_handlers(ui: &AppWindow) {
+pub fn setup_bucket_handlers(ui: &AppWindow, state: &AppState) {
     let ui_handle = ui.as_weak();
 
     // Load initial bucket list
-    let config = crate::config::load_config();
+    let config = state.config();
     let initial_buckets: Vec<slint::SharedString> = config
         .buckets
         .iter()
         .map(|s| slint::SharedString::from(s.clone()))
         .collect();
     ui.set_bucket_list(ModelRc::from(Rc::new(VecModel::from(initial_buckets))));
+    set_bucket_details(ui, &config);
 
     // Helper to refresh bucket list in UI and save to config
     let refresh_buckets = {
         let ui_handle = ui_handle.clone();
+        let state = state.clone();
         move |buckets: Vec<String>| {
             let shared_buckets: Vec<slint::SharedString> = buckets
                 .iter()
                 .map(|s| slint::SharedString::from(s.clone()))
                 .collect();
             
-            // Save to config
-            let mut config = crate::config::load_config();
-            config.buckets = buckets;
-            if let Err(e) = crate::config::save_config(&config) {
-                error!("Failed to save bucket config: {:?}", e);
-            }
+            // Save to config, dropping protection of buckets that no longer exist
+            let config = state.update(|config| {
+                config.buckets = buckets;
+                let known = config.buckets.clone();
+                config.protected_buckets.retain(|b| known.contains(b));
+                config.bucket_checks.retain(|b, _| known.contains(b));
+                config.bucket_defaults.retain(|b, _| known.contains(b));
+                config.clone()
+            });
 
             let _ = ui_handle.upgrade_in_event_loop(move |ui| {
                 ui.set_bucket_list(ModelRc::from(Rc::new(VecModel::from(shared_buckets))));
+                set_bucket_details(&ui, &config);
             });
         }
     };
@@ -840,15 +2975,25 @@ pub fn setup_bucket_handlers(ui: &AppWindow) {
     // Add bucket
     ui.on_add_bucket({
         let ui_handle = ui_handle.clone();
+        let state = state.clone();
         let refresh_buckets = refresh_buckets.clone();
         move |name| {
             let Some(ui) = ui_handle.upgrade() else { return; };
-            let mut config = crate::config::load_config();
+            let mut buckets = state.read().buckets.clone();
             
-            match validate_bucket_name(&name, &config.buckets, None) {
+            match validate_bucket_name(&name, &buckets, None) {
                 Ok(_) => {
-                    config.buckets.push(name.trim().to_string());
-                    refresh_buckets(config.buckets);
+                    let name = name.trim().to_string();
+                    // Production-looking buckets start out protected
+                    if crate::config::looks_like_production(&name) {
+                        state.update(|config| config.protected_buckets.push(name.clone()));
+                    }
+                    let index = buckets.len();
+                    buckets.push(name.clone());
+                    refresh_buckets(buckets);
+                    if ui.get_verify_new_bucket() {
+                        start_bucket_check(&ui, &state, index, name);
+                    }
                     ui.set_new_bucket_name("".into());
                     ui.set_bucket_manager_error("".into());
                     ui.set_show_add_input(false);
@@ -860,34 +3005,90 @@ pub fn setup_bucket_handlers(ui: &AppWindow) {
         }
     });
 
+    // Check bucket (badge click)
+    ui.on_verify_bucket({
+        let ui_handle = ui_handle.clone();
+        let state = state.clone();
+        move |index| {
+            let Some(ui) = ui_handle.upgrade() else { return; };
+            let Some(bucket) = state.read().buckets.get(index as usize).cloned() else { return; };
+            ui.set_bucket_manager_error("".into());
+            start_bucket_check(&ui, &state, index as usize, bucket);
+        }
+    });
+
+    ui.on_bucket_changed({
+        let ui_handle = ui_handle.clone();
+        let state = state.clone();
+        move || {
+            if let Some(ui) = ui_handle.upgrade() {
+                set_bucket_home_region(&ui, &state.read());
+            }
+        }
+    });
+
+    // Badges compare against the selected region
+    ui.on_region_changed({
+        let ui_handle = ui_handle.clone();
+        let state = state.clone();
+        move || {
+            if let Some(ui) = ui_handle.upgrade() {
+                set_bucket_details(&ui, &state.read());
+            }
+        }
+    });
+
     // Update bucket
     ui.on_update_bucket({
         let ui_handle = ui_handle.clone();
+        let state = state.clone();
         let refresh_buckets = refresh_buckets.clone();
         move |index, name| {
             let Some(ui) = ui_handle.upgrade() else { return; };
-            let mut config = crate::config::load_config();
+            let mut buckets = state.read().buckets.clone();
             let idx = index as usize;
             
-            if idx >= config.buckets.len() { return; }
+            if idx >= buckets.len() { return; }
 
-            match validate_bucket_name(&name, &config.buckets, Some(idx)) {
+            let defaults = crate::config::BucketDefaults {
+                base_prefix: ui.get_edit_bucket_prefix().trim().trim_start_matches('/').to_string(),
+                key_template: ui.get_edit_bucket_template().trim().to_string(),
+                preview_depth: ui.get_edit_bucket_depth().clamp(0, crate::config::MAX_PREVIEW_DEPTH as i32) as u8,
+            };
+            let valid = validate_bucket_name(&name, &buckets, Some(idx))
+                .and_then(|_| crate::utils::validate_key_template(&defaults.key_template));
+            match valid {
                 Ok(_) => {
-                    let old_name = config.buckets[idx].clone();
+                    let old_name = buckets[idx].clone();
                     let new_name = name.trim().to_string();
-                    config.buckets[idx] = new_name.clone();
-                    
-                    // If the updated bucket was selected, update selected_bucket
-                    if config.selected_bucket == old_name {
-                        config.selected_bucket = new_name.clone();
-                        ui.set_bucket_name(new_name.into());
-                        // Save config immediately to persist selected bucket change
-                        if let Err(e) = crate::config::save_config(&config) {
-                            error!("Failed to save config after bucket rename: {:?}", e);
+                    buckets[idx] = new_name.clone();
+
+                    let selected_renamed = state.update(|config| {
+                        // Protection follows the bucket across renames
+                        if let Some(p) = config.protected_buckets.iter_mut().find(|b| **b == old_name) {
+                            *p = new_name.clone();
+                        }
+                        // A different name is a different bucket; its check no longer applies
+                        if old_name != new_name {
+                            config.bucket_checks.remove(&old_name);
+                        }
+                        // Path defaults are edited alongside the name and move with it
+                        config.bucket_defaults.remove(&old_name);
+                        if !defaults.is_empty() {
+                            config.bucket_defaults.insert(new_name.clone(), defaults.clone());
                         }
+                        // If the updated bucket was selected, update selected_bucket
+                        let selected = config.selected_bucket == old_name;
+                        if selected {
+                            config.selected_bucket = new_name.clone();
+                        }
+                        selected
+                    });
+                    if selected_renamed {
+                        ui.set_bucket_name(new_name.into());
                     }
                     
-                    refresh_buckets(config.buckets);
+                    refresh_buckets(buckets);
                     ui.set_new_bucket_name("".into());
                     ui.set_editing_bucket_index(-1);
                     ui.set_bucket_manager_error("".into());
@@ -902,61 +3103,160 @@ pub fn setup_bucket_handlers(ui: &AppWindow) {
     // Delete bucket
     ui.on_delete_bucket({
         let ui_handle = ui_handle.clone();
+        let state = state.clone();
         let refresh_buckets = refresh_buckets.clone();
         move |index| {
             let Some(ui) = ui_handle.upgrade() else { return; };
-            let mut config = crate::config::load_config();
+            let mut buckets = state.read().buckets.clone();
             let idx = index as usize;
             
-            if idx < config.buckets.len() {
-                let deleted_name = config.buckets.remove(idx);
+            if idx < buckets.len() {
+                let deleted_name = buckets.remove(idx);
                 
                 // If the deleted bucket was selected, clear it
-                if config.selected_bucket == deleted_name {
-                    config.selected_bucket = String::new();
-                    ui.set_bucket_name("".into());
-                    // Save config immediately to persist selected bucket removal
-                    if let Err(e) = crate::config::save_config(&config) {
-                        error!("Failed to save config after bucket deletion: {:?}", e);
+                let was_selected = state.update(|config| {
+                    let selected = config.selected_bucket == deleted_name;
+                    if selected {
+                        config.selected_bucket = String::new();
                     }
+                    selected
+                });
+                if was_selected {
+                    ui.set_bucket_name("".into());
                 }
                 
-                refresh_buckets(config.buckets);
+                refresh_buckets(buckets);
                 ui.set_bucket_manager_error("".into());
             }
         }
     });
+
+    // Protect / unprotect bucket
+    ui.on_toggle_bucket_protected({
+        let ui_handle = ui_handle.clone();
+        let state = state.clone();
+        move |index| {
+            let Some(ui) = ui_handle.upgrade() else { return; };
+            let Some(bucket) = state.read().buckets.get(index as usize).cloned() else { return; };
+            let config = state.update(|config| {
+                if config.is_protected(&bucket) {
+                    config.protected_buckets.retain(|b| *b != bucket);
+                } else {
+                    config.protected_buckets.push(bucket);
+                }
+                config.clone()
+            });
+            set_bucket_details(&ui, &config);
+        }
+    });
+}
+
+/// Per-bucket lists aligned with `bucket-list`: protected flag, check badge, region and
+/// path defaults.
+fn set_bucket_details(ui: &AppWindow, config: &crate::config::AppConfig) {
+    let flags: Vec<bool> = config.buckets.iter().map(|b| config.is_protected(b)).collect();
+    ui.set_bucket_protected(ModelRc::from(Rc::new(VecModel::from(flags))));
+
+    let selected_region = ui.get_region().to_string();
+    let checks: Vec<_> = config.buckets.iter().map(|b| config.bucket_checks.get(b)).collect();
+    let badges: Vec<slint::SharedString> =
+        checks.iter().map(|c| c.map(|c| c.badge(&selected_region)).unwrap_or_default().into()).collect();
+    let regions: Vec<slint::SharedString> = checks.iter().map(|c| c.map(|c| c.region.as_str()).unwrap_or_default().into()).collect();
+    ui.set_bucket_badges(ModelRc::from(Rc::new(VecModel::from(badges))));
+    ui.set_bucket_regions(ModelRc::from(Rc::new(VecModel::from(regions))));
+    set_bucket_home_region(ui, config);
+
+    let defaults: Vec<_> = config.buckets.iter().map(|b| config.bucket_defaults.get(b).cloned().unwrap_or_default()).collect();
+    let prefixes: Vec<slint::SharedString> = defaults.iter().map(|d| d.base_prefix.clone().into()).collect();
+    let templates: Vec<slint::SharedString> = defaults.iter().map(|d| d.key_template.clone().into()).collect();
+    ui.set_bucket_prefixes(ModelRc::from(Rc::new(VecModel::from(prefixes))));
+    ui.set_bucket_templates(ModelRc::from(Rc::new(VecModel::from(templates))));
+    let depths: Vec<i32> = defaults.iter().map(|d| d.preview_depth as i32).collect();
+    ui.set_bucket_depths(ModelRc::from(Rc::new(VecModel::from(depths))));
+}
+
+/// Runs HeadBucket for `bucket` with the credentials in the main window and stores the result.
+fn start_bucket_check(ui: &AppWindow, state: &AppState, index: usize, bucket: String) {
+    let params = AwsParams { bucket: bucket.clone(), ..AwsParams::from_ui(ui) };
+    if params.acc_key.is_empty() || params.sec_key.is_empty() || params.region.is_empty() {
+        ui.set_bucket_manager_error("Nhập Access Key, Secret Key và region để kiểm tra bucket".into());
+        return;
+    }
+    ui.set_checking_bucket_index(index as i32);
+    let ui_handle = ui.as_weak();
+    let state = state.clone();
+    tokio::spawn(async move {
+        let check = match params.create_client().await {
+            Ok(client) => crate::s3_client::check_bucket(&client, &bucket).await,
+            Err(e) => {
+                error!("Failed to create S3 client for bucket check: {:?}", e);
+                crate::config::BucketCheck {
+                    status: crate::config::BucketStatus::Error,
+                    region: String::new(),
+                    checked_at: chrono::Local::now().timestamp(),
+                }
+            }
+        };
+        info!("Bucket check {}: {:?} in '{}'", bucket, check.status, check.region);
+        let config = state.update(|config| {
+            // The bucket may have been removed while the check ran
+            if config.buckets.contains(&bucket) {
+                config.bucket_checks.insert(bucket.clone(), check);
+            }
+            config.clone()
+        });
+        let _ = ui_handle.upgrade_in_event_loop(move |ui| {
+            ui.set_checking_bucket_index(-1);
+            set_bucket_details(&ui, &config);
+        });
+    });
 }
 
-pub fn setup_region_handlers(ui: &AppWindow) {
+/// Region ComboBox entries: the managed list first, then every other region the SDK knows.
+fn set_region_choices(ui: &AppWindow, pinned: &[String]) {
+    let choices: Vec<slint::SharedString> =
+        crate::regions::region_choices(pinned).into_iter().map(slint::SharedString::from).collect();
+    ui.set_region_choices(ModelRc::from(Rc::new(VecModel::from(choices))));
+}
+
+/// Region the selected bucket was found in by its last check; empty if unknown.
+fn set_bucket_home_region(ui: &AppWindow, config: &crate::config::AppConfig) {
+    let bucket = ui.get_bucket_name().to_string();
+    let region = config.bucket_checks.get(&bucket).map(|c| c.region.clone()).unwrap_or_default();
+    ui.set_bucket_home_region(region.into());
+}
+
+pub fn setup_region_handlers(ui: &AppWindow, state: &AppState) {
     let ui_handle = ui.as_weak();
 
     // Load initial region list
-    let config = crate::config::load_config();
+    let config = state.config();
     let initial_regions: Vec<slint::SharedString> = config
         .regions
         .iter()
         .map(|s| slint::SharedString::from(s.clone()))
         .collect();
     ui.set_region_list(ModelRc::from(Rc::new(VecModel::from(initial_regions))));
+    set_region_choices(ui, &config.regions);
 
     // Helper to refresh region list in UI and save to config
     let refresh_regions = {
         let ui_handle = ui_handle.clone();
+        let state = state.clone();
         move |regions: Vec<String>| {
             let shared_regions: Vec<slint::SharedString> = regions
                 .iter()
                 .map(|s| slint::SharedString::from(s.clone()))
                 .collect();
             
-            let mut config = crate::config::load_config();
-            config.regions = regions;
-            if let Err(e) = crate::config::save_config(&config) {
-                error!("Failed to save config: {:?}", e);
-            }
+            let choices = crate::regions::region_choices(&regions);
+            state.update(|config| config.regions = regions);
 
             let _ = ui_handle.upgrade_in_event_loop(move |ui| {
                 ui.set_region_list(ModelRc::from(Rc::new(VecModel::from(shared_regions))));
+                ui.set_region_choices(ModelRc::from(Rc::new(VecModel::from(
+                    choices.into_iter().map(slint::SharedString::from).collect::<Vec<_>>(),
+                ))));
             });
         }
     };
@@ -984,15 +3284,16 @@ pub fn setup_region_handlers(ui: &AppWindow) {
     // Add region
     ui.on_add_region({
         let ui_handle = ui_handle.clone();
+        let state = state.clone();
         let refresh_regions = refresh_regions.clone();
         move |name| {
             let Some(ui) = ui_handle.upgrade() else { return; };
-            let mut config = crate::config::load_config();
+            let mut regions = state.read().regions.clone();
             
-            match validate_region_name(&name, &config.regions, None) {
+            match validate_region_name(&name, &regions, None) {
                 Ok(_) => {
-                    config.regions.push(name.trim().to_string());
-                    refresh_regions(config.regions);
+                    regions.push(name.trim().to_string());
+                    refresh_regions(regions);
                     ui.set_new_region_name("".into());
                     ui.set_region_manager_error("".into());
                     ui.set_show_add_region_input(false);
@@ -1007,30 +3308,34 @@ pub fn setup_region_handlers(ui: &AppWindow) {
     // Update region
     ui.on_update_region({
         let ui_handle = ui_handle.clone();
+        let state = state.clone();
         let refresh_regions = refresh_regions.clone();
         move |index, name| {
             let Some(ui) = ui_handle.upgrade() else { return; };
-            let mut config = crate::config::load_config();
+            let mut regions = state.read().regions.clone();
             let idx = index as usize;
             
-            if idx >= config.regions.len() { return; }
+            if idx >= regions.len() { return; }
 
-            match validate_region_name(&name, &config.regions, Some(idx)) {
+            match validate_region_name(&name, &regions, Some(idx)) {
                 Ok(_) => {
-                    let old_name = config.regions[idx].clone();
+                    let old_name = regions[idx].clone();
                     let new_name = name.trim().to_string();
-                    config.regions[idx] = new_name.clone();
+                    regions[idx] = new_name.clone();
 
                     // If the updated region was selected, update selected_region
-                    if config.selected_region == old_name {
-                        config.selected_region = new_name.clone();
-                        ui.set_region(new_name.into());
-                        if let Err(e) = crate::config::save_config(&config) {
-                            error!("Failed to save config after region rename: {:?}", e);
+                    let selected_renamed = state.update(|config| {
+                        let selected = config.selected_region == old_name;
+                        if selected {
+                            config.selected_region = new_name.clone();
                         }
+                        selected
+                    });
+                    if selected_renamed {
+                        ui.set_region(new_name.into());
                     }
 
-                    refresh_regions(config.regions);
+                    refresh_regions(regions);
                     ui.set_new_region_name("".into());
                     ui.set_editing_region_index(-1);
                     ui.set_region_manager_error("".into());
@@ -1045,46 +3350,612 @@ pub fn setup_region_handlers(ui: &AppWindow) {
     // Delete region
     ui.on_delete_region({
         let ui_handle = ui_handle.clone();
+        let state = state.clone();
         let refresh_regions = refresh_regions.clone();
         move |index| {
             let Some(ui) = ui_handle.upgrade() else { return; };
-            let mut config = crate::config::load_config();
+            let mut regions = state.read().regions.clone();
             let idx = index as usize;
             
-            if idx < config.regions.len() {
-                let deleted_name = config.regions.remove(idx);
+            if idx < regions.len() {
+                let deleted_name = regions.remove(idx);
 
                 // If the deleted region was selected, clear it
-                if config.selected_region == deleted_name {
-                    config.selected_region = String::new();
-                    ui.set_region("".into());
-                    if let Err(e) = crate::config::save_config(&config) {
-                        error!("Failed to save config after region deletion: {:?}", e);
+                let was_selected = state.update(|config| {
+                    let selected = config.selected_region == deleted_name;
+                    if selected {
+                        config.selected_region = String::new();
                     }
+                    selected
+                });
+                if was_selected {
+                    ui.set_region("".into());
                 }
 
-                refresh_regions(config.regions);
+                refresh_regions(regions);
                 ui.set_region_manager_error("".into());
             }
         }
     });
 }
 
+/// Builds a fresh two-way plan for `dir` <-> `prefix` in the selected bucket.
+async fn build_two_way_plan(
+    params: &AwsParams,
+    dir: &std::path::Path,
+    prefix: &str,
+    policy: crate::config::ConflictPolicy,
+) -> Result<(aws_sdk_s3::Client, crate::two_way::TwoWayPlan), String> {
+    let client = params.create_client().await.map_err(|e| format!("Lỗi tạo client: {}", e))?;
+    let remote = crate::two_way::list_remote(&client, &params.bucket, prefix).await?;
+    let baseline = crate::two_way::load_baseline_for(&params.bucket, prefix, dir);
+    let scan_dir = dir.to_path_buf();
+    let (local, remote, baseline, unchanged) = tokio::task::spawn_blocking(move || {
+        let local = crate::two_way::scan_local(&scan_dir);
+        let unchanged = crate::two_way::unchanged_without_baseline(&scan_dir, &local, &remote, &baseline);
+        (local, remote, baseline, unchanged)
+    })
+    .await
+    .map_err(|e| format!("Lỗi quét thư mục: {}", e))?;
+    let mut plan = crate::two_way::plan_two_way(&local, &remote, &baseline, &unchanged, policy);
+    // Conflicts are compared by the uploaded file's own mtime where the object recorded it
+    let mut remote = remote;
+    if crate::two_way::fetch_conflict_mtimes(&client, &params.bucket, prefix, &plan, &mut remote).await {
+        plan = crate::two_way::plan_two_way(&local, &remote, &baseline, &unchanged, policy);
+    }
+    Ok((client, plan))
+}
+
+/// Sets up the two-way sync dialog handlers (analyze, resolve conflicts, run).
+pub fn setup_two_way_handlers(ui: &AppWindow, state: &AppState) {
+    ui.on_select_two_way_folder({
+        let ui_handle = ui.as_weak();
+        move || {
+            spawn_file_dialog(&ui_handle, || rfd::AsyncFileDialog::new().pick_folder(), |ui, picked| {
+                if let Some(handle) = picked {
+                    ui.set_two_way_dir(handle.path().to_string_lossy().to_string().into());
+                }
+            });
+        }
+    });
+
+    ui.on_analyze_two_way({
+        let ui_handle = ui.as_weak();
+        let state = state.clone();
+        move |dir, prefix, policy| {
+            let Some(ui) = ui_handle.upgrade() else { return; };
+            let params = AwsParams::from_ui(&ui);
+            if let Some(err) = params.validate() {
+                ui.set_two_way_summary(err.into());
+                return;
+            }
+            let dir = std::path::PathBuf::from(dir.trim());
+            if !dir.is_dir() {
+                ui.set_two_way_summary("Thư mục local không tồn tại".into());
+                return;
+            }
+            let policy = crate::config::ConflictPolicy::parse(&policy).unwrap_or_default();
+            state.update(|config| config.sync_options.conflict_policy = policy);
+
+            let prefix = prefix.to_string();
+            ui.set_is_two_way_busy(true);
+            ui.set_two_way_can_run(false);
+            ui.set_two_way_summary("Đang phân tích...".into());
+
+            let ui_handle = ui_handle.clone();
+            tokio::spawn(async move {
+                let result = build_two_way_plan(&params, &dir, &prefix, policy).await;
+                let _ = ui_handle.upgrade_in_event_loop(move |ui| {
+                    ui.set_is_two_way_busy(false);
+                    match result {
+                        Ok((_, plan)) => {
+                            let format_time = |secs: i64| {
+                                chrono::DateTime::from_timestamp(secs, 0)
+                                    .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+                                    .unwrap_or_default()
+                            };
+                            let rows: Vec<ConflictRow> = plan
+                                .conflicts
+                                .iter()
+                                .map(|c| ConflictRow {
+                                    key: c.key.clone().into(),
+                                    local_info: format!("{} · {}", crate::utils::format_size(c.local.size), format_time(c.local.modified)).into(),
+                                    remote_info: format!("{} · {}", crate::utils::format_size(c.remote.size), format_time(c.remote.modified_time())).into(),
+                                    resolution: match c.resolution {
+                                        Some(crate::two_way::Side::Local) => "local",
+                                        Some(crate::two_way::Side::Remote) => "remote",
+                                        None => "",
+                                    }
+                                    .into(),
+                                })
+                                .collect();
+                            let summary = format!(
+                                "{} upload, {} download, {} xung đột ({} chưa chọn), {} không đổi",
+                                plan.uploads.len(),
+                                plan.downloads.len(),
+                                plan.conflicts.len(),
+                                plan.unresolved(),
+                                plan.in_sync.len()
+                            );
+                            let has_work = !plan.uploads.is_empty() || !plan.downloads.is_empty() || !plan.conflicts.is_empty();
+                            ui.set_two_way_can_run(has_work && plan.unresolved() == 0);
+                            ui.set_two_way_conflicts(ModelRc::from(Rc::new(VecModel::from(rows))));
+                            ui.set_two_way_summary(summary.into());
+                        }
+                        Err(e) => {
+                            error!("Two-way analyze failed: {}", e);
+                            ui.set_two_way_summary(e.into());
+                        }
+                    }
+                });
+            });
+        }
+    });
+
+    ui.on_resolve_conflict({
+        let ui_handle = ui.as_weak();
+        move |index, side| {
+            let Some(ui) = ui_handle.upgrade() else { return; };
+            let model = ui.get_two_way_conflicts();
+            let index = index as usize;
+            if let Some(mut row) = model.row_data(index) {
+                row.resolution = side;
+                model.set_row_data(index, row);
+            }
+            let all_resolved = model.iter().all(|r| !r.resolution.is_empty());
+            ui.set_two_way_can_run(all_resolved);
+        }
+    });
+
+    ui.on_run_two_way({
+        let ui_handle = ui.as_weak();
+        move |dir, prefix| {
+            let Some(ui) = ui_handle.upgrade() else { return; };
+            let params = AwsParams::from_ui(&ui);
+            if let Some(err) = params.validate() {
+                ui.set_two_way_summary(err.into());
+                return;
+            }
+            let dir = std::path::PathBuf::from(dir.trim());
+            let prefix = prefix.to_string();
+            let policy = crate::config::ConflictPolicy::parse(&ui.get_conflict_policy()).unwrap_or_default();
+            let choices: std::collections::HashMap<String, crate::two_way::Side> = ui
+                .get_two_way_conflicts()
+                .iter()
+                .filter_map(|r| {
+                    let side = match r.resolution.as_str() {
+                        "local" => crate::two_way::Side::Local,
+                        "remote" => crate::two_way::Side::Remote,
+                        _ => return None,
+                    };
+                    Some((r.key.to_string(), side))
+                })
+                .collect();
+
+            ui.set_is_two_way_busy(true);
+            ui.set_two_way_summary("Đang đồng bộ hai chiều...".into());
+
+            let ui_handle = ui_handle.clone();
+            tokio::spawn(async move {
+                let result = match build_two_way_plan(&params, &dir, &prefix, policy).await {
+                    Ok((client, mut plan)) => {
+                        plan.resolve(&choices);
+                        let progress_handle = ui_handle.clone();
+                        let client = std::sync::Arc::new(client);
+                        let status = status_fn(&ui_handle);
+                        crate::restore::resume(&client, &params.bucket, &status);
+                        let throttle = std::sync::Arc::new(crate::scheduler::register(Default::default()));
+                        crate::two_way::execute_plan(
+                            client,
+                            &params.bucket,
+                            &prefix,
+                            &dir,
+                            &plan,
+                            &status,
+                            throttle,
+                            move |done, total| {
+                                update_status(
+                                    &progress_handle,
+                                    format!("Two-way sync ({}/{})", done, total),
+                                    done as f32 / total as f32,
+                                    false,
+                                );
+                            },
+                        )
+                        .await
+                    }
+                    Err(e) => Err(e),
+                };
+
+                let (message, is_error) = match result {
+                    Ok(r) if r.restoring > 0 => (
+                        format!(
+                            "Two-way sync hoàn tất: {} upload, {} download, {} file Glacier đang restore (sẽ tự tải khi xong)",
+                            r.uploaded, r.downloaded, r.restoring
+                        ),
+                        false,
+                    ),
+                    Ok(r) => (format!("Two-way sync hoàn tất: {} upload, {} download", r.uploaded, r.downloaded), false),
+                    Err(e) => {
+                        error!("Two-way sync failed: {}", e);
+                        (e, true)
+                    }
+                };
+                update_status(&ui_handle, message.clone(), if is_error { 0.0 } else { 1.0 }, is_error);
+                let _ = ui_handle.upgrade_in_event_loop(move |ui| {
+                    if !is_error {
+                        ui.set_two_way_conflicts(ModelRc::default());
+                        ui.set_two_way_can_run(false);
+                    }
+                    ui.set_two_way_summary(message.into());
+                    ui.set_is_two_way_busy(false);
+                });
+            });
+        }
+    });
+}
+
+/// Sets up the sync options handler.
+pub fn setup_sync_options_handler(ui: &AppWindow, state: &AppState) {
+    ui.on_select_redirects_file({
+        let ui_handle = ui.as_weak();
+        move || {
+            let open = || rfd::AsyncFileDialog::new().add_filter("Redirect map", &["txt", "map"]).pick_file();
+            spawn_file_dialog(&ui_handle, open, |ui, picked| {
+                if let Some(handle) = picked {
+                    ui.set_redirects_file(handle.path().to_string_lossy().to_string().into());
+                }
+            });
+        }
+    });
+
+    ui.on_save_sync_options({
+        let ui_handle = ui.as_weak();
+        let state = state.clone();
+        move || {
+            let Some(ui) = ui_handle.upgrade() else { return; };
+
+            let Ok(max_key_length) = ui.get_max_key_length_text().trim().parse::<usize>() else {
+                ui.set_sync_options_error("Độ dài key tối đa phải là số".into());
+                return;
+            };
+            let key_policy = crate::config::KeyPolicy {
+                enabled: ui.get_key_policy_enabled(),
+                max_key_length,
+                forbidden_chars: ui.get_forbidden_chars().to_string(),
+                required_prefix_regex: ui.get_required_prefix_regex().trim().to_string(),
+            };
+            if let Some(err) = crate::key_policy::validate_policy(&key_policy) {
+                ui.set_sync_options_error(err.into());
+                return;
+            }
+            let (Ok(large_file_threshold_mb), Ok(large_file_lane)) = (
+                ui.get_large_file_threshold_text().trim().parse::<u64>(),
+                ui.get_large_file_lane_text().trim().parse::<usize>(),
+            ) else {
+                ui.set_sync_options_error("Ngưỡng file lớn và số luồng phải là số".into());
+                return;
+            };
+            let sync_window = ui.get_sync_window_text().trim().to_string();
+            if let Err(e) = crate::sync_window::parse_window(&sync_window) {
+                ui.set_sync_options_error(e.into());
+                return;
+            }
+            let Ok(session_budget_mb) = ui.get_session_budget_text().trim().parse::<u64>() else {
+                ui.set_sync_options_error("Ngân sách upload phải là số MB (0 = không giới hạn)".into());
+                return;
+            };
+            let (Ok(scan_max_depth), Ok(scan_max_files)) = (
+                ui.get_scan_max_depth_text().trim().parse::<usize>(),
+                ui.get_scan_max_files_text().trim().parse::<u64>(),
+            ) else {
+                ui.set_sync_options_error("Giới hạn quét (số cấp, số file) phải là số, 0 = không giới hạn".into());
+                return;
+            };
+            let header_rules = match crate::utils::parse_header_rules(&ui.get_header_rules_text()) {
+                Ok(rules) => rules,
+                Err(e) => {
+                    ui.set_sync_options_error(e.into());
+                    return;
+                }
+            };
+            let disposition_rules = match crate::utils::parse_disposition_rules(&ui.get_disposition_rules_text()) {
+                Ok(rules) => rules,
+                Err(e) => {
+                    ui.set_sync_options_error(format!("Content-Disposition: {}", e).into());
+                    return;
+                }
+            };
+            let entry_patterns_text = ui.get_entry_patterns_text();
+            let invalid = crate::utils::validate_glob_patterns(&entry_patterns_text);
+            if !invalid.is_empty() {
+                ui.set_sync_options_error(format!("Pattern không hợp lệ: {}", invalid.join(", ")).into());
+                return;
+            }
+            let fingerprint_patterns_text = ui.get_fingerprint_patterns_text();
+            let invalid = crate::utils::validate_glob_patterns(&fingerprint_patterns_text);
+            if !invalid.is_empty() {
+                ui.set_sync_options_error(format!("Pattern không hợp lệ: {}", invalid.join(", ")).into());
+                return;
+            }
+            let fingerprint_manifest = ui.get_fingerprint_manifest().trim().trim_start_matches('/').to_string();
+            if !fingerprint_patterns_text.trim().is_empty() && fingerprint_manifest.is_empty() {
+                ui.set_sync_options_error("Cần nhập key cho file manifest".into());
+                return;
+            }
+            let replica_targets = match crate::config::ReplicaTarget::parse_list(&ui.get_replica_targets_text(), &ui.get_region()) {
+                Ok(targets) => targets,
+                Err(e) => {
+                    ui.set_sync_options_error(e.into());
+                    return;
+                }
+            };
+            let dir_index_template = ui.get_dir_index_template().trim().to_string();
+            if !dir_index_template.is_empty() {
+                match std::fs::read_to_string(&dir_index_template) {
+                    Ok(text) if text.contains("{entries}") => {}
+                    Ok(_) => {
+                        ui.set_sync_options_error("Template index phải có {entries}".into());
+                        return;
+                    }
+                    Err(e) => {
+                        ui.set_sync_options_error(format!("Không đọc được template index: {}", e).into());
+                        return;
+                    }
+                }
+            }
+            let Ok(batch_threshold) = ui.get_batch_threshold_text().trim().parse::<usize>() else {
+                ui.set_sync_options_error("Ngưỡng S3 Batch phải là số object (0 = tắt)".into());
+                return;
+            };
+            let batch_role_arn = ui.get_batch_role_arn().trim().to_string();
+            if batch_threshold > 0 && !(batch_role_arn.starts_with("arn:aws") && batch_role_arn.contains(":iam::")) {
+                ui.set_sync_options_error("S3 Batch cần ARN của IAM role (arn:aws:iam::...:role/...)".into());
+                return;
+            }
+            let batch_manifest_bucket = ui.get_batch_manifest_bucket().trim().to_string();
+            if batch_threshold > 0 && batch_manifest_bucket.is_empty() {
+                ui.set_sync_options_error("S3 Batch cần một bucket riêng (không public) để chứa manifest".into());
+                return;
+            }
+            let client_side_encryption = ui.get_client_side_encryption();
+            let passphrase = ui.get_encryption_passphrase();
+            if !passphrase.is_empty() {
+                crate::encryption::set_passphrase(&passphrase);
+                ui.set_encryption_passphrase("".into());
+            }
+            if ui.get_append_only() && (ui.get_archive_upload() || client_side_encryption) {
+                ui.set_sync_options_error("Chế độ append-only không dùng chung với chế độ nén hoặc mã hóa".into());
+                return;
+            }
+            if client_side_encryption && ui.get_archive_upload() {
+                ui.set_sync_options_error("Chế độ nén .tar.gz chưa hỗ trợ mã hóa phía client".into());
+                return;
+            }
+            if client_side_encryption && crate::encryption::passphrase().is_none() {
+                ui.set_sync_options_error("Cần nhập passphrase để mã hóa phía client".into());
+                return;
+            }
+            let redirects_file = ui.get_redirects_file().trim().to_string();
+            if !redirects_file.is_empty()
+                && let Err(e) = crate::redirects::load_redirects(std::path::Path::new(&redirects_file))
+            {
+                ui.set_sync_options_error(e.into());
+                return;
+            }
+            let smoke_test_urls: Vec<String> = ui
+                .get_smoke_test_text()
+                .lines()
+                .map(|l| l.trim().to_string())
+                .filter(|l| !l.is_empty())
+                .collect();
+            if let Err(e) = crate::smoke_test::validate_lines(&smoke_test_urls) {
+                ui.set_sync_options_error(e.into());
+                return;
+            }
+            let Ok(verify_sample) = ui.get_verify_sample_text().trim().parse::<usize>() else {
+                ui.set_sync_options_error("Số file kiểm tra HeadObject phải là số (0 = tắt)".into());
+                return;
+            };
+
+            let result = state.save(|config| {
+                config.sync_options.dedup_mode =
+                    crate::config::DedupMode::parse(&ui.get_dedup_mode()).unwrap_or_default();
+                config.sync_options.key_policy = key_policy;
+                config.sync_options.trash_overwritten = ui.get_trash_overwritten();
+                config.sync_options.staged_deploy = ui.get_staged_deploy();
+                config.sync_options.staged_cleanup = ui.get_staged_cleanup();
+                config.sync_options.create_folder_markers = ui.get_create_folder_markers();
+                config.sync_options.large_file_threshold_mb = large_file_threshold_mb;
+                config.sync_options.large_file_lane = large_file_lane;
+                config.sync_options.session_budget_mb = session_budget_mb;
+                config.sync_options.scan_max_depth = scan_max_depth;
+                config.sync_options.scan_max_files = scan_max_files;
+                config.sync_options.placeholder_policy =
+                    crate::config::PlaceholderPolicy::parse(&ui.get_placeholder_policy()).unwrap_or_default();
+                config.sync_options.sparse_policy =
+                    crate::config::SparsePolicy::parse(&ui.get_sparse_policy()).unwrap_or_default();
+                config.sync_options.date_partition =
+                    crate::config::DatePartition::parse(&ui.get_date_partition()).unwrap_or_default();
+                config.sync_options.sniff_mime = ui.get_sniff_mime();
+                config.sync_options.store_ctime = ui.get_store_ctime();
+                config.sync_options.sync_window = sync_window;
+                config.sync_options.pause_on_metered = ui.get_pause_on_metered();
+                config.sync_options.header_rules = header_rules;
+                config.sync_options.disposition_rules = disposition_rules;
+                config.sync_options.redirects_file = redirects_file;
+                config.sync_options.smoke_test_urls = smoke_test_urls;
+                config.sync_options.verify_sample = verify_sample;
+                config.sync_options.entry_patterns = entry_patterns_text
+                    .split(',')
+                    .map(|s| s.trim().to_string())
+                    .filter(|s| !s.is_empty())
+                    .collect();
+                config.sync_options.fingerprint_patterns = fingerprint_patterns_text
+                    .split(',')
+                    .map(|s| s.trim().to_string())
+                    .filter(|s| !s.is_empty())
+                    .collect();
+                config.sync_options.fingerprint_manifest = fingerprint_manifest;
+                config.sync_options.client_side_encryption = client_side_encryption;
+                config.sync_options.archive_upload = ui.get_archive_upload();
+                config.sync_options.append_only = ui.get_append_only();
+                config.sync_options.checksum_manifest = ui.get_checksum_manifest().trim().to_string();
+                config.sync_options.replica_targets = replica_targets;
+                config.sync_options.replica_mode =
+                    if ui.get_replica_copy() { crate::config::ReplicaMode::Copy } else { crate::config::ReplicaMode::Upload };
+                config.sync_options.dir_index = ui.get_dir_index();
+                config.sync_options.dir_index_template = dir_index_template;
+                config.sync_options.batch_threshold = batch_threshold;
+                config.sync_options.batch_role_arn = batch_role_arn;
+                config.sync_options.batch_manifest_bucket = batch_manifest_bucket;
+            });
+            if let Err(e) = result {
+                error!("Failed to save sync options: {:?}", e);
+                ui.set_sync_options_error(format!("Lỗi lưu cấu hình: {}", e).into());
+                return;
+            }
+
+            info!("Sync options saved: {:?}", state.read().sync_options);
+            ui.set_sync_options_error("".into());
+            ui.set_show_sync_options(false);
+            update_status(&ui_handle, "Đã lưu tùy chọn sync".to_string(), 0.0, false);
+        }
+    });
+}
+
+/// Samples upload throughput every second and feeds the live chart under the progress bar.
+/// The returned timer must be kept alive.
+pub fn start_throughput_timer(ui: &AppWindow) -> slint::Timer {
+    let timer = slint::Timer::default();
+    let ui_handle = ui.as_weak();
+    timer.start(slint::TimerMode::Repeated, time::Duration::from_secs(1), move || {
+        let Some(ui) = ui_handle.upgrade() else { return; };
+        let history = crate::throughput::sample();
+        if !history.is_active() {
+            if ui.get_throughput_visible() {
+                ui.set_throughput_visible(false);
+            }
+            return;
+        }
+
+        // Average of the last 5 seconds reads steadier than a single sample
+        let recent = |samples: &std::collections::VecDeque<f32>| {
+            let n = samples.len().min(5);
+            samples.iter().rev().take(n).sum::<f32>() / n.max(1) as f32
+        };
+        ui.set_throughput_text(
+            format!(
+                "{:.1} MB/s · {:.1} file/s",
+                recent(&history.mb_per_sec),
+                recent(&history.files_per_sec)
+            )
+            .into(),
+        );
+        ui.set_throughput_mb(ModelRc::new(VecModel::from(crate::throughput::normalized(&history.mb_per_sec))));
+        ui.set_throughput_files(ModelRc::new(VecModel::from(crate::throughput::normalized(&history.files_per_sec))));
+        ui.set_throughput_visible(true);
+    });
+    timer
+}
+
+/// Read-only model over the live transfer table. Rows are built on demand, so only the
+/// rows the ListView actually shows are converted, even for 100k files.
+struct TransfersModel {
+    row_count: std::cell::Cell<usize>,
+    notify: slint::ModelNotify,
+}
+
+impl Model for TransfersModel {
+    type Data = TransferRow;
+
+    fn row_count(&self) -> usize {
+        self.row_count.get()
+    }
+
+    fn row_data(&self, row: usize) -> Option<TransferRow> {
+        crate::transfers::row(row).map(|t| TransferRow {
+            name: t.name.as_str().into(),
+            size: crate::utils::format_size(t.size).into(),
+            state: t.state_label().into(),
+            speed: t.speed_label().into(),
+        })
+    }
+
+    fn model_tracker(&self) -> &dyn slint::ModelTracker {
+        &self.notify
+    }
+}
+
+/// Starts the timer that pushes transfer table changes to the file list twice a second.
+pub fn start_transfers_timer(ui: &AppWindow) -> slint::Timer {
+    let model = Rc::new(TransfersModel { row_count: std::cell::Cell::new(0), notify: Default::default() });
+    ui.set_transfers(ModelRc::from(model.clone()));
+
+    let timer = slint::Timer::default();
+    let ui_handle = ui.as_weak();
+    timer.start(slint::TimerMode::Repeated, time::Duration::from_millis(500), move || {
+        let Some(ui) = ui_handle.upgrade() else { return; };
+        match crate::transfers::take_changes() {
+            crate::transfers::Changes::None => return,
+            crate::transfers::Changes::Reset => {
+                model.row_count.set(crate::transfers::len());
+                model.notify.reset();
+            }
+            crate::transfers::Changes::Rows(rows) => {
+                for row in rows.into_iter().filter(|r| *r < model.row_count.get()) {
+                    model.notify.row_changed(row);
+                }
+            }
+        }
+        ui.set_transfer_summary(crate::transfers::summary().into());
+    });
+    timer
+}
+
 /// Convenience function to set up all UI handlers.
-pub fn setup_all_handlers(ui: &AppWindow) {
-    setup_test_access_handler(ui);
-    setup_select_folder_handler(ui);
-    setup_select_files_handler(ui);
+pub fn setup_all_handlers(ui: &AppWindow, state: &AppState) {
+    set_recent_folders(ui, &state.read().recent_folders);
+    setup_test_access_handler(ui, state);
+    setup_select_folder_handler(ui, state);
+    setup_select_files_handler(ui, state);
+    setup_recent_folders_handler(ui, state);
+    setup_paste_paths_handler(ui, state);
+    setup_command_palette_handlers(ui, state);
+    setup_restore_session_handler(ui, state);
     setup_clear_folders_handler(ui);
     setup_remove_folder_handler(ui);
-    setup_start_sync_handler(ui);
-    setup_select_log_path_handler(ui);
+    setup_edit_s3_path_handler(ui);
+    setup_toggle_path_item_handler(ui);
+    setup_toggle_flatten_handler(ui, state);
+    setup_start_sync_handler(ui, state);
+    setup_sync_queue_handlers(ui, state);
+    setup_select_log_path_handler(ui, state);
     setup_open_log_folder_handler(ui);
-    setup_select_base_path_handler(ui);
+    setup_log_level_handler(ui, state);
+    setup_log_file_mode_handler(ui, state);
+    setup_email_settings_handler(ui, state);
+    setup_app_settings_handler(ui, state);
+    setup_onboarding_handler(ui, state);
+    setup_update_handlers(ui);
+    setup_crash_report_handler(ui);
+    setup_select_metrics_path_handler(ui, state);
+    setup_bucket_usage_handler(ui);
+    setup_delete_prefix_handler(ui);
+    setup_bucket_browser_handlers(ui);
+    setup_compare_prefixes_handler(ui);
+    setup_transition_cold_handler(ui);
+    setup_restore_archived_handler(ui, state);
+    setup_listing_source_handlers(ui);
+    setup_session_history_handlers(ui);
+    setup_two_way_handlers(ui, state);
+    setup_sync_options_handler(ui, state);
+    setup_select_base_path_handler(ui, state);
     setup_toggle_filter_config_handler(ui);
-    setup_save_filter_config_handler(ui);
-    setup_reset_filter_config_handler(ui);
+    setup_save_filter_config_handler(ui, state);
+    setup_reset_filter_config_handler(ui, state);
     setup_preview_filtering_handler(ui);
-    setup_bucket_handlers(ui);
-    setup_region_handlers(ui);
+    setup_live_filter_handlers(ui, state);
+    setup_bucket_handlers(ui, state);
+    setup_region_handlers(ui, state);
 }
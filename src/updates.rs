@@ -0,0 +1,251 @@
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Latest published release of this app.
+pub const RELEASES_URL: &str = "https://api.github.com/repos/NgocVuThe/Sync_S3_Aws/releases/latest";
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// A release newer than the running build.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Release {
+    /// Tag without the leading `v`.
+    pub version: String,
+    /// Release notes as written on GitHub (markdown).
+    pub changelog: String,
+    pub page_url: String,
+    /// Installer for this platform, if the release has one.
+    pub installer: Option<Installer>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Installer {
+    pub name: String,
+    pub url: String,
+    /// `<installer>.sha256` or `SHA256SUMS` published with the release. Installers without one
+    /// are never run.
+    pub checksum_url: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GithubRelease {
+    tag_name: String,
+    #[serde(default)]
+    body: Option<String>,
+    html_url: String,
+    #[serde(default)]
+    draft: bool,
+    #[serde(default)]
+    prerelease: bool,
+    #[serde(default)]
+    assets: Vec<GithubAsset>,
+}
+
+#[derive(Deserialize)]
+struct GithubAsset {
+    name: String,
+    browser_download_url: String,
+}
+
+/// File extensions of installers for the platform this build runs on.
+fn installer_extensions() -> &'static [&'static str] {
+    match std::env::consts::OS {
+        "windows" => &[".msi", ".exe"],
+        "macos" => &[".dmg", ".pkg"],
+        _ => &[".appimage", ".deb"],
+    }
+}
+
+/// Parses `1.2.3`, `v1.2` or `v1.2.3-beta` into numbers; missing parts count as 0.
+fn parse_version(text: &str) -> Option<(u64, u64, u64)> {
+    let text = text.trim().trim_start_matches(['v', 'V']);
+    let core = text.split(['-', '+']).next()?;
+    let mut parts = core.split('.').map(|p| p.parse::<u64>());
+    let major = parts.next()?.ok()?;
+    let minor = parts.next().transpose().ok()?.unwrap_or(0);
+    let patch = parts.next().transpose().ok()?.unwrap_or(0);
+    Some((major, minor, patch))
+}
+
+/// True if `latest` is a higher version than `current`. Unparsable versions never count as newer.
+pub fn is_newer(latest: &str, current: &str) -> bool {
+    match (parse_version(latest), parse_version(current)) {
+        (Some(latest), Some(current)) => latest > current,
+        _ => false,
+    }
+}
+
+/// Reads the GitHub "latest release" response. Drafts and pre-releases give `None`.
+fn parse_release(json: &str, extensions: &[&str]) -> Result<Option<Release>, String> {
+    let release: GithubRelease = serde_json::from_str(json).map_err(|e| format!("Phản hồi không hợp lệ: {}", e))?;
+    if release.draft || release.prerelease {
+        return Ok(None);
+    }
+    // First matching extension wins, so the list order is the preference order
+    let installer = extensions.iter().find_map(|ext| {
+        release
+            .assets
+            .iter()
+            .find(|a| a.name.to_lowercase().ends_with(ext))
+            .map(|a| {
+                let sidecar = format!("{}.sha256", a.name);
+                let checksum_url = release
+                    .assets
+                    .iter()
+                    .find(|c| c.name == sidecar)
+                    .or_else(|| release.assets.iter().find(|c| c.name.eq_ignore_ascii_case("SHA256SUMS")))
+                    .map(|c| c.browser_download_url.clone());
+                Installer { name: a.name.clone(), url: a.browser_download_url.clone(), checksum_url }
+            })
+    });
+    Ok(Some(Release {
+        version: release.tag_name.trim_start_matches(['v', 'V']).to_string(),
+        changelog: release.body.unwrap_or_default().trim().to_string(),
+        page_url: release.html_url,
+        installer,
+    }))
+}
+
+fn http_client(timeout: Duration) -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .timeout(timeout)
+        // GitHub rejects API requests without a User-Agent
+        .user_agent(concat!("s3-sync-tool/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .map_err(|e| format!("Không tạo được HTTP client: {}", e))
+}
+
+/// Asks GitHub for the latest release; `Some` only if it is newer than this build.
+pub async fn check() -> Result<Option<Release>, String> {
+    let http = http_client(REQUEST_TIMEOUT)?;
+    let resp = http
+        .get(RELEASES_URL)
+        .header("Accept", "application/vnd.github+json")
+        .send()
+        .await
+        .map_err(|e| format!("Không kết nối được GitHub: {}", e))?;
+    if !resp.status().is_success() {
+        return Err(format!("GitHub trả về HTTP {}", resp.status().as_u16()));
+    }
+    let body = resp.text().await.map_err(|e| format!("Lỗi đọc phản hồi: {}", e))?;
+    let release = parse_release(&body, installer_extensions())?;
+    Ok(release.filter(|r| is_newer(&r.version, env!("CARGO_PKG_VERSION"))))
+}
+
+/// Finds the SHA-256 for `name` in a checksum file: either a bare hash (`<installer>.sha256`)
+/// or `sha256sum` lines (`<hash>  <name>`, `*` marks binary mode). A bare hash only counts as
+/// the file's single line, since in a multi-entry file it doesn't say which asset it is for.
+pub fn expected_sha256(checksums: &str, name: &str) -> Option<String> {
+    let is_hash = |h: &str| h.len() == 64 && h.chars().all(|c| c.is_ascii_hexdigit());
+    let single_line = checksums.lines().filter(|line| !line.trim().is_empty()).count() == 1;
+    checksums.lines().find_map(|line| {
+        let mut parts = line.split_whitespace();
+        let hash = parts.next()?;
+        let matches = match parts.next() {
+            Some(file) => file.trim_start_matches('*') == name,
+            None => single_line,
+        };
+        (matches && is_hash(hash)).then(|| hash.to_ascii_lowercase())
+    })
+}
+
+/// Downloads the installer into a fresh private temp folder, checks it against the SHA-256
+/// published with the release and returns its path. The folder is kept for the installer to run.
+pub async fn download(installer: &Installer) -> Result<PathBuf, String> {
+    let http = http_client(DOWNLOAD_TIMEOUT)?;
+    let checksum_url = installer
+        .checksum_url
+        .as_ref()
+        .ok_or_else(|| format!("Bản phát hành không có checksum SHA-256 cho {}, không cài tự động", installer.name))?;
+    let checksums = http
+        .get(checksum_url)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("Lỗi tải checksum: {}", e))?
+        .text()
+        .await
+        .map_err(|e| format!("Lỗi tải checksum: {}", e))?;
+    let expected = expected_sha256(&checksums, &installer.name)
+        .ok_or_else(|| format!("Không tìm thấy SHA-256 của {} trong checksum", installer.name))?;
+
+    let mut resp = http
+        .get(&installer.url)
+        .send()
+        .await
+        .map_err(|e| format!("Lỗi tải {}: {}", installer.name, e))?;
+    if !resp.status().is_success() {
+        return Err(format!("Lỗi tải {}: HTTP {}", installer.name, resp.status().as_u16()));
+    }
+
+    // A new directory only this user can write, so nobody can swap the file before it runs
+    let dir = tempfile::Builder::new()
+        .prefix("s3-sync-update-")
+        .tempdir()
+        .map_err(|e| format!("Lỗi tạo thư mục tạm: {}", e))?;
+    // The asset name comes from the server; keep only its file name part
+    let name = std::path::Path::new(&installer.name).file_name().ok_or("Tên file cài đặt không hợp lệ")?;
+    let path = dir.path().join(name);
+    let mut file = std::fs::File::create(&path).map_err(|e| format!("Lỗi ghi {}: {}", path.display(), e))?;
+    let mut hasher = Sha256::new();
+    while let Some(chunk) = resp.chunk().await.map_err(|e| format!("Lỗi tải {}: {}", installer.name, e))? {
+        hasher.update(&chunk);
+        file.write_all(&chunk).map_err(|e| format!("Lỗi ghi {}: {}", path.display(), e))?;
+    }
+    file.sync_all().map_err(|e| format!("Lỗi ghi {}: {}", path.display(), e))?;
+    let actual = format!("{:x}", hasher.finalize());
+    if actual != expected {
+        return Err(format!("SHA-256 của {} không khớp (tải về {}, công bố {}), đã hủy cài đặt", installer.name, actual, expected));
+    }
+    let _ = dir.keep();
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_compare_and_release_parsing() {
+        assert!(is_newer("v0.2.0", "0.1.0"));
+        assert!(is_newer("1.0", "0.9.9"));
+        assert!(!is_newer("v0.1.0", "0.1.0"));
+        assert!(!is_newer("0.1.0-beta", "0.1.0"));
+        assert!(!is_newer("nightly", "0.1.0"));
+
+        let json = r#"{
+            "tag_name": "v0.3.1",
+            "body": "- Sửa lỗi upload\r\n",
+            "html_url": "https://github.com/NgocVuThe/Sync_S3_Aws/releases/tag/v0.3.1",
+            "assets": [
+                {"name": "s3-sync-0.3.1.zip", "browser_download_url": "https://example.com/a.zip"},
+                {"name": "s3-sync-setup-0.3.1.EXE", "browser_download_url": "https://example.com/setup.exe"},
+                {"name": "SHA256SUMS", "browser_download_url": "https://example.com/SHA256SUMS"}
+            ]
+        }"#;
+        let release = parse_release(json, &[".msi", ".exe"]).unwrap().unwrap();
+        assert_eq!(release.version, "0.3.1");
+        assert_eq!(release.changelog, "- Sửa lỗi upload");
+        let installer = release.installer.unwrap();
+        assert_eq!(installer.url, "https://example.com/setup.exe");
+        assert_eq!(installer.checksum_url.as_deref(), Some("https://example.com/SHA256SUMS"));
+        assert!(parse_release(json, &[".dmg"]).unwrap().unwrap().installer.is_none());
+
+        let pre = r#"{"tag_name": "v0.4.0-rc1", "html_url": "x", "prerelease": true}"#;
+        assert_eq!(parse_release(pre, &[".exe"]).unwrap(), None);
+
+        let hash = "a".repeat(64);
+        let sums = format!("{}  other.zip\n{} *setup.exe\n", "b".repeat(64), hash);
+        assert_eq!(expected_sha256(&sums, "setup.exe"), Some(hash.clone()));
+        assert_eq!(expected_sha256(&sums, "missing.exe"), None);
+        assert_eq!(expected_sha256(&format!("{}\n", hash.to_uppercase()), "setup.exe"), Some(hash.clone()));
+        assert_eq!(expected_sha256("not a hash", "setup.exe"), None);
+        // A bare hash among named entries belongs to no particular asset
+        let other = "b".repeat(64);
+        assert_eq!(expected_sha256(&format!("{}\n{}  other.exe\n", hash, other), "setup.exe"), None);
+    }
+}
@@ -0,0 +1,138 @@
+use aws_sdk_s3::Client;
+use std::collections::BTreeMap;
+
+/// Label used for objects stored directly at the bucket root.
+pub const ROOT_PREFIX_LABEL: &str = "(root)";
+
+/// Approximate S3 storage price in USD per GB-month (us-east-1, first tier).
+/// Only meant for rough comparisons, not billing.
+pub fn storage_price_per_gb(storage_class: &str) -> f64 {
+    match storage_class {
+        "STANDARD" | "" => 0.023,
+        "INTELLIGENT_TIERING" => 0.023,
+        "STANDARD_IA" => 0.0125,
+        "ONEZONE_IA" => 0.01,
+        "GLACIER_IR" => 0.004,
+        "GLACIER" => 0.0036,
+        "DEEP_ARCHIVE" => 0.00099,
+        "REDUCED_REDUNDANCY" => 0.024,
+        _ => 0.023,
+    }
+}
+
+/// Size and object count for one top-level prefix.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PrefixUsage {
+    pub objects: u64,
+    pub bytes: u64,
+    /// Bytes per storage class, used for the cost estimate.
+    pub bytes_by_class: BTreeMap<String, u64>,
+}
+
+impl PrefixUsage {
+    pub fn add(&mut self, size: u64, storage_class: &str) {
+        self.objects += 1;
+        self.bytes += size;
+        *self.bytes_by_class.entry(storage_class.to_string()).or_insert(0) += size;
+    }
+
+    /// Estimated monthly storage cost in USD.
+    pub fn monthly_cost(&self) -> f64 {
+        self.bytes_by_class
+            .iter()
+            .map(|(class, bytes)| *bytes as f64 / (1024.0 * 1024.0 * 1024.0) * storage_price_per_gb(class))
+            .sum()
+    }
+}
+
+/// Usage of a whole bucket, grouped by top-level prefix.
+#[derive(Debug, Clone, Default)]
+pub struct BucketUsage {
+    pub prefixes: BTreeMap<String, PrefixUsage>,
+}
+
+impl BucketUsage {
+    pub fn add_object(&mut self, key: &str, size: u64, storage_class: &str) {
+        let prefix = match key.split_once('/') {
+            Some((first, _)) if !first.is_empty() => first.to_string(),
+            _ => ROOT_PREFIX_LABEL.to_string(),
+        };
+        self.prefixes.entry(prefix).or_default().add(size, storage_class);
+    }
+
+    pub fn total(&self) -> PrefixUsage {
+        let mut total = PrefixUsage::default();
+        for usage in self.prefixes.values() {
+            total.objects += usage.objects;
+            total.bytes += usage.bytes;
+            for (class, bytes) in &usage.bytes_by_class {
+                *total.bytes_by_class.entry(class.clone()).or_insert(0) += bytes;
+            }
+        }
+        total
+    }
+}
+
+/// Lists every object in the bucket (paginated) and aggregates usage per top-level prefix.
+/// `on_page` is called with the running object count after each page.
+pub async fn collect_bucket_usage(
+    client: &Client,
+    bucket: &str,
+    mut on_page: impl FnMut(u64),
+) -> Result<BucketUsage, String> {
+    let mut usage = BucketUsage::default();
+    let mut seen = 0u64;
+    let mut pages = client
+        .list_objects_v2()
+        .bucket(bucket)
+        .into_paginator()
+        .send();
+
+    while let Some(page) = pages.next().await {
+        let page = page.map_err(|e| format!("Lỗi list objects: {}", e))?;
+        for obj in page.contents() {
+            let key = obj.key().unwrap_or_default();
+            let size = obj.size().unwrap_or(0).max(0) as u64;
+            let class = obj.storage_class().map(|c| c.as_str()).unwrap_or("STANDARD");
+            usage.add_object(key, size, class);
+            seen += 1;
+        }
+        on_page(seen);
+    }
+
+    Ok(usage)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_object_groups_by_top_level_prefix() {
+        let mut usage = BucketUsage::default();
+        usage.add_object("assets/img/a.png", 100, "STANDARD");
+        usage.add_object("assets/b.css", 50, "STANDARD");
+        usage.add_object("index.html", 10, "STANDARD");
+        usage.add_object("backup/2024.zip", 1000, "GLACIER");
+
+        assert_eq!(usage.prefixes["assets"].objects, 2);
+        assert_eq!(usage.prefixes["assets"].bytes, 150);
+        assert_eq!(usage.prefixes[ROOT_PREFIX_LABEL].objects, 1);
+
+        let total = usage.total();
+        assert_eq!(total.objects, 4);
+        assert_eq!(total.bytes, 1160);
+        assert_eq!(total.bytes_by_class["GLACIER"], 1000);
+    }
+
+    #[test]
+    fn test_monthly_cost_uses_storage_class_price() {
+        let gb = 1024 * 1024 * 1024;
+        let mut usage = PrefixUsage::default();
+        usage.add(10 * gb, "STANDARD");
+        usage.add(10 * gb, "DEEP_ARCHIVE");
+
+        let expected = 10.0 * 0.023 + 10.0 * 0.00099;
+        assert!((usage.monthly_cost() - expected).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,117 @@
+//! Post-sync spot check: HeadObject a random sample of the uploaded keys and compare
+//! Content-Length, Content-Type and Cache-Control with what was sent, to catch proxies or
+//! middleware that silently alter uploads.
+
+use aws_sdk_s3::Client;
+use ring::rand::{SecureRandom, SystemRandom};
+use tracing::{info, warn};
+
+use crate::s3_client::UploadTiming;
+
+/// Headers S3 reports for an object, as far as they are compared.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StoredHeaders {
+    pub content_length: Option<u64>,
+    pub content_type: Option<String>,
+    pub cache_control: Option<String>,
+}
+
+/// Picks up to `n` distinct indices below `len`, using `random` as the source of randomness.
+pub fn sample_indices(len: usize, n: usize, mut random: impl FnMut() -> u64) -> Vec<usize> {
+    let mut indices: Vec<usize> = (0..len).collect();
+    let n = n.min(len);
+    // Partial Fisher-Yates: the first n slots end up a uniform sample
+    for i in 0..n {
+        let j = i + (random() % (len - i) as u64) as usize;
+        indices.swap(i, j);
+    }
+    indices.truncate(n);
+    indices
+}
+
+/// Differences between what was sent and what S3 stored, one line per header.
+pub fn mismatches(sent: &UploadTiming, stored: &StoredHeaders) -> Vec<String> {
+    let mut out = Vec::new();
+    if stored.content_length != Some(sent.bytes) {
+        out.push(format!("Content-Length {} ≠ {:?}", sent.bytes, stored.content_length));
+    }
+    if stored.content_type.as_deref() != Some(sent.content_type.as_str()) {
+        out.push(format!("Content-Type '{}' ≠ {:?}", sent.content_type, stored.content_type));
+    }
+    if stored.cache_control != sent.cache_control {
+        out.push(format!("Cache-Control {:?} ≠ {:?}", sent.cache_control, stored.cache_control));
+    }
+    out
+}
+
+/// HeadObjects `n` random uploads and returns a warning for every object whose stored headers
+/// differ from the sent ones. A failed HeadObject is a warning too; nothing fails the sync.
+pub async fn verify_sample(client: &Client, bucket: &str, uploads: &[UploadTiming], n: usize) -> Vec<String> {
+    let rng = SystemRandom::new();
+    let random = || {
+        let mut buf = [0u8; 8];
+        rng.fill(&mut buf).map(|_| u64::from_le_bytes(buf)).unwrap_or_default()
+    };
+    let sample = sample_indices(uploads.len(), n, random);
+    let mut warnings = Vec::new();
+    for &i in &sample {
+        let sent = &uploads[i];
+        match client.head_object().bucket(bucket).key(&sent.key).send().await {
+            Ok(head) => {
+                let stored = StoredHeaders {
+                    content_length: head.content_length().map(|l| l.max(0) as u64),
+                    content_type: head.content_type().map(String::from),
+                    cache_control: head.cache_control().map(String::from),
+                };
+                let diffs = mismatches(sent, &stored);
+                if !diffs.is_empty() {
+                    warnings.push(format!("{} khác với bản đã gửi: {}", sent.key, diffs.join(", ")));
+                }
+            }
+            Err(e) => warnings.push(format!("Không kiểm tra được {}: {}", sent.key, e)),
+        }
+    }
+    if warnings.is_empty() {
+        info!("Verified {} sampled uploads in {} with HeadObject", sample.len(), bucket);
+    } else {
+        warn!("HeadObject verification of {}: {}", bucket, warnings.join("; "));
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample_and_compare() {
+        let mut counter = 0u64;
+        let sample = sample_indices(10, 4, || {
+            counter += 7;
+            counter
+        });
+        assert_eq!(sample.len(), 4);
+        let mut unique = sample.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), 4);
+        assert!(sample.iter().all(|&i| i < 10));
+        assert_eq!(sample_indices(2, 5, || 0).len(), 2);
+
+        let sent = UploadTiming {
+            key: "site/app.js".into(),
+            bytes: 120,
+            duration: std::time::Duration::ZERO,
+            content_type: "text/javascript".into(),
+            cache_control: Some("no-cache".into()),
+        };
+        let stored = StoredHeaders {
+            content_length: Some(120),
+            content_type: Some("text/javascript".into()),
+            cache_control: Some("no-cache".into()),
+        };
+        assert!(mismatches(&sent, &stored).is_empty());
+        let altered = StoredHeaders { content_length: Some(98), cache_control: None, ..stored };
+        assert_eq!(mismatches(&sent, &altered).len(), 2);
+    }
+}
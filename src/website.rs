@@ -0,0 +1,91 @@
+use aws_sdk_s3::Client;
+use std::collections::HashSet;
+use tracing::{info, warn};
+
+/// Index and error documents of a bucket with static website hosting enabled.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WebsiteDocuments {
+    /// Appended to folder requests, usually `index.html`.
+    pub index_suffix: String,
+    /// Bucket-absolute key served for 4xx errors.
+    pub error_key: Option<String>,
+}
+
+/// Reads the bucket's website configuration. `None` when hosting is off or the bucket
+/// only redirects all requests elsewhere.
+pub async fn website_documents(client: &Client, bucket: &str) -> Result<Option<WebsiteDocuments>, String> {
+    match client.get_bucket_website().bucket(bucket).send().await {
+        Ok(website) => Ok(website.index_document().map(|index| WebsiteDocuments {
+            index_suffix: index.suffix().to_string(),
+            error_key: website.error_document().map(|e| e.key().to_string()),
+        })),
+        Err(e) if e.as_service_error().and_then(|s| s.meta().code()) == Some("NoSuchWebsiteConfiguration") => Ok(None),
+        Err(e) => Err(format!("Không đọc được cấu hình website của {}: {}", bucket, e)),
+    }
+}
+
+/// Key of the index document for an uploaded folder prefix: `site/` + `index.html` → `site/index.html`.
+pub fn index_key(prefix: &str, index_suffix: &str) -> String {
+    let prefix = prefix.trim_matches('/');
+    if prefix.is_empty() { index_suffix.to_string() } else { format!("{}/{}", prefix, index_suffix) }
+}
+
+/// Checks after a sync that every uploaded folder prefix has its index document and that the
+/// error document exists. Returns warnings; never fails the sync.
+pub async fn validate(client: &Client, bucket: &str, prefixes: &[String], uploaded_keys: &[String]) -> Vec<String> {
+    let docs = match website_documents(client, bucket).await {
+        Ok(Some(docs)) => docs,
+        Ok(None) => return Vec::new(),
+        Err(e) => {
+            warn!("{}", e);
+            return Vec::new();
+        }
+    };
+    let uploaded: HashSet<&str> = uploaded_keys.iter().map(String::as_str).collect();
+    let mut warnings = Vec::new();
+
+    let index_keys: HashSet<String> = prefixes.iter().map(|p| index_key(p, &docs.index_suffix)).collect();
+    for key in index_keys.iter().filter(|k| !uploaded.contains(k.as_str())) {
+        match object_exists(client, bucket, key).await {
+            Some(true) => warnings.push(format!("{} không có trong lần upload này (S3 vẫn phục vụ bản cũ)", key)),
+            Some(false) => warnings.push(format!("Thiếu index document {}", key)),
+            None => {}
+        }
+    }
+    if let Some(key) = docs.error_key.filter(|k| !uploaded.contains(k.as_str()))
+        && object_exists(client, bucket, &key).await == Some(false)
+    {
+        warnings.push(format!("Thiếu error document {}", key));
+    }
+
+    if warnings.is_empty() {
+        info!("Website documents of {} verified ({} index)", bucket, index_keys.len());
+    } else {
+        warn!("Website document check for {}: {}", bucket, warnings.join("; "));
+    }
+    warnings
+}
+
+/// `None` when the check itself failed (e.g. no HeadObject permission).
+async fn object_exists(client: &Client, bucket: &str, key: &str) -> Option<bool> {
+    match client.head_object().bucket(bucket).key(key).send().await {
+        Ok(_) => Some(true),
+        Err(e) if e.as_service_error().is_some_and(|s| s.is_not_found()) => Some(false),
+        Err(e) => {
+            warn!("HeadObject {} failed: {}", key, e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_index_key() {
+        assert_eq!(index_key("", "index.html"), "index.html");
+        assert_eq!(index_key("/site/", "index.html"), "site/index.html");
+        assert_eq!(index_key("docs/v2", "index.htm"), "docs/v2/index.htm");
+    }
+}